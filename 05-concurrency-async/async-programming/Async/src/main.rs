@@ -10,15 +10,25 @@
 
 use std::future::Future;
 use std::pin::Pin;
-use std::task::{Context, Poll, Waker};
+use std::task::{Context, Poll, Wake, Waker};
 use std::time::{Duration, Instant};
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
 
 // 引入运行时演示模块
 mod runtime_demo;
 
+// 基于 epoll 的 Reactor 和非阻塞 I/O 类型（仅 Linux）
+#[cfg(target_os = "linux")]
+mod reactor;
+#[cfg(target_os = "linux")]
+mod net;
+
+// 不阻塞线程的异步同步原语
+mod sync;
+
 // ============================================================================
 // 第一部分：异步编程基础
 // ============================================================================
@@ -310,60 +320,165 @@ async fn async_io_demo() {
     }
 }
 
-// ============================================================================
-// 第六部分：异步同步原语
-// ============================================================================
-
-/// 模拟异步 Mutex
-struct AsyncMutex<T> {
-    data: Arc<Mutex<T>>,
-}
-
-impl<T> AsyncMutex<T> {
-    fn new(data: T) -> Self {
-        AsyncMutex {
-            data: Arc::new(Mutex::new(data)),
-        }
-    }
+/// 演示基于 epoll Reactor 的真实异步 I/O（仅 Linux）
+#[cfg(target_os = "linux")]
+async fn reactor_io_demo() {
+    use futures::io::{AsyncReadExt, AsyncWriteExt};
     
-    async fn lock(&self) -> Result<std::sync::MutexGuard<'_, T>, &'static str> {
-        // 在实际的异步 Mutex 中，这里会是非阻塞的
-        match self.data.try_lock() {
-            Ok(guard) => Ok(guard),
-            Err(_) => {
-                // 模拟等待锁释放
-                async_sleep(Duration::from_millis(1)).await;
-                self.data.try_lock().map_err(|_| "锁获取失败")
+    println!("\n=== 基于 epoll 的异步 I/O ===");
+    println!("AsyncFile 只是用睡眠模拟 I/O；下面的回显服务器运行在真实的非阻塞 socket 上");
+    
+    let listener = match net::TcpListener::bind("127.0.0.1:0") {
+        Ok(listener) => listener,
+        Err(e) => {
+            println!("绑定端口失败: {}", e);
+            return;
+        }
+    };
+    let addr = listener.local_addr().unwrap();
+    println!("\n--- 回环回显服务器 ({}) ---", addr);
+    
+    let server = async {
+        let (mut stream, peer) = listener.accept().await?;
+        println!("[SERVER] 接受来自 {} 的连接", peer);
+        let mut buf = [0u8; 1024];
+        loop {
+            let n = stream.read(&mut buf).await?;
+            if n == 0 {
+                break;
             }
+            stream.write_all(&buf[..n]).await?;
+        }
+        println!("[SERVER] 客户端关闭连接");
+        std::io::Result::Ok(())
+    };
+    
+    let client = async {
+        let mut stream = net::TcpStream::connect(addr).await?;
+        for message in ["你好，reactor", "epoll 边沿触发"] {
+            stream.write_all(message.as_bytes()).await?;
+            let mut buf = vec![0u8; message.len()];
+            stream.read_exact(&mut buf).await?;
+            println!("[CLIENT] 收到回显: {}", String::from_utf8_lossy(&buf));
         }
+        stream.close().await
+    };
+    
+    // 服务端和客户端在同一个线程上并发推进，全靠 Reactor 唤醒
+    let (server_result, client_result) = futures::join!(server, client);
+    if let Err(e) = server_result.and(client_result) {
+        println!("回显演示失败: {}", e);
+    }
+    
+    println!("\n--- 非阻塞管道 ---");
+    let pipe_result = async {
+        let (mut reader, mut writer) = net::pipe()?;
+        writer.write_all("通过管道传递的消息".as_bytes()).await?;
+        drop(writer); // 关闭写端，读端随后读到 EOF
+        let mut message = String::new();
+        reader.read_to_string(&mut message).await?;
+        std::io::Result::Ok(message)
+    }
+    .await;
+    match pipe_result {
+        Ok(message) => println!("管道读取: {}", message),
+        Err(e) => println!("管道演示失败: {}", e),
     }
 }
 
-/// 演示异步同步
+// ============================================================================
+// 第六部分：异步同步原语
+// ============================================================================
+
+/// 演示异步同步原语
+///
+/// 原语的实现见 `sync` 模块：等待时挂起任务而不是阻塞线程。
 async fn async_sync_demo() {
     println!("\n=== 异步同步原语演示 ===");
     
-    let shared_counter = AsyncMutex::new(0);
+    let shared_counter = sync::Mutex::new(0);
     
-    async fn increment_counter(mutex: &AsyncMutex<i32>, id: u32) {
+    async fn increment_counter(mutex: &sync::Mutex<i32>, id: u32) {
         for i in 0..3 {
-            if let Ok(mut guard) = mutex.lock().await {
-                *guard += 1;
-                println!("任务 {} 第 {} 次增加计数器: {}", id, i + 1, *guard);
-            }
+            // 锁被占用时任务挂起，锁释放后按排队顺序被唤醒
+            let mut guard = mutex.lock().await;
+            *guard += 1;
+            println!("任务 {} 第 {} 次增加计数器: {}", id, i + 1, *guard);
+            drop(guard);
             // 模拟一些工作
             async_sleep(Duration::from_millis(5)).await;
         }
     }
     
-    println!("\n--- 共享状态访问 ---");
-    // 在实际应用中，这里会并发执行
-    increment_counter(&shared_counter, 1).await;
-    increment_counter(&shared_counter, 2).await;
+    println!("\n--- 共享状态访问（Mutex）---");
+    futures::join!(
+        increment_counter(&shared_counter, 1),
+        increment_counter(&shared_counter, 2),
+    );
+    println!("最终计数器值: {}", *shared_counter.lock().await);
     
-    if let Ok(final_value) = shared_counter.lock().await {
-        println!("最终计数器值: {}", *final_value);
-    }
+    println!("\n--- 读写锁（RwLock）---");
+    let config = sync::RwLock::new(String::from("v1"));
+    {
+        let (reader_a, reader_b) = futures::join!(config.read(), config.read());
+        println!("两个读者同时读取: {} / {}", *reader_a, *reader_b);
+    }
+    config.write().await.push_str("-patched");
+    println!("写者更新后: {}", *config.read().await);
+    
+    println!("\n--- 信号量限制并发（Semaphore）---");
+    let semaphore = sync::Semaphore::new(2);
+    let permit_a = semaphore.acquire().await.unwrap();
+    let permit_b = semaphore.acquire().await.unwrap();
+    println!("已获取 2 个许可，剩余: {}", semaphore.available_permits());
+    println!("try_acquire 结果: {:?}", semaphore.try_acquire().err());
+    drop((permit_a, permit_b));
+    println!("归还后剩余: {}", semaphore.available_permits());
+    
+    println!("\n--- 事件通知（Notify）---");
+    let notify = sync::Notify::new();
+    notify.notify_one(); // 没有等待者时保存一个许可
+    notify.notified().await;
+    println!("先通知、后等待也不会丢失唤醒");
+    futures::join!(
+        async {
+            notify.notified().await;
+            println!("等待者 A 被 notify_waiters 唤醒");
+        },
+        async {
+            notify.notified().await;
+            println!("等待者 B 被 notify_waiters 唤醒");
+        },
+        async { notify.notify_waiters() },
+    );
+    
+    println!("\n--- oneshot 与有界 mpsc 通道 ---");
+    let (reply_tx, reply_rx) = sync::oneshot::channel();
+    let (tx, mut rx) = sync::mpsc::channel(2);
+    let producer = async move {
+        for i in 1..=4 {
+            // 缓冲区满时在这里等待消费者腾出空位
+            tx.send(i).await.unwrap();
+            println!("生产者发送: {}", i);
+        }
+    };
+    let consumer = async move {
+        let mut sum = 0;
+        while let Some(value) = rx.recv().await {
+            println!("消费者接收: {}", value);
+            sum += value;
+        }
+        reply_tx.send(sum).unwrap();
+    };
+    futures::join!(producer, consumer);
+    println!("oneshot 回传的总和: {:?}", reply_rx.await);
+    
+    let (tx, rx) = sync::mpsc::channel::<i32>(1);
+    drop(rx);
+    println!("接收端关闭后 is_closed = {}，发送失败 = {}", tx.is_closed(), tx.send(5).await.is_err());
+    let (reply_tx, reply_rx) = sync::oneshot::channel::<i32>();
+    drop(reply_rx);
+    println!("oneshot 接收端关闭后 is_closed = {}", reply_tx.is_closed());
 }
 
 // ============================================================================
@@ -571,6 +686,7 @@ async fn common_pitfalls_demo() {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use test_executor::{explore, yield_now, TestExecutor};
     
     #[test]
     fn test_delay_future_creation() {
@@ -588,9 +704,66 @@ mod tests {
     
     #[test]
     fn test_async_mutex_creation() {
-        let mutex = AsyncMutex::new(42);
+        let mutex = sync::Mutex::new(42);
         // 测试异步 Mutex 创建
-        assert!(mutex.data.try_lock().is_ok());
+        assert!(mutex.try_lock().is_some());
+    }
+    
+    #[test]
+    fn test_counter_future_on_test_executor() {
+        let executor = TestExecutor::new(0);
+        // CounterFuture 每次 poll 都会自我唤醒，执行器应当持续调度直到完成
+        assert_eq!(executor.block_on(CounterFuture::new(3)), 3);
+    }
+    
+    #[test]
+    fn test_concurrent_tasks_in_virtual_time() {
+        let executor = TestExecutor::new(0);
+        let start = executor.now();
+        
+        let handles: Vec<_> = [50, 10, 30]
+            .into_iter()
+            .map(|ms| {
+                let executor_handle = executor.clone();
+                executor.spawn(async move {
+                    executor_handle.sleep(Duration::from_millis(ms)).await;
+                    ms
+                })
+            })
+            .collect();
+        
+        let results = executor.block_on(async {
+            let mut results = Vec::new();
+            for handle in handles {
+                results.push(handle.await);
+            }
+            results
+        });
+        
+        assert_eq!(results, vec![50, 10, 30]);
+        // 并发执行的总耗时等于最慢的任务，而不是所有任务之和
+        assert_eq!(executor.now() - start, Duration::from_millis(50));
+    }
+    
+    #[test]
+    fn test_async_mutex_no_lost_updates() {
+        // 每种交错顺序下，锁保护的读-改-写都不会丢失更新
+        explore(30, |executor| {
+            let counter = Arc::new(sync::Mutex::new(0));
+            
+            for _ in 0..3 {
+                let counter = Arc::clone(&counter);
+                executor.spawn(async move {
+                    for _ in 0..3 {
+                        *counter.lock().await += 1;
+                        yield_now().await;
+                    }
+                });
+            }
+            
+            executor.run_until_stalled();
+            assert_eq!(*counter.try_lock().unwrap(), 9);
+        });
     }
 }
 
@@ -599,48 +772,69 @@ mod tests {
 // ============================================================================
 
 /// 简单的异步运行时模拟（仅用于演示）
+///
+/// 在 Linux 上与 `reactor` 集成：Future 挂起且没有被唤醒时，
+/// 线程阻塞在 epoll 上等待 I/O 事件，而不是睡眠后盲目地重新轮询。
 struct SimpleExecutor;
 
+/// `block_on` 使用的 Waker：记录唤醒并打断 Reactor 的等待
+struct BlockOnWaker {
+    woken: AtomicBool,
+}
+
+impl Wake for BlockOnWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+    
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.woken.store(true, Ordering::Release);
+        #[cfg(target_os = "linux")]
+        reactor::Reactor::get().unpark();
+    }
+}
+
 impl SimpleExecutor {
     fn block_on<F: Future>(future: F) -> F::Output {
         // 这是一个极简的执行器实现，仅用于演示
         // 实际应用中应该使用 tokio、async-std 等成熟的运行时
-        
-        // 创建一个简单的 waker
-        use std::task::{RawWaker, RawWakerVTable};
-        
-        fn clone_raw(_: *const ()) -> RawWaker {
-            RawWaker::new(std::ptr::null(), &VTABLE)
-        }
-        
-        fn wake_raw(_: *const ()) {}
-        fn wake_by_ref_raw(_: *const ()) {}
-        fn drop_raw(_: *const ()) {}
-        
-        const VTABLE: RawWakerVTable = RawWakerVTable::new(
-            clone_raw,
-            wake_raw,
-            wake_by_ref_raw,
-            drop_raw,
-        );
-        
-        let raw_waker = RawWaker::new(std::ptr::null(), &VTABLE);
-        let waker = unsafe { Waker::from_raw(raw_waker) };
+        let waker_state = Arc::new(BlockOnWaker {
+            woken: AtomicBool::new(false),
+        });
+        let waker = Waker::from(Arc::clone(&waker_state));
         let mut context = Context::from_waker(&waker);
         
         let mut future = Box::pin(future);
         
-        // 简单的轮询循环
+        // 轮询循环：只有在没有被唤醒时才进入等待
         loop {
+            waker_state.woken.store(false, Ordering::Release);
             match future.as_mut().poll(&mut context) {
                 Poll::Ready(result) => return result,
                 Poll::Pending => {
-                    // 在实际运行时中，这里会等待事件或定时器
-                    thread::sleep(Duration::from_millis(1));
+                    if !waker_state.woken.load(Ordering::Acquire) {
+                        Self::park();
+                    }
                 }
             }
         }
     }
+    
+    /// 等待 I/O 事件或唤醒
+    #[cfg(target_os = "linux")]
+    fn park() {
+        // 超时作为兜底：多个线程共享同一个 Reactor 时，
+        // 本线程等待的 eventfd 通知可能被其他线程先一步取走
+        reactor::Reactor::get()
+            .turn(Some(Duration::from_millis(10)))
+            .expect("epoll_wait 失败");
+    }
+    
+    /// 没有 Reactor 的平台上退化为短暂睡眠
+    #[cfg(not(target_os = "linux"))]
+    fn park() {
+        thread::sleep(Duration::from_millis(1));
+    }
 }
 
 fn main() {
@@ -660,6 +854,8 @@ fn main() {
         concurrency_demo().await;
         timeout_demo().await;
         async_io_demo().await;
+        #[cfg(target_os = "linux")]
+        reactor_io_demo().await;
         async_sync_demo().await;
         real_world_demo().await;
         performance_demo().await;
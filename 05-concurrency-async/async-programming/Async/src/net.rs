@@ -0,0 +1,367 @@
+//! # 非阻塞网络与管道类型（仅 Linux）
+//!
+//! 在 `reactor` 模块之上实现真正的异步 I/O：
+//! - `TcpListener`：非阻塞 accept
+//! - `TcpStream`：非阻塞 connect / read / write，实现 `AsyncRead` / `AsyncWrite`
+//! - `pipe()`：非阻塞匿名管道，两端分别实现 `AsyncRead` / `AsyncWrite`
+//!
+//! 所有类型都不依赖 tokio，可以直接运行在本项目的 `SimpleExecutor` 上。
+
+use std::fs::File;
+use std::future::poll_fn;
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, SocketAddr, ToSocketAddrs};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::io::{AsyncRead, AsyncWrite};
+
+use crate::reactor::{Interest, Reactor, Registration};
+
+// ============================================================================
+// TCP
+// ============================================================================
+
+/// 异步 TCP 监听器
+#[derive(Debug)]
+pub struct TcpListener {
+    // 注册必须先于 socket 被 drop
+    registration: Registration,
+    inner: std::net::TcpListener,
+}
+
+impl TcpListener {
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let inner = std::net::TcpListener::bind(addr)?;
+        inner.set_nonblocking(true)?;
+        let registration = Reactor::get().register(inner.as_raw_fd())?;
+        Ok(TcpListener { registration, inner })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.local_addr()
+    }
+
+    /// 等待并接受一个新连接
+    pub async fn accept(&self) -> io::Result<(TcpStream, SocketAddr)> {
+        let (stream, addr) =
+            poll_fn(|cx| self.registration.poll_io(Interest::Read, cx, || self.inner.accept())).await?;
+        Ok((TcpStream::from_std(stream)?, addr))
+    }
+}
+
+/// 异步 TCP 连接
+#[derive(Debug)]
+pub struct TcpStream {
+    registration: Registration,
+    inner: std::net::TcpStream,
+}
+
+impl TcpStream {
+    /// 把标准库的 `TcpStream` 切换为非阻塞模式并注册到 Reactor
+    pub fn from_std(inner: std::net::TcpStream) -> io::Result<Self> {
+        inner.set_nonblocking(true)?;
+        let registration = Reactor::get().register(inner.as_raw_fd())?;
+        Ok(TcpStream { registration, inner })
+    }
+
+    /// 非阻塞地建立连接：`connect` 返回 `EINPROGRESS` 后等待 socket 可写
+    pub async fn connect(addr: SocketAddr) -> io::Result<Self> {
+        let domain = match addr {
+            SocketAddr::V4(_) => libc::AF_INET,
+            SocketAddr::V6(_) => libc::AF_INET6,
+        };
+        let fd = unsafe {
+            libc::socket(
+                domain,
+                libc::SOCK_STREAM | libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC,
+                0,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let socket = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let (storage, len) = raw_socket_addr(&addr);
+        let result = unsafe {
+            libc::connect(
+                fd,
+                &storage as *const libc::sockaddr_storage as *const libc::sockaddr,
+                len,
+            )
+        };
+        if result < 0 {
+            let err = io::Error::last_os_error();
+            if err.raw_os_error() != Some(libc::EINPROGRESS) {
+                return Err(err);
+            }
+        }
+
+        let stream = TcpStream::from_std(std::net::TcpStream::from(socket))?;
+        poll_fn(|cx| stream.registration.poll_ready(Interest::Write, cx)).await;
+
+        // 连接结果通过 SO_ERROR 返回
+        match stream.inner.take_error()? {
+            Some(err) => Err(err),
+            None => Ok(stream),
+        }
+    }
+
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.peer_addr()
+    }
+
+    fn poll_read_priv(&self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        self.registration
+            .poll_io(Interest::Read, cx, || (&self.inner).read(buf))
+    }
+
+    fn poll_write_priv(&self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        self.registration
+            .poll_io(Interest::Write, cx, || (&self.inner).write(buf))
+    }
+
+    fn poll_close_priv(&self) -> Poll<io::Result<()>> {
+        Poll::Ready(self.inner.shutdown(Shutdown::Write))
+    }
+}
+
+/// 把 `SocketAddr` 转换为 libc 的 sockaddr 表示
+fn raw_socket_addr(addr: &SocketAddr) -> (libc::sockaddr_storage, libc::socklen_t) {
+    let mut storage: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+    let len = match addr {
+        SocketAddr::V4(v4) => {
+            let sin = unsafe { &mut *(&mut storage as *mut _ as *mut libc::sockaddr_in) };
+            sin.sin_family = libc::AF_INET as libc::sa_family_t;
+            sin.sin_port = v4.port().to_be();
+            sin.sin_addr.s_addr = u32::from_ne_bytes(v4.ip().octets());
+            std::mem::size_of::<libc::sockaddr_in>()
+        }
+        SocketAddr::V6(v6) => {
+            let sin6 = unsafe { &mut *(&mut storage as *mut _ as *mut libc::sockaddr_in6) };
+            sin6.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+            sin6.sin6_port = v6.port().to_be();
+            sin6.sin6_flowinfo = v6.flowinfo();
+            sin6.sin6_addr.s6_addr = v6.ip().octets();
+            sin6.sin6_scope_id = v6.scope_id();
+            std::mem::size_of::<libc::sockaddr_in6>()
+        }
+    };
+    (storage, len as libc::socklen_t)
+}
+
+impl AsyncRead for TcpStream {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        self.poll_read_priv(cx, buf)
+    }
+}
+
+impl AsyncWrite for TcpStream {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        self.poll_write_priv(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_close_priv()
+    }
+}
+
+// 与标准库一样，`&TcpStream` 也可以读写，便于把读写两端交给不同的 Future
+impl AsyncRead for &TcpStream {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        self.poll_read_priv(cx, buf)
+    }
+}
+
+impl AsyncWrite for &TcpStream {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        self.poll_write_priv(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_close_priv()
+    }
+}
+
+// ============================================================================
+// 管道
+// ============================================================================
+
+/// 异步管道的读端
+#[derive(Debug)]
+pub struct PipeReader {
+    registration: Registration,
+    file: File,
+}
+
+/// 异步管道的写端，drop 后读端读到 EOF
+#[derive(Debug)]
+pub struct PipeWriter {
+    registration: Registration,
+    file: File,
+}
+
+/// 创建一对非阻塞的匿名管道
+pub fn pipe() -> io::Result<(PipeReader, PipeWriter)> {
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_NONBLOCK | libc::O_CLOEXEC) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let read_file = File::from(unsafe { OwnedFd::from_raw_fd(fds[0]) });
+    let write_file = File::from(unsafe { OwnedFd::from_raw_fd(fds[1]) });
+
+    let reader = PipeReader {
+        registration: Reactor::get().register(read_file.as_raw_fd())?,
+        file: read_file,
+    };
+    let writer = PipeWriter {
+        registration: Reactor::get().register(write_file.as_raw_fd())?,
+        file: write_file,
+    };
+    Ok((reader, writer))
+}
+
+impl AsyncRead for PipeReader {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        this.registration
+            .poll_io(Interest::Read, cx, || (&this.file).read(buf))
+    }
+}
+
+impl AsyncWrite for PipeWriter {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        this.registration
+            .poll_io(Interest::Write, cx, || (&this.file).write(buf))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        // 管道只能通过关闭 fd 来发出 EOF，由 drop 完成
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SimpleExecutor;
+    use futures::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// 回显一个连接上收到的所有数据，直到对端关闭写方向
+    async fn echo(stream: TcpStream) -> io::Result<u64> {
+        let (mut reader, mut writer) = (&stream, &stream);
+        let copied = futures::io::copy(&mut reader, &mut writer).await?;
+        writer.close().await?;
+        Ok(copied)
+    }
+
+    #[test]
+    fn test_loopback_echo_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        // 足够大的负载，保证读写两端都会遇到 WouldBlock
+        let payload: Vec<u8> = (0..4 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+
+        let server = async {
+            let (stream, peer) = listener.accept().await?;
+            assert_eq!(stream.peer_addr()?, peer);
+            echo(stream).await
+        };
+
+        let client = async {
+            let stream = TcpStream::connect(addr).await?;
+            let (mut reader, mut writer) = (&stream, &stream);
+            let send = async {
+                writer.write_all(&payload).await?;
+                writer.close().await
+            };
+            let mut received = Vec::new();
+            let receive = reader.read_to_end(&mut received);
+            let (sent, read) = futures::join!(send, receive);
+            sent?;
+            read?;
+            io::Result::Ok(received)
+        };
+
+        let (copied, received) = SimpleExecutor::block_on(async { futures::join!(server, client) });
+        assert_eq!(copied.unwrap(), payload.len() as u64);
+        assert_eq!(received.unwrap(), payload);
+    }
+
+    #[test]
+    fn test_echo_server_handles_multiple_clients() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client_count = 4;
+
+        let server = async {
+            let mut connections = Vec::new();
+            for _ in 0..client_count {
+                let (stream, _) = listener.accept().await?;
+                connections.push(echo(stream));
+            }
+            futures::future::try_join_all(connections).await
+        };
+
+        let clients = futures::future::try_join_all((0..client_count).map(|id| async move {
+            let mut stream = TcpStream::connect(addr).await?;
+            let message = format!("hello from client {}", id);
+            stream.write_all(message.as_bytes()).await?;
+            stream.close().await?;
+            let mut reply = String::new();
+            stream.read_to_string(&mut reply).await?;
+            io::Result::Ok((message, reply))
+        }));
+
+        let (served, replies) = SimpleExecutor::block_on(async { futures::join!(server, clients) });
+        assert_eq!(served.unwrap().len(), client_count);
+        for (message, reply) in replies.unwrap() {
+            assert_eq!(message, reply);
+        }
+    }
+
+    #[test]
+    fn test_connect_refused() {
+        // 先绑定再关闭，得到一个大概率无人监听的端口
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let result = SimpleExecutor::block_on(TcpStream::connect(addr));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::ConnectionRefused);
+    }
+
+    #[test]
+    fn test_pipe_round_trip() {
+        let (mut reader, mut writer) = pipe().unwrap();
+        let payload = vec![7u8; 256 * 1024];
+
+        let write = async {
+            writer.write_all(&payload).await?;
+            drop(writer);
+            io::Result::Ok(())
+        };
+        let mut received = Vec::new();
+        let read = reader.read_to_end(&mut received);
+
+        let (written, read) = SimpleExecutor::block_on(async { futures::join!(write, read) });
+        written.unwrap();
+        assert_eq!(read.unwrap(), payload.len());
+        assert_eq!(received, payload);
+    }
+}
@@ -0,0 +1,327 @@
+//! # 基于 epoll 的 I/O Reactor（仅 Linux）
+//!
+//! Reactor 是异步运行时中负责"等待 I/O 事件"的组件：
+//! - 文件描述符以非阻塞模式注册到 epoll（边沿触发）
+//! - I/O 操作返回 `WouldBlock` 时，任务把 Waker 交给 Reactor 后挂起
+//! - 执行器无事可做时调用 `Reactor::turn` 阻塞在 `epoll_wait` 上，
+//!   事件到达后唤醒对应的任务
+//! - 其他线程通过 eventfd（`Reactor::unpark`）打断阻塞中的 `turn`
+
+use std::collections::HashMap;
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::task::{Context, Poll, Waker, ready};
+use std::time::Duration;
+
+/// eventfd 在 epoll 中使用的 token
+const WAKE_TOKEN: u64 = 0;
+
+/// 单次 `epoll_wait` 最多处理的事件数
+const MAX_EVENTS: usize = 64;
+
+/// 把 libc 的 -1 返回值转换为 `io::Error`
+fn cvt(result: libc::c_int) -> io::Result<libc::c_int> {
+    if result < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(result)
+    }
+}
+
+/// 关注的就绪方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interest {
+    Read,
+    Write,
+}
+
+/// 某个方向上的就绪状态
+///
+/// `tick` 在每次事件到达时递增：I/O 操作返回 `WouldBlock` 后，
+/// 只有在期间没有新事件到达时才清除就绪标记，避免丢失唤醒。
+#[derive(Debug, Default)]
+struct ReadyState {
+    ready: bool,
+    tick: u64,
+    waker: Option<Waker>,
+}
+
+#[derive(Debug, Default)]
+struct IoSource {
+    read: Mutex<ReadyState>,
+    write: Mutex<ReadyState>,
+}
+
+impl IoSource {
+    fn state(&self, interest: Interest) -> &Mutex<ReadyState> {
+        match interest {
+            Interest::Read => &self.read,
+            Interest::Write => &self.write,
+        }
+    }
+
+    fn set_ready(&self, interest: Interest) {
+        let waker = {
+            let mut state = self.state(interest).lock().unwrap();
+            state.ready = true;
+            state.tick += 1;
+            state.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+/// 基于 epoll 的 Reactor
+#[derive(Debug)]
+pub struct Reactor {
+    epoll: OwnedFd,
+    event_fd: OwnedFd,
+    next_token: AtomicU64,
+    sources: Mutex<HashMap<u64, Arc<IoSource>>>,
+}
+
+impl Reactor {
+    /// 进程内共享的全局 Reactor
+    pub fn get() -> &'static Reactor {
+        static REACTOR: OnceLock<Reactor> = OnceLock::new();
+        REACTOR.get_or_init(|| Reactor::new().expect("创建 epoll reactor 失败"))
+    }
+
+    fn new() -> io::Result<Self> {
+        let epoll = cvt(unsafe { libc::epoll_create1(libc::EPOLL_CLOEXEC) })?;
+        let epoll = unsafe { OwnedFd::from_raw_fd(epoll) };
+
+        let event_fd = cvt(unsafe { libc::eventfd(0, libc::EFD_CLOEXEC | libc::EFD_NONBLOCK) })?;
+        let event_fd = unsafe { OwnedFd::from_raw_fd(event_fd) };
+
+        let reactor = Reactor {
+            epoll,
+            event_fd,
+            next_token: AtomicU64::new(WAKE_TOKEN + 1),
+            sources: Mutex::new(HashMap::new()),
+        };
+        reactor.ctl(
+            libc::EPOLL_CTL_ADD,
+            reactor.event_fd.as_raw_fd(),
+            libc::EPOLLIN as u32,
+            WAKE_TOKEN,
+        )?;
+        Ok(reactor)
+    }
+
+    fn ctl(&self, op: libc::c_int, fd: RawFd, events: u32, token: u64) -> io::Result<()> {
+        let mut event = libc::epoll_event { events, u64: token };
+        cvt(unsafe { libc::epoll_ctl(self.epoll.as_raw_fd(), op, fd, &mut event) })?;
+        Ok(())
+    }
+
+    /// 注册一个非阻塞的文件描述符，同时关注读写两个方向（边沿触发）
+    pub fn register(&self, fd: RawFd) -> io::Result<Registration> {
+        let token = self.next_token.fetch_add(1, Ordering::Relaxed);
+        let source = Arc::new(IoSource::default());
+        self.sources
+            .lock()
+            .unwrap()
+            .insert(token, Arc::clone(&source));
+
+        let events = libc::EPOLLIN | libc::EPOLLOUT | libc::EPOLLRDHUP | libc::EPOLLET;
+        if let Err(err) = self.ctl(libc::EPOLL_CTL_ADD, fd, events as u32, token) {
+            self.sources.lock().unwrap().remove(&token);
+            return Err(err);
+        }
+
+        Ok(Registration { fd, token, source })
+    }
+
+    fn deregister(&self, fd: RawFd, token: u64) {
+        // fd 可能已经出错，注销失败时也没有更多可做的事
+        let _ = self.ctl(libc::EPOLL_CTL_DEL, fd, 0, token);
+        self.sources.lock().unwrap().remove(&token);
+    }
+
+    /// 等待 I/O 事件并唤醒对应的任务，返回收到的事件数。
+    ///
+    /// `timeout` 为 `None` 时一直阻塞，直到有事件到达或被 `unpark` 打断。
+    pub fn turn(&self, timeout: Option<Duration>) -> io::Result<usize> {
+        let mut events = [libc::epoll_event { events: 0, u64: 0 }; MAX_EVENTS];
+        let timeout_ms = match timeout {
+            // 向上取整，避免亚毫秒的超时变成忙轮询
+            Some(timeout) => timeout.as_nanos().div_ceil(1_000_000).min(i32::MAX as u128) as i32,
+            None => -1,
+        };
+
+        let count = unsafe {
+            libc::epoll_wait(
+                self.epoll.as_raw_fd(),
+                events.as_mut_ptr(),
+                MAX_EVENTS as libc::c_int,
+                timeout_ms,
+            )
+        };
+        let count = match cvt(count) {
+            Ok(count) => count as usize,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => return Ok(0),
+            Err(err) => return Err(err),
+        };
+
+        for event in &events[..count] {
+            let (flags, token) = (event.events, event.u64);
+
+            if token == WAKE_TOKEN {
+                self.drain_event_fd();
+                continue;
+            }
+
+            let source = self.sources.lock().unwrap().get(&token).cloned();
+            let Some(source) = source else { continue };
+
+            let read_flags = libc::EPOLLIN | libc::EPOLLRDHUP | libc::EPOLLHUP | libc::EPOLLERR;
+            let write_flags = libc::EPOLLOUT | libc::EPOLLHUP | libc::EPOLLERR;
+            if flags & read_flags as u32 != 0 {
+                source.set_ready(Interest::Read);
+            }
+            if flags & write_flags as u32 != 0 {
+                source.set_ready(Interest::Write);
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// 唤醒阻塞在 `turn` 中的线程
+    pub fn unpark(&self) {
+        let value: u64 = 1;
+        unsafe {
+            libc::write(
+                self.event_fd.as_raw_fd(),
+                &value as *const u64 as *const libc::c_void,
+                std::mem::size_of::<u64>(),
+            );
+        }
+    }
+
+    fn drain_event_fd(&self) {
+        let mut value: u64 = 0;
+        unsafe {
+            libc::read(
+                self.event_fd.as_raw_fd(),
+                &mut value as *mut u64 as *mut libc::c_void,
+                std::mem::size_of::<u64>(),
+            );
+        }
+    }
+}
+
+/// 文件描述符在 Reactor 中的注册，drop 时自动注销
+///
+/// 持有者必须保证在关闭 fd 之前先 drop 本注册（结构体中把它放在 fd 前面）。
+#[derive(Debug)]
+pub struct Registration {
+    fd: RawFd,
+    token: u64,
+    source: Arc<IoSource>,
+}
+
+impl Registration {
+    /// 等待某个方向就绪，返回就绪时的 tick
+    pub fn poll_ready(&self, interest: Interest, cx: &mut Context<'_>) -> Poll<u64> {
+        let mut state = self.source.state(interest).lock().unwrap();
+        if state.ready {
+            Poll::Ready(state.tick)
+        } else {
+            state.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+
+    /// 清除就绪标记（仅当 tick 之后没有新事件到达）
+    pub fn clear_ready(&self, interest: Interest, tick: u64) {
+        let mut state = self.source.state(interest).lock().unwrap();
+        if state.tick == tick {
+            state.ready = false;
+        }
+    }
+
+    /// 执行一次非阻塞 I/O：遇到 `WouldBlock` 就清除就绪标记并等待下一次事件
+    pub fn poll_io<R>(
+        &self,
+        interest: Interest,
+        cx: &mut Context<'_>,
+        mut op: impl FnMut() -> io::Result<R>,
+    ) -> Poll<io::Result<R>> {
+        loop {
+            let tick = ready!(self.poll_ready(interest, cx));
+            match op() {
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                    self.clear_ready(interest, tick);
+                }
+                result => return Poll::Ready(result),
+            }
+        }
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        Reactor::get().deregister(self.fd, self.token);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::os::unix::net::UnixStream;
+
+    /// 反复驱动 Reactor，直到 `registration` 在 `interest` 方向就绪
+    fn wait_ready(registration: &Registration, interest: Interest) -> u64 {
+        let mut cx = Context::from_waker(Waker::noop());
+        for _ in 0..100 {
+            if let Poll::Ready(tick) = registration.poll_ready(interest, &mut cx) {
+                return tick;
+            }
+            Reactor::get().turn(Some(Duration::from_millis(10))).unwrap();
+        }
+        panic!("等待就绪超时");
+    }
+
+    #[test]
+    fn test_readiness_follows_epoll_events() {
+        let (mut writer, reader) = UnixStream::pair().unwrap();
+        reader.set_nonblocking(true).unwrap();
+        let registration = Reactor::get().register(reader.as_raw_fd()).unwrap();
+
+        // 新注册的 socket 可写但不可读
+        wait_ready(&registration, Interest::Write);
+        let mut cx = Context::from_waker(Waker::noop());
+        assert!(registration.poll_ready(Interest::Read, &mut cx).is_pending());
+
+        writer.write_all(b"ping").unwrap();
+        let tick = wait_ready(&registration, Interest::Read);
+
+        // 清除后重新进入等待状态
+        registration.clear_ready(Interest::Read, tick);
+        assert!(registration.poll_ready(Interest::Read, &mut cx).is_pending());
+    }
+
+    #[test]
+    fn test_stale_tick_does_not_clear_new_readiness() {
+        let (mut writer, reader) = UnixStream::pair().unwrap();
+        reader.set_nonblocking(true).unwrap();
+        let registration = Reactor::get().register(reader.as_raw_fd()).unwrap();
+
+        writer.write_all(b"a").unwrap();
+        let stale_tick = wait_ready(&registration, Interest::Read);
+
+        // 模拟 "WouldBlock 之后、清除之前" 又到达了一个事件
+        registration.source.set_ready(Interest::Read);
+        registration.clear_ready(Interest::Read, stale_tick);
+
+        let mut cx = Context::from_waker(Waker::noop());
+        assert!(registration.poll_ready(Interest::Read, &mut cx).is_ready());
+    }
+}
@@ -0,0 +1,1308 @@
+//! # 异步同步原语
+//!
+//! 与 `std::sync::Mutex` 不同，这里的原语在等待时不会阻塞线程：
+//! 等待者把 Waker 放进队列后返回 `Poll::Pending`，资源释放时再被唤醒。
+//! - `Semaphore`：公平（FIFO）的计数信号量，是其他原语的基础
+//! - `Mutex` / `RwLock`：基于信号量实现，先到先得，写者不会被读者饿死
+//! - `Notify`：任务之间的事件通知
+//! - `oneshot`：一次性的单值通道
+//! - `mpsc`：有界多生产者单消费者通道，用信号量实现背压
+//!
+//! 内部状态由 `std::sync::Mutex` 保护，但只在很短的临界区内持有，
+//! 从不跨越 `.await`，因此可以运行在任意执行器上。
+
+use std::cell::UnsafeCell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::task::{Context, Poll, Waker};
+
+/// 唤醒一组 Waker（在释放内部锁之后调用）
+fn wake_all(wakers: Vec<Waker>) {
+    for waker in wakers {
+        waker.wake();
+    }
+}
+
+// ============================================================================
+// Semaphore
+// ============================================================================
+
+/// 信号量已关闭
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AcquireError(());
+
+impl fmt::Display for AcquireError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "信号量已关闭")
+    }
+}
+
+impl std::error::Error for AcquireError {}
+
+/// `try_acquire` 失败的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryAcquireError {
+    Closed,
+    NoPermits,
+}
+
+/// 排队中的 `acquire` 请求
+#[derive(Debug)]
+struct Waiter {
+    needed: usize,
+    state: StdMutex<WaiterState>,
+}
+
+#[derive(Debug, Default)]
+struct WaiterState {
+    granted: bool,
+    waker: Option<Waker>,
+}
+
+#[derive(Debug)]
+struct SemaphoreState {
+    permits: usize,
+    closed: bool,
+    waiters: VecDeque<Arc<Waiter>>,
+}
+
+impl SemaphoreState {
+    /// 按 FIFO 顺序把许可分配给队首的等待者。
+    /// 队首拿不到时后面的也不分配，保证公平。
+    fn grant_waiters(&mut self) -> Vec<Waker> {
+        let mut wakers = Vec::new();
+        while let Some(front) = self.waiters.front() {
+            if front.needed > self.permits {
+                break;
+            }
+            self.permits -= front.needed;
+            let waiter = self.waiters.pop_front().unwrap();
+            let mut state = waiter.state.lock().unwrap();
+            state.granted = true;
+            wakers.extend(state.waker.take());
+        }
+        wakers
+    }
+}
+
+/// 公平的异步计数信号量
+#[derive(Debug)]
+pub struct Semaphore {
+    state: StdMutex<SemaphoreState>,
+}
+
+impl Semaphore {
+    pub fn new(permits: usize) -> Self {
+        Semaphore {
+            state: StdMutex::new(SemaphoreState {
+                permits,
+                closed: false,
+                waiters: VecDeque::new(),
+            }),
+        }
+    }
+
+    pub fn available_permits(&self) -> usize {
+        self.state.lock().unwrap().permits
+    }
+
+    /// 获取一个许可
+    pub fn acquire(&self) -> Acquire<'_> {
+        self.acquire_many(1)
+    }
+
+    /// 一次获取 `permits` 个许可（要么全部拿到，要么继续排队）
+    pub fn acquire_many(&self, permits: usize) -> Acquire<'_> {
+        Acquire {
+            semaphore: self,
+            needed: permits,
+            waiter: None,
+        }
+    }
+
+    /// 不等待地尝试获取一个许可；有人排队时同样失败，不允许插队
+    pub fn try_acquire(&self) -> Result<SemaphorePermit<'_>, TryAcquireError> {
+        let mut state = self.state.lock().unwrap();
+        if state.closed {
+            return Err(TryAcquireError::Closed);
+        }
+        if !state.waiters.is_empty() || state.permits == 0 {
+            return Err(TryAcquireError::NoPermits);
+        }
+        state.permits -= 1;
+        Ok(SemaphorePermit {
+            semaphore: self,
+            permits: 1,
+        })
+    }
+
+    /// 归还（或新增）许可，并唤醒能够满足的等待者
+    pub fn add_permits(&self, permits: usize) {
+        let wakers = {
+            let mut state = self.state.lock().unwrap();
+            state.permits += permits;
+            state.grant_waiters()
+        };
+        wake_all(wakers);
+    }
+
+    /// 关闭信号量：所有排队和之后的 `acquire` 都返回 `AcquireError`
+    pub fn close(&self) {
+        let waiters = {
+            let mut state = self.state.lock().unwrap();
+            state.closed = true;
+            std::mem::take(&mut state.waiters)
+        };
+        let wakers = waiters
+            .iter()
+            .filter_map(|waiter| waiter.state.lock().unwrap().waker.take())
+            .collect();
+        wake_all(wakers);
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.state.lock().unwrap().closed
+    }
+}
+
+/// `Semaphore::acquire` 返回的 Future
+///
+/// 在排队期间被 drop 时会退出队列；如果许可已经分配给它，则原样归还。
+#[derive(Debug)]
+pub struct Acquire<'a> {
+    semaphore: &'a Semaphore,
+    needed: usize,
+    waiter: Option<Arc<Waiter>>,
+}
+
+impl<'a> Future for Acquire<'a> {
+    type Output = Result<SemaphorePermit<'a>, AcquireError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let permit = SemaphorePermit {
+            semaphore: this.semaphore,
+            permits: this.needed,
+        };
+        let mut state = this.semaphore.state.lock().unwrap();
+
+        match &this.waiter {
+            None => {
+                if state.closed {
+                    std::mem::forget(permit);
+                    return Poll::Ready(Err(AcquireError(())));
+                }
+                // 只有没人排队时才能直接拿走许可
+                if state.waiters.is_empty() && state.permits >= this.needed {
+                    state.permits -= this.needed;
+                    return Poll::Ready(Ok(permit));
+                }
+                std::mem::forget(permit);
+
+                let waiter = Arc::new(Waiter {
+                    needed: this.needed,
+                    state: StdMutex::new(WaiterState {
+                        granted: false,
+                        waker: Some(cx.waker().clone()),
+                    }),
+                });
+                state.waiters.push_back(Arc::clone(&waiter));
+                this.waiter = Some(waiter);
+                Poll::Pending
+            }
+            Some(waiter) => {
+                let mut waiter_state = waiter.state.lock().unwrap();
+                if waiter_state.granted {
+                    drop(waiter_state);
+                    this.waiter = None;
+                    return Poll::Ready(Ok(permit));
+                }
+                std::mem::forget(permit);
+
+                if state.closed {
+                    drop(waiter_state);
+                    this.waiter = None;
+                    return Poll::Ready(Err(AcquireError(())));
+                }
+                waiter_state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Drop for Acquire<'_> {
+    fn drop(&mut self) {
+        let Some(waiter) = self.waiter.take() else {
+            return;
+        };
+
+        let wakers = {
+            let mut state = self.semaphore.state.lock().unwrap();
+            if waiter.state.lock().unwrap().granted {
+                // 许可已经分配但没有被取走，归还给后面的等待者
+                state.permits += self.needed;
+            } else {
+                state.waiters.retain(|w| !Arc::ptr_eq(w, &waiter));
+            }
+            // 队首离开后，后面的等待者可能已经可以满足
+            state.grant_waiters()
+        };
+        wake_all(wakers);
+    }
+}
+
+/// 信号量许可，drop 时自动归还
+#[derive(Debug)]
+pub struct SemaphorePermit<'a> {
+    semaphore: &'a Semaphore,
+    permits: usize,
+}
+
+impl SemaphorePermit<'_> {
+    /// 放弃归还许可（许可由调用方之后通过 `add_permits` 手动归还）
+    pub fn forget(mut self) {
+        self.permits = 0;
+    }
+}
+
+impl Drop for SemaphorePermit<'_> {
+    fn drop(&mut self) {
+        if self.permits > 0 {
+            self.semaphore.add_permits(self.permits);
+        }
+    }
+}
+
+// ============================================================================
+// Mutex
+// ============================================================================
+
+/// 公平的异步互斥锁
+///
+/// `lock().await` 在锁被占用时挂起任务而不是阻塞线程，
+/// 守卫可以安全地跨越 `.await` 持有。
+pub struct Mutex<T: ?Sized> {
+    semaphore: Semaphore,
+    data: UnsafeCell<T>,
+}
+
+// 与 std::sync::Mutex 相同：只要 T: Send，锁就可以在线程之间共享
+unsafe impl<T: ?Sized + Send> Send for Mutex<T> {}
+unsafe impl<T: ?Sized + Send> Sync for Mutex<T> {}
+
+impl<T> Mutex<T> {
+    pub fn new(data: T) -> Self {
+        Mutex {
+            semaphore: Semaphore::new(1),
+            data: UnsafeCell::new(data),
+        }
+    }
+}
+
+impl<T: ?Sized> Mutex<T> {
+    pub async fn lock(&self) -> MutexGuard<'_, T> {
+        let permit = self
+            .semaphore
+            .acquire()
+            .await
+            .expect("Mutex 的信号量不会被关闭");
+        MutexGuard {
+            mutex: self,
+            _permit: permit,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        let permit = self.semaphore.try_acquire().ok()?;
+        Some(MutexGuard {
+            mutex: self,
+            _permit: permit,
+            _marker: PhantomData,
+        })
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for Mutex<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.try_lock() {
+            Some(guard) => f.debug_struct("Mutex").field("data", &&*guard).finish(),
+            None => f.debug_struct("Mutex").field("data", &"<locked>").finish(),
+        }
+    }
+}
+
+/// `Mutex::lock` 返回的守卫，drop 时释放锁
+pub struct MutexGuard<'a, T: ?Sized> {
+    mutex: &'a Mutex<T>,
+    _permit: SemaphorePermit<'a>,
+    // 守卫提供 &mut T，因此只有 T: Sync 时守卫才是 Sync
+    _marker: PhantomData<&'a mut T>,
+}
+
+impl<T: ?Sized> Deref for MutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // 持有唯一的许可，保证独占访问
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for MutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
+
+// ============================================================================
+// RwLock
+// ============================================================================
+
+/// 读者的最大并发数；写者一次性拿走全部许可
+const MAX_READERS: usize = usize::MAX >> 3;
+
+/// 公平的异步读写锁
+///
+/// 请求按到达顺序排队：写者排在读者前面时，后来的读者必须等待，
+/// 因此写者不会被源源不断的读者饿死。
+pub struct RwLock<T: ?Sized> {
+    semaphore: Semaphore,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send> Send for RwLock<T> {}
+unsafe impl<T: ?Sized + Send + Sync> Sync for RwLock<T> {}
+
+impl<T> RwLock<T> {
+    pub fn new(data: T) -> Self {
+        RwLock {
+            semaphore: Semaphore::new(MAX_READERS),
+            data: UnsafeCell::new(data),
+        }
+    }
+}
+
+impl<T: ?Sized> RwLock<T> {
+    pub async fn read(&self) -> RwLockReadGuard<'_, T> {
+        let permit = self
+            .semaphore
+            .acquire()
+            .await
+            .expect("RwLock 的信号量不会被关闭");
+        RwLockReadGuard {
+            lock: self,
+            _permit: permit,
+        }
+    }
+
+    pub async fn write(&self) -> RwLockWriteGuard<'_, T> {
+        let permit = self
+            .semaphore
+            .acquire_many(MAX_READERS)
+            .await
+            .expect("RwLock 的信号量不会被关闭");
+        RwLockWriteGuard {
+            lock: self,
+            _permit: permit,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// 读守卫，可以与其他读守卫同时存在
+pub struct RwLockReadGuard<'a, T: ?Sized> {
+    lock: &'a RwLock<T>,
+    _permit: SemaphorePermit<'a>,
+}
+
+impl<T: ?Sized> Deref for RwLockReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+/// 写守卫，持有期间没有任何其他守卫
+pub struct RwLockWriteGuard<'a, T: ?Sized> {
+    lock: &'a RwLock<T>,
+    _permit: SemaphorePermit<'a>,
+    _marker: PhantomData<&'a mut T>,
+}
+
+impl<T: ?Sized> Deref for RwLockWriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for RwLockWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+// ============================================================================
+// Notify
+// ============================================================================
+
+/// 等待者收到的通知类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Notification {
+    One,
+    All,
+}
+
+#[derive(Debug, Default)]
+struct NotifyWaiter {
+    state: StdMutex<(Option<Notification>, Option<Waker>)>,
+}
+
+#[derive(Debug, Default)]
+struct NotifyState {
+    permit: bool,
+    waiters: VecDeque<Arc<NotifyWaiter>>,
+}
+
+/// 任务间的事件通知
+///
+/// - `notify_one`：唤醒最早等待的任务；没有等待者时保存一个许可，
+///   下一次 `notified().await` 会立即返回
+/// - `notify_waiters`：唤醒当前所有已经在等待的任务，不保存许可
+#[derive(Debug, Default)]
+pub struct Notify {
+    state: StdMutex<NotifyState>,
+}
+
+impl Notify {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn notified(&self) -> Notified<'_> {
+        Notified {
+            notify: self,
+            waiter: None,
+        }
+    }
+
+    pub fn notify_one(&self) {
+        let waker = {
+            let mut state = self.state.lock().unwrap();
+            match state.waiters.pop_front() {
+                Some(waiter) => {
+                    let mut waiter_state = waiter.state.lock().unwrap();
+                    waiter_state.0 = Some(Notification::One);
+                    waiter_state.1.take()
+                }
+                None => {
+                    state.permit = true;
+                    None
+                }
+            }
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    pub fn notify_waiters(&self) {
+        let waiters = std::mem::take(&mut self.state.lock().unwrap().waiters);
+        let wakers = waiters
+            .iter()
+            .filter_map(|waiter| {
+                let mut waiter_state = waiter.state.lock().unwrap();
+                waiter_state.0 = Some(Notification::All);
+                waiter_state.1.take()
+            })
+            .collect();
+        wake_all(wakers);
+    }
+}
+
+/// `Notify::notified` 返回的 Future，第一次被 poll 时开始排队
+#[derive(Debug)]
+pub struct Notified<'a> {
+    notify: &'a Notify,
+    waiter: Option<Arc<NotifyWaiter>>,
+}
+
+impl Future for Notified<'_> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let this = self.get_mut();
+
+        match &this.waiter {
+            None => {
+                let mut state = this.notify.state.lock().unwrap();
+                if state.permit {
+                    state.permit = false;
+                    return Poll::Ready(());
+                }
+                let waiter = Arc::new(NotifyWaiter::default());
+                waiter.state.lock().unwrap().1 = Some(cx.waker().clone());
+                state.waiters.push_back(Arc::clone(&waiter));
+                this.waiter = Some(waiter);
+                Poll::Pending
+            }
+            Some(waiter) => {
+                let mut waiter_state = waiter.state.lock().unwrap();
+                if waiter_state.0.is_some() {
+                    drop(waiter_state);
+                    this.waiter = None;
+                    return Poll::Ready(());
+                }
+                waiter_state.1 = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Drop for Notified<'_> {
+    fn drop(&mut self) {
+        let Some(waiter) = self.waiter.take() else {
+            return;
+        };
+
+        let notification = {
+            let mut state = self.notify.state.lock().unwrap();
+            state.waiters.retain(|w| !Arc::ptr_eq(w, &waiter));
+            waiter.state.lock().unwrap().0
+        };
+        // 收到了 notify_one 却没来得及处理，把通知转交给下一个等待者
+        if notification == Some(Notification::One) {
+            self.notify.notify_one();
+        }
+    }
+}
+
+// ============================================================================
+// oneshot
+// ============================================================================
+
+/// 一次性的单值通道
+pub mod oneshot {
+    use super::*;
+
+    /// 发送端在发送之前被 drop
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct RecvError(());
+
+    impl fmt::Display for RecvError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "发送端已关闭")
+        }
+    }
+
+    impl std::error::Error for RecvError {}
+
+    #[derive(Debug)]
+    struct Inner<T> {
+        value: Option<T>,
+        rx_waker: Option<Waker>,
+        tx_dropped: bool,
+        rx_dropped: bool,
+    }
+
+    pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+        let inner = Arc::new(StdMutex::new(Inner {
+            value: None,
+            rx_waker: None,
+            tx_dropped: false,
+            rx_dropped: false,
+        }));
+        (
+            Sender {
+                inner: Arc::clone(&inner),
+            },
+            Receiver { inner },
+        )
+    }
+
+    #[derive(Debug)]
+    pub struct Sender<T> {
+        inner: Arc<StdMutex<Inner<T>>>,
+    }
+
+    impl<T> Sender<T> {
+        /// 发送值；接收端已经被 drop 时把值原样返回
+        pub fn send(self, value: T) -> Result<(), T> {
+            let waker = {
+                let mut inner = self.inner.lock().unwrap();
+                if inner.rx_dropped {
+                    return Err(value);
+                }
+                inner.value = Some(value);
+                inner.rx_waker.take()
+            };
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+            Ok(())
+        }
+
+        pub fn is_closed(&self) -> bool {
+            self.inner.lock().unwrap().rx_dropped
+        }
+    }
+
+    impl<T> Drop for Sender<T> {
+        fn drop(&mut self) {
+            let waker = {
+                let mut inner = self.inner.lock().unwrap();
+                inner.tx_dropped = true;
+                inner.rx_waker.take()
+            };
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        }
+    }
+
+    /// 接收端，本身就是一个 Future
+    #[derive(Debug)]
+    pub struct Receiver<T> {
+        inner: Arc<StdMutex<Inner<T>>>,
+    }
+
+    impl<T> Future for Receiver<T> {
+        type Output = Result<T, RecvError>;
+
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+            let mut inner = self.inner.lock().unwrap();
+            if let Some(value) = inner.value.take() {
+                return Poll::Ready(Ok(value));
+            }
+            if inner.tx_dropped {
+                return Poll::Ready(Err(RecvError(())));
+            }
+            inner.rx_waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+
+    impl<T> Drop for Receiver<T> {
+        fn drop(&mut self) {
+            self.inner.lock().unwrap().rx_dropped = true;
+        }
+    }
+}
+
+// ============================================================================
+// mpsc
+// ============================================================================
+
+/// 有界多生产者单消费者通道
+///
+/// 缓冲区的空位由信号量表示：发送前获取一个许可，接收后归还一个许可，
+/// 缓冲区满时发送方在信号量上排队，形成背压。
+pub mod mpsc {
+    use super::*;
+    use std::future::poll_fn;
+
+    /// 接收端已关闭，未发送的值原样返回
+    pub struct SendError<T>(pub T);
+
+    impl<T> fmt::Debug for SendError<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("SendError").finish_non_exhaustive()
+        }
+    }
+
+    impl<T> fmt::Display for SendError<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "接收端已关闭")
+        }
+    }
+
+    impl<T> std::error::Error for SendError<T> {}
+
+    #[derive(Debug)]
+    struct State<T> {
+        buffer: VecDeque<T>,
+        senders: usize,
+        /// 接收端调用了 `close`：缓冲区取空后通道即结束，不再等待发送端
+        closed: bool,
+        rx_waker: Option<Waker>,
+    }
+
+    #[derive(Debug)]
+    struct Chan<T> {
+        slots: Semaphore,
+        state: StdMutex<State<T>>,
+    }
+
+    pub fn channel<T>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+        assert!(capacity > 0, "通道容量必须大于 0");
+        let chan = Arc::new(Chan {
+            slots: Semaphore::new(capacity),
+            state: StdMutex::new(State {
+                buffer: VecDeque::with_capacity(capacity),
+                senders: 1,
+                closed: false,
+                rx_waker: None,
+            }),
+        });
+        (
+            Sender {
+                chan: Arc::clone(&chan),
+            },
+            Receiver { chan },
+        )
+    }
+
+    #[derive(Debug)]
+    pub struct Sender<T> {
+        chan: Arc<Chan<T>>,
+    }
+
+    impl<T> Sender<T> {
+        /// 发送一个值；缓冲区满时等待空位，接收端关闭时返回错误
+        pub async fn send(&self, value: T) -> Result<(), SendError<T>> {
+            match self.chan.slots.acquire().await {
+                Ok(permit) => {
+                    // 空位由接收端在取走元素后归还
+                    permit.forget();
+                    let waker = {
+                        let mut state = self.chan.state.lock().unwrap();
+                        // 拿到许可之后接收端才关闭：接收端可能已经返回了 None，不能再放入缓冲区
+                        if state.closed {
+                            return Err(SendError(value));
+                        }
+                        state.buffer.push_back(value);
+                        state.rx_waker.take()
+                    };
+                    if let Some(waker) = waker {
+                        waker.wake();
+                    }
+                    Ok(())
+                }
+                Err(_) => Err(SendError(value)),
+            }
+        }
+
+        pub fn is_closed(&self) -> bool {
+            self.chan.slots.is_closed()
+        }
+    }
+
+    impl<T> Clone for Sender<T> {
+        fn clone(&self) -> Self {
+            self.chan.state.lock().unwrap().senders += 1;
+            Sender {
+                chan: Arc::clone(&self.chan),
+            }
+        }
+    }
+
+    impl<T> Drop for Sender<T> {
+        fn drop(&mut self) {
+            let waker = {
+                let mut state = self.chan.state.lock().unwrap();
+                state.senders -= 1;
+                if state.senders == 0 {
+                    state.rx_waker.take()
+                } else {
+                    None
+                }
+            };
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        }
+    }
+
+    #[derive(Debug)]
+    pub struct Receiver<T> {
+        chan: Arc<Chan<T>>,
+    }
+
+    impl<T> Receiver<T> {
+        /// 接收下一个值；缓冲区为空，且所有发送端都已关闭或接收端调用过 `close` 时返回 `None`
+        pub async fn recv(&mut self) -> Option<T> {
+            poll_fn(|cx| self.poll_recv(cx)).await
+        }
+
+        pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+            let mut state = self.chan.state.lock().unwrap();
+            if let Some(value) = state.buffer.pop_front() {
+                drop(state);
+                self.chan.slots.add_permits(1);
+                return Poll::Ready(Some(value));
+            }
+            if state.senders == 0 || state.closed {
+                return Poll::Ready(None);
+            }
+            state.rx_waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+
+        /// 关闭通道：之后的发送都会失败，已缓冲的值仍可以接收，取空后 `recv` 返回 `None`
+        pub fn close(&mut self) {
+            self.chan.state.lock().unwrap().closed = true;
+            self.chan.slots.close();
+        }
+    }
+
+    impl<T> Drop for Receiver<T> {
+        fn drop(&mut self) {
+            self.close();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SimpleExecutor;
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+    use std::thread;
+    use std::time::Duration;
+    use test_executor::{explore, yield_now};
+
+    // ------------------------------------------------------------------
+    // 交错探索：在确定性执行器上用大量种子穷举调度顺序
+    // ------------------------------------------------------------------
+
+    #[test]
+    fn test_mutex_mutual_exclusion_across_await() {
+        explore(200, |executor| {
+            let mutex = Rc::new(Mutex::new(0));
+            let in_critical = Rc::new(Cell::new(false));
+
+            for _ in 0..4 {
+                let mutex = Rc::clone(&mutex);
+                let in_critical = Rc::clone(&in_critical);
+                executor.spawn(async move {
+                    for _ in 0..3 {
+                        let mut guard = mutex.lock().await;
+                        assert!(!in_critical.replace(true), "两个任务同时进入临界区");
+                        let value = *guard;
+                        // 持有守卫跨越 await，其他任务必须挂起等待
+                        yield_now().await;
+                        *guard = value + 1;
+                        in_critical.set(false);
+                    }
+                });
+            }
+
+            executor.run_until_stalled();
+            assert_eq!(*mutex.try_lock().unwrap(), 12);
+        });
+    }
+
+    #[test]
+    fn test_mutex_is_fair() {
+        explore(100, |executor| {
+            let mutex = Rc::new(Mutex::new(()));
+            let queued = Rc::new(RefCell::new(Vec::new()));
+            let acquired = Rc::new(RefCell::new(Vec::new()));
+
+            // 先占住锁，让后面的任务都进入排队
+            let holder = mutex.try_lock();
+            for id in 0..5 {
+                let (mutex, queued, acquired) =
+                    (Rc::clone(&mutex), Rc::clone(&queued), Rc::clone(&acquired));
+                executor.spawn(async move {
+                    queued.borrow_mut().push(id);
+                    let _guard = mutex.lock().await;
+                    acquired.borrow_mut().push(id);
+                    yield_now().await;
+                });
+            }
+            executor.run_until_stalled();
+            drop(holder);
+            executor.run_until_stalled();
+
+            // 获得锁的顺序与排队顺序完全一致
+            assert_eq!(*queued.borrow(), *acquired.borrow());
+            assert_eq!(acquired.borrow().len(), 5);
+        });
+    }
+
+    #[test]
+    fn test_rwlock_readers_share_writers_exclusive() {
+        explore(200, |executor| {
+            let lock = Rc::new(RwLock::new(0));
+            let readers = Rc::new(Cell::new(0));
+            let writer = Rc::new(Cell::new(false));
+            let max_readers = Rc::new(Cell::new(0));
+
+            for id in 0..6 {
+                let (lock, readers, writer, max_readers) = (
+                    Rc::clone(&lock),
+                    Rc::clone(&readers),
+                    Rc::clone(&writer),
+                    Rc::clone(&max_readers),
+                );
+                executor.spawn(async move {
+                    if id % 3 == 0 {
+                        let mut guard = lock.write().await;
+                        assert!(!writer.replace(true));
+                        assert_eq!(readers.get(), 0, "写者与读者同时持有锁");
+                        yield_now().await;
+                        *guard += 1;
+                        writer.set(false);
+                    } else {
+                        let guard = lock.read().await;
+                        assert!(!writer.get(), "读者与写者同时持有锁");
+                        readers.set(readers.get() + 1);
+                        max_readers.set(max_readers.get().max(readers.get()));
+                        yield_now().await;
+                        let _ = *guard;
+                        readers.set(readers.get() - 1);
+                    }
+                });
+            }
+
+            executor.run_until_stalled();
+            assert_eq!(executor.block_on(async move { *lock.read().await }), 2);
+            assert!(max_readers.get() >= 1);
+        });
+    }
+
+    #[test]
+    fn test_rwlock_writer_not_starved() {
+        let executor = test_executor::TestExecutor::new(0);
+        let lock = Rc::new(RwLock::new(0));
+        let order = Rc::new(RefCell::new(Vec::new()));
+
+        // 读者持有锁时写者排队；写者之后到来的读者必须排在写者后面
+        let reader = executor.block_on({
+            let lock = Rc::clone(&lock);
+            async move { lock.semaphore.acquire().await.map(|permit| permit.forget()) }
+        });
+        assert!(reader.is_ok());
+
+        for id in 0..3 {
+            let (lock, order) = (Rc::clone(&lock), Rc::clone(&order));
+            executor.spawn(async move {
+                if id == 0 {
+                    *lock.write().await += 1;
+                } else {
+                    assert_eq!(*lock.read().await, 1, "读者插队到了写者前面");
+                }
+                order.borrow_mut().push(id);
+            });
+            executor.run_until_stalled();
+        }
+        assert!(order.borrow().is_empty());
+
+        // 第一个读者释放后，写者先于后来的读者拿到锁
+        lock.semaphore.add_permits(1);
+        executor.run_until_stalled();
+        assert_eq!(order.borrow()[0], 0);
+        assert_eq!(order.borrow().len(), 3);
+    }
+
+    #[test]
+    fn test_semaphore_limits_concurrency() {
+        explore(200, |executor| {
+            let semaphore = Rc::new(Semaphore::new(2));
+            let active = Rc::new(Cell::new(0));
+            let peak = Rc::new(Cell::new(0));
+
+            for _ in 0..6 {
+                let (semaphore, active, peak) =
+                    (Rc::clone(&semaphore), Rc::clone(&active), Rc::clone(&peak));
+                executor.spawn(async move {
+                    let _permit = semaphore.acquire().await.unwrap();
+                    active.set(active.get() + 1);
+                    peak.set(peak.get().max(active.get()));
+                    yield_now().await;
+                    yield_now().await;
+                    active.set(active.get() - 1);
+                });
+            }
+
+            executor.run_until_stalled();
+            assert_eq!(peak.get(), 2);
+            assert_eq!(semaphore.available_permits(), 2);
+        });
+    }
+
+    #[test]
+    fn test_cancelled_acquire_releases_its_place() {
+        let semaphore = Semaphore::new(1);
+        let mut cx = Context::from_waker(Waker::noop());
+
+        let held = semaphore.try_acquire().unwrap();
+        let mut big = Box::pin(semaphore.acquire_many(1));
+        let mut small = Box::pin(semaphore.acquire());
+        assert!(big.as_mut().poll(&mut cx).is_pending());
+        assert!(small.as_mut().poll(&mut cx).is_pending());
+
+        // 释放后许可分配给队首，随后队首被取消：许可必须转交给下一个
+        drop(held);
+        drop(big);
+        assert!(matches!(small.as_mut().poll(&mut cx), Poll::Ready(Ok(_))));
+        assert_eq!(semaphore.available_permits(), 1);
+    }
+
+    #[test]
+    fn test_closed_semaphore_fails_waiters() {
+        let semaphore = Semaphore::new(0);
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut acquire = Box::pin(semaphore.acquire());
+        assert!(acquire.as_mut().poll(&mut cx).is_pending());
+
+        semaphore.close();
+        assert!(matches!(acquire.as_mut().poll(&mut cx), Poll::Ready(Err(_))));
+        assert_eq!(semaphore.try_acquire().unwrap_err(), TryAcquireError::Closed);
+    }
+
+    #[test]
+    fn test_notify_no_lost_wakeups() {
+        explore(200, |executor| {
+            let ping = Rc::new(Notify::new());
+            let pong = Rc::new(Notify::new());
+            let rounds = Rc::new(Cell::new(0));
+
+            {
+                let (ping, pong, rounds) = (Rc::clone(&ping), Rc::clone(&pong), Rc::clone(&rounds));
+                executor.spawn(async move {
+                    for _ in 0..5 {
+                        ping.notified().await;
+                        rounds.set(rounds.get() + 1);
+                        pong.notify_one();
+                    }
+                });
+            }
+            {
+                let (ping, pong) = (Rc::clone(&ping), Rc::clone(&pong));
+                executor.spawn(async move {
+                    for _ in 0..5 {
+                        // 可能先于对方等待就通知：许可会被保存下来
+                        ping.notify_one();
+                        pong.notified().await;
+                    }
+                });
+            }
+
+            executor.run_until_stalled();
+            assert_eq!(rounds.get(), 5);
+            assert_eq!(executor.active_tasks(), 0);
+        });
+    }
+
+    #[test]
+    fn test_notify_waiters_wakes_everyone_registered() {
+        let executor = test_executor::TestExecutor::new(1);
+        let notify = Rc::new(Notify::new());
+        let woken = Rc::new(Cell::new(0));
+
+        for _ in 0..3 {
+            let (notify, woken) = (Rc::clone(&notify), Rc::clone(&woken));
+            executor.spawn(async move {
+                notify.notified().await;
+                woken.set(woken.get() + 1);
+            });
+        }
+        executor.run_until_stalled();
+
+        notify.notify_waiters();
+        executor.run_until_stalled();
+        assert_eq!(woken.get(), 3);
+
+        // notify_waiters 不保存许可
+        let mut cx = Context::from_waker(Waker::noop());
+        assert!(Box::pin(notify.notified()).as_mut().poll(&mut cx).is_pending());
+    }
+
+    #[test]
+    fn test_dropped_notified_forwards_notification() {
+        let notify = Notify::new();
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut first = Box::pin(notify.notified());
+        let mut second = Box::pin(notify.notified());
+        assert!(first.as_mut().poll(&mut cx).is_pending());
+        assert!(second.as_mut().poll(&mut cx).is_pending());
+
+        notify.notify_one();
+        drop(first);
+        assert!(second.as_mut().poll(&mut cx).is_ready());
+    }
+
+    #[test]
+    fn test_oneshot_send_and_drop() {
+        let (tx, rx) = oneshot::channel();
+        assert!(tx.send(42).is_ok());
+        assert_eq!(SimpleExecutor::block_on(rx), Ok(42));
+
+        let (tx, rx) = oneshot::channel::<i32>();
+        drop(tx);
+        assert!(SimpleExecutor::block_on(rx).is_err());
+
+        let (tx, rx) = oneshot::channel();
+        drop(rx);
+        assert!(tx.is_closed());
+        assert_eq!(tx.send(7), Err(7));
+    }
+
+    #[test]
+    fn test_mpsc_backpressure_and_ordering() {
+        explore(200, |executor| {
+            let (tx, mut rx) = mpsc::channel(2);
+            let in_flight = Rc::new(Cell::new(0usize));
+
+            for producer in 0..3 {
+                let (tx, in_flight) = (tx.clone(), Rc::clone(&in_flight));
+                executor.spawn(async move {
+                    for seq in 0..4 {
+                        tx.send((producer, seq)).await.unwrap();
+                        in_flight.set(in_flight.get() + 1);
+                        // 缓冲区容量为 2，已发送但未接收的数量不会超过它
+                        assert!(in_flight.get() <= 2);
+                    }
+                });
+            }
+            drop(tx);
+
+            let received = executor.block_on({
+                let in_flight = Rc::clone(&in_flight);
+                async move {
+                    let mut received = Vec::new();
+                    while let Some(message) = rx.recv().await {
+                        in_flight.set(in_flight.get() - 1);
+                        received.push(message);
+                        yield_now().await;
+                    }
+                    received
+                }
+            });
+
+            assert_eq!(received.len(), 12);
+            for producer in 0..3 {
+                let seqs: Vec<_> = received.iter().filter(|(p, _)| *p == producer).map(|(_, s)| *s).collect();
+                assert_eq!(seqs, vec![0, 1, 2, 3]);
+            }
+        });
+    }
+
+    #[test]
+    fn test_mpsc_send_fails_after_receiver_dropped() {
+        let (tx, rx) = mpsc::channel(1);
+        SimpleExecutor::block_on(tx.send(1)).unwrap();
+
+        // 缓冲区已满，第二次发送挂起；接收端关闭后它必须失败
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut pending = Box::pin(tx.send(2));
+        assert!(pending.as_mut().poll(&mut cx).is_pending());
+        drop(rx);
+        match pending.as_mut().poll(&mut cx) {
+            Poll::Ready(Err(mpsc::SendError(value))) => assert_eq!(value, 2),
+            other => panic!("发送应当失败: {:?}", other.map(|r| r.is_ok())),
+        }
+        assert!(tx.is_closed());
+    }
+
+    #[test]
+    fn test_mpsc_close_drains_then_ends_with_live_sender() {
+        let (tx, mut rx) = mpsc::channel(4);
+        SimpleExecutor::block_on(async {
+            tx.send(1).await.unwrap();
+            tx.send(2).await.unwrap();
+        });
+
+        rx.close();
+        assert!(tx.is_closed());
+        assert!(SimpleExecutor::block_on(tx.send(3)).is_err());
+        // 发送端仍然存活，但关闭后的通道取空缓冲区即结束
+        let received = SimpleExecutor::block_on(async {
+            let mut received = Vec::new();
+            while let Some(value) = rx.recv().await {
+                received.push(value);
+            }
+            received
+        });
+        assert_eq!(received, vec![1, 2]);
+        drop(tx);
+    }
+
+    // ------------------------------------------------------------------
+    // 多线程压力测试：每个线程运行自己的执行器，跨线程唤醒
+    // ------------------------------------------------------------------
+
+    #[test]
+    fn test_mutex_stress_across_threads() {
+        let mutex = Arc::new(Mutex::new(0u64));
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let mutex = Arc::clone(&mutex);
+                thread::spawn(move || {
+                    SimpleExecutor::block_on(async {
+                        for _ in 0..1000 {
+                            *mutex.lock().await += 1;
+                        }
+                    })
+                })
+            })
+            .collect();
+
+        for handle in threads {
+            handle.join().unwrap();
+        }
+        assert_eq!(*mutex.try_lock().unwrap(), 8000);
+    }
+
+    #[test]
+    fn test_mpsc_stress_across_threads() {
+        let (tx, mut rx) = mpsc::channel(4);
+        let producers: Vec<_> = (0..4u64)
+            .map(|producer| {
+                let tx = tx.clone();
+                thread::spawn(move || {
+                    SimpleExecutor::block_on(async {
+                        for seq in 0..500u64 {
+                            tx.send(producer * 1000 + seq).await.unwrap();
+                        }
+                    })
+                })
+            })
+            .collect();
+        drop(tx);
+
+        let consumer = thread::spawn(move || {
+            SimpleExecutor::block_on(async {
+                let mut last = [None; 4];
+                let mut count = 0;
+                while let Some(value) = rx.recv().await {
+                    let producer = (value / 1000) as usize;
+                    // 同一个生产者的消息保持顺序
+                    assert!(last[producer].is_none_or(|prev| prev < value));
+                    last[producer] = Some(value);
+                    count += 1;
+                }
+                count
+            })
+        });
+
+        for handle in producers {
+            handle.join().unwrap();
+        }
+        assert_eq!(consumer.join().unwrap(), 2000);
+    }
+
+    #[test]
+    fn test_oneshot_across_threads() {
+        let (tx, rx) = oneshot::channel();
+        let sender = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            tx.send("来自另一个线程").unwrap();
+        });
+        assert_eq!(SimpleExecutor::block_on(rx), Ok("来自另一个线程"));
+        sender.join().unwrap();
+    }
+}
@@ -10,13 +10,14 @@
 
 use std::pin::Pin;
 use std::marker::{PhantomPinned, Unpin};
-use std::ptr::NonNull;
 use std::future::Future;
 use std::task::{Context, Poll, Waker};
 use std::time::{Duration, Instant};
 use std::mem;
 use std::boxed::Box;
 
+use pinned_self_ref::{PinnedSelfRef, Ref};
+
 #[tokio::main]
 async fn main() {
     println!("🔒 Pin 和 Unpin 深度分析");
@@ -773,59 +774,50 @@ fn demonstrate_self_ref_challenges() {
 fn demonstrate_safe_self_ref() {
     println!("安全的自引用实现策略：");
     
-    println!("\n1. 使用 Pin + PhantomPinned：");
+    println!("\n1. 所有者 + 借用视图（pinned-self-ref）：");
     println!("```rust");
     println!("struct SafeSelfRef {{");
-    println!("    data: String,");
-    println!("    ptr: Option<NonNull<String>>,");
-    println!("    _pin: PhantomPinned,");
+    println!("    inner: PinnedSelfRef<String, Ref<str>>,");
     println!("}}");
     println!("```");
     
-    println!("\n2. 两阶段构造：");
-    println!("   - 第一阶段：创建基本结构");
-    println!("   - 第二阶段：建立内部引用");
+    println!("\n2. 闭包构造：");
+    println!("   - 第一步：所有者移到堆上，地址固定");
+    println!("   - 第二步：闭包从 &Owner 借用出视图");
     
-    println!("\n3. Pin 保护：");
-    println!("   - 防止移动");
-    println!("   - 保证指针有效性");
+    println!("\n3. 借用检查器保护：");
+    println!("   - 结构体本身可以移动，堆上的所有者不动");
+    println!("   - 视图只能在高阶闭包中访问，无法逃逸");
+    println!("   - unsafe 代码集中在一个 crate 中，使用者不写 unsafe");
     
     // 实际创建安全的自引用结构体
     let safe_self_ref = SafeSelfRef::new("Hello, self-reference!".to_string());
     println!("\n创建成功: {}", safe_self_ref.get_data());
 }
 
-/// 安全的自引用结构体
+/// 安全的自引用结构体：视图是数据中的第一个单词
 struct SafeSelfRef {
-    data: String,
-    ptr: Option<NonNull<String>>,
-    _pin: PhantomPinned,
+    inner: PinnedSelfRef<String, Ref<str>>,
 }
 
 impl SafeSelfRef {
-    fn new(data: String) -> Pin<Box<Self>> {
-        let mut boxed = Box::pin(SafeSelfRef {
-            data,
-            ptr: None,
-            _pin: PhantomPinned,
-        });
-        
-        // 安全地建立自引用
-        let ptr = NonNull::from(&boxed.data);
-        unsafe {
-            let mut_ref = Pin::as_mut(&mut boxed);
-            Pin::get_unchecked_mut(mut_ref).ptr = Some(ptr);
+    fn new(data: String) -> Self {
+        Self {
+            inner: PinnedSelfRef::new(data, |data| data.split_whitespace().next().unwrap_or("")),
         }
-        
-        boxed
     }
     
     fn get_data(&self) -> &str {
-        &self.data
+        self.inner.owner()
     }
     
-    fn get_ptr_data(&self) -> Option<&str> {
-        self.ptr.map(|ptr| unsafe { ptr.as_ref() }.as_str())
+    fn get_ptr_data(&self) -> String {
+        self.inner.with(|_, first_word| first_word.to_string())
+    }
+    
+    /// 视图确实指向所有者内部，而不是一份拷贝
+    fn view_points_into_owner(&self) -> bool {
+        self.inner.with(|owner, first_word| owner.as_ptr() == first_word.as_ptr())
     }
 }
 
@@ -837,21 +829,24 @@ async fn demonstrate_self_ref_construction() {
     let self_ref = SafeSelfRef::new("Construction example".to_string());
     
     println!("   原始数据: {}", self_ref.get_data());
-    if let Some(ptr_data) = self_ref.get_ptr_data() {
-        println!("   指针数据: {}", ptr_data);
-        println!("   ✅ 自引用建立成功！");
+    println!("   视图数据: {}", self_ref.get_ptr_data());
+    
+    // 跨 await 点和任务移动后视图依然有效
+    let self_ref = tokio::spawn(async move { self_ref }).await.unwrap();
+    if self_ref.view_points_into_owner() {
+        println!("   ✅ 移动到另一个任务后视图仍指向原数据！");
     }
     
     println!("\n2. 构造步骤：");
-    println!("   a) 创建 Box::pin 包装的结构体");
-    println!("   b) 获取内部数据的指针");
-    println!("   c) 安全地设置自引用指针");
-    println!("   d) 返回固定的结构体");
+    println!("   a) PinnedSelfRef::new 把所有者移到堆上");
+    println!("   b) 构造闭包从 &String 借用出 &str 视图");
+    println!("   c) 视图的生命周期被高阶闭包约束");
+    println!("   d) 返回可以自由移动的结构体");
     
     println!("\n3. 安全保证：");
-    println!("   - Pin 防止移动");
-    println!("   - NonNull 保证指针有效");
-    println!("   - PhantomPinned 标记 !Unpin");
+    println!("   - 所有者在堆上的地址固定，构造后只读");
+    println!("   - 先释放视图，再释放所有者");
+    println!("   - 测试覆盖移动、跨线程和 panic，可以用 miri 检查");
 }
 
 /// 演示生命周期管理
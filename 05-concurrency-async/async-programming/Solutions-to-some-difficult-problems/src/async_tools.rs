@@ -0,0 +1,438 @@
+//! # 异步递归与异步闭包工具
+//!
+//! 手写 `BoxFuture` 的递归（`old_recursive_async`）和 `#[async_recursion]` 都只解决了
+//! “future 大小无限”的问题：每一层递归仍然在上一层的 `poll` 里被 `poll`，
+//! 递归十万层时照样栈溢出。本模块换个思路：
+//! - [`Walker`]：用显式的待访问队列代替递归遍历树/图，子节点的异步加载有并发上限
+//! - [`AsyncMemo`]：带缓存的异步递归，每个子问题作为独立任务运行，
+//!   任务之间通过 `JoinHandle` 等待，`poll` 不再层层嵌套
+//! - [`CallbackRegistry`]：保存 `AsyncFnMut` 回调，回调可以在 `.await` 前后修改自己捕获的状态
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use futures::future::{BoxFuture, LocalBoxFuture};
+use futures::stream::{FuturesUnordered, StreamExt};
+use futures::FutureExt;
+use tokio::sync::OnceCell;
+
+// ============================================================================
+// 树/图遍历
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalkOrder {
+    DepthFirst,
+    BreadthFirst,
+}
+
+/// 遍历到的节点及其深度（根节点深度为 0）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Visited<N> {
+    pub node: N,
+    pub depth: usize,
+}
+
+/// 异步树/图遍历器
+///
+/// `children` 异步地返回一个节点的子节点。同一个节点只访问一次，所以也可以遍历有环的图。
+/// 并发度为 1 时访问顺序是严格的 DFS/BFS；并发度更高时，先加载完成的子节点先入队，
+/// 顺序只是大致的 DFS/BFS。
+pub struct Walker<F> {
+    children: F,
+    order: WalkOrder,
+    concurrency: usize,
+    max_depth: Option<usize>,
+}
+
+impl<F> Walker<F> {
+    /// 默认：广度优先，并发度 4，不限深度
+    pub fn new(children: F) -> Self {
+        Self {
+            children,
+            order: WalkOrder::BreadthFirst,
+            concurrency: 4,
+            max_depth: None,
+        }
+    }
+
+    pub fn order(mut self, order: WalkOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// 同时加载子节点的最大数量
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// 深度达到 `max_depth` 的节点仍会被访问，但不再加载它们的子节点
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// 从 `roots` 出发遍历，按访问顺序返回所有节点；任何一次加载失败都会立即返回错误
+    pub async fn walk<N, E, Fut>(&self, roots: impl IntoIterator<Item = N>) -> Result<Vec<Visited<N>>, E>
+    where
+        N: Clone + Eq + Hash,
+        F: Fn(N) -> Fut,
+        Fut: Future<Output = Result<Vec<N>, E>>,
+    {
+        let mut seen = HashSet::new();
+        let mut frontier = VecDeque::new();
+        for root in roots {
+            if seen.insert(root.clone()) {
+                frontier.push_back((root, 0));
+            }
+        }
+
+        let mut loading = FuturesUnordered::new();
+        let mut visited = Vec::new();
+
+        loop {
+            while loading.len() < self.concurrency {
+                let next = match self.order {
+                    WalkOrder::BreadthFirst => frontier.pop_front(),
+                    WalkOrder::DepthFirst => frontier.pop_back(),
+                };
+                let Some((node, depth)) = next else { break };
+
+                visited.push(Visited {
+                    node: node.clone(),
+                    depth,
+                });
+                if self.max_depth.is_some_and(|max_depth| depth >= max_depth) {
+                    continue;
+                }
+                let children = (self.children)(node);
+                loading.push(async move { (depth, children.await) });
+            }
+
+            let Some((depth, children)) = loading.next().await else {
+                break;
+            };
+            let children = children?;
+            let children: Vec<_> = match self.order {
+                WalkOrder::BreadthFirst => children,
+                // 栈顶先出，倒序入栈才能按原顺序访问兄弟节点
+                WalkOrder::DepthFirst => children.into_iter().rev().collect(),
+            };
+            for child in children {
+                if seen.insert(child.clone()) {
+                    frontier.push_back((child, depth + 1));
+                }
+            }
+        }
+
+        Ok(visited)
+    }
+}
+
+// ============================================================================
+// 带缓存的异步递归
+// ============================================================================
+
+type Compute<K, V> = dyn Fn(AsyncMemo<K, V>, K) -> BoxFuture<'static, V> + Send + Sync;
+
+struct MemoInner<K, V> {
+    cells: Mutex<HashMap<K, Arc<OnceCell<V>>>>,
+    compute: Box<Compute<K, V>>,
+    computations: AtomicUsize,
+}
+
+/// 带缓存的异步递归函数
+///
+/// 计算函数拿到 memo 本身，通过 [`AsyncMemo::get`] 递归地求子问题。
+/// 每个键只计算一次，并发请求同一个键的调用者共享同一次计算。
+/// 递归关系必须无环，否则互相等待的任务永远不会完成。
+pub struct AsyncMemo<K, V> {
+    inner: Arc<MemoInner<K, V>>,
+}
+
+impl<K, V> Clone for AsyncMemo<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<K, V> AsyncMemo<K, V>
+where
+    K: Clone + Eq + Hash + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    pub fn new<F, Fut>(compute: F) -> Self
+    where
+        F: Fn(AsyncMemo<K, V>, K) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = V> + Send + 'static,
+    {
+        Self {
+            inner: Arc::new(MemoInner {
+                cells: Mutex::new(HashMap::new()),
+                compute: Box::new(move |memo, key| compute(memo, key).boxed()),
+                computations: AtomicUsize::new(0),
+            }),
+        }
+    }
+
+    pub async fn get(&self, key: K) -> V {
+        let cell = self.inner.cells.lock().unwrap().entry(key.clone()).or_default().clone();
+        if let Some(value) = cell.get() {
+            return value.clone();
+        }
+
+        // 在独立任务中计算：递归深度不再占用调用栈，调用者被取消也不会打断计算
+        let memo = self.clone();
+        let task = tokio::spawn(async move {
+            cell.get_or_init(|| async {
+                memo.inner.computations.fetch_add(1, Ordering::Relaxed);
+                (memo.inner.compute)(memo.clone(), key).await
+            })
+            .await
+            .clone()
+        });
+        match task.await {
+            Ok(value) => value,
+            Err(e) => std::panic::resume_unwind(e.into_panic()),
+        }
+    }
+
+    /// 已缓存（或正在计算）的键数
+    pub fn len(&self) -> usize {
+        self.inner.cells.lock().unwrap().len()
+    }
+
+    /// 计算函数被实际调用的次数
+    pub fn computations(&self) -> usize {
+        self.inner.computations.load(Ordering::Relaxed)
+    }
+}
+
+// ============================================================================
+// AsyncFnMut 回调注册表
+// ============================================================================
+
+/// `AsyncFnMut` 不是 dyn 兼容的，用这个 trait 把它擦除成可以装箱的形式
+trait ErasedCallback<E> {
+    fn call(&mut self, event: E) -> LocalBoxFuture<'_, ()>;
+}
+
+impl<E: 'static, F: AsyncFnMut(E)> ErasedCallback<E> for F {
+    fn call(&mut self, event: E) -> LocalBoxFuture<'_, ()> {
+        Box::pin(self(event))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CallbackId(u64);
+
+/// 按注册顺序依次调用的异步回调集合
+///
+/// 和 `AsyncClosureProcessor` 要求的 `Fn(i32) -> Fut + 'static` 不同，这里的回调是
+/// `async` 闭包，返回的 future 可以借用闭包自己的状态，不需要 `Arc<Mutex<..>>`。
+/// 代价是 future 不一定是 `Send`，注册表只能在一个任务内使用。
+pub struct CallbackRegistry<E> {
+    callbacks: Vec<(CallbackId, Box<dyn ErasedCallback<E>>)>,
+    next_id: u64,
+}
+
+impl<E: Clone + 'static> CallbackRegistry<E> {
+    pub fn new() -> Self {
+        Self {
+            callbacks: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    pub fn register(&mut self, callback: impl AsyncFnMut(E) + 'static) -> CallbackId {
+        let id = CallbackId(self.next_id);
+        self.next_id += 1;
+        self.callbacks.push((id, Box::new(callback)));
+        id
+    }
+
+    /// 返回回调是否存在
+    pub fn unregister(&mut self, id: CallbackId) -> bool {
+        let before = self.callbacks.len();
+        self.callbacks.retain(|(callback_id, _)| *callback_id != id);
+        self.callbacks.len() != before
+    }
+
+    pub fn len(&self) -> usize {
+        self.callbacks.len()
+    }
+
+    /// 依次调用所有回调，前一个完成后才调用下一个
+    pub async fn emit(&mut self, event: E) {
+        for (_, callback) in &mut self.callbacks {
+            callback.call(event.clone()).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::time::Duration;
+    use tokio::time::{sleep, Instant};
+
+    /// 一条 20 万个节点的链：递归实现会栈溢出
+    const DEEP: u64 = 200_000;
+
+    #[tokio::test]
+    async fn test_walk_deep_chain_without_stack_overflow() {
+        let walker = Walker::new(|n: u64| async move { Ok::<_, ()>(if n < DEEP { vec![n + 1] } else { vec![] }) })
+            .order(WalkOrder::DepthFirst);
+
+        let visited = walker.walk([0]).await.unwrap();
+        assert_eq!(visited.len() as u64, DEEP + 1);
+        assert_eq!(visited.last(), Some(&Visited { node: DEEP, depth: DEEP as usize }));
+    }
+
+    /// 完全二叉树：节点 n 的子节点是 2n+1 和 2n+2
+    fn binary_children(limit: u32) -> impl Fn(u32) -> futures::future::Ready<Result<Vec<u32>, ()>> {
+        move |n| futures::future::ready(Ok([2 * n + 1, 2 * n + 2].into_iter().filter(|c| *c < limit).collect()))
+    }
+
+    #[tokio::test]
+    async fn test_walk_orders() {
+        let nodes = |visited: Vec<Visited<u32>>| visited.into_iter().map(|v| v.node).collect::<Vec<_>>();
+
+        let bfs = Walker::new(binary_children(7)).concurrency(1);
+        assert_eq!(nodes(bfs.walk([0]).await.unwrap()), [0, 1, 2, 3, 4, 5, 6]);
+
+        let dfs = Walker::new(binary_children(7)).order(WalkOrder::DepthFirst).concurrency(1);
+        assert_eq!(nodes(dfs.walk([0]).await.unwrap()), [0, 1, 3, 4, 2, 5, 6]);
+
+        let shallow = Walker::new(binary_children(100)).max_depth(1);
+        assert_eq!(nodes(shallow.walk([0]).await.unwrap()), [0, 1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_walk_graph_with_cycles_visits_each_node_once() {
+        // 0 → 1 → 2 → 0，2 → 3
+        let edges = HashMap::from([(0, vec![1]), (1, vec![2]), (2, vec![0, 3]), (3, vec![])]);
+        let walker = Walker::new(|n: i32| {
+            let next = edges[&n].clone();
+            async move { Ok::<_, ()>(next) }
+        });
+
+        let visited = walker.walk([0, 1]).await.unwrap();
+        let mut nodes: Vec<_> = visited.iter().map(|v| v.node).collect();
+        nodes.sort();
+        assert_eq!(nodes, [0, 1, 2, 3]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_walk_bounds_parallel_loads() {
+        let loading = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        let walker = Walker::new(|n: u32| {
+            let (loading, peak) = (&loading, &peak);
+            async move {
+                let now = loading.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                sleep(Duration::from_millis(10)).await;
+                loading.fetch_sub(1, Ordering::SeqCst);
+                binary_children(63)(n).await
+            }
+        })
+        .concurrency(8);
+
+        let start = Instant::now();
+        assert_eq!(walker.walk([0]).await.unwrap().len(), 63);
+        assert_eq!(peak.load(Ordering::SeqCst), 8);
+        // 63 次加载，每批 8 个并行，远快于串行的 630ms
+        assert!(start.elapsed() < Duration::from_millis(200));
+    }
+
+    #[tokio::test]
+    async fn test_walk_stops_on_error() {
+        let walker = Walker::new(|n: u32| async move {
+            if n == 5 {
+                Err(format!("加载节点 {} 失败", n))
+            } else {
+                binary_children(1_000)(n).await.map_err(|_| String::new())
+            }
+        });
+        assert_eq!(walker.walk([0]).await, Err("加载节点 5 失败".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_memo_computes_each_key_once() {
+        let fib = AsyncMemo::new(|memo: AsyncMemo<u64, u64>, n| async move {
+            if n < 2 {
+                n
+            } else {
+                memo.get(n - 1).await.wrapping_add(memo.get(n - 2).await)
+            }
+        });
+
+        assert_eq!(fib.get(90).await, 2_880_067_194_370_816_120);
+        // 不带缓存需要指数次调用
+        assert_eq!(fib.computations(), 91);
+        assert_eq!(fib.len(), 91);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_memo_shares_concurrent_computation() {
+        let slow = AsyncMemo::new(|_: AsyncMemo<&'static str, usize>, key| async move {
+            sleep(Duration::from_millis(100)).await;
+            key.len()
+        });
+
+        let results = futures::future::join_all((0..10).map(|_| slow.get("共享"))).await;
+        assert!(results.iter().all(|len| *len == "共享".len()));
+        assert_eq!(slow.computations(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_memo_deep_recursion_without_stack_overflow() {
+        let sum = AsyncMemo::new(|memo: AsyncMemo<u64, u64>, n| async move {
+            if n == 0 {
+                0
+            } else {
+                n + memo.get(n - 1).await
+            }
+        });
+        assert_eq!(sum.get(DEEP).await, DEEP * (DEEP + 1) / 2);
+    }
+
+    #[tokio::test]
+    async fn test_callbacks_keep_state_across_awaits() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut registry = CallbackRegistry::new();
+
+        let mut total = 0;
+        let sum_log = log.clone();
+        registry.register(async move |x: i32| {
+            total += x;
+            tokio::task::yield_now().await;
+            sum_log.borrow_mut().push(format!("累计 {}", total));
+        });
+
+        let mut seen = Vec::new();
+        let echo_log = log.clone();
+        let echo = registry.register(async move |x: i32| {
+            seen.push(x);
+            echo_log.borrow_mut().push(format!("收到 {:?}", seen));
+        });
+
+        registry.emit(1).await;
+        registry.emit(2).await;
+        assert!(registry.unregister(echo));
+        assert!(!registry.unregister(echo));
+        registry.emit(3).await;
+
+        assert_eq!(registry.len(), 1);
+        assert_eq!(*log.borrow(), ["累计 1", "收到 [1]", "累计 3", "收到 [1, 2]", "累计 6"]);
+    }
+}
@@ -0,0 +1,577 @@
+//! # 结构化异步清理
+//!
+//! Rust 的 `Drop` 不能 `.await`，`AsyncResource` 只能在忘记调用
+//! `async_cleanup` 时打印一条警告。本模块把清理责任交给一个作用域：
+//! - `AsyncDrop`：需要异步清理的资源实现的 trait
+//! - `Nursery`：运行一段异步代码，期间通过 `Scope::adopt` 交给它的资源
+//!   在代码结束后按**相反顺序**执行 `async_drop`
+//! - 正常返回、返回错误、panic 都会先完成清理再把结果交给调用方；
+//!   外层 future 被取消（例如超时）时，剩余的清理交给运行时后台完成
+//! - 清理时仍被作用域外持有的资源（句柄逃逸）记为泄漏并报告
+//!
+//! 每次清理都会生成一份 `CleanupReport`，可以通过 `CleanupLog` 收集。
+
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use futures::future::{BoxFuture, FutureExt};
+use tokio::sync::{Mutex as TokioMutex, MutexGuard as TokioMutexGuard, Notify};
+use tracing::{info, warn};
+
+/// 需要异步清理的资源
+#[async_trait]
+pub trait AsyncDrop: Send {
+    async fn async_drop(&mut self) -> Result<()>;
+}
+
+/// 作用域结束的方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeOutcome {
+    Completed,
+    Failed,
+    Panicked,
+    Cancelled,
+}
+
+/// 一次作用域清理的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CleanupReport {
+    pub scope: String,
+    pub outcome: ScopeOutcome,
+    /// 按实际清理顺序排列（与登记顺序相反）
+    pub cleaned: Vec<String>,
+    /// 清理失败的资源及错误信息
+    pub failed: Vec<(String, String)>,
+    /// 清理时仍被作用域外持有的资源
+    pub leaked: Vec<String>,
+}
+
+impl CleanupReport {
+    fn new(scope: &str) -> Self {
+        Self {
+            scope: scope.to_string(),
+            outcome: ScopeOutcome::Completed,
+            cleaned: Vec::new(),
+            failed: Vec::new(),
+            leaked: Vec::new(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct LogInner {
+    reports: Mutex<Vec<CleanupReport>>,
+    notify: Notify,
+}
+
+/// 收集清理报告，克隆后共享
+#[derive(Clone, Default)]
+pub struct CleanupLog {
+    inner: Arc<LogInner>,
+}
+
+impl CleanupLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn reports(&self) -> Vec<CleanupReport> {
+        self.inner.reports.lock().unwrap().clone()
+    }
+
+    /// 等待至少收到 `count` 份报告（取消路径上的清理在后台完成）
+    pub async fn wait_for(&self, count: usize) {
+        loop {
+            let notified = self.inner.notify.notified();
+            if self.inner.reports.lock().unwrap().len() >= count {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    fn push(&self, report: CleanupReport) {
+        self.inner.reports.lock().unwrap().push(report);
+        self.inner.notify.notify_waiters();
+    }
+}
+
+// ============================================================================
+// 作用域中登记的资源
+// ============================================================================
+
+/// 类型擦除后的资源，供作用域统一清理
+trait Registered: Send {
+    fn escaped(&self) -> bool;
+    /// 清理 future 自己持有一份资源引用，不需要先把条目从列表中取出
+    fn cleanup(&self) -> BoxFuture<'static, Result<()>>;
+}
+
+struct Entry<R> {
+    resource: Arc<TokioMutex<R>>,
+}
+
+impl<R: AsyncDrop + 'static> Registered for Entry<R> {
+    fn escaped(&self) -> bool {
+        // 作用域自己持有一份，多出来的就是逃逸到作用域之外的句柄
+        Arc::strong_count(&self.resource) > 1
+    }
+
+    fn cleanup(&self) -> BoxFuture<'static, Result<()>> {
+        let resource = Arc::clone(&self.resource);
+        async move { resource.lock().await.async_drop().await }.boxed()
+    }
+}
+
+/// 作用域登记的资源句柄，可以克隆并在作用域内的任务之间共享
+pub struct Managed<R> {
+    resource: Arc<TokioMutex<R>>,
+}
+
+impl<R> Clone for Managed<R> {
+    fn clone(&self) -> Self {
+        Self {
+            resource: Arc::clone(&self.resource),
+        }
+    }
+}
+
+impl<R> Managed<R> {
+    pub async fn lock(&self) -> TokioMutexGuard<'_, R> {
+        self.resource.lock().await
+    }
+}
+
+// ============================================================================
+// Scope / Nursery
+// ============================================================================
+
+struct ScopeInner {
+    name: String,
+    resources: Mutex<Vec<(String, Box<dyn Registered>)>>,
+    closed: AtomicBool,
+    report: Mutex<CleanupReport>,
+}
+
+/// 作用域句柄，传给 `Nursery::run` 的代码，用来登记资源
+#[derive(Clone)]
+pub struct Scope {
+    inner: Arc<ScopeInner>,
+}
+
+impl Scope {
+    fn new(name: &str) -> Self {
+        Self {
+            inner: Arc::new(ScopeInner {
+                name: name.to_string(),
+                resources: Mutex::new(Vec::new()),
+                closed: AtomicBool::new(false),
+                report: Mutex::new(CleanupReport::new(name)),
+            }),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.inner.name
+    }
+
+    /// 把资源交给作用域管理，返回访问它的句柄
+    pub fn adopt<R: AsyncDrop + 'static>(&self, label: impl Into<String>, resource: R) -> Result<Managed<R>> {
+        let label = label.into();
+        let mut resources = self.inner.resources.lock().unwrap();
+        if self.inner.closed.load(Ordering::SeqCst) {
+            return Err(anyhow!("作用域 {} 已开始清理，不能再登记资源 {}", self.inner.name, label));
+        }
+
+        let resource = Arc::new(TokioMutex::new(resource));
+        resources.push((
+            label,
+            Box::new(Entry {
+                resource: Arc::clone(&resource),
+            }),
+        ));
+        Ok(Managed { resource })
+    }
+
+    /// 按登记的相反顺序逐个清理资源，返回报告。
+    ///
+    /// 资源在自己的清理完成之后才从列表中移除：清理途中被取消时，
+    /// 正在清理的资源和剩余资源都仍然留在列表里，由后台任务重新清理。
+    async fn cleanup(&self, outcome: ScopeOutcome) -> CleanupReport {
+        self.inner.closed.store(true, Ordering::SeqCst);
+        self.inner.report.lock().unwrap().outcome = outcome;
+
+        loop {
+            let next = {
+                let resources = self.inner.resources.lock().unwrap();
+                resources
+                    .last()
+                    .map(|(label, resource)| (label.clone(), resource.escaped(), resource.cleanup()))
+            };
+            let Some((label, escaped, cleanup)) = next else { break };
+
+            if escaped {
+                let mut report = self.inner.report.lock().unwrap();
+                // 被取消后重新清理同一个资源时不重复报告
+                if !report.leaked.contains(&label) {
+                    warn!("      ⚠️ 作用域 {} 的资源 {} 逃逸到了作用域之外", self.inner.name, label);
+                    report.leaked.push(label.clone());
+                }
+            }
+
+            let result = cleanup.await;
+            // closed 之后不会再有新资源登记，列表末尾仍是刚清理的这一个
+            self.inner.resources.lock().unwrap().pop();
+            let mut report = self.inner.report.lock().unwrap();
+            match result {
+                Ok(()) => report.cleaned.push(label),
+                Err(e) => {
+                    warn!("      ⚠️ 资源 {} 清理失败: {}", label, e);
+                    report.failed.push((label, e.to_string()));
+                }
+            }
+        }
+
+        self.inner.report.lock().unwrap().clone()
+    }
+}
+
+/// 外层 future 被取消时把剩余的清理交给运行时
+struct CancelGuard {
+    scope: Option<Scope>,
+    log: Option<CleanupLog>,
+}
+
+impl CancelGuard {
+    fn disarm(&mut self) {
+        self.scope = None;
+    }
+}
+
+impl Drop for CancelGuard {
+    fn drop(&mut self) {
+        let Some(scope) = self.scope.take() else { return };
+        let log = self.log.take();
+
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn(async move {
+                    let report = scope.cleanup(ScopeOutcome::Cancelled).await;
+                    info!("      🧹 作用域 {} 被取消，后台清理了 {} 个资源", report.scope, report.cleaned.len());
+                    if let Some(log) = log {
+                        log.push(report);
+                    }
+                });
+            }
+            Err(_) => {
+                // 没有运行时就无法执行异步清理，只能把剩余资源全部报告为泄漏
+                let remaining: Vec<_> = scope.inner.resources.lock().unwrap().drain(..).map(|(label, _)| label).collect();
+                warn!("      ⚠️ 作用域 {} 在运行时之外被取消，{} 个资源未清理", scope.inner.name, remaining.len());
+                let mut report = scope.inner.report.lock().unwrap().clone();
+                report.outcome = ScopeOutcome::Cancelled;
+                report.leaked.extend(remaining);
+                if let Some(log) = log {
+                    log.push(report);
+                }
+            }
+        }
+    }
+}
+
+/// 拥有资源并保证其异步清理的作用域
+pub struct Nursery {
+    name: String,
+    log: Option<CleanupLog>,
+}
+
+impl Nursery {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            log: None,
+        }
+    }
+
+    /// 把清理报告写入 `log`
+    pub fn with_log(mut self, log: CleanupLog) -> Self {
+        self.log = Some(log);
+        self
+    }
+
+    /// 运行 `body`，结束后按相反顺序清理它登记的所有资源。
+    ///
+    /// - `body` 返回错误：清理后原样返回该错误
+    /// - `body` panic：清理后继续 panic
+    /// - `body` 成功但有资源清理失败：返回清理错误
+    pub async fn run<T, F, Fut>(self, body: F) -> Result<T>
+    where
+        F: FnOnce(Scope) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let scope = Scope::new(&self.name);
+        let mut guard = CancelGuard {
+            scope: Some(scope.clone()),
+            log: self.log.clone(),
+        };
+
+        let outcome = AssertUnwindSafe(body(scope.clone())).catch_unwind().await;
+        let scope_outcome = match &outcome {
+            Ok(Ok(_)) => ScopeOutcome::Completed,
+            Ok(Err(_)) => ScopeOutcome::Failed,
+            Err(_) => ScopeOutcome::Panicked,
+        };
+
+        // 清理期间守卫仍然有效：清理本身被取消时，剩下的资源交给后台
+        let report = scope.cleanup(scope_outcome).await;
+        guard.disarm();
+
+        info!(
+            "      🧹 作用域 {} 结束（{:?}），清理顺序: {:?}",
+            report.scope, report.outcome, report.cleaned
+        );
+        let cleanup_error = (!report.failed.is_empty())
+            .then(|| anyhow!("作用域 {} 中 {} 个资源清理失败", report.scope, report.failed.len()));
+        if let Some(log) = &self.log {
+            log.push(report);
+        }
+
+        match outcome {
+            Ok(Ok(value)) => match cleanup_error {
+                Some(e) => Err(e),
+                None => Ok(value),
+            },
+            Ok(Err(e)) => Err(e),
+            Err(panic) => std::panic::resume_unwind(panic),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::time::sleep;
+
+    /// 清理时把自己的名字记录到共享列表中
+    struct Recorder {
+        name: &'static str,
+        order: Arc<Mutex<Vec<&'static str>>>,
+        fail: bool,
+    }
+
+    #[async_trait]
+    impl AsyncDrop for Recorder {
+        async fn async_drop(&mut self) -> Result<()> {
+            sleep(Duration::from_millis(10)).await;
+            if self.fail {
+                return Err(anyhow!("{} 清理失败", self.name));
+            }
+            self.order.lock().unwrap().push(self.name);
+            Ok(())
+        }
+    }
+
+    fn recorder(name: &'static str, order: &Arc<Mutex<Vec<&'static str>>>) -> Recorder {
+        Recorder {
+            name,
+            order: Arc::clone(order),
+            fail: false,
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cleanup_runs_in_reverse_order() {
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let log = CleanupLog::new();
+
+        let value = Nursery::new("正常")
+            .with_log(log.clone())
+            .run(|scope| {
+                let order = Arc::clone(&order);
+                async move {
+                    for name in ["a", "b", "c"] {
+                        scope.adopt(name, recorder(name, &order))?;
+                    }
+                    Ok(42)
+                }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(value, 42);
+        assert_eq!(*order.lock().unwrap(), vec!["c", "b", "a"]);
+        let report = &log.reports()[0];
+        assert_eq!(report.outcome, ScopeOutcome::Completed);
+        assert_eq!(report.cleaned, vec!["c", "b", "a"]);
+        assert!(report.leaked.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cleanup_runs_on_error() {
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let log = CleanupLog::new();
+
+        let result: Result<()> = Nursery::new("出错")
+            .with_log(log.clone())
+            .run(|scope| {
+                let order = Arc::clone(&order);
+                async move {
+                    scope.adopt("a", recorder("a", &order))?;
+                    scope.adopt("b", recorder("b", &order))?;
+                    Err(anyhow!("业务失败"))
+                }
+            })
+            .await;
+
+        assert_eq!(result.unwrap_err().to_string(), "业务失败");
+        assert_eq!(*order.lock().unwrap(), vec!["b", "a"]);
+        assert_eq!(log.reports()[0].outcome, ScopeOutcome::Failed);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cleanup_runs_before_panic_propagates() {
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let log = CleanupLog::new();
+
+        let task = {
+            let (order, log) = (Arc::clone(&order), log.clone());
+            tokio::spawn(async move {
+                Nursery::new("panic")
+                    .with_log(log)
+                    .run(|scope| async move {
+                        scope.adopt("a", recorder("a", &order))?;
+                        scope.adopt("b", recorder("b", &order))?;
+                        sleep(Duration::from_millis(5)).await;
+                        panic!("任务崩溃");
+                        #[allow(unreachable_code)]
+                        Ok(())
+                    })
+                    .await
+            })
+        };
+
+        assert!(task.await.unwrap_err().is_panic());
+        assert_eq!(*order.lock().unwrap(), vec!["b", "a"]);
+        assert_eq!(log.reports()[0].outcome, ScopeOutcome::Panicked);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cleanup_runs_after_timeout_cancellation() {
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let log = CleanupLog::new();
+
+        let run = Nursery::new("超时").with_log(log.clone()).run(|scope| {
+            let order = Arc::clone(&order);
+            async move {
+                scope.adopt("a", recorder("a", &order))?;
+                scope.adopt("b", recorder("b", &order))?;
+                sleep(Duration::from_secs(60)).await;
+                Ok(())
+            }
+        });
+        assert!(tokio::time::timeout(Duration::from_millis(100), run).await.is_err());
+
+        // 外层 future 被 drop 后，清理在后台完成
+        log.wait_for(1).await;
+        assert_eq!(*order.lock().unwrap(), vec!["b", "a"]);
+        let report = &log.reports()[0];
+        assert_eq!(report.outcome, ScopeOutcome::Cancelled);
+        assert_eq!(report.cleaned, vec!["b", "a"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_escaped_resource_reported_as_leak() {
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let log = CleanupLog::new();
+
+        let escaped = Nursery::new("逃逸")
+            .with_log(log.clone())
+            .run(|scope| {
+                let order = Arc::clone(&order);
+                async move {
+                    scope.adopt("local", recorder("local", &order))?;
+                    let handle = scope.adopt("escaped", recorder("escaped", &order))?;
+                    // 把句柄带出作用域
+                    Ok(handle)
+                }
+            })
+            .await
+            .unwrap();
+
+        let report = &log.reports()[0];
+        assert_eq!(report.leaked, vec!["escaped"]);
+        // 逃逸的资源仍然被清理，作用域的保证不变
+        assert_eq!(report.cleaned, vec!["escaped", "local"]);
+        drop(escaped);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cleanup_failure_surfaces_on_success() {
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let log = CleanupLog::new();
+
+        let result = Nursery::new("清理失败")
+            .with_log(log.clone())
+            .run(|scope| {
+                let order = Arc::clone(&order);
+                async move {
+                    scope.adopt("ok", recorder("ok", &order))?;
+                    scope.adopt(
+                        "bad",
+                        Recorder {
+                            fail: true,
+                            ..recorder("bad", &order)
+                        },
+                    )?;
+                    Ok(())
+                }
+            })
+            .await;
+
+        assert!(result.is_err());
+        // 一个资源清理失败不影响其余资源
+        assert_eq!(*order.lock().unwrap(), vec!["ok"]);
+        assert_eq!(log.reports()[0].failed.len(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_adopt_after_close_is_rejected() {
+        let leaked_scope = Nursery::new("关闭")
+            .run(|scope| async move { Ok(scope) })
+            .await
+            .unwrap();
+
+        let order = Arc::new(Mutex::new(Vec::new()));
+        assert!(leaked_scope.adopt("late", recorder("late", &order)).is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_resource_being_cleaned_survives_cancellation() {
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let log = CleanupLog::new();
+
+        let run = Nursery::new("清理中取消").with_log(log.clone()).run(|scope| {
+            let order = Arc::clone(&order);
+            async move {
+                scope.adopt("a", recorder("a", &order))?;
+                scope.adopt("b", recorder("b", &order))?;
+                Ok(())
+            }
+        });
+        // 每个资源清理耗时 10ms：b 已经清理完，a 正在清理时外层被取消
+        assert!(tokio::time::timeout(Duration::from_millis(15), run).await.is_err());
+        assert_eq!(*order.lock().unwrap(), vec!["b"]);
+
+        // 正在清理的 a 没有丢失，后台任务重新清理了它
+        log.wait_for(1).await;
+        assert_eq!(*order.lock().unwrap(), vec!["b", "a"]);
+        let report = &log.reports()[0];
+        assert_eq!(report.outcome, ScopeOutcome::Cancelled);
+        assert_eq!(report.cleaned, vec!["b", "a"]);
+        assert!(report.leaked.is_empty());
+    }
+}
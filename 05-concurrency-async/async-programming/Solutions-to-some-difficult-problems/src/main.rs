@@ -39,14 +39,45 @@ use tokio::{
     sync::{mpsc, RwLock, Semaphore},
     time::{sleep, timeout},
 };
-use tracing::{info, warn, error, debug};
+use tracing::{info, info_span, warn, error, debug, Instrument};
 use anyhow::{Result, anyhow};
 use thiserror::Error;
 
+// span 初始化与跨任务传递
+mod telemetry;
+use telemetry::{spawn_traced, LogFormat};
+
+// 基于 DatabaseConnection 的通用连接池
+mod pool;
+use pool::{Pool, PoolConfig};
+
+// 结构化异步清理：AsyncDrop + 作用域
+mod cleanup;
+use cleanup::{AsyncDrop, CleanupLog, Nursery};
+
+// 带背压和阶段指标的类型化流水线
+mod pipeline;
+use pipeline::{ErrorPolicy, Pipeline, Stage};
+
+// 回环地址上的最小 HTTP/1.1 服务器
+mod web_server;
+use web_server::{AsyncWebServer, HttpClient, Request, Response};
+
+// 回环地址上的 WebSocket 聊天室
+mod ws_chat;
+use ws_chat::{ChatClient, ChatConfig, ChatEvent, ChatServer};
+
+// 不占调用栈的异步递归、遍历和 AsyncFnMut 回调
+mod async_tools;
+use async_tools::{AsyncMemo, CallbackRegistry, WalkOrder, Walker};
+
+use pinned_self_ref::{PinnedSelfRef, Ref};
+use resilience::{BreakerConfig, Bulkhead, CircuitBreaker, ResilienceError, ResiliencePolicy, Retry};
+
 #[tokio::main]
 async fn main() -> Result<()> {
-    // 初始化日志系统
-    tracing_subscriber::fmt::init();
+    // 初始化日志系统：LOG_FORMAT=json 时输出带 span 链的 JSON
+    telemetry::init(LogFormat::from_env());
     
     info!("🚀 开始 Rust 异步编程疑难问题解决方案演示");
     
@@ -120,6 +151,11 @@ trait DatabaseConnection: Send + Sync {
     async fn connect(&self) -> Result<()>;
     async fn execute_query(&self, query: &str) -> Result<Vec<String>>;
     async fn close(&self) -> Result<()>;
+    
+    /// 健康检查，连接池借出连接前调用
+    async fn ping(&self) -> Result<()> {
+        self.execute_query("SELECT 1").await.map(|_| ())
+    }
 }
 
 /// MySQL 连接实现
@@ -430,50 +466,15 @@ async fn modern_recursive_fibonacci(n: u64) -> u64 {
     }
 }
 
-/// 优化的递归异步函数：带缓存
-#[async_recursion::async_recursion]
-async fn cached_recursive_fibonacci(n: u64, cache: &mut HashMap<u64, u64>) -> u64 {
-    if let Some(&result) = cache.get(&n) {
-        return result;
-    }
-    
-    let result = if n <= 1 {
-        n
-    } else if n > 50 {
-        // 防止溢出，限制最大值
-        u64::MAX
-    } else {
-        let a = cached_recursive_fibonacci(n - 1, cache).await;
-        let b = cached_recursive_fibonacci(n - 2, cache).await;
-        a.saturating_add(b)
-    };
-    
-    cache.insert(n, result);
-    result
-}
-
-/// 递归目录遍历
-#[async_recursion::async_recursion]
-async fn recursive_directory_scan(path: String, depth: usize) -> Result<Vec<String>> {
-    if depth > 10 {
-        return Ok(vec![]);
-    }
-    
-    // 模拟文件系统操作
+/// 模拟异步读取目录：返回子目录列表
+async fn list_directory(path: String) -> Result<Vec<String>> {
     sleep(Duration::from_millis(10)).await;
     
-    let mut results = vec![path.clone()];
-    
-    // 模拟子目录
-    if depth < 3 {
-        for i in 1..=2 {
-            let sub_path = format!("{}/子目录{}", path, i);
-            let mut sub_results = recursive_directory_scan(sub_path, depth + 1).await?;
-            results.append(&mut sub_results);
-        }
+    let depth = path.matches('/').count();
+    if depth > 3 {
+        return Ok(vec![]);
     }
-    
-    Ok(results)
+    Ok((1..=2).map(|i| format!("{}/子目录{}", path, i)).collect())
 }
 
 async fn demonstrate_recursive_async_solutions() -> Result<()> {
@@ -491,21 +492,48 @@ async fn demonstrate_recursive_async_solutions() -> Result<()> {
     info!("\n🔍 3.2 带缓存的递归异步函数");
     
     let start = Instant::now();
-    let mut cache = HashMap::new();
-    let cached_result = cached_recursive_fibonacci(15, &mut cache).await; // 减小数值避免溢出
+    let fibonacci = AsyncMemo::new(|memo: AsyncMemo<u64, u64>, n| async move {
+        if n <= 1 {
+            n
+        } else {
+            memo.get(n - 1).await.saturating_add(memo.get(n - 2).await)
+        }
+    });
+    let cached_result = fibonacci.get(90).await;
     let duration = start.elapsed();
-    info!("      ✅ 缓存斐波那契(15) = {}, 耗时: {:?}", cached_result, duration);
-    info!("      📊 缓存大小: {}", cache.len());
+    info!("      ✅ 缓存斐波那契(90) = {}, 耗时: {:?}", cached_result, duration);
+    info!("      📊 缓存大小: {}, 实际计算次数: {}", fibonacci.len(), fibonacci.computations());
     
     // 3.3 递归目录遍历
     info!("\n🔍 3.3 递归目录遍历");
     
-    let paths = recursive_directory_scan("/根目录".to_string(), 0).await?;
-    info!("      ✅ 扫描到的路径:");
-    for path in paths {
-        info!("        - {}", path);
+    let start = Instant::now();
+    let walker = Walker::new(list_directory).concurrency(8);
+    let paths = walker.walk(["/根目录".to_string()]).await?;
+    info!("      ✅ 广度优先扫描到 {} 个路径，耗时: {:?}", paths.len(), start.elapsed());
+    
+    let walker = Walker::new(list_directory)
+        .order(WalkOrder::DepthFirst)
+        .concurrency(1)
+        .max_depth(2);
+    info!("      ✅ 深度优先扫描（最多两层）:");
+    for visited in walker.walk(["/根目录".to_string()]).await? {
+        info!("        {}- {}", "  ".repeat(visited.depth), visited.node);
     }
     
+    // 3.4 深度递归不再栈溢出
+    info!("\n🔍 3.4 深度递归不再栈溢出");
+    
+    let start = Instant::now();
+    let sum = AsyncMemo::new(|memo: AsyncMemo<u64, u64>, n| async move {
+        if n == 0 {
+            0
+        } else {
+            n + memo.get(n - 1).await
+        }
+    });
+    info!("      ✅ 递归求和(100000) = {}, 耗时: {:?}", sum.get(100_000).await, start.elapsed());
+    
     Ok(())
 }
 
@@ -610,6 +638,31 @@ async fn demonstrate_async_closure_solutions() -> Result<()> {
         Err(e) => info!("      ❌ 处理失败: {}", e),
     }
     
+    // 4.4 AsyncFnMut 回调注册表
+    info!("\n🔍 4.4 AsyncFnMut 回调注册表");
+    
+    let mut registry = CallbackRegistry::new();
+    
+    // 回调直接修改自己捕获的状态，不需要 Arc<Mutex<..>>
+    let mut total = 0;
+    registry.register(async move |x: i32| {
+        sleep(Duration::from_millis(10)).await;
+        total += x;
+        info!("      📈 累计: {}", total);
+    });
+    let mut history = Vec::new();
+    let audit = registry.register(async move |x: i32| {
+        history.push(x);
+        info!("      📝 审计记录: {:?}", history);
+    });
+    
+    for x in [3, 5] {
+        registry.emit(x).await;
+    }
+    registry.unregister(audit);
+    registry.emit(7).await;
+    info!("      ✅ 剩余回调: {}", registry.len());
+    
     Ok(())
 }
 
@@ -675,45 +728,32 @@ impl StreamPipeline {
     async fn backpressure_processing() -> Result<()> {
         info!("\n🔍 5.1 背压控制流处理");
         
-        let (tx, mut rx) = mpsc::channel(10); // 小缓冲区模拟背压
-        
-        // 生产者任务
-        let producer = tokio::spawn(async move {
-            for i in 1..=100 {
-                if let Err(_) = tx.send(i).await {
-                    warn!("生产者发送失败: {}", i);
-                    break;
-                }
-                
-                if i % 10 == 0 {
-                    info!("      📤 生产者已发送: {}", i);
-                }
-                
-                // 模拟生产延迟
-                sleep(Duration::from_millis(10)).await;
-            }
-            info!("      ✅ 生产者完成");
-        });
-        
-        // 消费者任务
-        let consumer = tokio::spawn(async move {
-            let mut count = 0;
-            
-            while let Some(item) = rx.recv().await {
+        // 快速的生产阶段 + 慢速的消费阶段，小缓冲区模拟背压
+        let pipeline = Pipeline::from_iter(1..=100)
+            .stage(
+                Stage::new("生产者", |i: i32| async move {
+                    // 模拟生产延迟
+                    sleep(Duration::from_millis(10)).await;
+                    if i % 10 == 0 {
+                        info!("      📤 生产者已发送: {}", i);
+                    }
+                    Ok(i)
+                })
+                .buffer(10),
+            )
+            .stage(Stage::new("消费者", |item: i32| async move {
                 // 模拟处理时间
                 sleep(Duration::from_millis(50)).await;
-                count += 1;
-                
-                if count % 10 == 0 {
-                    info!("      📥 消费者已处理: {} 项，当前项: {}", count, item);
+                if item % 10 == 0 {
+                    info!("      📥 消费者已处理: {} 项", item);
                 }
-            }
-            
-            info!("      ✅ 消费者完成，总处理: {} 项", count);
-        });
+                Ok(item)
+            }));
+        let metrics = pipeline.metrics();
         
-        // 等待完成
-        let _ = tokio::try_join!(producer, consumer)?;
+        let count = pipeline.collect().await?.len();
+        info!("      ✅ 流水线完成，总处理: {} 项", count);
+        metrics.log_summary();
         
         Ok(())
     }
@@ -722,37 +762,34 @@ impl StreamPipeline {
     async fn error_handling_stream() -> Result<()> {
         info!("\n🔍 5.2 流错误处理和恢复");
         
-        let data_stream = stream::iter(1..=20)
-            .map(|x| async move {
+        // 失败的项目按 Skip 策略跳过，其余项目按完成顺序并发输出
+        let pipeline = Pipeline::from_iter(1..=20).stage(
+            Stage::new("处理", |x: i32| async move {
                 sleep(Duration::from_millis(20)).await;
                 if x % 7 == 0 {
-                    Err(AsyncError::ProcessingError(format!("处理项目 {} 失败", x)))
+                    Err(AsyncError::ProcessingError(format!("处理项目 {} 失败", x)).into())
                 } else {
                     Ok(x * 2)
                 }
             })
-            .buffer_unordered(5); // 并发处理
+            .concurrency(5)
+            .unordered()
+            .on_error(ErrorPolicy::Skip),
+        );
+        let metrics = pipeline.metrics();
         
         let mut success_count = 0;
-        let mut error_count = 0;
-        
-        tokio::pin!(data_stream);
+        let mut data_stream = pipeline.into_stream();
         
         while let Some(result) = data_stream.next().await {
-            match result {
-                Ok(value) => {
-                    success_count += 1;
-                    if success_count % 5 == 0 {
-                        info!("      ✅ 成功处理: {}, 累计成功: {}", value, success_count);
-                    }
-                }
-                Err(e) => {
-                    error_count += 1;
-                    warn!("      ❌ 处理错误: {}, 累计错误: {}", e, error_count);
-                }
+            let value = result?;
+            success_count += 1;
+            if success_count % 5 == 0 {
+                info!("      ✅ 成功处理: {}, 累计成功: {}", value, success_count);
             }
         }
         
+        let error_count = metrics.snapshot()[0].skipped;
         info!("      📊 流处理统计 - 成功: {}, 错误: {}", success_count, error_count);
         
         Ok(())
@@ -805,45 +842,27 @@ async fn demonstrate_stream_processing_solutions() -> Result<()> {
 
 // ==================== 6. Pin 和 Unpin 相关问题 ====================
 
-use std::marker::PhantomPinned;
-
 /// 自引用结构体示例
-#[pin_project]
+///
+/// 不再手写裸指针：字符串放在堆上固定不动，借用它的视图只能在闭包中访问，
+/// 所以结构体本身可以随意移动，视图也不可能比数据活得更久
 struct SelfReferential {
-    data: String,
-    #[pin]
-    pointer: *const u8,
-    _pin: PhantomPinned,
+    inner: PinnedSelfRef<String, Ref<str>>,
 }
 
 impl SelfReferential {
-    fn new(data: String) -> Pin<Box<Self>> {
-        let mut boxed = Box::pin(SelfReferential {
-            pointer: std::ptr::null(),
-            data,
-            _pin: PhantomPinned,
-        });
-        
-        // 安全地设置自引用指针
-        let ptr = boxed.as_ref().data.as_ptr();
-        unsafe {
-            let mut_ref = Pin::as_mut(&mut boxed);
-            mut_ref.get_unchecked_mut().pointer = ptr;
+    fn new(data: String) -> Self {
+        Self {
+            inner: PinnedSelfRef::new(data, |data| data.as_str()),
         }
-        
-        boxed
     }
     
     fn get_data(&self) -> &str {
-        &self.data
+        self.inner.owner()
     }
     
     fn get_pointer_value(&self) -> Option<u8> {
-        if self.pointer.is_null() {
-            None
-        } else {
-            unsafe { Some(*self.pointer) }
-        }
+        self.inner.with(|_, view| view.as_bytes().first().copied())
     }
 }
 
@@ -897,6 +916,8 @@ async fn demonstrate_pin_unpin_solutions() -> Result<()> {
     info!("\n🔍 6.1 自引用结构体");
     
     let self_ref = SelfReferential::new("Hello, Pin!".to_string());
+    // 移动到别处后视图依然有效
+    let self_ref = vec![self_ref].pop().unwrap();
     info!("      ✅ 自引用数据: {}", self_ref.get_data());
     if let Some(first_byte) = self_ref.get_pointer_value() {
         info!("      ✅ 指针指向的第一个字节: {} ('{}')", first_byte, first_byte as char);
@@ -988,6 +1009,13 @@ impl AsyncResource {
     }
 }
 
+#[async_trait]
+impl AsyncDrop for AsyncResource {
+    async fn async_drop(&mut self) -> Result<()> {
+        self.async_cleanup().await
+    }
+}
+
 // 同步 Drop 实现（作为备用）
 impl Drop for AsyncResource {
     fn drop(&mut self) {
@@ -1055,6 +1083,70 @@ async fn demonstrate_async_drop_solutions() -> Result<()> {
     let raii_manager = RaiiAsyncManager::new().await?;
     raii_manager.demonstrate_raii_cleanup().await?;
     
+    // 7.3 结构化异步清理：资源交给作用域，由作用域保证 async_drop 执行
+    info!("\n🔍 7.3 结构化异步清理（Nursery）");
+    
+    let log = CleanupLog::new();
+    let (cleanup_sender, mut cleanup_receiver) = mpsc::unbounded_channel();
+    let cleanup_recorder = tokio::spawn(async move {
+        let mut cleaned = Vec::new();
+        while let Some(resource_id) = cleanup_receiver.recv().await {
+            cleaned.push(resource_id);
+        }
+        cleaned
+    });
+    
+    // 正常结束：按登记的相反顺序清理
+    let sender = cleanup_sender.clone();
+    Nursery::new("正常作用域")
+        .with_log(log.clone())
+        .run(|scope| async move {
+            info!("      🔧 作用域 {} 登记资源", scope.name());
+            let db = scope.adopt("数据库连接", AsyncResource::new("数据库连接".to_string(), sender.clone()))?;
+            scope.adopt("文件句柄", AsyncResource::new("文件句柄".to_string(), sender))?;
+            info!("      🔧 使用资源: {}", db.lock().await.id);
+            Ok(())
+        })
+        .await?;
+    
+    // 返回错误：先清理，再把错误交给调用方
+    let sender = cleanup_sender.clone();
+    let result: Result<()> = Nursery::new("出错作用域")
+        .with_log(log.clone())
+        .run(|scope| async move {
+            scope.adopt("网络连接", AsyncResource::new("网络连接".to_string(), sender))?;
+            Err(anyhow!("模拟业务错误"))
+        })
+        .await;
+    info!("      ❌ 作用域返回错误: {:?}", result.err().map(|e| e.to_string()));
+    
+    // 超时取消：外层 future 被 drop，清理转到后台完成
+    let sender = cleanup_sender.clone();
+    let timed_out = timeout(
+        Duration::from_millis(100),
+        Nursery::new("超时作用域")
+            .with_log(log.clone())
+            .run(|scope| async move {
+                scope.adopt("临时缓存", AsyncResource::new("临时缓存".to_string(), sender))?;
+                sleep(Duration::from_secs(10)).await;
+                Ok(())
+            }),
+    )
+    .await;
+    info!("      ⏰ 作用域超时被取消: {}", timed_out.is_err());
+    
+    log.wait_for(3).await;
+    for report in log.reports() {
+        info!(
+            "      📊 {} -> {:?}，清理顺序: {:?}，泄漏: {:?}",
+            report.scope, report.outcome, report.cleaned, report.leaked
+        );
+    }
+    
+    drop(cleanup_sender);
+    let cleaned = cleanup_recorder.await?;
+    info!("      📝 清理监控共记录 {} 个资源: {:?}", cleaned.len(), cleaned);
+    
     Ok(())
 }
 
@@ -1236,80 +1328,33 @@ async fn demonstrate_performance_optimization() -> Result<()> {
 
 // ==================== 9. 实际应用解决方案 ====================
 
-/// Web 服务器模拟
-struct AsyncWebServer {
-    connection_pool: Arc<Semaphore>,
-    request_count: Arc<RwLock<u64>>,
+/// 不稳定的连接：按一定概率让查询失败，模拟网络抖动
+struct FlakyConnection<C> {
+    inner: C,
+    failure_rate: f64,
 }
 
-impl AsyncWebServer {
-    fn new(max_connections: usize) -> Self {
-        Self {
-            connection_pool: Arc::new(Semaphore::new(max_connections)),
-            request_count: Arc::new(RwLock::new(0)),
-        }
-    }
-    
-    async fn handle_request(&self, request_id: u64) -> Result<String> {
-        let _permit = self.connection_pool.acquire().await?;
-        
-        // 增加请求计数
-        {
-            let mut count = self.request_count.write().await;
-            *count += 1;
-        }
-        
-        // 模拟请求处理
-        sleep(Duration::from_millis(rand::random::<u64>() % 100 + 50)).await;
-        
-        Ok(format!("请求 {} 处理完成", request_id))
+#[async_trait]
+impl<C: DatabaseConnection> DatabaseConnection for FlakyConnection<C> {
+    async fn connect(&self) -> Result<()> {
+        self.inner.connect().await
     }
     
-    async fn get_stats(&self) -> u64 {
-        *self.request_count.read().await
-    }
-}
-
-/// 数据库连接池模拟
-struct AsyncDatabasePool {
-    connections: Arc<Semaphore>,
-    active_queries: Arc<RwLock<HashMap<u64, String>>>,
-}
-
-impl AsyncDatabasePool {
-    fn new(pool_size: usize) -> Self {
-        Self {
-            connections: Arc::new(Semaphore::new(pool_size)),
-            active_queries: Arc::new(RwLock::new(HashMap::new())),
+    async fn execute_query(&self, query: &str) -> Result<Vec<String>> {
+        if rand::random::<f64>() < self.failure_rate {
+            sleep(Duration::from_millis(10)).await;
+            return Err(AsyncError::NetworkError(format!("查询 {} 时连接被重置", query)).into());
         }
+        self.inner.execute_query(query).await
     }
     
-    async fn execute_query(&self, query_id: u64, sql: String) -> Result<Vec<String>> {
-        let _permit = self.connections.acquire().await?;
-        
-        // 记录活跃查询
-        {
-            let mut queries = self.active_queries.write().await;
-            queries.insert(query_id, sql.clone());
-        }
-        
-        // 模拟查询执行
-        sleep(Duration::from_millis(rand::random::<u64>() % 200 + 100)).await;
-        
-        // 移除活跃查询记录
-        {
-            let mut queries = self.active_queries.write().await;
-            queries.remove(&query_id);
-        }
-        
-        Ok(vec![
-            format!("查询 {} 结果1", query_id),
-            format!("查询 {} 结果2", query_id),
-        ])
+    async fn close(&self) -> Result<()> {
+        self.inner.close().await
     }
     
-    async fn get_active_query_count(&self) -> usize {
-        self.active_queries.read().await.len()
+    // 健康检查不受随机故障影响，否则连接池会不停地替换连接
+    async fn ping(&self) -> Result<()> {
+        self.inner.ping().await
     }
 }
 
@@ -1317,29 +1362,50 @@ impl AsyncDatabasePool {
 struct AsyncFileProcessor;
 
 impl AsyncFileProcessor {
+    /// 读取、处理、写入三个阶段各自并发，互相之间通过有界缓冲区施加背压
     async fn process_files(&self, file_paths: Vec<String>) -> Result<Vec<String>> {
-        let results = stream::iter(file_paths)
-            .map(|path| async move {
-                self.process_single_file(path).await
-            })
-            .buffered(5) // 并发处理 5 个文件
-            .collect::<Vec<_>>()
-            .await;
+        let pipeline = self.pipeline(file_paths);
+        let metrics = pipeline.metrics();
+        
+        let results = pipeline.collect().await?;
+        metrics.log_summary();
         
-        results.into_iter().collect()
+        Ok(results)
     }
     
-    async fn process_single_file(&self, file_path: String) -> Result<String> {
-        // 模拟文件读取
-        sleep(Duration::from_millis(50)).await;
-        
-        // 模拟文件处理
-        sleep(Duration::from_millis(100)).await;
-        
-        // 模拟文件写入
-        sleep(Duration::from_millis(30)).await;
-        
-        Ok(format!("处理完成: {}", file_path))
+    fn pipeline(&self, file_paths: Vec<String>) -> Pipeline<String> {
+        Pipeline::from_iter(file_paths)
+            .stage(
+                Stage::new("读取", |path: String| async move {
+                    // 模拟文件读取
+                    sleep(Duration::from_millis(50)).await;
+                    Ok(path)
+                })
+                .concurrency(5)
+                .buffer(5)
+                .on_error(ErrorPolicy::Retry {
+                    max_retries: 3,
+                    backoff: Duration::from_millis(20),
+                }),
+            )
+            .stage(
+                Stage::new("处理", |path: String| async move {
+                    // 模拟文件处理
+                    sleep(Duration::from_millis(100)).await;
+                    Ok(path)
+                })
+                .concurrency(5)
+                .buffer(5),
+            )
+            .stage(
+                Stage::new("写入", |path: String| async move {
+                    // 模拟文件写入
+                    sleep(Duration::from_millis(30)).await;
+                    Ok(format!("处理完成: {}", path))
+                })
+                .concurrency(5)
+                .ordered(),
+            )
     }
 }
 
@@ -1349,54 +1415,106 @@ async fn demonstrate_real_world_solutions() -> Result<()> {
     // 9.1 Web 服务器负载测试
     info!("\n🔍 9.1 Web 服务器负载测试");
     
-    let web_server = Arc::new(AsyncWebServer::new(10));
-    let mut request_handles = Vec::new();
+    // 回环地址上的真实 HTTP/1.1 服务器，最多同时服务 10 个连接
+    let web_server = AsyncWebServer::new(10);
+    let handle = web_server
+        .bind("127.0.0.1:0", |request: Request| async move {
+            // 访问日志在后台任务中写入，仍然挂在这个请求的 span 下面
+            let path = request.path.clone();
+            spawn_traced(async move { debug!("📝 访问日志: {}", path) });
+            
+            // 模拟请求处理
+            sleep(Duration::from_millis(rand::random::<u64>() % 100 + 50)).await;
+            match request.path.strip_prefix("/request/") {
+                Some(id) => Response::ok(format!("请求 {} 处理完成", id)).with_header("X-Request-Id", id),
+                None => Response::new(404, "未知路径"),
+            }
+        })
+        .await?;
+    let addr = handle.local_addr();
+    info!("      🌐 服务器监听: {}", addr);
     
-    // 模拟并发请求
-    for i in 1..=50 {
-        let server = web_server.clone();
+    // 25 个客户端各自通过一个 keep-alive 连接发送 2 个请求
+    let start = Instant::now();
+    let mut request_handles = Vec::new();
+    for client_id in 0..25u64 {
         let handle = tokio::spawn(async move {
-            server.handle_request(i).await
+            let mut client = HttpClient::connect(addr).await?;
+            let mut responses = Vec::new();
+            for n in 1..=2 {
+                responses.push(client.get(&format!("/request/{}", client_id * 2 + n)).await?);
+            }
+            Ok::<_, anyhow::Error>(responses)
         });
         request_handles.push(handle);
     }
     
+    sleep(Duration::from_millis(20)).await;
+    info!("      🔌 当前活跃连接: {}（上限 10，其余在监听队列中等待）", web_server.active_connections());
+    
     // 等待所有请求完成
-    let start = Instant::now();
     let results = futures::future::try_join_all(request_handles).await?;
     let duration = start.elapsed();
     
-    let success_count = results.iter().filter(|r| r.is_ok()).count();
-    let total_requests = web_server.get_stats().await;
+    let responses: Vec<Response> = results.into_iter().collect::<Result<Vec<_>>>()?.into_iter().flatten().collect();
+    let success_count = responses.iter().filter(|r| r.status == 200).count();
+    if let Some(response) = responses.first() {
+        info!("      📄 示例响应: {} (X-Request-Id: {:?})", response.text(), response.header("X-Request-Id"));
+    }
+    
+    let report = handle.shutdown().await;
+    let stats = web_server.stats();
     
     info!("      ✅ Web 服务器测试完成");
-    info!("      📊 成功请求: {}/{}", success_count, results.len());
-    info!("      📊 总请求数: {}", total_requests);
+    info!("      📊 成功请求: {}/{}", success_count, responses.len());
+    info!("      📊 连接数: {}，总请求数: {}，超时: {}", stats.connections, stats.requests, stats.timeouts);
+    info!("      🛑 优雅关闭完成，强制中止连接: {}", report.aborted);
     info!("      ⏱️ 总耗时: {:?}", duration);
     
     // 9.2 数据库连接池测试
     info!("\n🔍 9.2 数据库连接池测试");
     
-    let db_pool = Arc::new(AsyncDatabasePool::new(5));
+    let config = PoolConfig {
+        min_size: 2,
+        max_size: 5,
+        acquire_timeout: Duration::from_secs(2),
+        idle_timeout: Some(Duration::from_secs(30)),
+        max_lifetime: Some(Duration::from_secs(300)),
+        test_on_checkout: true,
+    };
+    let db_pool = Pool::new(config, || MySqlConnection {
+        host: "mysql://localhost:3306".to_string(),
+        connected: Arc::new(RwLock::new(false)),
+    });
+    
+    // 预热到 min_size，之后由后台任务定期维护
+    db_pool.maintain().await?;
+    let maintenance = db_pool.spawn_maintenance(Duration::from_millis(200));
+    info!("      📊 预热后连接池状态: {:?}", db_pool.status());
+    
     let mut query_handles = Vec::new();
     
     // 模拟并发数据库查询
     for i in 1..=20 {
         let pool = db_pool.clone();
-        let handle = tokio::spawn(async move {
-            let sql = format!("SELECT * FROM table_{}", i);
-            pool.execute_query(i, sql).await
-        });
+        let handle = tokio::spawn(
+            async move {
+                let conn = pool.get().await?;
+                debug!("查询 {} 使用连接 #{}", i, conn.id());
+                conn.execute_query(&format!("SELECT * FROM table_{}", i)).await
+            }
+            .instrument(info_span!("db_query", id = i)),
+        );
         query_handles.push(handle);
     }
     
-    // 监控活跃查询
+    // 监控连接池状态
     let monitor_handle = {
         let pool = db_pool.clone();
         tokio::spawn(async move {
-            for _ in 0..10 {
-                let active_count = pool.get_active_query_count().await;
-                info!("      📊 活跃查询数: {}", active_count);
+            for _ in 0..5 {
+                let status = pool.status();
+                info!("      📊 连接数: {}，使用中: {}，空闲: {}", status.size, status.in_use, status.idle);
                 sleep(Duration::from_millis(100)).await;
             }
         })
@@ -1409,6 +1527,60 @@ async fn demonstrate_real_world_solutions() -> Result<()> {
     let successful_queries = query_results.iter().filter(|r| r.is_ok()).count();
     info!("      ✅ 数据库查询完成: {}/{}", successful_queries, query_results.len());
     
+    let status = db_pool.status();
+    info!("      📊 共创建 {} 个连接服务 {} 个查询", status.created, query_results.len());
+    maintenance.abort();
+    db_pool.close().await;
+    info!("      🔒 连接池已关闭: {}", db_pool.is_closed());
+    
+    // 9.2.1 弹性查询：不稳定的连接 + 重试/熔断/舱壁/超时
+    info!("\n🔍 9.2.1 弹性数据库查询");
+    
+    let flaky_pool = Pool::new(PoolConfig::default(), || FlakyConnection {
+        inner: MySqlConnection {
+            host: "mysql://replica:3306".to_string(),
+            connected: Arc::new(RwLock::new(false)),
+        },
+        failure_rate: 0.3,
+    });
+    let breaker = CircuitBreaker::new(BreakerConfig {
+        failure_threshold: 5,
+        open_duration: Duration::from_secs(1),
+        ..BreakerConfig::default()
+    });
+    let policy = ResiliencePolicy::new()
+        .retry(Retry::new(3).backoff(Duration::from_millis(20), Duration::from_millis(200)))
+        .circuit_breaker(breaker.clone())
+        .bulkhead(Bulkhead::new(5, 20))
+        .timeout(Duration::from_millis(300));
+    
+    let query_handles: Vec<_> = (1..=20)
+        .map(|i| {
+            let (pool, policy) = (flaky_pool.clone(), policy.clone());
+            tokio::spawn(async move {
+                let query = format!("SELECT * FROM orders WHERE shard = {}", i);
+                let (pool, query) = (&pool, &query);
+                policy
+                    .call(move || async move {
+                        let conn = pool.get().await?;
+                        conn.execute_query(query).await
+                    })
+                    .await
+            })
+        })
+        .collect();
+    
+    let mut recovered = 0;
+    for result in futures::future::try_join_all(query_handles).await? {
+        match result {
+            Ok(_) => recovered += 1,
+            Err(ResilienceError::CircuitOpen) => warn!("      ⛔ 熔断器打开，查询被拒绝"),
+            Err(e) => warn!("      ❌ 查询最终失败: {}", e),
+        }
+    }
+    info!("      ✅ 30% 故障率下成功查询: {}/20，熔断器: {:?}", recovered, breaker.state());
+    flaky_pool.close().await;
+    
     // 9.3 文件处理管道测试
     info!("\n🔍 9.3 文件处理管道测试");
     
@@ -1432,6 +1604,75 @@ async fn demonstrate_real_world_solutions() -> Result<()> {
         info!("      ... 还有 {} 个文件处理结果", processing_results.len() - 5);
     }
     
+    // 9.4 WebSocket 聊天室
+    info!("\n🔍 9.4 WebSocket 聊天室");
+    
+    /// 在给定时间内持续读取（读取时会自动回复服务器的 Ping），返回收到的聊天消息
+    async fn read_messages(client: &mut ChatClient, duration: Duration) -> Vec<String> {
+        let mut messages = Vec::new();
+        let _ = tokio::time::timeout(duration, async {
+            while let ChatEvent::Message(text) = client.next_event().await {
+                messages.push(text);
+            }
+        })
+        .await;
+        messages
+    }
+    
+    let chat = ChatServer::new(ChatConfig {
+        ping_interval: Duration::from_millis(100),
+        idle_timeout: Duration::from_millis(300),
+        ..ChatConfig::default()
+    });
+    let chat_handle = chat.bind("127.0.0.1:0").await?;
+    let chat_addr = chat_handle.local_addr();
+    
+    let mut alice = ChatClient::connect(chat_addr, "rust").await?;
+    let mut bob = ChatClient::connect(chat_addr, "rust").await?;
+    let mut carol = ChatClient::connect(chat_addr, "go").await?;
+    // 只连接不读取，不会回复 Ping
+    let mut silent = ChatClient::connect(chat_addr, "rust").await?;
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    info!("      🏠 房间: {:?}", chat.rooms().await);
+    
+    alice.send("大家好").await?;
+    carol.send("go 房间有人吗").await?;
+    
+    // 在线客户端持续读取，期间静默客户端因心跳超时被断开
+    let (alice_messages, bob_messages, carol_messages) = tokio::join!(
+        read_messages(&mut alice, Duration::from_millis(500)),
+        read_messages(&mut bob, Duration::from_millis(500)),
+        read_messages(&mut carol, Duration::from_millis(500)),
+    );
+    for text in alice_messages {
+        info!("      💬 [alice] {}", text);
+    }
+    info!("      💬 [bob] 收到 {} 条消息", bob_messages.len());
+    info!("      💬 [carol] 只收到 go 房间的消息: {:?}", carol_messages);
+    
+    loop {
+        if let ChatEvent::Closed(reason) = silent.next_event().await {
+            info!("      💤 静默客户端被断开: {:?}", reason);
+            break;
+        }
+    }
+    
+    bob.close().await;
+    carol.close().await;
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    info!("      🏠 房间: {:?}", chat.rooms().await);
+    
+    chat_handle.shutdown().await;
+    while let ChatEvent::Message(text) = alice.next_event().await {
+        info!("      💬 [alice] {}", text);
+    }
+    info!("      🛑 alice 的连接已关闭");
+    let stats = chat.stats();
+    info!(
+        "      📊 连接 {} 个，消息 {} 条，心跳超时 {} 个，慢消费者断开 {} 个",
+        stats.connections, stats.messages, stats.heartbeat_timeouts, stats.slow_disconnects
+    );
+    
     Ok(())
 }
 
@@ -0,0 +1,599 @@
+//! # 带背压的类型化流水线
+//!
+//! `StreamPipeline::backpressure_processing` 手工连接了一个生产者、一个有界通道和一个消费者。
+//! 这里把这种结构推广为可组合的构建器：
+//! - 每个阶段（`Stage`）运行在独立的任务中，阶段之间用有界通道连接；
+//!   下游处理不过来时上游的 `send` 会等待，背压自然地逐级向上传递
+//! - 阶段声明自己的并发度、输出缓冲区大小和输出顺序（`ordered` / `unordered`）
+//! - 错误策略：跳过（`Skip`）、重试（`Retry`）、中止（`Abort`）
+//! - 每个阶段的指标：吞吐量、队列深度、因背压阻塞的时间
+//!
+//! ```ignore
+//! let pipeline = Pipeline::from_iter(paths)
+//!     .stage(Stage::new("读取", read_file).concurrency(4).buffer(8))
+//!     .stage(Stage::new("解析", parse).unordered().on_error(ErrorPolicy::Skip));
+//! let metrics = pipeline.metrics();
+//! let results = pipeline.collect().await?;
+//! metrics.log_summary();
+//! ```
+//!
+//! 阶段任务在流水线第一次被拉取时才启动，时间统计使用 `tokio::time::Instant`，
+//! 因此可以在暂停时间的测试运行时中得到确定的结果。
+
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::stream::{self, BoxStream, Stream, StreamExt};
+use thiserror::Error;
+use tokio::sync::mpsc;
+use tokio::time::{sleep, Instant};
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{info, info_span, warn, Instrument, Span};
+
+/// 阶段处理失败且策略要求中止时产生的错误
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+#[error("阶段 {stage} 处理失败: {message}")]
+pub struct PipelineError {
+    pub stage: String,
+    pub message: String,
+}
+
+/// 阶段处理单个元素失败时的策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorPolicy {
+    /// 丢弃该元素，继续处理后续元素
+    Skip,
+    /// 按指数退避重试，仍然失败时中止
+    Retry { max_retries: usize, backoff: Duration },
+    /// 把错误传给下游并停止整条流水线
+    Abort,
+}
+
+// ============================================================================
+// 指标
+// ============================================================================
+
+/// 单个阶段的运行指标（原子计数，可在运行中读取）
+#[derive(Debug)]
+pub struct StageMetrics {
+    name: String,
+    processed: AtomicU64,
+    skipped: AtomicU64,
+    failed: AtomicU64,
+    retries: AtomicU64,
+    /// 已写入输出通道 / 已被下游取走的元素数，二者之差就是队列深度
+    enqueued: AtomicU64,
+    dequeued: AtomicU64,
+    max_depth: AtomicU64,
+    blocked_nanos: AtomicU64,
+    started: Mutex<Option<Instant>>,
+    finished: Mutex<Option<Instant>>,
+}
+
+impl StageMetrics {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            processed: AtomicU64::new(0),
+            skipped: AtomicU64::new(0),
+            failed: AtomicU64::new(0),
+            retries: AtomicU64::new(0),
+            enqueued: AtomicU64::new(0),
+            dequeued: AtomicU64::new(0),
+            max_depth: AtomicU64::new(0),
+            blocked_nanos: AtomicU64::new(0),
+            started: Mutex::new(None),
+            finished: Mutex::new(None),
+        }
+    }
+
+    fn record_enqueue(&self, blocked: Duration) {
+        let enqueued = self.enqueued.fetch_add(1, Ordering::Relaxed) + 1;
+        let depth = enqueued.saturating_sub(self.dequeued.load(Ordering::Relaxed));
+        self.max_depth.fetch_max(depth, Ordering::Relaxed);
+        self.blocked_nanos
+            .fetch_add(blocked.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    fn record_dequeue(&self) {
+        self.dequeued.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> StageSnapshot {
+        let started = *self.started.lock().unwrap();
+        let finished = *self.finished.lock().unwrap();
+        let elapsed = match (started, finished) {
+            (Some(start), Some(end)) => end - start,
+            (Some(start), None) => start.elapsed(),
+            _ => Duration::ZERO,
+        };
+        let processed = self.processed.load(Ordering::Relaxed);
+        let enqueued = self.enqueued.load(Ordering::Relaxed);
+
+        StageSnapshot {
+            name: self.name.clone(),
+            processed,
+            skipped: self.skipped.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
+            retries: self.retries.load(Ordering::Relaxed),
+            queue_depth: enqueued.saturating_sub(self.dequeued.load(Ordering::Relaxed)),
+            max_queue_depth: self.max_depth.load(Ordering::Relaxed),
+            blocked: Duration::from_nanos(self.blocked_nanos.load(Ordering::Relaxed)),
+            elapsed,
+            throughput: if elapsed.is_zero() {
+                0.0
+            } else {
+                processed as f64 / elapsed.as_secs_f64()
+            },
+        }
+    }
+}
+
+/// 阶段指标快照
+#[derive(Debug, Clone, PartialEq)]
+pub struct StageSnapshot {
+    pub name: String,
+    pub processed: u64,
+    pub skipped: u64,
+    pub failed: u64,
+    pub retries: u64,
+    /// 输出通道中等待下游处理的元素数
+    pub queue_depth: u64,
+    pub max_queue_depth: u64,
+    /// 因下游缓冲区已满而阻塞在 `send` 上的总时间
+    pub blocked: Duration,
+    pub elapsed: Duration,
+    /// 每秒处理的元素数
+    pub throughput: f64,
+}
+
+/// 整条流水线的指标，克隆后可以在流水线运行时读取
+#[derive(Debug, Clone, Default)]
+pub struct PipelineMetrics {
+    stages: Vec<Arc<StageMetrics>>,
+}
+
+impl PipelineMetrics {
+    pub fn snapshot(&self) -> Vec<StageSnapshot> {
+        self.stages.iter().map(|stage| stage.snapshot()).collect()
+    }
+
+    pub fn log_summary(&self) {
+        for stage in self.snapshot() {
+            info!(
+                "      📊 阶段 {}: 处理 {}，跳过 {}，失败 {}，重试 {}，最大队列深度 {}，背压阻塞 {:?}，吞吐 {:.1}/s",
+                stage.name,
+                stage.processed,
+                stage.skipped,
+                stage.failed,
+                stage.retries,
+                stage.max_queue_depth,
+                stage.blocked,
+                stage.throughput
+            );
+        }
+    }
+}
+
+// ============================================================================
+// Stage
+// ============================================================================
+
+/// 流水线中的一个处理阶段
+pub struct Stage<F> {
+    name: String,
+    handler: F,
+    concurrency: usize,
+    buffer: usize,
+    ordered: bool,
+    policy: ErrorPolicy,
+}
+
+impl<F> Stage<F> {
+    /// 默认：并发度 1，缓冲区 16，保持顺序，出错中止
+    pub fn new(name: impl Into<String>, handler: F) -> Self {
+        Self {
+            name: name.into(),
+            handler,
+            concurrency: 1,
+            buffer: 16,
+            ordered: true,
+            policy: ErrorPolicy::Abort,
+        }
+    }
+
+    /// 同时处理的元素数
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        assert!(concurrency > 0, "并发度必须大于 0");
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// 输出通道容量，下游来不及处理时最多积压这么多元素
+    pub fn buffer(mut self, buffer: usize) -> Self {
+        assert!(buffer > 0, "缓冲区大小必须大于 0");
+        self.buffer = buffer;
+        self
+    }
+
+    /// 按输入顺序输出（默认）
+    pub fn ordered(mut self) -> Self {
+        self.ordered = true;
+        self
+    }
+
+    /// 按完成顺序输出，慢元素不会挡住后面的元素
+    pub fn unordered(mut self) -> Self {
+        self.ordered = false;
+        self
+    }
+
+    pub fn on_error(mut self, policy: ErrorPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+}
+
+// ============================================================================
+// Pipeline
+// ============================================================================
+
+type Item<T> = Result<T, PipelineError>;
+
+/// 类型化的流水线，`T` 是当前最后一个阶段的输出类型
+pub struct Pipeline<T> {
+    stream: BoxStream<'static, Item<T>>,
+    metrics: PipelineMetrics,
+}
+
+impl<T: Send + 'static> Pipeline<T> {
+    pub fn from_stream(source: impl Stream<Item = T> + Send + 'static) -> Self {
+        Self {
+            stream: source.map(Ok).boxed(),
+            metrics: PipelineMetrics::default(),
+        }
+    }
+
+    pub fn from_iter<I>(source: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        I::IntoIter: Send + 'static,
+    {
+        Self::from_stream(stream::iter(source))
+    }
+
+    /// 追加一个阶段
+    pub fn stage<U, F, Fut>(self, stage: Stage<F>) -> Pipeline<U>
+    where
+        T: Clone,
+        U: Send + 'static,
+        F: Fn(T) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = anyhow::Result<U>> + Send + 'static,
+    {
+        let stage_metrics = Arc::new(StageMetrics::new(&stage.name));
+        let mut metrics = self.metrics;
+        metrics.stages.push(Arc::clone(&stage_metrics));
+
+        let input = self.stream;
+        // 阶段任务挂在构建流水线的上下文下面；等到被拉取时再取当前 span，
+        // 上游阶段就会错误地嵌套在下游阶段的任务里
+        let parent = Span::current();
+        // 下游第一次拉取时才启动阶段任务
+        let stream = stream::once(async move {
+            let (tx, rx) = mpsc::channel(stage.buffer);
+            let span = info_span!(parent: &parent, "pipeline_stage", stage = %stage.name);
+            tokio::spawn(run_stage(input, stage, tx, Arc::clone(&stage_metrics)).instrument(span));
+            ReceiverStream::new(rx).inspect(move |_| stage_metrics.record_dequeue())
+        })
+        .flatten()
+        .boxed();
+
+        Pipeline { stream, metrics }
+    }
+
+    pub fn metrics(&self) -> PipelineMetrics {
+        self.metrics.clone()
+    }
+
+    /// 以流的形式消费结果；中止时最后一个元素是错误
+    pub fn into_stream(self) -> BoxStream<'static, Item<T>> {
+        self.stream
+    }
+
+    /// 收集全部结果，遇到第一个错误时停止整条流水线
+    pub async fn collect(self) -> Result<Vec<T>, PipelineError> {
+        let mut stream = self.stream;
+        let mut results = Vec::new();
+        while let Some(item) = stream.next().await {
+            results.push(item?);
+        }
+        Ok(results)
+    }
+}
+
+/// 阶段任务：从上游拉取元素，按并发度处理，再写入有界的输出通道
+async fn run_stage<T, U, F, Fut>(
+    input: BoxStream<'static, Item<T>>,
+    stage: Stage<F>,
+    tx: mpsc::Sender<Item<U>>,
+    metrics: Arc<StageMetrics>,
+) where
+    T: Clone + Send + 'static,
+    U: Send + 'static,
+    F: Fn(T) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = anyhow::Result<U>> + Send + 'static,
+{
+    *metrics.started.lock().unwrap() = Some(Instant::now());
+
+    let Stage {
+        name,
+        handler,
+        concurrency,
+        ordered,
+        policy,
+        ..
+    } = stage;
+    let handler = Arc::new(handler);
+    let name = Arc::new(name);
+
+    let futures = {
+        let metrics = Arc::clone(&metrics);
+        input.map(move |item| {
+            let (handler, metrics, name) = (Arc::clone(&handler), Arc::clone(&metrics), Arc::clone(&name));
+            async move {
+                match item {
+                    // 上游中止产生的错误原样向下传递
+                    Err(e) => Some(Err(e)),
+                    Ok(item) => process_item(&*handler, item, policy, &metrics, &name).await,
+                }
+            }
+        })
+    };
+    let mut outputs = if ordered {
+        futures.buffered(concurrency).boxed()
+    } else {
+        futures.buffer_unordered(concurrency).boxed()
+    };
+
+    while let Some(output) = outputs.next().await {
+        let Some(output) = output else { continue };
+        let abort = output.is_err();
+
+        // 输出通道已满时 send 会等待，这段时间就是背压造成的阻塞
+        let start = Instant::now();
+        if tx.send(output).await.is_err() {
+            // 下游已经停止
+            break;
+        }
+        metrics.record_enqueue(start.elapsed());
+
+        if abort {
+            break;
+        }
+    }
+
+    *metrics.finished.lock().unwrap() = Some(Instant::now());
+}
+
+/// 按错误策略处理单个元素；返回 `None` 表示元素被跳过
+async fn process_item<T, U, F, Fut>(
+    handler: &F,
+    item: T,
+    policy: ErrorPolicy,
+    metrics: &StageMetrics,
+    stage: &str,
+) -> Option<Item<U>>
+where
+    T: Clone,
+    F: Fn(T) -> Fut,
+    Fut: Future<Output = anyhow::Result<U>>,
+{
+    let mut attempt = 0;
+    loop {
+        let error = match handler(item.clone()).await {
+            Ok(output) => {
+                metrics.processed.fetch_add(1, Ordering::Relaxed);
+                return Some(Ok(output));
+            }
+            Err(e) => e,
+        };
+
+        match policy {
+            ErrorPolicy::Skip => {
+                warn!("      ⚠️ 阶段 {} 跳过失败的元素: {}", stage, error);
+                metrics.skipped.fetch_add(1, Ordering::Relaxed);
+                return None;
+            }
+            ErrorPolicy::Retry { max_retries, backoff } if attempt < max_retries => {
+                metrics.retries.fetch_add(1, Ordering::Relaxed);
+                sleep(backoff.saturating_mul(1 << attempt.min(16))).await;
+                attempt += 1;
+            }
+            _ => {
+                warn!("      ❌ 阶段 {} 中止: {}", stage, error);
+                metrics.failed.fetch_add(1, Ordering::Relaxed);
+                return Some(Err(PipelineError {
+                    stage: stage.to_string(),
+                    message: error.to_string(),
+                }));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use std::sync::atomic::AtomicUsize;
+
+    fn delay_ms(value: u64) -> Duration {
+        Duration::from_millis(value)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_ordered_and_unordered_output() {
+        let slow_first = || {
+            Stage::new("延迟", |x: u64| async move {
+                sleep(delay_ms(40 - x * 10)).await;
+                Ok(x)
+            })
+            .concurrency(4)
+        };
+
+        let ordered = Pipeline::from_iter(0..4).stage(slow_first()).collect().await.unwrap();
+        assert_eq!(ordered, vec![0, 1, 2, 3]);
+
+        let unordered = Pipeline::from_iter(0..4)
+            .stage(slow_first().unordered())
+            .collect()
+            .await
+            .unwrap();
+        assert_eq!(unordered, vec![3, 2, 1, 0]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_stages_compose_types() {
+        let pipeline = Pipeline::from_iter(vec!["1", "2", "3"])
+            .stage(Stage::new("解析", |s: &'static str| async move { Ok(s.parse::<i32>()?) }))
+            .stage(Stage::new("格式化", |n: i32| async move { Ok(format!("#{}", n * n)) }));
+
+        assert_eq!(pipeline.collect().await.unwrap(), vec!["#1", "#4", "#9"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_backpressure_is_measured() {
+        let pipeline = Pipeline::from_iter(0..20u32)
+            .stage(Stage::new("快速生产", |x: u32| async move { Ok(x) }).buffer(2))
+            .stage(Stage::new("慢速消费", |x: u32| async move {
+                sleep(delay_ms(10)).await;
+                Ok(x)
+            }));
+        let metrics = pipeline.metrics();
+
+        let start = Instant::now();
+        assert_eq!(pipeline.collect().await.unwrap().len(), 20);
+        assert_eq!(start.elapsed(), delay_ms(200));
+
+        let stages = metrics.snapshot();
+        // 生产阶段大部分时间都在等待下游腾出空间
+        assert!(stages[0].blocked >= delay_ms(150), "阻塞时间: {:?}", stages[0].blocked);
+        assert!(stages[0].max_queue_depth <= 2);
+        assert_eq!(stages[1].blocked, Duration::ZERO);
+        // 慢速阶段 200ms 处理 20 个元素
+        assert!((stages[1].throughput - 100.0).abs() < 1.0);
+        assert_eq!(stages[1].queue_depth, 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_skip_policy_drops_failures() {
+        let pipeline = Pipeline::from_iter(1..=10).stage(
+            Stage::new("过滤", |x: i32| async move {
+                if x % 3 == 0 {
+                    Err(anyhow!("{} 不合法", x))
+                } else {
+                    Ok(x)
+                }
+            })
+            .on_error(ErrorPolicy::Skip),
+        );
+        let metrics = pipeline.metrics();
+
+        assert_eq!(pipeline.collect().await.unwrap(), vec![1, 2, 4, 5, 7, 8, 10]);
+        let stage = &metrics.snapshot()[0];
+        assert_eq!(stage.skipped, 3);
+        assert_eq!(stage.processed, 7);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retry_policy_recovers_transient_errors() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let pipeline = Pipeline::from_iter(vec![7]).stage(
+            Stage::new("不稳定", {
+                let attempts = Arc::clone(&attempts);
+                move |x: i32| {
+                    let attempt = attempts.fetch_add(1, Ordering::SeqCst);
+                    async move {
+                        if attempt < 2 {
+                            Err(anyhow!("第 {} 次失败", attempt + 1))
+                        } else {
+                            Ok(x)
+                        }
+                    }
+                }
+            })
+            .on_error(ErrorPolicy::Retry {
+                max_retries: 3,
+                backoff: delay_ms(10),
+            }),
+        );
+        let metrics = pipeline.metrics();
+
+        let start = Instant::now();
+        assert_eq!(pipeline.collect().await.unwrap(), vec![7]);
+        // 退避 10ms + 20ms
+        assert_eq!(start.elapsed(), delay_ms(30));
+        assert_eq!(metrics.snapshot()[0].retries, 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_abort_stops_whole_pipeline() {
+        let seen = Arc::new(AtomicUsize::new(0));
+        let pipeline = Pipeline::from_iter(0..1000)
+            .stage(Stage::new("计数", {
+                let seen = Arc::clone(&seen);
+                move |x: i32| {
+                    seen.fetch_add(1, Ordering::SeqCst);
+                    async move { Ok(x) }
+                }
+            })
+            .buffer(4))
+            .stage(Stage::new("校验", |x: i32| async move {
+                if x == 5 {
+                    Err(anyhow!("元素 {} 损坏", x))
+                } else {
+                    Ok(x)
+                }
+            }));
+        let metrics = pipeline.metrics();
+
+        let err = pipeline.collect().await.unwrap_err();
+        assert_eq!(err.stage, "校验");
+        assert_eq!(err.message, "元素 5 损坏");
+
+        // 上游很快感知到下游停止，不会把 1000 个元素全部处理完
+        sleep(delay_ms(10)).await;
+        assert!(seen.load(Ordering::SeqCst) < 20);
+        assert_eq!(metrics.snapshot()[1].failed, 1);
+    }
+
+    #[tokio::test]
+    async fn test_stage_spans_hang_off_the_building_context() {
+        let (capture, _guard) = crate::telemetry::testing::Capture::install();
+
+        let pipeline = {
+            let _batch = info_span!("batch", id = 42).entered();
+            Pipeline::from_iter(1..=3)
+                .stage(Stage::new("读取", |x: i32| async move {
+                    info!("读取 {}", x);
+                    Ok(x)
+                }))
+                .stage(Stage::new("写入", |x: i32| async move {
+                    info!("写入 {}", x);
+                    Ok(x)
+                }))
+        };
+        // 在另一个 span 中消费，阶段任务仍然属于 batch
+        let results = pipeline.collect().instrument(info_span!("consumer")).await.unwrap();
+        assert_eq!(results, [1, 2, 3]);
+
+        assert_eq!(capture.event_path("读取 1").as_deref(), Some("batch > pipeline_stage"));
+        assert_eq!(capture.event_path("写入 3").as_deref(), Some("batch > pipeline_stage"));
+        let stages: Vec<_> = capture.spans("pipeline_stage").into_iter().map(|span| span.fields).collect();
+        assert_eq!(stages.len(), 2);
+        assert!(stages.iter().any(|fields| fields.contains("stage=读取")));
+        assert!(stages.iter().any(|fields| fields.contains("stage=写入")));
+    }
+}
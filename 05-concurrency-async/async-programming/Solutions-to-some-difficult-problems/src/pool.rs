@@ -0,0 +1,614 @@
+//! # 通用异步连接池
+//!
+//! `Pool<C>` 真正持有 `DatabaseConnection` 对象，而不只是一个计数信号量：
+//! - 惰性创建：没有可用的空闲连接时才新建，总数不超过 `max_size`
+//! - 借出前健康检查：`ping` 失败的连接被关闭，换一个再试
+//! - 空闲超时 / 最大存活时间：过期的连接在借出或维护时被回收
+//! - 获取超时：池满时最多等待 `acquire_timeout`
+//! - RAII 守卫：`PooledConnection` drop 时自动把连接归还给池
+//! - 后台维护：定期清理过期连接，并把连接数补足到 `min_size`
+//!
+//! 连接数的统计放在 `Conn` 的 Drop 中完成，
+//! 即使 `get()` 在健康检查途中被取消，也不会让计数失真。
+
+use std::collections::VecDeque;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use thiserror::Error;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tracing::{debug, field, info_span, warn, Instrument};
+
+use crate::DatabaseConnection;
+
+/// 连接池配置
+#[derive(Debug, Clone)]
+pub struct PoolConfig {
+    /// 维护任务会把连接数补足到该值，空闲回收也不会低于该值
+    pub min_size: usize,
+    pub max_size: usize,
+    pub acquire_timeout: Duration,
+    /// 空闲超过该时长的连接被回收（`None` 表示不限制）
+    pub idle_timeout: Option<Duration>,
+    /// 创建超过该时长的连接被回收（`None` 表示不限制）
+    pub max_lifetime: Option<Duration>,
+    /// 借出前是否先 `ping` 一次
+    pub test_on_checkout: bool,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            min_size: 0,
+            max_size: 10,
+            acquire_timeout: Duration::from_secs(5),
+            idle_timeout: Some(Duration::from_secs(600)),
+            max_lifetime: Some(Duration::from_secs(1800)),
+            test_on_checkout: true,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum PoolError {
+    #[error("获取连接超时（等待了 {0:?}）")]
+    Timeout(Duration),
+    #[error("创建连接失败: {0}")]
+    Connect(#[source] anyhow::Error),
+    #[error("连接池已关闭")]
+    Closed,
+}
+
+/// 连接池状态快照
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolStatus {
+    /// 当前存活的连接数（空闲 + 借出）
+    pub size: usize,
+    pub idle: usize,
+    pub in_use: usize,
+    /// 累计创建 / 关闭的连接数
+    pub created: u64,
+    pub closed: u64,
+    pub health_check_failures: u64,
+    pub timeouts: u64,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    size: AtomicUsize,
+    created: AtomicU64,
+    closed: AtomicU64,
+    health_check_failures: AtomicU64,
+    timeouts: AtomicU64,
+}
+
+/// 池中的一条连接
+struct Conn<C> {
+    id: u64,
+    raw: C,
+    created_at: Instant,
+    counters: Arc<Counters>,
+}
+
+impl<C> Drop for Conn<C> {
+    fn drop(&mut self) {
+        self.counters.size.fetch_sub(1, Ordering::Relaxed);
+        self.counters.closed.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+struct IdleConn<C> {
+    conn: Conn<C>,
+    idle_since: Instant,
+}
+
+struct PoolInner<C> {
+    config: PoolConfig,
+    factory: Box<dyn Fn() -> C + Send + Sync>,
+    /// 每个借出（或正在创建）的连接占用一个许可
+    semaphore: Arc<Semaphore>,
+    idle: Mutex<VecDeque<IdleConn<C>>>,
+    counters: Arc<Counters>,
+    next_id: AtomicU64,
+}
+
+impl<C: DatabaseConnection + 'static> PoolInner<C> {
+    fn lifetime_expired(&self, conn: &Conn<C>, now: Instant) -> bool {
+        self.config
+            .max_lifetime
+            .is_some_and(|lifetime| now.duration_since(conn.created_at) >= lifetime)
+    }
+
+    fn idle_expired(&self, idle: &IdleConn<C>, now: Instant) -> bool {
+        self.config
+            .idle_timeout
+            .is_some_and(|timeout| now.duration_since(idle.idle_since) >= timeout)
+    }
+
+    async fn open(&self) -> Result<Conn<C>, PoolError> {
+        let raw = (self.factory)();
+        raw.connect().await.map_err(PoolError::Connect)?;
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.counters.size.fetch_add(1, Ordering::Relaxed);
+        self.counters.created.fetch_add(1, Ordering::Relaxed);
+        debug!("🔌 连接池创建连接 #{}", id);
+        Ok(Conn {
+            id,
+            raw,
+            created_at: Instant::now(),
+            counters: Arc::clone(&self.counters),
+        })
+    }
+
+    async fn discard(conn: Conn<C>) {
+        debug!("🗑️ 连接池关闭连接 #{}", conn.id);
+        if let Err(e) = conn.raw.close().await {
+            warn!("关闭连接 #{} 失败: {}", conn.id, e);
+        }
+    }
+}
+
+/// 通用异步连接池，克隆后共享同一个池
+pub struct Pool<C: DatabaseConnection + 'static> {
+    inner: Arc<PoolInner<C>>,
+}
+
+impl<C: DatabaseConnection + 'static> Clone for Pool<C> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<C: DatabaseConnection + 'static> Pool<C> {
+    /// 创建连接池；连接由 `factory` 构造并在第一次需要时才 `connect`
+    pub fn new(config: PoolConfig, factory: impl Fn() -> C + Send + Sync + 'static) -> Self {
+        assert!(config.max_size > 0, "max_size 必须大于 0");
+        assert!(config.min_size <= config.max_size, "min_size 不能超过 max_size");
+
+        Self {
+            inner: Arc::new(PoolInner {
+                semaphore: Arc::new(Semaphore::new(config.max_size)),
+                config,
+                factory: Box::new(factory),
+                idle: Mutex::new(VecDeque::new()),
+                counters: Arc::new(Counters::default()),
+                next_id: AtomicU64::new(1),
+            }),
+        }
+    }
+
+    pub fn status(&self) -> PoolStatus {
+        let counters = &self.inner.counters;
+        let size = counters.size.load(Ordering::Relaxed);
+        let idle = self.inner.idle.lock().unwrap().len();
+        PoolStatus {
+            size,
+            idle,
+            in_use: size.saturating_sub(idle),
+            created: counters.created.load(Ordering::Relaxed),
+            closed: counters.closed.load(Ordering::Relaxed),
+            health_check_failures: counters.health_check_failures.load(Ordering::Relaxed),
+            timeouts: counters.timeouts.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.inner.semaphore.is_closed()
+    }
+
+    /// 借出一个连接，最多等待 `acquire_timeout`
+    pub async fn get(&self) -> Result<PooledConnection<C>, PoolError> {
+        // 等待许可、健康检查、创建连接都记在调用者的请求下面
+        let span = info_span!("pool_get", conn = field::Empty);
+        let timeout = self.inner.config.acquire_timeout;
+        match tokio::time::timeout(timeout, self.checkout().instrument(span.clone())).await {
+            Ok(Ok(conn)) => {
+                span.record("conn", conn.id());
+                Ok(conn)
+            }
+            Ok(Err(e)) => Err(e),
+            Err(_) => {
+                self.inner.counters.timeouts.fetch_add(1, Ordering::Relaxed);
+                Err(PoolError::Timeout(timeout))
+            }
+        }
+    }
+
+    async fn checkout(&self) -> Result<PooledConnection<C>, PoolError> {
+        let permit = Arc::clone(&self.inner.semaphore)
+            .acquire_owned()
+            .await
+            .map_err(|_| PoolError::Closed)?;
+
+        // 优先复用最近归还的连接，让长期不用的连接自然空闲超时
+        loop {
+            let candidate = self.inner.idle.lock().unwrap().pop_back();
+            let Some(idle) = candidate else { break };
+
+            let now = Instant::now();
+            if self.inner.lifetime_expired(&idle.conn, now) || self.inner.idle_expired(&idle, now) {
+                PoolInner::discard(idle.conn).await;
+                continue;
+            }
+
+            if self.inner.config.test_on_checkout {
+                if let Err(e) = idle.conn.raw.ping().await {
+                    warn!("连接 #{} 健康检查失败: {}", idle.conn.id, e);
+                    self.inner
+                        .counters
+                        .health_check_failures
+                        .fetch_add(1, Ordering::Relaxed);
+                    PoolInner::discard(idle.conn).await;
+                    continue;
+                }
+            }
+
+            return Ok(PooledConnection::new(idle.conn, Arc::clone(&self.inner), permit));
+        }
+
+        let conn = self.inner.open().await?;
+        Ok(PooledConnection::new(conn, Arc::clone(&self.inner), permit))
+    }
+
+    /// 回收过期的空闲连接，并把连接数补足到 `min_size`
+    pub async fn maintain(&self) -> Result<(), PoolError> {
+        let now = Instant::now();
+        let expired: Vec<Conn<C>> = {
+            let mut idle = self.inner.idle.lock().unwrap();
+            let size = self.inner.counters.size.load(Ordering::Relaxed);
+            // 空闲超时的回收不能让连接数低于 min_size；超过最大存活时间的总是回收
+            let mut removable = size.saturating_sub(self.inner.config.min_size);
+            let mut expired = Vec::new();
+            let mut kept = VecDeque::with_capacity(idle.len());
+            for item in idle.drain(..) {
+                if self.inner.lifetime_expired(&item.conn, now) {
+                    removable = removable.saturating_sub(1);
+                    expired.push(item.conn);
+                } else if removable > 0 && self.inner.idle_expired(&item, now) {
+                    removable -= 1;
+                    expired.push(item.conn);
+                } else {
+                    kept.push_back(item);
+                }
+            }
+            *idle = kept;
+            expired
+        };
+        for conn in expired {
+            PoolInner::discard(conn).await;
+        }
+
+        while self.inner.counters.size.load(Ordering::Relaxed) < self.inner.config.min_size {
+            // 创建连接同样要占用许可，保证总数不超过 max_size；池正忙时留到下次
+            let Ok(_permit) = Arc::clone(&self.inner.semaphore).try_acquire_owned() else {
+                break;
+            };
+            let conn = self.inner.open().await?;
+            self.inner.idle.lock().unwrap().push_back(IdleConn {
+                conn,
+                idle_since: Instant::now(),
+            });
+        }
+        Ok(())
+    }
+
+    /// 启动后台维护任务；连接池被 drop 或关闭后任务自动退出
+    pub fn spawn_maintenance(&self, interval: Duration) -> JoinHandle<()> {
+        let weak = Arc::downgrade(&self.inner);
+        // 后台任务活得比启动它的请求更久，不挂在调用者的 span 下面
+        let span = info_span!(parent: None, "pool_maintenance");
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let Some(inner) = weak.upgrade() else { break };
+                let pool = Pool { inner };
+                if pool.is_closed() {
+                    break;
+                }
+                if let Err(e) = pool.maintain().await {
+                    warn!("连接池维护失败: {}", e);
+                }
+            }
+        }
+        .instrument(span))
+    }
+
+    /// 关闭连接池：拒绝新的 `get`，关闭所有空闲连接；借出的连接归还时关闭
+    pub async fn close(&self) {
+        self.inner.semaphore.close();
+        let idle: Vec<_> = self.inner.idle.lock().unwrap().drain(..).collect();
+        for item in idle {
+            PoolInner::discard(item.conn).await;
+        }
+    }
+}
+
+/// 借出的连接，drop 时归还给连接池
+pub struct PooledConnection<C: DatabaseConnection + 'static> {
+    conn: Option<Conn<C>>,
+    pool: Arc<PoolInner<C>>,
+    // 在 Drop 中先归还连接，再释放许可，等待者才能看到归还的连接
+    _permit: OwnedSemaphorePermit,
+}
+
+impl<C: DatabaseConnection + 'static> PooledConnection<C> {
+    fn new(conn: Conn<C>, pool: Arc<PoolInner<C>>, permit: OwnedSemaphorePermit) -> Self {
+        Self {
+            conn: Some(conn),
+            pool,
+            _permit: permit,
+        }
+    }
+
+    /// 连接在池内的编号
+    pub fn id(&self) -> u64 {
+        self.conn.as_ref().unwrap().id
+    }
+}
+
+impl<C: DatabaseConnection + 'static> Deref for PooledConnection<C> {
+    type Target = C;
+
+    fn deref(&self) -> &C {
+        &self.conn.as_ref().unwrap().raw
+    }
+}
+
+impl<C: DatabaseConnection + 'static> DerefMut for PooledConnection<C> {
+    fn deref_mut(&mut self) -> &mut C {
+        &mut self.conn.as_mut().unwrap().raw
+    }
+}
+
+impl<C: DatabaseConnection + 'static> Drop for PooledConnection<C> {
+    fn drop(&mut self) {
+        let Some(conn) = self.conn.take() else { return };
+
+        let now = Instant::now();
+        if self.pool.semaphore.is_closed() || self.pool.lifetime_expired(&conn, now) {
+            // Drop 中不能 await，关闭操作交给运行时；没有运行时就直接丢弃
+            if let Ok(handle) = tokio::runtime::Handle::try_current() {
+                handle.spawn(PoolInner::discard(conn).in_current_span());
+            }
+            return;
+        }
+
+        self.pool.idle.lock().unwrap().push_back(IdleConn {
+            conn,
+            idle_since: now,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::{anyhow, Result};
+    use async_trait::async_trait;
+    use std::sync::atomic::AtomicBool;
+
+    /// 可以从外部切换健康状态的测试连接
+    struct TestConnection {
+        healthy: Arc<AtomicBool>,
+        connects: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl DatabaseConnection for TestConnection {
+        async fn connect(&self) -> Result<()> {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            self.connects.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn execute_query(&self, query: &str) -> Result<Vec<String>> {
+            if self.healthy.load(Ordering::SeqCst) {
+                Ok(vec![query.to_string()])
+            } else {
+                Err(anyhow!("连接已断开"))
+            }
+        }
+
+        async fn close(&self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    struct Harness {
+        pool: Pool<TestConnection>,
+        healthy: Arc<AtomicBool>,
+        connects: Arc<AtomicUsize>,
+    }
+
+    fn harness(config: PoolConfig) -> Harness {
+        let healthy = Arc::new(AtomicBool::new(true));
+        let connects = Arc::new(AtomicUsize::new(0));
+        let pool = {
+            let (healthy, connects) = (Arc::clone(&healthy), Arc::clone(&connects));
+            Pool::new(config, move || TestConnection {
+                healthy: Arc::clone(&healthy),
+                connects: Arc::clone(&connects),
+            })
+        };
+        Harness {
+            pool,
+            healthy,
+            connects,
+        }
+    }
+
+    fn config(max_size: usize) -> PoolConfig {
+        PoolConfig {
+            max_size,
+            acquire_timeout: Duration::from_secs(1),
+            ..PoolConfig::default()
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_lazy_creation_and_reuse() {
+        let h = harness(config(4));
+        assert_eq!(h.pool.status().size, 0);
+
+        let first = h.pool.get().await.unwrap();
+        let id = first.id();
+        assert_eq!(first.execute_query("SELECT 1").await.unwrap(), vec!["SELECT 1"]);
+        drop(first);
+
+        // 归还后再次借出的是同一条连接
+        let second = h.pool.get().await.unwrap();
+        assert_eq!(second.id(), id);
+        assert_eq!(h.connects.load(Ordering::SeqCst), 1);
+        assert_eq!(h.pool.status().in_use, 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_acquire_times_out_when_exhausted() {
+        let h = harness(config(2));
+        let _a = h.pool.get().await.unwrap();
+        let _b = h.pool.get().await.unwrap();
+
+        let start = Instant::now();
+        let err = h.pool.get().await.err().unwrap();
+        assert!(matches!(err, PoolError::Timeout(_)));
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+        assert_eq!(h.pool.status().timeouts, 1);
+        assert_eq!(h.pool.status().size, 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_waiter_receives_returned_connection() {
+        let h = harness(config(1));
+        let held = h.pool.get().await.unwrap();
+        let held_id = held.id();
+
+        let waiter = {
+            let pool = h.pool.clone();
+            tokio::spawn(async move { pool.get().await.map(|conn| conn.id()) })
+        };
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        drop(held);
+
+        assert_eq!(waiter.await.unwrap().unwrap(), held_id);
+        assert_eq!(h.pool.status().created, 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_unhealthy_connection_replaced_on_checkout() {
+        let h = harness(config(2));
+        let conn = h.pool.get().await.unwrap();
+        let old_id = conn.id();
+        drop(conn);
+
+        h.healthy.store(false, Ordering::SeqCst);
+        let conn = h.pool.get().await.unwrap();
+        assert_ne!(conn.id(), old_id);
+
+        let status = h.pool.status();
+        assert_eq!(status.health_check_failures, 1);
+        assert_eq!(status.created, 2);
+        assert_eq!(status.closed, 1);
+        assert_eq!(status.size, 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_idle_timeout_and_max_lifetime() {
+        let h = harness(PoolConfig {
+            idle_timeout: Some(Duration::from_secs(60)),
+            max_lifetime: Some(Duration::from_secs(300)),
+            ..config(2)
+        });
+
+        let id = h.pool.get().await.unwrap().id();
+        tokio::time::advance(Duration::from_secs(61)).await;
+        // 空闲超时的连接不会再被借出
+        let conn = h.pool.get().await.unwrap();
+        assert_ne!(conn.id(), id);
+
+        // 借出期间超过最大存活时间，归还时直接关闭
+        tokio::time::advance(Duration::from_secs(301)).await;
+        drop(conn);
+        tokio::task::yield_now().await;
+        assert_eq!(h.pool.status().size, 0);
+        assert_eq!(h.pool.status().idle, 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_maintenance_keeps_min_size() {
+        let h = harness(PoolConfig {
+            min_size: 2,
+            idle_timeout: Some(Duration::from_secs(60)),
+            ..config(4)
+        });
+
+        h.pool.maintain().await.unwrap();
+        assert_eq!(h.pool.status().idle, 2);
+
+        // 四条连接全部空闲超时，只回收到 min_size 为止
+        let conns: Vec<_> = futures::future::join_all((0..4).map(|_| h.pool.get())).await;
+        drop(conns);
+        assert_eq!(h.pool.status().size, 4);
+        tokio::time::advance(Duration::from_secs(61)).await;
+        h.pool.maintain().await.unwrap();
+        assert_eq!(h.pool.status().size, 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_close_rejects_new_checkouts() {
+        let h = harness(config(2));
+        let conn = h.pool.get().await.unwrap();
+        drop(h.pool.get().await.unwrap());
+
+        h.pool.close().await;
+        assert!(matches!(h.pool.get().await, Err(PoolError::Closed)));
+        assert_eq!(h.pool.status().idle, 0);
+
+        drop(conn);
+        tokio::task::yield_now().await;
+        assert_eq!(h.pool.status().size, 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_checkout_spans_follow_the_caller() {
+        let (capture, _guard) = crate::telemetry::testing::Capture::install();
+        let h = harness(PoolConfig {
+            min_size: 1,
+            ..config(2)
+        });
+
+        async {
+            drop(h.pool.get().await.unwrap());
+            drop(h.pool.get().await.unwrap());
+        }
+        .instrument(info_span!("query"))
+        .await;
+
+        // 第一次借出时创建连接，事件挂在调用者的 pool_get 下面
+        assert_eq!(capture.event_path("连接池创建连接 #1").as_deref(), Some("query > pool_get"));
+        let checkouts = capture.spans("pool_get");
+        assert_eq!(checkouts.len(), 2);
+        assert!(checkouts.iter().all(|span| span.path == "query > pool_get" && span.fields.contains("conn=1")));
+
+        // 维护任务的生命周期和启动它的请求无关，是一棵独立的 span 树
+        let h = harness(PoolConfig {
+            min_size: 1,
+            ..config(2)
+        });
+        let maintenance = {
+            let _request = info_span!("startup").entered();
+            h.pool.spawn_maintenance(Duration::from_secs(1))
+        };
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        maintenance.abort();
+        assert_eq!(capture.spans("pool_maintenance")[0].path, "pool_maintenance");
+        assert_eq!(h.pool.status().idle, 1);
+    }
+}
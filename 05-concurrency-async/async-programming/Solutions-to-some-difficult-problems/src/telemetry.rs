@@ -0,0 +1,248 @@
+//! # 跨任务的链路追踪
+//!
+//! `info!` 只记录一行文字，看不出它属于哪个请求。`tracing` 的 span 可以把一次请求里的
+//! 所有日志串起来，但 `tokio::spawn` 出去的任务默认不会继承当前 span，链路就在这里断开。
+//! 本模块约定：
+//! - 需要跟随请求的后台任务都用 [`spawn_traced`]（或 `Instrument`）启动
+//! - 连接池、Web 服务器和流水线在各自的任务边界上创建 span：
+//!
+//! ```text
+//! http_server{addr} > connection{peer} > http_request{method, path, status}
+//! pipeline_stage{stage}
+//! pool_get{conn}        pool_maintenance
+//! ```
+//!
+//! - `LOG_FORMAT=json` 时输出 JSON，每行带上当前 span 及其所有祖先，方便日志系统按请求聚合
+//! - 测试中用 `testing::Capture` 记录 span 树并断言父子关系
+
+use std::future::Future;
+
+use tokio::task::JoinHandle;
+use tracing::Instrument;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// 面向人阅读的多彩文本（默认）
+    Pretty,
+    /// 每行一个 JSON 对象
+    Json,
+}
+
+impl LogFormat {
+    /// 读取 `LOG_FORMAT` 环境变量，`json` 表示 JSON 格式
+    pub fn from_env() -> Self {
+        match std::env::var("LOG_FORMAT") {
+            Ok(format) if format.eq_ignore_ascii_case("json") => LogFormat::Json,
+            _ => LogFormat::Pretty,
+        }
+    }
+}
+
+/// 安装全局订阅者，日志级别由 `RUST_LOG` 控制
+pub fn init(format: LogFormat) {
+    let registry = tracing_subscriber::registry().with(EnvFilter::from_default_env());
+    match format {
+        LogFormat::Pretty => registry.with(tracing_subscriber::fmt::layer()).init(),
+        LogFormat::Json => registry.with(json_layer(std::io::stdout)).init(),
+    }
+}
+
+/// JSON 输出层：事件带上 span 链，span 关闭时额外输出一行耗时
+fn json_layer<S, W>(writer: W) -> impl Layer<S>
+where
+    S: tracing::Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    tracing_subscriber::fmt::layer()
+        .json()
+        .with_current_span(true)
+        .with_span_list(true)
+        .with_span_events(FmtSpan::CLOSE)
+        .with_writer(writer)
+}
+
+/// 在当前 span 中启动任务，任务里的日志和子 span 都挂在调用者的请求下面
+pub fn spawn_traced<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    tokio::spawn(future.in_current_span())
+}
+
+/// 记录 span 树的测试订阅者
+///
+/// 订阅者通过 `set_default` 安装在当前线程上，所以只适用于单线程运行时
+/// （`#[tokio::test]` 的默认设置），所有任务都在这个线程上执行。
+#[cfg(test)]
+pub mod testing {
+    use std::fmt::Write;
+    use std::sync::{Arc, Mutex};
+
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::subscriber::DefaultGuard;
+    use tracing::{Event, Subscriber};
+    use tracing_subscriber::layer::{Context, SubscriberExt};
+    use tracing_subscriber::registry::LookupSpan;
+    use tracing_subscriber::Layer;
+
+    /// 一个 span：从根到自己的路径，例如 `http_server > connection > http_request`
+    #[derive(Debug, Clone)]
+    pub struct SpanRecord {
+        pub path: String,
+        pub fields: String,
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct EventRecord {
+        pub path: String,
+        pub message: String,
+    }
+
+    #[derive(Clone, Default)]
+    pub struct Capture {
+        spans: Arc<Mutex<Vec<(Id, SpanRecord)>>>,
+        events: Arc<Mutex<Vec<EventRecord>>>,
+    }
+
+    impl Capture {
+        pub fn install() -> (Self, DefaultGuard) {
+            let capture = Self::default();
+            let subscriber = tracing_subscriber::registry().with(capture.clone());
+            (capture, tracing::subscriber::set_default(subscriber))
+        }
+
+        /// 所有名为 `name` 的 span（按创建顺序）
+        pub fn spans(&self, name: &str) -> Vec<SpanRecord> {
+            let suffix = format!(" > {}", name);
+            self.spans
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(_, span)| span.path == name || span.path.ends_with(&suffix))
+                .map(|(_, span)| span.clone())
+                .collect()
+        }
+
+        /// 第一条消息包含 `text` 的事件所在的 span 路径
+        pub fn event_path(&self, text: &str) -> Option<String> {
+            self.events
+                .lock()
+                .unwrap()
+                .iter()
+                .find(|event| event.message.contains(text))
+                .map(|event| event.path.clone())
+        }
+    }
+
+    fn path<S: for<'a> LookupSpan<'a>>(scope: Option<tracing_subscriber::registry::Scope<'_, S>>) -> String {
+        scope
+            .map(|scope| scope.from_root().map(|span| span.name()).collect::<Vec<_>>().join(" > "))
+            .unwrap_or_default()
+    }
+
+    struct FieldWriter<'a>(&'a mut String);
+
+    impl Visit for FieldWriter<'_> {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            let _ = write!(self.0, "{}={:?} ", field.name(), value);
+        }
+        fn record_str(&mut self, field: &Field, value: &str) {
+            let _ = write!(self.0, "{}={} ", field.name(), value);
+        }
+    }
+
+    impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for Capture {
+        fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+            let mut fields = String::new();
+            attrs.record(&mut FieldWriter(&mut fields));
+            let path = path(ctx.span_scope(id));
+            self.spans.lock().unwrap().push((id.clone(), SpanRecord { path, fields }));
+        }
+
+        fn on_record(&self, id: &Id, values: &Record<'_>, _ctx: Context<'_, S>) {
+            // span id 可能被复用，更新最近创建的那一个
+            if let Some((_, span)) = self.spans.lock().unwrap().iter_mut().rev().find(|(span_id, _)| span_id == id) {
+                values.record(&mut FieldWriter(&mut span.fields));
+            }
+        }
+
+        fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+            let mut message = String::new();
+            event.record(&mut FieldWriter(&mut message));
+            let path = path(ctx.event_scope(event));
+            self.events.lock().unwrap().push(EventRecord { path, message });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::testing::Capture;
+    use super::*;
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+    use tracing::{info, info_span};
+
+    #[tokio::test]
+    async fn test_spawn_traced_keeps_request_span() {
+        let (capture, _guard) = Capture::install();
+
+        async {
+            spawn_traced(async { info!("后台任务") }).await.unwrap();
+            tokio::spawn(async { info!("普通任务") }).await.unwrap();
+        }
+        .instrument(info_span!("request", id = 7))
+        .await;
+
+        assert_eq!(capture.event_path("后台任务").as_deref(), Some("request"));
+        // 没有传递 span 的任务丢失了上下文
+        assert_eq!(capture.event_path("普通任务").as_deref(), Some(""));
+        assert!(capture.spans("request")[0].fields.contains("id=7"));
+    }
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_json_lines_carry_span_list() {
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let subscriber = tracing_subscriber::registry().with(json_layer(move || writer.clone()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let _outer = info_span!("http_request", path = "/users").entered();
+            let _inner = info_span!("pool_get").entered();
+            info!(rows = 3, "查询完成");
+        });
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<serde_json::Value> = output.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+
+        let event = &lines[0];
+        assert_eq!(event["fields"]["message"], "查询完成");
+        assert_eq!(event["fields"]["rows"], 3);
+        assert_eq!(event["span"]["name"], "pool_get");
+        assert_eq!(event["spans"][0]["name"], "http_request");
+        assert_eq!(event["spans"][0]["path"], "/users");
+        // 两个 span 关闭时各输出一行，带有耗时
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1]["fields"]["time.busy"].is_string());
+    }
+}
@@ -0,0 +1,783 @@
+//! # 基于真实套接字的 HTTP/1.1 服务器
+//!
+//! `AsyncWebServer` 原本只是在信号量后面睡眠并累加计数器，这里把它变成一个
+//! 监听回环地址的最小 HTTP/1.1 服务器，让并发模式在真实的 TCP 连接上运行：
+//! - 连接上限：accept 之前先从信号量拿许可，超出上限的连接留在内核监听队列里排队
+//! - keep-alive：HTTP/1.1 默认复用连接，`Connection: close` 或 HTTP/1.0 时关闭
+//! - 超时：读取请求超时返回 408，处理超时返回 503，空闲连接超时直接关闭
+//! - 优雅关闭：停止 accept，空闲连接立即关闭，进行中的请求处理完再关闭，
+//!   超过 `shutdown_timeout` 仍未结束的连接被强制中止
+//!
+//! 请求解析只支持 `Content-Length` 请求体，不支持分块传输编码。
+
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use thiserror::Error;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::sync::{watch, OwnedSemaphorePermit, Semaphore};
+use tokio::task::{JoinHandle, JoinSet};
+use tokio::time::timeout;
+use tracing::{debug, field, info_span, warn, Instrument};
+
+const MAX_HEAD_SIZE: usize = 8 * 1024;
+const MAX_BODY_SIZE: usize = 1024 * 1024;
+
+/// 服务器配置
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// 同时服务的最大连接数
+    pub max_connections: usize,
+    /// 读取一个完整请求（以及 keep-alive 空闲等待）的时限
+    pub read_timeout: Duration,
+    /// 处理器生成响应的时限
+    pub handler_timeout: Duration,
+    /// 优雅关闭时等待进行中请求的时限
+    pub shutdown_timeout: Duration,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: 10,
+            read_timeout: Duration::from_secs(5),
+            handler_timeout: Duration::from_secs(30),
+            shutdown_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+// ============================================================================
+// 请求与响应
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Version {
+    Http10,
+    Http11,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub version: Version,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// 按名称查找请求头（大小写不敏感）
+    pub fn header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+
+    fn keep_alive(&self) -> bool {
+        let connection = self.header("connection").map(str::to_ascii_lowercase);
+        match self.version {
+            Version::Http11 => connection.as_deref() != Some("close"),
+            Version::Http10 => connection.as_deref() == Some("keep-alive"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn new(status: u16, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: body.into(),
+        }
+    }
+
+    pub fn ok(body: impl Into<Vec<u8>>) -> Self {
+        Self::new(200, body)
+    }
+
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            408 => "Request Timeout",
+            413 => "Payload Too Large",
+            500 => "Internal Server Error",
+            503 => "Service Unavailable",
+            _ => "Unknown",
+        }
+    }
+}
+
+fn find_header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+// ============================================================================
+// 报文读取
+// ============================================================================
+
+#[derive(Debug, Error)]
+enum ReadError {
+    #[error("连接已关闭")]
+    Closed,
+    #[error("报文格式错误: {0}")]
+    Malformed(&'static str),
+    #[error("报文过大")]
+    TooLarge,
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// 起始行 + 头部 + 请求体
+type Message = (String, Vec<(String, String)>, Vec<u8>);
+
+/// 从套接字读取一些字节追加到缓冲区；只在 `read` 完成后才修改缓冲区，因此可以安全地取消
+async fn fill(stream: &mut TcpStream, buf: &mut Vec<u8>) -> io::Result<usize> {
+    let mut chunk = [0u8; 4096];
+    let n = stream.read(&mut chunk).await?;
+    buf.extend_from_slice(&chunk[..n]);
+    Ok(n)
+}
+
+/// 读取一个完整报文；缓冲区中多出的字节（流水线化的下一个请求）保留给下一次调用
+async fn read_message(stream: &mut TcpStream, buf: &mut Vec<u8>) -> Result<Message, ReadError> {
+    let head_end = loop {
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos;
+        }
+        if buf.len() > MAX_HEAD_SIZE {
+            return Err(ReadError::TooLarge);
+        }
+        if fill(stream, buf).await? == 0 {
+            return Err(if buf.is_empty() {
+                ReadError::Closed
+            } else {
+                ReadError::Malformed("报文头未结束连接就关闭了")
+            });
+        }
+    };
+
+    let head = std::str::from_utf8(&buf[..head_end]).map_err(|_| ReadError::Malformed("报文头不是 UTF-8"))?;
+    let mut lines = head.split("\r\n");
+    let start_line = lines.next().unwrap_or_default().to_string();
+    let mut headers = Vec::new();
+    for line in lines {
+        let (name, value) = line.split_once(':').ok_or(ReadError::Malformed("无效的头部行"))?;
+        headers.push((name.trim().to_string(), value.trim().to_string()));
+    }
+
+    if find_header(&headers, "transfer-encoding").is_some() {
+        return Err(ReadError::Malformed("不支持分块传输编码"));
+    }
+    let body_len = match find_header(&headers, "content-length") {
+        Some(value) => value.parse::<usize>().map_err(|_| ReadError::Malformed("无效的 Content-Length"))?,
+        None => 0,
+    };
+    if body_len > MAX_BODY_SIZE {
+        return Err(ReadError::TooLarge);
+    }
+
+    let body_start = head_end + 4;
+    while buf.len() < body_start + body_len {
+        if fill(stream, buf).await? == 0 {
+            return Err(ReadError::Malformed("请求体未结束连接就关闭了"));
+        }
+    }
+    let body = buf[body_start..body_start + body_len].to_vec();
+    buf.drain(..body_start + body_len);
+
+    Ok((start_line, headers, body))
+}
+
+async fn read_request(stream: &mut TcpStream, buf: &mut Vec<u8>) -> Result<Request, ReadError> {
+    let (start_line, headers, body) = read_message(stream, buf).await?;
+
+    let mut parts = start_line.split_whitespace();
+    let (Some(method), Some(path), Some(version), None) = (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(ReadError::Malformed("无效的请求行"));
+    };
+    let version = match version {
+        "HTTP/1.1" => Version::Http11,
+        "HTTP/1.0" => Version::Http10,
+        _ => return Err(ReadError::Malformed("不支持的 HTTP 版本")),
+    };
+
+    Ok(Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        version,
+        headers,
+        body,
+    })
+}
+
+async fn write_response(stream: &mut TcpStream, response: &Response, keep_alive: bool) -> io::Result<()> {
+    let mut out = format!("HTTP/1.1 {} {}\r\n", response.status, response.reason());
+    for (name, value) in &response.headers {
+        out.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    out.push_str(&format!("Content-Length: {}\r\n", response.body.len()));
+    out.push_str(if keep_alive {
+        "Connection: keep-alive\r\n\r\n"
+    } else {
+        "Connection: close\r\n\r\n"
+    });
+
+    let mut bytes = out.into_bytes();
+    bytes.extend_from_slice(&response.body);
+    stream.write_all(&bytes).await?;
+    stream.flush().await
+}
+
+// ============================================================================
+// 服务器
+// ============================================================================
+
+/// 服务器统计快照
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ServerStats {
+    pub connections: u64,
+    pub requests: u64,
+    pub timeouts: u64,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    connections: AtomicU64,
+    requests: AtomicU64,
+    timeouts: AtomicU64,
+}
+
+/// 优雅关闭的结果
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ShutdownReport {
+    /// 超过关闭时限后被强制中止的连接数
+    pub aborted: usize,
+}
+
+/// HTTP 服务器：信号量限制同时服务的连接数
+pub struct AsyncWebServer {
+    connection_pool: Arc<Semaphore>,
+    counters: Arc<Counters>,
+    config: ServerConfig,
+}
+
+impl AsyncWebServer {
+    pub fn new(max_connections: usize) -> Self {
+        Self::with_config(ServerConfig {
+            max_connections,
+            ..ServerConfig::default()
+        })
+    }
+
+    pub fn with_config(config: ServerConfig) -> Self {
+        Self {
+            connection_pool: Arc::new(Semaphore::new(config.max_connections)),
+            counters: Arc::new(Counters::default()),
+            config,
+        }
+    }
+
+    pub fn stats(&self) -> ServerStats {
+        ServerStats {
+            connections: self.counters.connections.load(Ordering::Relaxed),
+            requests: self.counters.requests.load(Ordering::Relaxed),
+            timeouts: self.counters.timeouts.load(Ordering::Relaxed),
+        }
+    }
+
+    /// 当前正在服务的连接数
+    pub fn active_connections(&self) -> usize {
+        self.config.max_connections - self.connection_pool.available_permits()
+    }
+
+    /// 绑定地址并在后台开始服务；丢弃返回的 `ServerHandle` 同样会触发关闭
+    pub async fn bind<H, Fut>(&self, addr: impl ToSocketAddrs, handler: H) -> io::Result<ServerHandle>
+    where
+        H: Fn(Request) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Response> + Send + 'static,
+    {
+        let listener = TcpListener::bind(addr).await?;
+        let local_addr = listener.local_addr()?;
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+
+        let server = Server {
+            handler: Arc::new(handler),
+            connection_pool: Arc::clone(&self.connection_pool),
+            counters: Arc::clone(&self.counters),
+            config: self.config.clone(),
+        };
+        // 服务器 span 挂在调用 bind 的上下文下面，之后每个连接、每个请求都是它的后代
+        let span = info_span!("http_server", addr = %local_addr);
+        let task = tokio::spawn(server.accept_loop(listener, shutdown_rx).instrument(span));
+
+        Ok(ServerHandle {
+            local_addr,
+            shutdown: shutdown_tx,
+            task,
+        })
+    }
+}
+
+/// 运行中的服务器句柄
+pub struct ServerHandle {
+    local_addr: SocketAddr,
+    shutdown: watch::Sender<bool>,
+    task: JoinHandle<ShutdownReport>,
+}
+
+impl ServerHandle {
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// 优雅关闭并等待所有连接结束
+    pub async fn shutdown(self) -> ShutdownReport {
+        let _ = self.shutdown.send(true);
+        self.task.await.unwrap_or_default()
+    }
+}
+
+struct Server<H> {
+    handler: Arc<H>,
+    connection_pool: Arc<Semaphore>,
+    counters: Arc<Counters>,
+    config: ServerConfig,
+}
+
+impl<H> Clone for Server<H> {
+    fn clone(&self) -> Self {
+        Self {
+            handler: Arc::clone(&self.handler),
+            connection_pool: Arc::clone(&self.connection_pool),
+            counters: Arc::clone(&self.counters),
+            config: self.config.clone(),
+        }
+    }
+}
+
+impl<H, Fut> Server<H>
+where
+    H: Fn(Request) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Response> + Send + 'static,
+{
+    async fn accept_loop(self, listener: TcpListener, mut shutdown: watch::Receiver<bool>) -> ShutdownReport {
+        let mut connections = JoinSet::new();
+
+        loop {
+            // 先拿许可再 accept：超出上限的连接在监听队列里等待，而不是被拒绝
+            let permit = tokio::select! {
+                _ = shutdown.changed() => break,
+                permit = Arc::clone(&self.connection_pool).acquire_owned() => {
+                    permit.expect("连接信号量不会被关闭")
+                }
+            };
+            let (stream, peer) = tokio::select! {
+                _ = shutdown.changed() => break,
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, peer)) => {
+                        debug!("      🔌 接受连接: {}", peer);
+                        (stream, peer)
+                    }
+                    Err(e) => {
+                        warn!("      ⚠️ accept 失败: {}", e);
+                        continue;
+                    }
+                },
+            };
+
+            self.counters.connections.fetch_add(1, Ordering::Relaxed);
+            let span = info_span!("connection", %peer);
+            connections.spawn(
+                self.clone()
+                    .serve_connection(stream, permit, shutdown.clone())
+                    .instrument(span),
+            );
+            // 顺便回收已经结束的连接任务
+            while connections.try_join_next().is_some() {}
+        }
+
+        // 停止监听，新的连接会被拒绝
+        drop(listener);
+
+        let drain = async { while connections.join_next().await.is_some() {} };
+        if timeout(self.config.shutdown_timeout, drain).await.is_ok() {
+            return ShutdownReport::default();
+        }
+
+        let aborted = connections.len();
+        warn!("      ⚠️ 关闭超时，强制中止 {} 个连接", aborted);
+        connections.shutdown().await;
+        ShutdownReport { aborted }
+    }
+
+    async fn serve_connection(
+        self,
+        mut stream: TcpStream,
+        _permit: OwnedSemaphorePermit,
+        mut shutdown: watch::Receiver<bool>,
+    ) {
+        let mut buf = Vec::new();
+
+        loop {
+            if buf.is_empty() {
+                if *shutdown.borrow() {
+                    break;
+                }
+                // 空闲连接：等待下一个请求的首批字节，收到关闭信号或空闲超时就关闭
+                tokio::select! {
+                    _ = shutdown.changed() => break,
+                    read = timeout(self.config.read_timeout, fill(&mut stream, &mut buf)) => match read {
+                        Ok(Ok(n)) if n > 0 => {}
+                        _ => break,
+                    },
+                }
+            }
+
+            let request = match timeout(self.config.read_timeout, read_request(&mut stream, &mut buf)).await {
+                Ok(Ok(request)) => request,
+                Ok(Err(ReadError::Closed | ReadError::Io(_))) => break,
+                Ok(Err(e)) => {
+                    let status = if matches!(e, ReadError::TooLarge) { 413 } else { 400 };
+                    let _ = write_response(&mut stream, &Response::new(status, e.to_string()), false).await;
+                    break;
+                }
+                Err(_) => {
+                    self.counters.timeouts.fetch_add(1, Ordering::Relaxed);
+                    let _ = write_response(&mut stream, &Response::new(408, "读取请求超时"), false).await;
+                    break;
+                }
+            };
+
+            self.counters.requests.fetch_add(1, Ordering::Relaxed);
+            let keep_alive = request.keep_alive();
+
+            let span = info_span!(
+                "http_request",
+                method = %request.method,
+                path = %request.path,
+                status = field::Empty,
+            );
+            let handling = (self.handler)(request).instrument(span.clone());
+            let response = match timeout(self.config.handler_timeout, handling).await {
+                Ok(response) => response,
+                Err(_) => {
+                    self.counters.timeouts.fetch_add(1, Ordering::Relaxed);
+                    Response::new(503, "处理请求超时")
+                }
+            };
+            span.record("status", response.status);
+
+            // 关闭期间完成的请求告诉客户端不要再复用连接
+            let keep_alive = keep_alive && !*shutdown.borrow();
+            if write_response(&mut stream, &response, keep_alive).await.is_err() || !keep_alive {
+                break;
+            }
+        }
+    }
+}
+
+// ============================================================================
+// 客户端
+// ============================================================================
+
+/// 最小的 keep-alive HTTP/1.1 客户端，在同一个连接上顺序发送请求
+pub struct HttpClient {
+    stream: TcpStream,
+    buf: Vec<u8>,
+}
+
+impl HttpClient {
+    pub async fn connect(addr: SocketAddr) -> io::Result<Self> {
+        Ok(Self {
+            stream: TcpStream::connect(addr).await?,
+            buf: Vec::new(),
+        })
+    }
+
+    pub async fn get(&mut self, path: &str) -> anyhow::Result<Response> {
+        self.send("GET", path, &[], b"").await
+    }
+
+    pub async fn send(
+        &mut self,
+        method: &str,
+        path: &str,
+        headers: &[(&str, &str)],
+        body: &[u8],
+    ) -> anyhow::Result<Response> {
+        let mut out = format!("{} {} HTTP/1.1\r\nHost: localhost\r\n", method, path);
+        for (name, value) in headers {
+            out.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        out.push_str(&format!("Content-Length: {}\r\n\r\n", body.len()));
+
+        let mut bytes = out.into_bytes();
+        bytes.extend_from_slice(body);
+        self.stream.write_all(&bytes).await?;
+
+        let (status_line, headers, body) = read_message(&mut self.stream, &mut self.buf).await?;
+        let status = status_line
+            .split_whitespace()
+            .nth(1)
+            .and_then(|code| code.parse().ok())
+            .ok_or_else(|| anyhow::anyhow!("无效的状态行: {}", status_line))?;
+
+        Ok(Response { status, headers, body })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::time::sleep;
+
+    fn test_config() -> ServerConfig {
+        ServerConfig {
+            max_connections: 4,
+            read_timeout: Duration::from_millis(200),
+            handler_timeout: Duration::from_millis(200),
+            shutdown_timeout: Duration::from_secs(2),
+        }
+    }
+
+    async fn echo(request: Request) -> Response {
+        match request.path.as_str() {
+            "/slow" => {
+                sleep(Duration::from_millis(100)).await;
+                Response::ok("slow")
+            }
+            "/hang" => {
+                sleep(Duration::from_secs(60)).await;
+                Response::ok("never")
+            }
+            "/echo" => Response::ok(request.body).with_header("X-Method", request.method),
+            _ => Response::new(404, "not found"),
+        }
+    }
+
+    async fn read_to_end(stream: &mut TcpStream) -> String {
+        let mut raw = Vec::new();
+        stream.read_to_end(&mut raw).await.unwrap();
+        String::from_utf8(raw).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_request_roundtrip() {
+        let server = AsyncWebServer::with_config(test_config());
+        let handle = server.bind("127.0.0.1:0", echo).await.unwrap();
+        let mut client = HttpClient::connect(handle.local_addr()).await.unwrap();
+
+        let response = client.send("POST", "/echo", &[("X-Test", "1")], b"hello").await.unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.text(), "hello");
+        assert_eq!(response.header("x-method"), Some("POST"));
+
+        let response = client.get("/missing").await.unwrap();
+        assert_eq!(response.status, 404);
+
+        drop(client);
+        assert_eq!(handle.shutdown().await, ShutdownReport::default());
+    }
+
+    #[tokio::test]
+    async fn test_keep_alive_reuses_connection() {
+        let server = AsyncWebServer::with_config(test_config());
+        let handle = server.bind("127.0.0.1:0", echo).await.unwrap();
+
+        let mut client = HttpClient::connect(handle.local_addr()).await.unwrap();
+        for _ in 0..3 {
+            let response = client.get("/echo").await.unwrap();
+            assert_eq!(response.header("connection"), Some("keep-alive"));
+        }
+        let response = client.send("GET", "/echo", &[("Connection", "close")], b"").await.unwrap();
+        assert_eq!(response.header("connection"), Some("close"));
+        // 服务器已经关闭了这个连接
+        assert!(client.get("/echo").await.is_err());
+
+        let stats = server.stats();
+        assert_eq!(stats.connections, 1);
+        assert_eq!(stats.requests, 4);
+        handle.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_pipelined_requests() {
+        let server = AsyncWebServer::with_config(test_config());
+        let handle = server.bind("127.0.0.1:0", echo).await.unwrap();
+
+        let mut stream = TcpStream::connect(handle.local_addr()).await.unwrap();
+        stream
+            .write_all(b"GET /echo HTTP/1.1\r\n\r\nGET /missing HTTP/1.1\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+
+        let raw = read_to_end(&mut stream).await;
+        assert_eq!(raw.matches("HTTP/1.1 200 OK").count(), 1);
+        assert_eq!(raw.matches("HTTP/1.1 404 Not Found").count(), 1);
+        handle.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_connection_limit_queues_extra_connections() {
+        let server = AsyncWebServer::with_config(ServerConfig {
+            max_connections: 1,
+            ..test_config()
+        });
+        let handle = server.bind("127.0.0.1:0", echo).await.unwrap();
+        let addr = handle.local_addr();
+
+        let mut first = HttpClient::connect(addr).await.unwrap();
+        assert_eq!(first.get("/echo").await.unwrap().status, 200);
+        assert_eq!(server.active_connections(), 1);
+
+        // 第二个连接在 TCP 层已建立，但在第一个连接释放许可之前得不到服务
+        let second = tokio::spawn(async move {
+            let mut client = HttpClient::connect(addr).await.unwrap();
+            client.get("/echo").await.unwrap().status
+        });
+        sleep(Duration::from_millis(100)).await;
+        assert!(!second.is_finished());
+
+        drop(first);
+        assert_eq!(second.await.unwrap(), 200);
+        handle.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_timeouts() {
+        let server = AsyncWebServer::with_config(test_config());
+        let handle = server.bind("127.0.0.1:0", echo).await.unwrap();
+
+        // 请求头只发了一半
+        let mut stream = TcpStream::connect(handle.local_addr()).await.unwrap();
+        stream.write_all(b"GET /echo HTTP/1.1\r\n").await.unwrap();
+        assert!(read_to_end(&mut stream).await.starts_with("HTTP/1.1 408"));
+
+        // 处理器超时
+        let mut client = HttpClient::connect(handle.local_addr()).await.unwrap();
+        assert_eq!(client.get("/hang").await.unwrap().status, 503);
+
+        assert_eq!(server.stats().timeouts, 2);
+        handle.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_malformed_request_rejected() {
+        let server = AsyncWebServer::with_config(test_config());
+        let handle = server.bind("127.0.0.1:0", echo).await.unwrap();
+
+        let mut stream = TcpStream::connect(handle.local_addr()).await.unwrap();
+        stream.write_all(b"NONSENSE\r\n\r\n").await.unwrap();
+        assert!(read_to_end(&mut stream).await.starts_with("HTTP/1.1 400"));
+        handle.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_graceful_shutdown_finishes_in_flight_requests() {
+        let server = AsyncWebServer::with_config(test_config());
+        let handle = server.bind("127.0.0.1:0", echo).await.unwrap();
+        let addr = handle.local_addr();
+
+        // 一个空闲的 keep-alive 连接和一个正在处理中的请求
+        let mut idle = HttpClient::connect(addr).await.unwrap();
+        idle.get("/echo").await.unwrap();
+        let in_flight = tokio::spawn(async move {
+            let mut client = HttpClient::connect(addr).await.unwrap();
+            client.get("/slow").await.unwrap()
+        });
+        sleep(Duration::from_millis(30)).await;
+
+        assert_eq!(handle.shutdown().await, ShutdownReport { aborted: 0 });
+
+        let response = in_flight.await.unwrap();
+        assert_eq!(response.text(), "slow");
+        assert_eq!(response.header("connection"), Some("close"));
+        assert!(idle.get("/echo").await.is_err());
+        assert!(TcpStream::connect(addr).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_aborts_after_timeout() {
+        let server = AsyncWebServer::with_config(ServerConfig {
+            handler_timeout: Duration::from_secs(60),
+            shutdown_timeout: Duration::from_millis(100),
+            ..test_config()
+        });
+        let handle = server.bind("127.0.0.1:0", echo).await.unwrap();
+
+        let mut stream = TcpStream::connect(handle.local_addr()).await.unwrap();
+        stream.write_all(b"GET /hang HTTP/1.1\r\n\r\n").await.unwrap();
+        sleep(Duration::from_millis(30)).await;
+
+        assert_eq!(handle.shutdown().await, ShutdownReport { aborted: 1 });
+        // 被中止的连接直接关闭，没有响应
+        assert_eq!(read_to_end(&mut stream).await, "");
+        assert_eq!(server.active_connections(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_request_spans_link_handler_and_spawned_tasks() {
+        let (capture, _guard) = crate::telemetry::testing::Capture::install();
+
+        let server = AsyncWebServer::with_config(test_config());
+        let handle = server
+            .bind("127.0.0.1:0", |request: Request| async move {
+                tracing::info!("处理 {}", request.path);
+                // 处理函数派生的后台任务仍然属于这个请求
+                crate::telemetry::spawn_traced(async { tracing::info!("后台审计") })
+                    .await
+                    .unwrap();
+                Response::ok("ok")
+            })
+            .instrument(tracing::info_span!("test"))
+            .await
+            .unwrap();
+
+        let mut client = HttpClient::connect(handle.local_addr()).await.unwrap();
+        client.get("/users").await.unwrap();
+        client.get("/orders").await.unwrap();
+        drop(client);
+        handle.shutdown().await;
+
+        let request_path = "test > http_server > connection > http_request";
+        assert_eq!(capture.event_path("处理 /users").as_deref(), Some(request_path));
+        assert_eq!(capture.event_path("后台审计").as_deref(), Some(request_path));
+
+        // 同一个 keep-alive 连接下的两个请求，状态码在处理完成后记录
+        assert_eq!(capture.spans("connection").len(), 1);
+        let requests = capture.spans("http_request");
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].fields.contains("path=/users") && requests[0].fields.contains("status=200"));
+        assert!(requests[1].fields.contains("path=/orders"));
+    }
+}
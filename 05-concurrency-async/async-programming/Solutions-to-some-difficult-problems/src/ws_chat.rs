@@ -0,0 +1,553 @@
+//! # WebSocket 聊天室
+//!
+//! 用 `tokio-tungstenite` 在回环地址上实现的广播服务，把前面几节的模式组合起来：
+//! - 共享状态：和 `SharedState` 一样用 `Arc<RwLock<HashMap<..>>>` 保存房间表
+//! - 广播通道：每个房间一个 `broadcast` 通道，房间空了就从表中移除
+//! - 房间：连接地址的路径就是房间名，`ws://127.0.0.1:port/rust` 进入 `rust` 房间
+//! - 心跳：服务器定期发送 Ping，超过 `idle_timeout` 没收到任何帧就断开
+//! - 慢消费者：落后超过广播通道容量，或单条消息写入超过 `send_timeout`，就断开
+//! - 优雅关闭：停止 accept，向所有客户端发送 Close 帧；
+//!   超过 `shutdown_timeout` 仍未结束的连接（例如卡在握手中的）被强制中止
+//!
+//! 协议是纯文本：客户端发送的每条文本消息以 `#<id>: <内容>` 的形式广播给房间内所有人
+//! （包括发送者自己，相当于回显），进出房间时广播 `*** #<id> 加入/离开了房间`。
+
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::sync::{broadcast, watch, RwLock};
+use tokio::task::{JoinHandle, JoinSet};
+use tokio::time::{interval, timeout, timeout_at, Instant, MissedTickBehavior};
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::frame::CloseFrame;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{accept_hdr_async, connect_async, MaybeTlsStream, WebSocketStream};
+use tracing::{debug, info, warn};
+
+const DEFAULT_ROOM: &str = "lobby";
+
+#[derive(Debug, Clone)]
+pub struct ChatConfig {
+    /// 每个房间广播通道的容量，落后超过这个数量的客户端被断开
+    pub room_capacity: usize,
+    pub ping_interval: Duration,
+    /// 多久没收到客户端的任何帧（包括 Pong）就断开
+    pub idle_timeout: Duration,
+    /// 单条消息写入套接字的时限
+    pub send_timeout: Duration,
+    /// 完成 WebSocket 握手的时限，只建立 TCP 连接却不发握手请求的对端会被丢弃
+    pub handshake_timeout: Duration,
+    /// 优雅关闭时等待连接结束的时限
+    pub shutdown_timeout: Duration,
+}
+
+impl Default for ChatConfig {
+    fn default() -> Self {
+        Self {
+            room_capacity: 64,
+            ping_interval: Duration::from_secs(5),
+            idle_timeout: Duration::from_secs(15),
+            send_timeout: Duration::from_secs(1),
+            handshake_timeout: Duration::from_secs(5),
+            shutdown_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+/// 服务器统计快照
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChatStats {
+    pub connections: u64,
+    pub messages: u64,
+    pub heartbeat_timeouts: u64,
+    pub slow_disconnects: u64,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    connections: AtomicU64,
+    messages: AtomicU64,
+    heartbeat_timeouts: AtomicU64,
+    slow_disconnects: AtomicU64,
+}
+
+/// 房间表：房间名 → 广播通道
+#[derive(Clone, Default)]
+struct ChatState {
+    rooms: Arc<RwLock<HashMap<String, broadcast::Sender<Arc<str>>>>>,
+    counters: Arc<Counters>,
+}
+
+impl ChatState {
+    async fn join(&self, room: &str, capacity: usize) -> (broadcast::Sender<Arc<str>>, broadcast::Receiver<Arc<str>>) {
+        let mut rooms = self.rooms.write().await;
+        let sender = rooms
+            .entry(room.to_string())
+            .or_insert_with(|| broadcast::channel(capacity).0)
+            .clone();
+        let receiver = sender.subscribe();
+        (sender, receiver)
+    }
+
+    /// 调用前必须先丢弃自己的接收端，否则房间永远不会被判定为空
+    async fn leave(&self, room: &str) {
+        let mut rooms = self.rooms.write().await;
+        if rooms.get(room).is_some_and(|sender| sender.receiver_count() == 0) {
+            rooms.remove(room);
+            debug!("      🧹 房间 {} 已清空", room);
+        }
+    }
+}
+
+/// 连接结束的原因
+enum Exit {
+    Left,
+    HeartbeatTimeout,
+    SlowConsumer,
+    Shutdown,
+}
+
+pub struct ChatServer {
+    state: ChatState,
+    config: ChatConfig,
+}
+
+impl ChatServer {
+    pub fn new(config: ChatConfig) -> Self {
+        Self {
+            state: ChatState::default(),
+            config,
+        }
+    }
+
+    pub fn stats(&self) -> ChatStats {
+        let counters = &self.state.counters;
+        ChatStats {
+            connections: counters.connections.load(Ordering::Relaxed),
+            messages: counters.messages.load(Ordering::Relaxed),
+            heartbeat_timeouts: counters.heartbeat_timeouts.load(Ordering::Relaxed),
+            slow_disconnects: counters.slow_disconnects.load(Ordering::Relaxed),
+        }
+    }
+
+    /// 当前的房间及在线人数，按房间名排序
+    pub async fn rooms(&self) -> Vec<(String, usize)> {
+        let rooms = self.state.rooms.read().await;
+        let mut rooms: Vec<_> = rooms
+            .iter()
+            .map(|(name, sender)| (name.clone(), sender.receiver_count()))
+            .collect();
+        rooms.sort();
+        rooms
+    }
+
+    pub async fn bind(&self, addr: impl ToSocketAddrs) -> io::Result<ChatHandle> {
+        let listener = TcpListener::bind(addr).await?;
+        let local_addr = listener.local_addr()?;
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+
+        let task = tokio::spawn(accept_loop(listener, self.state.clone(), self.config.clone(), shutdown_rx));
+        Ok(ChatHandle {
+            local_addr,
+            shutdown: shutdown_tx,
+            task,
+        })
+    }
+}
+
+pub struct ChatHandle {
+    local_addr: SocketAddr,
+    shutdown: watch::Sender<bool>,
+    task: JoinHandle<()>,
+}
+
+impl ChatHandle {
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// 通知所有连接关闭并等待它们结束
+    pub async fn shutdown(self) {
+        let _ = self.shutdown.send(true);
+        let _ = self.task.await;
+    }
+}
+
+async fn accept_loop(listener: TcpListener, state: ChatState, config: ChatConfig, mut shutdown: watch::Receiver<bool>) {
+    let mut connections = JoinSet::new();
+    let mut next_id = 0u64;
+
+    loop {
+        let stream = tokio::select! {
+            _ = shutdown.changed() => break,
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    warn!("      ⚠️ accept 失败: {}", e);
+                    continue;
+                }
+            },
+        };
+
+        next_id += 1;
+        state.counters.connections.fetch_add(1, Ordering::Relaxed);
+        connections.spawn(serve_client(
+            next_id,
+            stream,
+            state.clone(),
+            config.clone(),
+            shutdown.clone(),
+        ));
+        while connections.try_join_next().is_some() {}
+    }
+
+    let drain = async { while connections.join_next().await.is_some() {} };
+    if timeout(config.shutdown_timeout, drain).await.is_err() {
+        warn!("      ⚠️ 关闭超时，强制中止 {} 个连接", connections.len());
+        connections.shutdown().await;
+    }
+}
+
+async fn serve_client(
+    id: u64,
+    stream: TcpStream,
+    state: ChatState,
+    config: ChatConfig,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    // 握手时从请求路径中取出房间名
+    let mut room = String::new();
+    let callback = |request: &Request, response: Response| -> Result<Response, ErrorResponse> {
+        room = request.uri().path().trim_start_matches('/').to_string();
+        Ok(response)
+    };
+    let ws = match timeout(config.handshake_timeout, accept_hdr_async(stream, callback)).await {
+        Ok(Ok(ws)) => ws,
+        Ok(Err(e)) => {
+            warn!("      ⚠️ WebSocket 握手失败: {}", e);
+            return;
+        }
+        Err(_) => {
+            warn!("      ⚠️ #{} WebSocket 握手超时", id);
+            return;
+        }
+    };
+    if room.is_empty() {
+        room = DEFAULT_ROOM.to_string();
+    }
+
+    let (mut sink, mut incoming) = ws.split();
+    let (room_tx, mut room_rx) = state.join(&room, config.room_capacity).await;
+    let _ = room_tx.send(format!("*** #{} 加入了房间 {}", id, room).into());
+
+    let mut heartbeat = interval(config.ping_interval);
+    heartbeat.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut last_seen = Instant::now();
+
+    let exit = loop {
+        tokio::select! {
+            frame = timeout_at(last_seen + config.idle_timeout, incoming.next()) => match frame {
+                Err(_) => break Exit::HeartbeatTimeout,
+                Ok(Some(Ok(Message::Text(text)))) => {
+                    last_seen = Instant::now();
+                    state.counters.messages.fetch_add(1, Ordering::Relaxed);
+                    let _ = room_tx.send(format!("#{}: {}", id, text).into());
+                }
+                Ok(Some(Ok(Message::Close(_)))) | Ok(Some(Err(_))) | Ok(None) => break Exit::Left,
+                // Ping / Pong / Binary 都说明客户端还活着
+                Ok(Some(Ok(_))) => last_seen = Instant::now(),
+            },
+            _ = heartbeat.tick() => {
+                if timeout(config.send_timeout, sink.send(Message::Ping(Vec::new()))).await.is_err() {
+                    break Exit::SlowConsumer;
+                }
+            }
+            message = room_rx.recv() => match message {
+                Ok(text) => {
+                    match timeout(config.send_timeout, sink.send(Message::Text(text.to_string()))).await {
+                        Ok(Ok(())) => {}
+                        Ok(Err(_)) => break Exit::Left,
+                        Err(_) => break Exit::SlowConsumer,
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    debug!("      🐢 #{} 落后 {} 条消息", id, skipped);
+                    break Exit::SlowConsumer;
+                }
+                Err(broadcast::error::RecvError::Closed) => break Exit::Left,
+            },
+            _ = shutdown.changed() => break Exit::Shutdown,
+        }
+    };
+
+    let reason = match exit {
+        Exit::Left => None,
+        Exit::HeartbeatTimeout => {
+            state.counters.heartbeat_timeouts.fetch_add(1, Ordering::Relaxed);
+            Some((CloseCode::Policy, "心跳超时"))
+        }
+        Exit::SlowConsumer => {
+            state.counters.slow_disconnects.fetch_add(1, Ordering::Relaxed);
+            Some((CloseCode::Policy, "消费太慢"))
+        }
+        Exit::Shutdown => Some((CloseCode::Away, "服务器关闭")),
+    };
+    if let Some((code, reason)) = reason {
+        info!("      👋 断开 #{}: {}", id, reason);
+        let frame = CloseFrame {
+            code,
+            reason: reason.into(),
+        };
+        let _ = timeout(config.send_timeout, sink.send(Message::Close(Some(frame)))).await;
+    }
+
+    drop(room_rx);
+    let _ = room_tx.send(format!("*** #{} 离开了房间 {}", id, room).into());
+    state.leave(&room).await;
+}
+
+// ============================================================================
+// 客户端
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChatEvent {
+    Message(String),
+    /// 连接关闭，附带服务器给出的原因；连接被直接重置时没有原因
+    Closed(Option<String>),
+}
+
+pub struct ChatClient {
+    ws: WebSocketStream<MaybeTlsStream<TcpStream>>,
+}
+
+impl ChatClient {
+    pub async fn connect(addr: SocketAddr, room: &str) -> anyhow::Result<Self> {
+        let (ws, _) = connect_async(format!("ws://{}/{}", addr, room)).await?;
+        Ok(Self { ws })
+    }
+
+    pub async fn send(&mut self, text: &str) -> anyhow::Result<()> {
+        self.ws.send(Message::Text(text.to_string())).await?;
+        Ok(())
+    }
+
+    /// 读取下一条聊天消息；读取的同时 tungstenite 会自动回复服务器的 Ping
+    pub async fn next_event(&mut self) -> ChatEvent {
+        loop {
+            match self.ws.next().await {
+                Some(Ok(Message::Text(text))) => return ChatEvent::Message(text),
+                Some(Ok(Message::Close(frame))) => {
+                    return ChatEvent::Closed(frame.map(|frame| frame.reason.into_owned()));
+                }
+                Some(Ok(_)) => continue,
+                Some(Err(_)) | None => return ChatEvent::Closed(None),
+            }
+        }
+    }
+
+    pub async fn close(mut self) {
+        let _ = self.ws.close(None).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::time::sleep;
+
+    fn test_config() -> ChatConfig {
+        ChatConfig {
+            room_capacity: 16,
+            ping_interval: Duration::from_millis(50),
+            idle_timeout: Duration::from_millis(200),
+            send_timeout: Duration::from_millis(200),
+            handshake_timeout: Duration::from_millis(200),
+            shutdown_timeout: Duration::from_millis(500),
+        }
+    }
+
+    async fn expect_message(client: &mut ChatClient) -> String {
+        match timeout(Duration::from_secs(2), client.next_event()).await {
+            Ok(ChatEvent::Message(text)) => text,
+            other => panic!("期望收到消息，实际: {:?}", other),
+        }
+    }
+
+    /// 等待服务器端异步清理完成
+    async fn wait_for_rooms(server: &ChatServer, expected: &[(&str, usize)]) {
+        for _ in 0..100 {
+            let rooms = server.rooms().await;
+            if rooms.iter().map(|(name, n)| (name.as_str(), *n)).eq(expected.iter().copied()) {
+                return;
+            }
+            sleep(Duration::from_millis(10)).await;
+        }
+        panic!("房间状态不符: {:?}", server.rooms().await);
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_within_room_only() {
+        let server = ChatServer::new(test_config());
+        let handle = server.bind("127.0.0.1:0").await.unwrap();
+        let addr = handle.local_addr();
+
+        let mut alice = ChatClient::connect(addr, "rust").await.unwrap();
+        assert_eq!(expect_message(&mut alice).await, "*** #1 加入了房间 rust");
+        let mut bob = ChatClient::connect(addr, "rust").await.unwrap();
+        assert_eq!(expect_message(&mut bob).await, "*** #2 加入了房间 rust");
+        assert_eq!(expect_message(&mut alice).await, "*** #2 加入了房间 rust");
+        let mut carol = ChatClient::connect(addr, "go").await.unwrap();
+        assert_eq!(expect_message(&mut carol).await, "*** #3 加入了房间 go");
+        wait_for_rooms(&server, &[("go", 1), ("rust", 2)]).await;
+
+        alice.send("你好").await.unwrap();
+        assert_eq!(expect_message(&mut alice).await, "#1: 你好");
+        assert_eq!(expect_message(&mut bob).await, "#1: 你好");
+        // 其他房间收不到
+        assert!(timeout(Duration::from_millis(100), carol.next_event()).await.is_err());
+
+        alice.close().await;
+        assert_eq!(expect_message(&mut bob).await, "*** #1 离开了房间 rust");
+        bob.close().await;
+        wait_for_rooms(&server, &[("go", 1)]).await;
+
+        assert_eq!(server.stats().messages, 1);
+        handle.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_keeps_active_clients_and_drops_silent_ones() {
+        let server = ChatServer::new(test_config());
+        let handle = server.bind("127.0.0.1:0").await.unwrap();
+        let addr = handle.local_addr();
+
+        let mut active = ChatClient::connect(addr, "lobby").await.unwrap();
+        let mut silent = ChatClient::connect(addr, "lobby").await.unwrap();
+
+        // 持续读取的客户端会自动回复 Ping，超过 idle_timeout 仍然在线
+        let mut seen = Vec::new();
+        let _ = timeout(Duration::from_millis(500), async {
+            loop {
+                seen.push(active.next_event().await);
+            }
+        })
+        .await;
+        assert!(seen.contains(&ChatEvent::Message("*** #2 离开了房间 lobby".to_string())));
+        assert!(!seen.iter().any(|event| matches!(event, ChatEvent::Closed(_))));
+
+        // 从不读取的客户端不会回复 Ping，被服务器断开
+        assert_eq!(server.stats().heartbeat_timeouts, 1);
+        // 现在才开始读：补发的 Pong 可能让已关闭的服务器端回 RST，丢掉缓冲中的 Close 帧
+        let closed = loop {
+            if let ChatEvent::Closed(reason) = silent.next_event().await {
+                break reason;
+            }
+        };
+        assert!(closed.is_none() || closed.as_deref() == Some("心跳超时"));
+
+        active.send("还在").await.unwrap();
+        assert_eq!(expect_message(&mut active).await, "#1: 还在");
+        handle.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_slow_consumer_is_disconnected() {
+        let server = ChatServer::new(ChatConfig {
+            idle_timeout: Duration::from_secs(30),
+            ..test_config()
+        });
+        let handle = server.bind("127.0.0.1:0").await.unwrap();
+        let addr = handle.local_addr();
+
+        let mut slow = ChatClient::connect(addr, "firehose").await.unwrap();
+        let fast = ChatClient::connect(addr, "firehose").await.unwrap();
+
+        // 快速客户端一边发送大消息一边读取自己的回显；慢客户端一直不读
+        let ChatClient { ws } = fast;
+        let (mut fast_tx, mut fast_rx) = ws.split();
+        let reader = tokio::spawn(async move {
+            let mut echoes = 0;
+            while let Some(Ok(message)) = fast_rx.next().await {
+                if matches!(message, Message::Text(ref text) if text.starts_with("#2:")) {
+                    echoes += 1;
+                    if echoes == 64 {
+                        break;
+                    }
+                }
+            }
+            echoes
+        });
+        let payload = "x".repeat(1 << 20);
+        for _ in 0..64 {
+            fast_tx.send(Message::Text(payload.clone())).await.unwrap();
+        }
+        assert_eq!(reader.await.unwrap(), 64);
+
+        assert_eq!(server.stats().slow_disconnects, 1);
+        wait_for_rooms(&server, &[("firehose", 1)]).await;
+
+        // 慢客户端读完积压的数据后看到断开原因（或者连接直接被重置）
+        let closed = loop {
+            if let ChatEvent::Closed(reason) = slow.next_event().await {
+                break reason;
+            }
+        };
+        assert!(closed.is_none() || closed.as_deref() == Some("消费太慢"));
+        handle.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_closes_clients() {
+        let server = ChatServer::new(test_config());
+        let handle = server.bind("127.0.0.1:0").await.unwrap();
+
+        let mut client = ChatClient::connect(handle.local_addr(), "").await.unwrap();
+        assert_eq!(expect_message(&mut client).await, "*** #1 加入了房间 lobby");
+
+        handle.shutdown().await;
+        assert_eq!(client.next_event().await, ChatEvent::Closed(Some("服务器关闭".to_string())));
+        assert!(server.rooms().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_stalled_handshake_does_not_block_shutdown() {
+        let server = ChatServer::new(ChatConfig {
+            // 握手时限比关闭时限长，逼出强制中止的路径
+            handshake_timeout: Duration::from_secs(30),
+            ..test_config()
+        });
+        let handle = server.bind("127.0.0.1:0").await.unwrap();
+
+        // 只建立 TCP 连接，从不发送握手请求
+        let _raw = TcpStream::connect(handle.local_addr()).await.unwrap();
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(server.stats().connections, 1);
+
+        timeout(Duration::from_secs(2), handle.shutdown())
+            .await
+            .expect("卡在握手中的连接不应阻塞关闭");
+    }
+
+    #[tokio::test]
+    async fn test_stalled_handshake_times_out() {
+        let server = ChatServer::new(test_config());
+        let handle = server.bind("127.0.0.1:0").await.unwrap();
+
+        let mut raw = TcpStream::connect(handle.local_addr()).await.unwrap();
+        // 握手超时后服务器关闭连接，读到 EOF
+        let mut buf = [0u8; 16];
+        let read = timeout(Duration::from_secs(2), tokio::io::AsyncReadExt::read(&mut raw, &mut buf))
+            .await
+            .expect("握手超时后连接应被关闭");
+        assert!(matches!(read, Ok(0) | Err(_)));
+        handle.shutdown().await;
+    }
+}
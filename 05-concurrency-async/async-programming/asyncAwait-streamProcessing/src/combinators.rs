@@ -0,0 +1,749 @@
+//! # 时间相关的 Stream 组合子
+//!
+//! `NumberStream`、`AsyncRange` 这类演示用的 Stream 只服务于各自的函数。
+//! 本模块把常见的流处理模式提取成可复用的适配器：
+//! - `throttle`：一个周期内只放行第一个元素，其余丢弃
+//! - `debounce`：输入停顿一段时间后才输出最新的元素
+//! - `batch`：凑满 `size` 个或等待超时后成批输出
+//! - `window`：按固定时间窗口（翻滚窗口）分组输出
+//! - `rate_limit`：每个周期最多放行 `limit` 个元素，多余的延后而不丢弃
+//! - `merge_sorted`：合并两个各自有序的流，结果仍然有序
+//! - `retry_with_backoff`：流出错时按指数退避重新建立
+//!
+//! 所有适配器都通过 `Timer` trait 读取时间和创建定时器：
+//! 运行时使用 `TokioTimer`，测试中换成 `test_executor::MockClock`，
+//! 就能在虚拟时间里确定性地验证时间行为。
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use std::time::{Duration, Instant};
+
+use futures::stream::{Fuse, Stream, StreamExt};
+use pin_project::pin_project;
+
+// ============================================================================
+// 时间源
+// ============================================================================
+
+/// 适配器使用的时间源
+pub trait Timer: Clone {
+    type Sleep: Future<Output = ()>;
+
+    fn now(&self) -> Instant;
+
+    fn sleep_until(&self, deadline: Instant) -> Self::Sleep;
+}
+
+/// 基于 tokio 定时器的时间源
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioTimer;
+
+impl Timer for TokioTimer {
+    type Sleep = tokio::time::Sleep;
+
+    fn now(&self) -> Instant {
+        // 与 tokio 的时钟保持一致（暂停时间的测试中二者会不同）
+        tokio::time::Instant::now().into_std()
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> Self::Sleep {
+        tokio::time::sleep_until(deadline.into())
+    }
+}
+
+// ============================================================================
+// 扩展 trait
+// ============================================================================
+
+/// 为所有 Stream 提供时间相关的适配器
+pub trait StreamTimeExt: Stream + Sized {
+    /// 每个 `period` 内只放行第一个元素，冷却期间到达的元素被丢弃
+    fn throttle<T: Timer>(self, period: Duration, timer: T) -> Throttle<Self, T> {
+        Throttle {
+            stream: self,
+            timer,
+            period,
+            next_allowed: None,
+        }
+    }
+
+    /// 上游安静 `quiet` 之后才输出最近的一个元素；上游结束时立即输出
+    fn debounce<T: Timer>(self, quiet: Duration, timer: T) -> Debounce<Self, T> {
+        Debounce {
+            stream: self,
+            sleep: None,
+            timer,
+            quiet,
+            pending: None,
+            done: false,
+        }
+    }
+
+    /// 凑满 `size` 个元素，或批次中第一个元素等待超过 `timeout` 时输出
+    fn batch<T: Timer>(self, size: usize, timeout: Duration, timer: T) -> Batch<Self, T> {
+        assert!(size > 0, "批大小必须大于 0");
+        Batch {
+            stream: self,
+            sleep: None,
+            timer,
+            size,
+            timeout,
+            buffer: Vec::with_capacity(size),
+            done: false,
+        }
+    }
+
+    /// 按首次 poll 时刻对齐的固定窗口分组，每个非空窗口结束时输出一次
+    fn window<T: Timer>(self, duration: Duration, timer: T) -> Window<Self, T> {
+        assert!(!duration.is_zero(), "窗口长度必须大于 0");
+        Window {
+            stream: self,
+            sleep: None,
+            timer,
+            duration,
+            origin: None,
+            window_end: None,
+            buffer: Vec::new(),
+            done: false,
+        }
+    }
+
+    /// 任意 `period` 时间内最多放行 `limit` 个元素（滑动窗口），超出的元素延后输出
+    fn rate_limit<T: Timer>(self, limit: usize, period: Duration, timer: T) -> RateLimit<Self, T> {
+        assert!(limit > 0, "限流数量必须大于 0");
+        RateLimit {
+            stream: self,
+            sleep: None,
+            timer,
+            limit,
+            period,
+            sent: VecDeque::with_capacity(limit),
+            pending: None,
+        }
+    }
+
+    /// 合并两个各自升序的流；相等时先输出 `self` 的元素
+    fn merge_sorted<B>(self, other: B) -> MergeSorted<Self, B>
+    where
+        B: Stream<Item = Self::Item>,
+        Self::Item: Ord,
+    {
+        MergeSorted {
+            left: self.fuse(),
+            right: other.fuse(),
+            left_peek: None,
+            right_peek: None,
+        }
+    }
+}
+
+impl<S: Stream> StreamTimeExt for S {}
+
+// ============================================================================
+// throttle
+// ============================================================================
+
+#[pin_project]
+pub struct Throttle<S, T> {
+    #[pin]
+    stream: S,
+    timer: T,
+    period: Duration,
+    next_allowed: Option<Instant>,
+}
+
+impl<S: Stream, T: Timer> Stream for Throttle<S, T> {
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+        loop {
+            let Some(item) = ready!(this.stream.as_mut().poll_next(cx)) else {
+                return Poll::Ready(None);
+            };
+            let now = this.timer.now();
+            if this.next_allowed.is_none_or(|allowed| now >= allowed) {
+                *this.next_allowed = Some(now + *this.period);
+                return Poll::Ready(Some(item));
+            }
+            // 冷却期内的元素直接丢弃
+        }
+    }
+}
+
+// ============================================================================
+// debounce
+// ============================================================================
+
+#[pin_project]
+pub struct Debounce<S: Stream, T: Timer> {
+    #[pin]
+    stream: S,
+    #[pin]
+    sleep: Option<T::Sleep>,
+    timer: T,
+    quiet: Duration,
+    pending: Option<S::Item>,
+    done: bool,
+}
+
+impl<S: Stream, T: Timer> Stream for Debounce<S, T> {
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+
+        // 取走上游所有已就绪的元素，只保留最新的一个并重新计时
+        while !*this.done {
+            match this.stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some(item)) => {
+                    *this.pending = Some(item);
+                    let deadline = this.timer.now() + *this.quiet;
+                    this.sleep.set(Some(this.timer.sleep_until(deadline)));
+                }
+                Poll::Ready(None) => *this.done = true,
+                Poll::Pending => break,
+            }
+        }
+
+        if *this.done {
+            this.sleep.set(None);
+            return Poll::Ready(this.pending.take());
+        }
+
+        if let Some(sleep) = this.sleep.as_mut().as_pin_mut() {
+            ready!(sleep.poll(cx));
+            this.sleep.set(None);
+            return Poll::Ready(this.pending.take());
+        }
+        Poll::Pending
+    }
+}
+
+// ============================================================================
+// batch
+// ============================================================================
+
+#[pin_project]
+pub struct Batch<S: Stream, T: Timer> {
+    #[pin]
+    stream: S,
+    #[pin]
+    sleep: Option<T::Sleep>,
+    timer: T,
+    size: usize,
+    timeout: Duration,
+    buffer: Vec<S::Item>,
+    done: bool,
+}
+
+impl<S: Stream, T: Timer> Stream for Batch<S, T> {
+    type Item = Vec<S::Item>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+
+        while !*this.done {
+            match this.stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some(item)) => {
+                    if this.buffer.is_empty() {
+                        // 超时从批次的第一个元素开始计算
+                        let deadline = this.timer.now() + *this.timeout;
+                        this.sleep.set(Some(this.timer.sleep_until(deadline)));
+                    }
+                    this.buffer.push(item);
+                    if this.buffer.len() >= *this.size {
+                        this.sleep.set(None);
+                        return Poll::Ready(Some(std::mem::take(this.buffer)));
+                    }
+                }
+                Poll::Ready(None) => *this.done = true,
+                Poll::Pending => break,
+            }
+        }
+
+        if *this.done {
+            this.sleep.set(None);
+            return Poll::Ready(non_empty(this.buffer));
+        }
+
+        if let Some(sleep) = this.sleep.as_mut().as_pin_mut() {
+            ready!(sleep.poll(cx));
+            this.sleep.set(None);
+            return Poll::Ready(non_empty(this.buffer));
+        }
+        Poll::Pending
+    }
+}
+
+/// 取走缓冲区中的全部元素，缓冲区为空时返回 `None`
+fn non_empty<I>(buffer: &mut Vec<I>) -> Option<Vec<I>> {
+    if buffer.is_empty() {
+        None
+    } else {
+        Some(std::mem::take(buffer))
+    }
+}
+
+// ============================================================================
+// window
+// ============================================================================
+
+#[pin_project]
+pub struct Window<S: Stream, T: Timer> {
+    #[pin]
+    stream: S,
+    #[pin]
+    sleep: Option<T::Sleep>,
+    timer: T,
+    duration: Duration,
+    origin: Option<Instant>,
+    window_end: Option<Instant>,
+    buffer: Vec<S::Item>,
+    done: bool,
+}
+
+/// 计算 `now` 所在窗口的结束时刻（窗口从 `origin` 开始，每 `duration` 一个）
+fn window_end_for(origin: Instant, duration: Duration, now: Instant) -> Instant {
+    let index = (now - origin).as_nanos() / duration.as_nanos();
+    origin + Duration::from_nanos((duration.as_nanos() * (index + 1)) as u64)
+}
+
+impl<S: Stream, T: Timer> Stream for Window<S, T> {
+    type Item = Vec<S::Item>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+        let origin = *this.origin.get_or_insert_with(|| this.timer.now());
+
+        while !*this.done {
+            match this.stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some(item)) => {
+                    let now = this.timer.now();
+                    match *this.window_end {
+                        // 定时器还没来得及触发，但当前窗口已经结束：元素属于新窗口
+                        Some(end) if now >= end => {
+                            let finished = std::mem::replace(this.buffer, vec![item]);
+                            let end = window_end_for(origin, *this.duration, now);
+                            *this.window_end = Some(end);
+                            this.sleep.set(Some(this.timer.sleep_until(end)));
+                            return Poll::Ready(Some(finished));
+                        }
+                        Some(_) => this.buffer.push(item),
+                        None => {
+                            let end = window_end_for(origin, *this.duration, now);
+                            *this.window_end = Some(end);
+                            this.sleep.set(Some(this.timer.sleep_until(end)));
+                            this.buffer.push(item);
+                        }
+                    }
+                }
+                Poll::Ready(None) => *this.done = true,
+                Poll::Pending => break,
+            }
+        }
+
+        if *this.done {
+            this.sleep.set(None);
+            return Poll::Ready(non_empty(this.buffer));
+        }
+
+        if let Some(sleep) = this.sleep.as_mut().as_pin_mut() {
+            ready!(sleep.poll(cx));
+            this.sleep.set(None);
+            *this.window_end = None;
+            return Poll::Ready(non_empty(this.buffer));
+        }
+        Poll::Pending
+    }
+}
+
+// ============================================================================
+// rate_limit
+// ============================================================================
+
+#[pin_project]
+pub struct RateLimit<S: Stream, T: Timer> {
+    #[pin]
+    stream: S,
+    #[pin]
+    sleep: Option<T::Sleep>,
+    timer: T,
+    limit: usize,
+    period: Duration,
+    /// 最近放行的元素的时间戳（最多 `limit` 个）
+    sent: VecDeque<Instant>,
+    pending: Option<S::Item>,
+}
+
+impl<S: Stream, T: Timer> Stream for RateLimit<S, T> {
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+        loop {
+            if this.pending.is_none() {
+                match ready!(this.stream.as_mut().poll_next(cx)) {
+                    Some(item) => *this.pending = Some(item),
+                    None => return Poll::Ready(None),
+                }
+            }
+
+            if let Some(sleep) = this.sleep.as_mut().as_pin_mut() {
+                ready!(sleep.poll(cx));
+                this.sleep.set(None);
+            }
+
+            let now = this.timer.now();
+            while this
+                .sent
+                .front()
+                .is_some_and(|&sent| now >= sent + *this.period)
+            {
+                this.sent.pop_front();
+            }
+
+            if this.sent.len() < *this.limit {
+                this.sent.push_back(now);
+                return Poll::Ready(this.pending.take());
+            }
+
+            // 额度用完：等到最早的一次放行滑出窗口
+            let deadline = this.sent[0] + *this.period;
+            this.sleep.set(Some(this.timer.sleep_until(deadline)));
+        }
+    }
+}
+
+// ============================================================================
+// merge_sorted
+// ============================================================================
+
+#[pin_project]
+pub struct MergeSorted<A: Stream, B: Stream<Item = A::Item>> {
+    #[pin]
+    left: Fuse<A>,
+    #[pin]
+    right: Fuse<B>,
+    left_peek: Option<A::Item>,
+    right_peek: Option<A::Item>,
+}
+
+impl<A, B> Stream for MergeSorted<A, B>
+where
+    A: Stream,
+    B: Stream<Item = A::Item>,
+    A::Item: Ord,
+{
+    type Item = A::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+
+        // 两边都要先拿到下一个元素（或确认已结束）才能决定输出哪个
+        let mut waiting = false;
+        if this.left_peek.is_none() {
+            match this.left.poll_next(cx) {
+                Poll::Ready(item) => *this.left_peek = item,
+                Poll::Pending => waiting = true,
+            }
+        }
+        if this.right_peek.is_none() {
+            match this.right.poll_next(cx) {
+                Poll::Ready(item) => *this.right_peek = item,
+                Poll::Pending => waiting = true,
+            }
+        }
+        if waiting {
+            return Poll::Pending;
+        }
+
+        let item = match (this.left_peek.take(), this.right_peek.take()) {
+            (Some(left), Some(right)) if left <= right => {
+                *this.right_peek = Some(right);
+                Some(left)
+            }
+            (Some(left), Some(right)) => {
+                *this.left_peek = Some(left);
+                Some(right)
+            }
+            (Some(item), None) | (None, Some(item)) => Some(item),
+            (None, None) => None,
+        };
+        Poll::Ready(item)
+    }
+}
+
+// ============================================================================
+// retry_with_backoff
+// ============================================================================
+
+/// 指数退避策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Backoff {
+    pub initial: Duration,
+    pub max: Duration,
+    /// 连续失败超过该次数后放弃，把最后一个错误交给调用方
+    pub max_retries: usize,
+}
+
+impl Backoff {
+    pub fn exponential(initial: Duration, max: Duration, max_retries: usize) -> Self {
+        Backoff {
+            initial,
+            max,
+            max_retries,
+        }
+    }
+
+    /// 第 `attempt` 次重试前的等待时间：initial * 2^attempt，不超过 max
+    pub fn delay(&self, attempt: usize) -> Duration {
+        let factor = 1u32.checked_shl(attempt as u32).unwrap_or(u32::MAX);
+        self.initial.saturating_mul(factor).min(self.max)
+    }
+}
+
+/// 用 `make_stream` 建立流；流产生 `Err` 时丢弃它，退避后重新建立。
+///
+/// 成功产生一个元素后失败计数清零；连续失败超过 `backoff.max_retries` 次时
+/// 输出最后一个错误并结束。
+pub fn retry_with_backoff<F, S, I, E, T>(
+    make_stream: F,
+    backoff: Backoff,
+    timer: T,
+) -> RetryWithBackoff<F, S, T>
+where
+    F: FnMut() -> S,
+    S: Stream<Item = Result<I, E>>,
+    T: Timer,
+{
+    RetryWithBackoff {
+        make_stream,
+        stream: None,
+        sleep: None,
+        timer,
+        backoff,
+        failures: 0,
+        done: false,
+    }
+}
+
+#[pin_project]
+pub struct RetryWithBackoff<F, S, T: Timer> {
+    make_stream: F,
+    #[pin]
+    stream: Option<S>,
+    #[pin]
+    sleep: Option<T::Sleep>,
+    timer: T,
+    backoff: Backoff,
+    failures: usize,
+    done: bool,
+}
+
+impl<F, S, I, E, T> Stream for RetryWithBackoff<F, S, T>
+where
+    F: FnMut() -> S,
+    S: Stream<Item = Result<I, E>>,
+    T: Timer,
+{
+    type Item = Result<I, E>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+        loop {
+            if *this.done {
+                return Poll::Ready(None);
+            }
+
+            if let Some(sleep) = this.sleep.as_mut().as_pin_mut() {
+                ready!(sleep.poll(cx));
+                this.sleep.set(None);
+            }
+
+            if this.stream.is_none() {
+                this.stream.set(Some((this.make_stream)()));
+            }
+            let stream = this.stream.as_mut().as_pin_mut().unwrap();
+
+            match ready!(stream.poll_next(cx)) {
+                Some(Ok(item)) => {
+                    *this.failures = 0;
+                    return Poll::Ready(Some(Ok(item)));
+                }
+                Some(Err(err)) => {
+                    this.stream.set(None);
+                    if *this.failures >= this.backoff.max_retries {
+                        *this.done = true;
+                        return Poll::Ready(Some(Err(err)));
+                    }
+                    let deadline = this.timer.now() + this.backoff.delay(*this.failures);
+                    *this.failures += 1;
+                    this.sleep.set(Some(this.timer.sleep_until(deadline)));
+                }
+                None => {
+                    *this.done = true;
+                    return Poll::Ready(None);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::stream;
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use test_executor::{MockClock, TestExecutor, explore};
+
+    impl Timer for MockClock {
+        type Sleep = test_executor::Sleep;
+
+        fn now(&self) -> Instant {
+            MockClock::now(self)
+        }
+
+        fn sleep_until(&self, deadline: Instant) -> Self::Sleep {
+            MockClock::sleep_until(self, deadline)
+        }
+    }
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    /// 按给定的虚拟时刻（毫秒）依次产生元素的源
+    fn timed<V: 'static>(clock: &MockClock, items: Vec<(u64, V)>) -> impl Stream<Item = V> + use<V> {
+        let clock = clock.clone();
+        let start = clock.now();
+        stream::iter(items).then(move |(at, value)| {
+            let sleep = clock.sleep_until(start + ms(at));
+            async move {
+                sleep.await;
+                value
+            }
+        })
+    }
+
+    /// 运行流并给每个输出打上虚拟时间戳
+    fn collect_timed<S: Stream>(executor: &TestExecutor, stream: S) -> Vec<(u64, S::Item)> {
+        let clock = executor.clock().clone();
+        executor.block_on(
+            stream
+                .map(move |item| (clock.elapsed().as_millis() as u64, item))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_throttle_drops_items_during_cooldown() {
+        let executor = TestExecutor::new(0);
+        let clock = executor.clock().clone();
+        let source = timed(&clock, vec![(0, 'a'), (10, 'b'), (20, 'c'), (60, 'd'), (70, 'e'), (130, 'f')]);
+
+        let output = collect_timed(&executor, source.throttle(ms(50), clock));
+        assert_eq!(output, vec![(0, 'a'), (60, 'd'), (130, 'f')]);
+    }
+
+    #[test]
+    fn test_debounce_emits_latest_after_quiet_period() {
+        let executor = TestExecutor::new(0);
+        let clock = executor.clock().clone();
+        let source = timed(&clock, vec![(0, 1), (10, 2), (20, 3), (100, 4), (110, 5)]);
+
+        let output = collect_timed(&executor, source.debounce(ms(30), clock));
+        // 3 在安静 30ms 后输出；5 之后上游结束，立即输出
+        assert_eq!(output, vec![(50, 3), (110, 5)]);
+    }
+
+    #[test]
+    fn test_batch_flushes_on_size_or_timeout() {
+        let executor = TestExecutor::new(0);
+        let clock = executor.clock().clone();
+        let source = timed(&clock, vec![(0, 1), (10, 2), (20, 3), (30, 4), (200, 5), (400, 6)]);
+
+        let output = collect_timed(&executor, source.batch(3, ms(100), clock));
+        assert_eq!(
+            output,
+            vec![(20, vec![1, 2, 3]), (130, vec![4]), (300, vec![5]), (400, vec![6])]
+        );
+    }
+
+    #[test]
+    fn test_window_groups_by_fixed_intervals() {
+        let executor = TestExecutor::new(0);
+        let clock = executor.clock().clone();
+        let source = timed(&clock, vec![(10, 1), (50, 2), (120, 3), (130, 4), (350, 5)]);
+
+        let output = collect_timed(&executor, source.window(ms(100), clock));
+        // 空窗口 [200, 300) 不产生输出
+        assert_eq!(output, vec![(100, vec![1, 2]), (200, vec![3, 4]), (350, vec![5])]);
+    }
+
+    #[test]
+    fn test_rate_limit_delays_instead_of_dropping() {
+        let executor = TestExecutor::new(0);
+        let clock = executor.clock().clone();
+
+        let output = collect_timed(&executor, stream::iter(1..=5).rate_limit(2, ms(100), clock));
+        assert_eq!(output, vec![(0, 1), (0, 2), (100, 3), (100, 4), (200, 5)]);
+    }
+
+    #[test]
+    fn test_merge_sorted_interleaves_by_value() {
+        explore(20, |executor| {
+            let clock = executor.clock().clone();
+            let left = timed(&clock, vec![(0, 1), (50, 4), (100, 7)]);
+            let right = timed(&clock, vec![(30, 2), (40, 3), (200, 8)]);
+
+            let merged: Vec<_> = executor.block_on(left.merge_sorted(right).collect());
+            assert_eq!(merged, vec![1, 2, 3, 4, 7, 8]);
+        });
+    }
+
+    #[test]
+    fn test_retry_with_backoff_reconnects() {
+        let executor = TestExecutor::new(0);
+        let clock = executor.clock().clone();
+        let attempts = Rc::new(Cell::new(0));
+
+        let connect = {
+            let attempts = Rc::clone(&attempts);
+            move || {
+                let attempt = attempts.get();
+                attempts.set(attempt + 1);
+                let items: Vec<Result<i32, &str>> = match attempt {
+                    0 => vec![Ok(1), Err("连接断开")],
+                    1 => vec![Err("连接失败")],
+                    _ => vec![Ok(2), Ok(3)],
+                };
+                stream::iter(items)
+            }
+        };
+
+        let backoff = Backoff::exponential(ms(10), ms(1000), 3);
+        let output = collect_timed(&executor, retry_with_backoff(connect, backoff, clock));
+        // 第一次失败后等待 10ms，第二次等待 20ms
+        assert_eq!(output, vec![(0, Ok(1)), (30, Ok(2)), (30, Ok(3))]);
+        assert_eq!(attempts.get(), 3);
+    }
+
+    #[test]
+    fn test_retry_with_backoff_gives_up() {
+        let executor = TestExecutor::new(0);
+        let clock = executor.clock().clone();
+
+        let backoff = Backoff::exponential(ms(10), ms(25), 3);
+        let output = collect_timed(
+            &executor,
+            retry_with_backoff(|| stream::iter([Err::<i32, _>("不可用")]), backoff, clock),
+        );
+        // 等待 10 + 20 + 25（封顶）后放弃
+        assert_eq!(output, vec![(55, Err("不可用"))]);
+        assert_eq!(backoff.delay(40), ms(25));
+    }
+}
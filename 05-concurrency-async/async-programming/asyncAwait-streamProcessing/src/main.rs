@@ -30,6 +30,10 @@ use serde::{Deserialize, Serialize};
 use rand::Rng;
 use chrono::{DateTime, Utc};
 
+// 可复用的时间相关 Stream 组合子
+mod combinators;
+use combinators::{Backoff, StreamTimeExt, TokioTimer, retry_with_backoff};
+
 #[tokio::main]
 async fn main() {
     println!("🚀 async/await 和 Stream 流处理 - 全面深度分析");
@@ -479,6 +483,7 @@ async fn demonstrate_stream_processing() {
     demonstrate_stream_vs_iterator().await;
     demonstrate_stream_combinators().await;
     demonstrate_custom_stream().await;
+    demonstrate_time_combinators().await;
 }
 
 /// 演示 Stream 基础
@@ -618,6 +623,67 @@ async fn demonstrate_custom_stream() {
     println!("      💡 自定义 Stream 允许实现特定的异步数据生成逻辑");
 }
 
+/// 演示时间相关的 Stream 组合子（实现见 combinators 模块）
+async fn demonstrate_time_combinators() {
+    println!("\n🔍 4.5 时间相关的 Stream 组合子");
+    
+    use futures::stream;
+    
+    // 每 10ms 产生一个元素的源
+    let ticks = || {
+        stream::iter(1..=10).then(|x| async move {
+            sleep(Duration::from_millis(10)).await;
+            x
+        })
+    };
+    
+    let throttled: Vec<_> = ticks().throttle(Duration::from_millis(35), TokioTimer).collect().await;
+    println!("      📊 throttle(35ms): {:?}", throttled);
+    
+    let debounced: Vec<_> = ticks().debounce(Duration::from_millis(30), TokioTimer).collect().await;
+    println!("      📊 debounce(30ms): {:?}", debounced);
+    
+    let batches: Vec<_> = ticks().batch(4, Duration::from_millis(25), TokioTimer).collect().await;
+    println!("      📊 batch(4, 25ms): {:?}", batches);
+    
+    let windows: Vec<_> = ticks().window(Duration::from_millis(50), TokioTimer).collect().await;
+    println!("      📊 window(50ms): {:?}", windows);
+    
+    let start = Instant::now();
+    let limited: Vec<_> = stream::iter(1..=6)
+        .rate_limit(2, Duration::from_millis(50), TokioTimer)
+        .collect()
+        .await;
+    println!("      📊 rate_limit(2/50ms): {:?}, 耗时: {:?}", limited, start.elapsed());
+    
+    let merged: Vec<_> = stream::iter(vec![1, 4, 9])
+        .merge_sorted(stream::iter(vec![2, 3, 10]))
+        .collect()
+        .await;
+    println!("      📊 merge_sorted: {:?}", merged);
+    
+    let attempts = Arc::new(Mutex::new(0));
+    let connect = {
+        let attempts = Arc::clone(&attempts);
+        move || {
+            let mut attempt = attempts.lock().unwrap();
+            *attempt += 1;
+            // 前两次连接失败，第三次成功
+            let items: Vec<Result<u32, String>> = if *attempt < 3 {
+                vec![Err(format!("第 {} 次连接失败", attempt))]
+            } else {
+                vec![Ok(200), Ok(201)]
+            };
+            stream::iter(items)
+        }
+    };
+    let backoff = Backoff::exponential(Duration::from_millis(10), Duration::from_millis(100), 5);
+    println!("      🔄 退避间隔: {:?}, {:?}, {:?}", backoff.delay(0), backoff.delay(1), backoff.delay(2));
+    let results: Vec<_> = retry_with_backoff(connect, backoff, TokioTimer).collect().await;
+    println!("      📊 retry_with_backoff: {:?}（共尝试 {} 次）", results, attempts.lock().unwrap());
+    println!("      💡 适配器通过 Timer trait 取时间，测试中可替换为虚拟时钟");
+}
+
 /// 演示异步编程模式
 async fn demonstrate_async_patterns() {
     println!("\n🔍 5. 异步编程模式");
@@ -1059,3 +1125,102 @@ async fn demonstrate_async_iterator() {
     
     println!("      ✅ 异步迭代完成");
 }
+
+// ============================================================================
+// 测试模块：使用虚拟时间的确定性测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{future::Either, stream};
+    use test_executor::{explore, TestExecutor};
+
+    async fn virtual_operation(executor: &TestExecutor, delay: u64) -> u64 {
+        executor.sleep(Duration::from_millis(delay)).await;
+        delay
+    }
+
+    #[test]
+    fn test_sequential_vs_concurrent_await() {
+        let executor = TestExecutor::new(0);
+
+        let start = executor.now();
+        executor.block_on(async {
+            virtual_operation(&executor, 100).await;
+            virtual_operation(&executor, 100).await;
+        });
+        assert_eq!(executor.now() - start, Duration::from_millis(200));
+
+        let start = executor.now();
+        let results = executor.block_on(async {
+            join!(
+                virtual_operation(&executor, 100),
+                virtual_operation(&executor, 100)
+            )
+        });
+        assert_eq!(results, (100, 100));
+        assert_eq!(executor.now() - start, Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_timeout_with_virtual_clock() {
+        let executor = TestExecutor::new(0);
+
+        let outcome = executor.block_on(async {
+            let task = Box::pin(virtual_operation(&executor, 200));
+            let deadline = executor.sleep(Duration::from_millis(100));
+            match futures::future::select(task, deadline).await {
+                Either::Left((value, _)) => Some(value),
+                Either::Right(_) => None,
+            }
+        });
+
+        assert_eq!(outcome, None);
+        assert_eq!(executor.clock().elapsed(), Duration::from_millis(100));
+        // 被取消的任务不会留下悬挂的定时器
+        assert_eq!(executor.clock().pending_timers(), 0);
+    }
+
+    #[test]
+    fn test_buffer_unordered_yields_in_completion_order() {
+        // 无论任务以何种顺序被调度，结果都按虚拟完成时间排列
+        explore(20, |executor| {
+            let results: Vec<u64> = executor.block_on(
+                stream::iter([30, 10, 20])
+                    .map(|delay| virtual_operation(&executor, delay))
+                    .buffer_unordered(3)
+                    .collect(),
+            );
+            assert_eq!(results, vec![10, 20, 30]);
+            assert_eq!(executor.clock().elapsed(), Duration::from_millis(30));
+        });
+    }
+
+    #[test]
+    fn test_spawned_producers_share_channel() {
+        explore(20, |executor| {
+            let (tx, rx) = futures::channel::mpsc::unbounded();
+
+            for id in 0..3u64 {
+                let tx = tx.clone();
+                let executor_handle = executor.clone();
+                executor.spawn(async move {
+                    for step in 0..3 {
+                        executor_handle.sleep(Duration::from_millis(10 * (id + 1))).await;
+                        tx.unbounded_send((id, step)).unwrap();
+                    }
+                });
+            }
+            drop(tx);
+
+            let received: Vec<(u64, u64)> = executor.block_on(rx.collect());
+            assert_eq!(received.len(), 9);
+            // 每个生产者内部的消息保持发送顺序
+            for id in 0..3 {
+                let steps: Vec<_> = received.iter().filter(|(i, _)| *i == id).map(|(_, s)| *s).collect();
+                assert_eq!(steps, vec![0, 1, 2]);
+            }
+        });
+    }
+}
@@ -18,6 +18,10 @@ use std::sync::{Arc, Mutex, Condvar};
 use std::thread::{self, JoinHandle};
 use std::cmp::Ordering;
 
+mod metrics;
+
+use metrics::{Instrumented, MetricsRecorder, RuntimeMetrics, SlowPoll};
+
 fn main() {
     println!("🚀 Future 执行与任务调度深度分析");
     println!("{}", "=".repeat(60));
@@ -230,6 +234,18 @@ async fn executor_architecture() {
     // 4. 执行器性能对比
     println!("\n4. 执行器性能对比：");
     benchmark_executors().await;
+    
+    // 5. 运行时指标与慢 poll 检测
+    println!("\n5. 运行时指标与慢 poll 检测：");
+    demonstrate_runtime_metrics().await;
+}
+
+/// 慢 poll 回调：打印警告
+fn warn_slow_poll(poll: &SlowPoll) {
+    println!(
+        "⚠️ [SLOW POLL] 任务 {} 单次 poll 阻塞 {:?}，超过阈值 {:?}",
+        poll.task_id, poll.elapsed, poll.threshold
+    );
 }
 
 /// 演示简单执行器
@@ -250,6 +266,17 @@ fn demonstrate_simple_executor() {
     println!("    waker: Arc<Mutex<Option<Waker>>>,");
     println!("}}");
     println!("```");
+    
+    // 运行几个计数任务，观察每个任务被 poll 的次数
+    executor.set_slow_poll_threshold(Duration::from_millis(5));
+    executor.on_slow_poll(warn_slow_poll);
+    for target in [2, 4] {
+        executor.spawn(async move {
+            CounterFuture::new(target).await;
+        });
+    }
+    executor.block_on(DelayFuture::new(Duration::from_millis(20)));
+    executor.metrics().print_summary();
 }
 
 /// 演示多线程执行器
@@ -272,6 +299,14 @@ async fn demonstrate_multithreaded_executor() {
     
     // 等待一段时间让任务执行
     DelayFuture::new(Duration::from_millis(200)).await;
+    let metrics = executor.metrics();
+    println!(
+        "📈 完成任务 {}/{}，总 poll 次数 {}，队列峰值 {}",
+        metrics.completed_tasks(),
+        metrics.task_count(),
+        metrics.total_polls(),
+        metrics.queues[0].max_depth
+    );
     println!("✅ 多线程执行器演示完成");
 }
 
@@ -296,9 +331,52 @@ async fn demonstrate_work_stealing_executor() {
     }
     
     DelayFuture::new(Duration::from_millis(300)).await;
+    let metrics = executor.metrics();
+    println!(
+        "📈 完成任务 {}/{}，总 poll 次数 {}，窃取次数 {}",
+        metrics.completed_tasks(),
+        metrics.task_count(),
+        metrics.total_polls(),
+        metrics.total_steals()
+    );
     println!("✅ 工作窃取执行器演示完成");
 }
 
+/// 演示运行时指标快照与慢 poll 检测
+async fn demonstrate_runtime_metrics() {
+    println!("运行时指标内容：");
+    println!("- 每个任务的 poll 次数、累计忙碌时间、单次最长 poll");
+    println!("- 队列深度（当前值 / 峰值）与工作窃取次数");
+    println!("- 慢 poll 检测：单次 poll 超过阈值时发出警告");
+    
+    let executor = WorkStealingExecutor::new(2);
+    executor.set_slow_poll_threshold(Duration::from_millis(5));
+    executor.on_slow_poll(warn_slow_poll);
+    
+    for i in 0..4 {
+        executor.spawn(async move {
+            DelayFuture::new(Duration::from_millis(10 * (i + 1))).await;
+        });
+    }
+    
+    // 在 poll 中阻塞线程的任务会触发慢 poll 警告
+    executor.spawn(async {
+        std::thread::sleep(Duration::from_millis(20));
+    });
+    
+    DelayFuture::new(Duration::from_millis(150)).await;
+    executor.metrics().print_summary();
+    
+    let multi_executor = MultiThreadedExecutor::new(2);
+    multi_executor.set_slow_poll_threshold(Duration::from_millis(5));
+    multi_executor.on_slow_poll(warn_slow_poll);
+    multi_executor.spawn(async {
+        std::thread::sleep(Duration::from_millis(10));
+    });
+    DelayFuture::new(Duration::from_millis(50)).await;
+    println!("多线程执行器慢 poll 次数: {}", multi_executor.metrics().slow_polls);
+}
+
 /// 执行器性能基准测试
 async fn benchmark_executors() {
     println!("执行器性能基准测试：");
@@ -878,6 +956,7 @@ impl NumberStream {
 /// 简单执行器
 struct SimpleExecutor {
     task_queue: Arc<Mutex<VecDeque<BoxFuture>>>,
+    metrics: Arc<MetricsRecorder>,
 }
 
 type BoxFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
@@ -886,6 +965,7 @@ impl SimpleExecutor {
     fn new() -> Self {
         SimpleExecutor {
             task_queue: Arc::new(Mutex::new(VecDeque::new())),
+            metrics: Arc::new(MetricsRecorder::new(1, 1)),
         }
     }
     
@@ -893,8 +973,25 @@ impl SimpleExecutor {
     where
         F: Future<Output = ()> + Send + 'static,
     {
+        let task = Instrumented::new(future, Arc::clone(&self.metrics));
         let mut queue = self.task_queue.lock().unwrap();
-        queue.push_back(Box::pin(future));
+        queue.push_back(Box::pin(task));
+        self.metrics.record_queue_depth(0, queue.len());
+    }
+    
+    /// 获取运行时指标快照
+    fn metrics(&self) -> RuntimeMetrics {
+        self.metrics.snapshot()
+    }
+    
+    /// 设置慢 poll 警告阈值
+    fn set_slow_poll_threshold(&self, threshold: Duration) {
+        self.metrics.slow_poll_detector().set_threshold(threshold);
+    }
+    
+    /// 设置慢 poll 回调
+    fn on_slow_poll(&self, hook: impl Fn(&SlowPoll) + Send + Sync + 'static) {
+        self.metrics.slow_poll_detector().set_hook(hook);
     }
     
     fn block_on<F>(&self, future: F) -> F::Output
@@ -909,11 +1006,18 @@ impl SimpleExecutor {
             match future.as_mut().poll(&mut context) {
                 Poll::Ready(output) => return output,
                 Poll::Pending => {
-                    // 执行队列中的其他任务
-                    if let Ok(mut queue) = self.task_queue.try_lock() {
-                        if let Some(mut task) = queue.pop_front() {
-                            let _ = task.as_mut().poll(&mut context);
-                        }
+                    // 执行队列中的其他任务，未完成的任务重新入队
+                    let task = self.task_queue.try_lock().ok().and_then(|mut queue| {
+                        let task = queue.pop_front();
+                        self.metrics.record_queue_depth(0, queue.len());
+                        task
+                    });
+                    if let Some(mut task) = task
+                        && task.as_mut().poll(&mut context).is_pending()
+                    {
+                        let mut queue = self.task_queue.lock().unwrap();
+                        queue.push_back(task);
+                        self.metrics.record_queue_depth(0, queue.len());
                     }
                     std::thread::sleep(Duration::from_millis(1));
                 }
@@ -926,24 +1030,32 @@ impl SimpleExecutor {
 struct MultiThreadedExecutor {
     task_queue: Arc<Mutex<VecDeque<BoxFuture>>>,
     workers: Vec<JoinHandle<()>>,
+    metrics: Arc<MetricsRecorder>,
 }
 
 impl MultiThreadedExecutor {
     fn new(worker_count: usize) -> Self {
         let task_queue = Arc::new(Mutex::new(VecDeque::<BoxFuture>::new()));
+        let metrics = Arc::new(MetricsRecorder::new(1, worker_count));
         let mut workers = Vec::new();
         
         for i in 0..worker_count {
             let queue = Arc::clone(&task_queue);
+            let metrics = Arc::clone(&metrics);
             let worker = thread::spawn(move || {
                 println!("[WORKER {}] 启动", i);
                 loop {
-                    if let Ok(mut queue) = queue.try_lock() {
-                        if let Some(mut task) = queue.pop_front() {
-                            drop(queue); // 释放锁
-                            let waker = create_dummy_waker();
-                            let mut context = Context::from_waker(&waker);
-                            let _ = task.as_mut().poll(&mut context);
+                    if let Ok(mut guard) = queue.try_lock()
+                        && let Some(mut task) = guard.pop_front()
+                    {
+                        metrics.record_queue_depth(0, guard.len());
+                        drop(guard); // 释放锁
+                        let waker = create_dummy_waker();
+                        let mut context = Context::from_waker(&waker);
+                        if task.as_mut().poll(&mut context).is_pending() {
+                            let mut guard = queue.lock().unwrap();
+                            guard.push_back(task);
+                            metrics.record_queue_depth(0, guard.len());
                         }
                     }
                     std::thread::sleep(Duration::from_millis(1));
@@ -955,6 +1067,7 @@ impl MultiThreadedExecutor {
         MultiThreadedExecutor {
             task_queue,
             workers,
+            metrics,
         }
     }
     
@@ -962,8 +1075,25 @@ impl MultiThreadedExecutor {
     where
         F: Future<Output = ()> + Send + 'static,
     {
+        let task = Instrumented::new(future, Arc::clone(&self.metrics));
         let mut queue = self.task_queue.lock().unwrap();
-        queue.push_back(Box::pin(future));
+        queue.push_back(Box::pin(task));
+        self.metrics.record_queue_depth(0, queue.len());
+    }
+    
+    /// 获取运行时指标快照
+    fn metrics(&self) -> RuntimeMetrics {
+        self.metrics.snapshot()
+    }
+    
+    /// 设置慢 poll 警告阈值
+    fn set_slow_poll_threshold(&self, threshold: Duration) {
+        self.metrics.slow_poll_detector().set_threshold(threshold);
+    }
+    
+    /// 设置慢 poll 回调
+    fn on_slow_poll(&self, hook: impl Fn(&SlowPoll) + Send + Sync + 'static) {
+        self.metrics.slow_poll_detector().set_hook(hook);
     }
 }
 
@@ -971,12 +1101,14 @@ impl MultiThreadedExecutor {
 struct WorkStealingExecutor {
     queues: Vec<Arc<Mutex<VecDeque<BoxFuture>>>>,
     workers: Vec<JoinHandle<()>>,
+    metrics: Arc<MetricsRecorder>,
 }
 
 impl WorkStealingExecutor {
     fn new(worker_count: usize) -> Self {
         let mut queues = Vec::new();
         let mut workers = Vec::new();
+        let metrics = Arc::new(MetricsRecorder::new(worker_count, worker_count));
         
         // 为每个工作线程创建独立队列
         for _ in 0..worker_count {
@@ -987,19 +1119,30 @@ impl WorkStealingExecutor {
         for i in 0..worker_count {
             let local_queue = Arc::clone(&queues[i]);
             let all_queues = queues.clone();
+            let metrics = Arc::clone(&metrics);
             
             let worker = thread::spawn(move || {
                 println!("[STEAL-WORKER {}] 启动", i);
+                // 未完成的任务放回本地队列
+                let requeue = |task: BoxFuture| {
+                    let mut queue = local_queue.lock().unwrap();
+                    queue.push_back(task);
+                    metrics.record_queue_depth(i, queue.len());
+                };
+                
                 loop {
                     let mut task_found = false;
                     
                     // 首先尝试从本地队列获取任务
                     if let Ok(mut queue) = local_queue.try_lock() {
                         if let Some(mut task) = queue.pop_front() {
+                            metrics.record_queue_depth(i, queue.len());
                             drop(queue);
                             let waker = create_dummy_waker();
                             let mut context = Context::from_waker(&waker);
-                            let _ = task.as_mut().poll(&mut context);
+                            if task.as_mut().poll(&mut context).is_pending() {
+                                requeue(task);
+                            }
                             task_found = true;
                         }
                     }
@@ -1010,11 +1153,15 @@ impl WorkStealingExecutor {
                             if i != j {
                                 if let Ok(mut queue) = queue.try_lock() {
                                     if let Some(mut task) = queue.pop_back() {
+                                        metrics.record_queue_depth(j, queue.len());
                                         drop(queue);
+                                        metrics.record_steal(i);
                                         println!("[STEAL-WORKER {}] 从 Worker {} 窃取任务", i, j);
                                         let waker = create_dummy_waker();
                                         let mut context = Context::from_waker(&waker);
-                                        let _ = task.as_mut().poll(&mut context);
+                                        if task.as_mut().poll(&mut context).is_pending() {
+                                            requeue(task);
+                                        }
                                         task_found = true;
                                         break;
                                     }
@@ -1032,7 +1179,7 @@ impl WorkStealingExecutor {
             workers.push(worker);
         }
         
-        WorkStealingExecutor { queues, workers }
+        WorkStealingExecutor { queues, workers, metrics }
     }
     
     fn spawn<F>(&self, future: F)
@@ -1041,8 +1188,25 @@ impl WorkStealingExecutor {
     {
         // 简单的负载均衡：轮询分配到不同队列
         let queue_index = (rand::random::<u64>() as usize) % self.queues.len();
+        let task = Instrumented::new(future, Arc::clone(&self.metrics));
         let mut queue = self.queues[queue_index].lock().unwrap();
-        queue.push_back(Box::pin(future));
+        queue.push_back(Box::pin(task));
+        self.metrics.record_queue_depth(queue_index, queue.len());
+    }
+    
+    /// 获取运行时指标快照
+    fn metrics(&self) -> RuntimeMetrics {
+        self.metrics.snapshot()
+    }
+    
+    /// 设置慢 poll 警告阈值
+    fn set_slow_poll_threshold(&self, threshold: Duration) {
+        self.metrics.slow_poll_detector().set_threshold(threshold);
+    }
+    
+    /// 设置慢 poll 回调
+    fn on_slow_poll(&self, hook: impl Fn(&SlowPoll) + Send + Sync + 'static) {
+        self.metrics.slow_poll_detector().set_hook(hook);
     }
 }
 
@@ -0,0 +1,606 @@
+//! # 执行器运行时指标
+//!
+//! 为本项目中的各个执行器提供统一的可观测性支持：
+//! - 每个任务的 poll 次数、累计忙碌时间、单次最长 poll
+//! - 各任务队列的当前深度与历史峰值
+//! - 工作窃取次数
+//! - 慢 poll 检测：单次 poll 超过阈值时计数，并交给可选的回调处理（例如打印警告）
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// 默认的慢 poll 阈值
+pub const DEFAULT_SLOW_POLL_THRESHOLD: Duration = Duration::from_millis(10);
+
+/// 快照中保留明细的已结束任务数，更早结束的任务只计入 [`RetiredTasks`]
+pub const FINISHED_TASK_HISTORY: usize = 256;
+
+// ============================================================================
+// 指标快照
+// ============================================================================
+
+/// 单个任务的运行指标
+#[derive(Debug, Clone, Default)]
+pub struct TaskMetrics {
+    pub task_id: usize,
+    pub poll_count: u64,
+    pub busy_time: Duration,
+    pub max_poll: Duration,
+    pub completed: bool,
+}
+
+impl TaskMetrics {
+    /// 平均每次 poll 的耗时
+    pub fn mean_poll(&self) -> Duration {
+        if self.poll_count == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_nanos((self.busy_time.as_nanos() / self.poll_count as u128) as u64)
+    }
+}
+
+/// 已结束且移出明细的任务的累计指标
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RetiredTasks {
+    pub count: usize,
+    /// 其中正常完成的任务数，其余是完成前被丢弃的
+    pub completed: usize,
+    pub poll_count: u64,
+    pub busy_time: Duration,
+    pub max_poll: Duration,
+}
+
+impl RetiredTasks {
+    fn absorb(&mut self, task: &TaskMetrics) {
+        self.count += 1;
+        self.completed += task.completed as usize;
+        self.poll_count += task.poll_count;
+        self.busy_time += task.busy_time;
+        self.max_poll = self.max_poll.max(task.max_poll);
+    }
+}
+
+/// 单个任务队列的深度指标
+#[derive(Debug, Clone, Copy, Default)]
+pub struct QueueMetrics {
+    pub depth: usize,
+    pub max_depth: usize,
+}
+
+/// 运行时指标快照
+///
+/// 由 `MetricsRecorder::snapshot` 生成，生成后不再随执行器变化。
+#[derive(Debug, Clone, Default)]
+pub struct RuntimeMetrics {
+    /// 按任务 ID 排序的任务指标：所有未结束的任务，加上最近结束（完成或被丢弃）的
+    /// [`FINISHED_TASK_HISTORY`] 个任务
+    pub tasks: Vec<TaskMetrics>,
+    /// 更早结束的任务的汇总
+    pub retired: RetiredTasks,
+    /// 按队列下标排列的队列指标
+    pub queues: Vec<QueueMetrics>,
+    /// 按工作线程下标排列的窃取次数
+    pub steal_counts: Vec<u64>,
+    /// 超过阈值的 poll 次数
+    pub slow_polls: u64,
+}
+
+impl RuntimeMetrics {
+    /// 登记过的任务总数，包括已移出明细的任务
+    pub fn task_count(&self) -> usize {
+        self.tasks.len() + self.retired.count
+    }
+
+    /// 所有任务的 poll 总次数
+    pub fn total_polls(&self) -> u64 {
+        self.tasks.iter().map(|t| t.poll_count).sum::<u64>() + self.retired.poll_count
+    }
+
+    /// 所有任务的累计忙碌时间
+    pub fn total_busy_time(&self) -> Duration {
+        self.tasks.iter().map(|t| t.busy_time).sum::<Duration>() + self.retired.busy_time
+    }
+
+    /// 所有任务中单次最长的 poll
+    pub fn max_poll(&self) -> Duration {
+        self.tasks
+            .iter()
+            .map(|t| t.max_poll)
+            .fold(self.retired.max_poll, Duration::max)
+    }
+
+    /// 工作窃取总次数
+    pub fn total_steals(&self) -> u64 {
+        self.steal_counts.iter().sum()
+    }
+
+    /// 已完成的任务数
+    pub fn completed_tasks(&self) -> usize {
+        self.tasks.iter().filter(|t| t.completed).count() + self.retired.completed
+    }
+
+    /// 打印指标摘要
+    pub fn print_summary(&self) {
+        println!("📈 运行时指标快照：");
+        println!(
+            "  任务: {} 个（已完成 {} 个），总 poll 次数: {}，总忙碌时间: {:?}",
+            self.task_count(),
+            self.completed_tasks(),
+            self.total_polls(),
+            self.total_busy_time()
+        );
+        println!(
+            "  单次最长 poll: {:?}，慢 poll 次数: {}",
+            self.max_poll(),
+            self.slow_polls
+        );
+
+        for (i, queue) in self.queues.iter().enumerate() {
+            println!("  队列 {}: 当前深度 {}，峰值 {}", i, queue.depth, queue.max_depth);
+        }
+
+        if self.total_steals() > 0 {
+            for (i, steals) in self.steal_counts.iter().enumerate() {
+                println!("  Worker {}: 窃取 {} 次", i, steals);
+            }
+        }
+
+        if self.retired.count > 0 {
+            println!("  更早结束的 {} 个任务仅保留汇总", self.retired.count);
+        }
+
+        for task in &self.tasks {
+            println!(
+                "  任务 {}: poll {} 次，忙碌 {:?}，最长 {:?}，平均 {:?}{}",
+                task.task_id,
+                task.poll_count,
+                task.busy_time,
+                task.max_poll,
+                task.mean_poll(),
+                if task.completed { "" } else { "（未完成）" }
+            );
+        }
+    }
+}
+
+// ============================================================================
+// 慢 poll 检测
+// ============================================================================
+
+/// 一次超过阈值的 poll
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlowPoll {
+    pub task_id: usize,
+    pub elapsed: Duration,
+    pub threshold: Duration,
+}
+
+/// 在执行 poll 的线程上调用，不能阻塞
+type SlowPollHook = Arc<dyn Fn(&SlowPoll) + Send + Sync>;
+
+/// 慢 poll 检测器
+///
+/// Future 的 `poll` 应当很快返回；如果单次 poll 阻塞超过阈值，
+/// 说明任务里很可能混入了阻塞调用，会拖慢同一线程上的其他任务。
+pub struct SlowPollDetector {
+    threshold_nanos: AtomicU64,
+    slow_polls: AtomicU64,
+    hook: Mutex<Option<SlowPollHook>>,
+}
+
+impl SlowPollDetector {
+    pub fn new(threshold: Duration) -> Self {
+        SlowPollDetector {
+            threshold_nanos: AtomicU64::new(threshold.as_nanos() as u64),
+            slow_polls: AtomicU64::new(0),
+            hook: Mutex::new(None),
+        }
+    }
+
+    /// 每次检测到慢 poll 时调用 `hook`，替换之前设置的回调
+    pub fn set_hook(&self, hook: impl Fn(&SlowPoll) + Send + Sync + 'static) {
+        *self.hook.lock().unwrap() = Some(Arc::new(hook));
+    }
+
+    pub fn threshold(&self) -> Duration {
+        Duration::from_nanos(self.threshold_nanos.load(Ordering::Relaxed))
+    }
+
+    pub fn set_threshold(&self, threshold: Duration) {
+        self.threshold_nanos
+            .store(threshold.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    /// 检查一次 poll 的耗时，超过阈值时计数、通知回调并返回 `true`
+    pub fn check(&self, task_id: usize, elapsed: Duration) -> bool {
+        let threshold = self.threshold();
+        if elapsed <= threshold {
+            return false;
+        }
+
+        self.slow_polls.fetch_add(1, Ordering::Relaxed);
+        // 先取出回调再调用，回调里可以重新设置回调
+        let hook = self.hook.lock().unwrap().clone();
+        if let Some(hook) = hook {
+            hook(&SlowPoll {
+                task_id,
+                elapsed,
+                threshold,
+            });
+        }
+        true
+    }
+
+    pub fn slow_poll_count(&self) -> u64 {
+        self.slow_polls.load(Ordering::Relaxed)
+    }
+}
+
+impl fmt::Debug for SlowPollDetector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SlowPollDetector")
+            .field("threshold", &self.threshold())
+            .field("slow_polls", &self.slow_poll_count())
+            .field("hook", &self.hook.lock().unwrap().is_some())
+            .finish()
+    }
+}
+
+// ============================================================================
+// 指标收集器
+// ============================================================================
+
+/// 单个队列的深度计量
+#[derive(Debug, Default)]
+struct QueueGauge {
+    depth: AtomicUsize,
+    max_depth: AtomicUsize,
+}
+
+/// 任务指标表
+///
+/// 任务完成或被丢弃后从 `live` 移到 `finished`，超出 [`FINISHED_TASK_HISTORY`] 的最早一个
+/// 再汇总进 `retired`，长时间运行的执行器占用的内存只与未结束的任务数有关。
+#[derive(Debug, Default)]
+struct TaskTable {
+    live: HashMap<usize, TaskMetrics>,
+    finished: VecDeque<TaskMetrics>,
+    retired: RetiredTasks,
+}
+
+impl TaskTable {
+    fn finish(&mut self, task: TaskMetrics) {
+        self.finished.push_back(task);
+        if self.finished.len() > FINISHED_TASK_HISTORY {
+            let oldest = self.finished.pop_front().unwrap();
+            self.retired.absorb(&oldest);
+        }
+    }
+}
+
+/// 指标收集器
+///
+/// 由执行器持有，并通过 `Arc` 共享给工作线程和 `Instrumented` 任务。
+#[derive(Debug)]
+pub struct MetricsRecorder {
+    next_task_id: AtomicUsize,
+    tasks: Mutex<TaskTable>,
+    queues: Vec<QueueGauge>,
+    steals: Vec<AtomicU64>,
+    detector: SlowPollDetector,
+}
+
+impl MetricsRecorder {
+    pub fn new(queue_count: usize, worker_count: usize) -> Self {
+        MetricsRecorder {
+            next_task_id: AtomicUsize::new(0),
+            tasks: Mutex::new(TaskTable::default()),
+            queues: (0..queue_count).map(|_| QueueGauge::default()).collect(),
+            steals: (0..worker_count).map(|_| AtomicU64::new(0)).collect(),
+            detector: SlowPollDetector::new(DEFAULT_SLOW_POLL_THRESHOLD),
+        }
+    }
+
+    /// 登记一个新任务并分配任务 ID
+    pub fn register_task(&self) -> usize {
+        let task_id = self.next_task_id.fetch_add(1, Ordering::Relaxed);
+        self.tasks.lock().unwrap().live.insert(
+            task_id,
+            TaskMetrics {
+                task_id,
+                ..TaskMetrics::default()
+            },
+        );
+        task_id
+    }
+
+    /// 记录一次 poll；未登记或已结束的任务只参与慢 poll 检测
+    pub fn record_poll(&self, task_id: usize, elapsed: Duration, ready: bool) {
+        {
+            let mut tasks = self.tasks.lock().unwrap();
+            if let Some(task) = tasks.live.get_mut(&task_id) {
+                task.poll_count += 1;
+                task.busy_time += elapsed;
+                task.max_poll = task.max_poll.max(elapsed);
+
+                if ready {
+                    let mut task = tasks.live.remove(&task_id).unwrap();
+                    task.completed = true;
+                    tasks.finish(task);
+                }
+            }
+        }
+
+        self.detector.check(task_id, elapsed);
+    }
+
+    /// 任务在完成前被丢弃（取消、执行器关闭），按未完成结束；已完成的任务不受影响
+    pub fn record_drop(&self, task_id: usize) {
+        let mut tasks = self.tasks.lock().unwrap();
+        if let Some(task) = tasks.live.remove(&task_id) {
+            tasks.finish(task);
+        }
+    }
+
+    /// 记录队列当前深度（调用方应在持有队列锁时调用）
+    pub fn record_queue_depth(&self, queue: usize, depth: usize) {
+        if let Some(gauge) = self.queues.get(queue) {
+            gauge.depth.store(depth, Ordering::Relaxed);
+            gauge.max_depth.fetch_max(depth, Ordering::Relaxed);
+        }
+    }
+
+    /// 记录一次工作窃取
+    pub fn record_steal(&self, worker: usize) {
+        if let Some(steals) = self.steals.get(worker) {
+            steals.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn slow_poll_detector(&self) -> &SlowPollDetector {
+        &self.detector
+    }
+
+    /// 生成当前指标的快照
+    pub fn snapshot(&self) -> RuntimeMetrics {
+        let table = self.tasks.lock().unwrap();
+        let mut tasks: Vec<TaskMetrics> = table.live.values().chain(&table.finished).cloned().collect();
+        tasks.sort_by_key(|t| t.task_id);
+
+        RuntimeMetrics {
+            tasks,
+            retired: table.retired,
+            queues: self
+                .queues
+                .iter()
+                .map(|g| QueueMetrics {
+                    depth: g.depth.load(Ordering::Relaxed),
+                    max_depth: g.max_depth.load(Ordering::Relaxed),
+                })
+                .collect(),
+            steal_counts: self.steals.iter().map(|s| s.load(Ordering::Relaxed)).collect(),
+            slow_polls: self.detector.slow_poll_count(),
+        }
+    }
+}
+
+// ============================================================================
+// 带指标采集的 Future
+// ============================================================================
+
+/// 带指标采集的 Future 包装器
+///
+/// 每次 poll 都会计时，并把结果写入共享的 `MetricsRecorder`。
+pub struct Instrumented<F> {
+    task_id: usize,
+    future: Pin<Box<F>>,
+    recorder: Arc<MetricsRecorder>,
+}
+
+impl<F: Future> Instrumented<F> {
+    pub fn new(future: F, recorder: Arc<MetricsRecorder>) -> Self {
+        Instrumented {
+            task_id: recorder.register_task(),
+            future: Box::pin(future),
+            recorder,
+        }
+    }
+}
+
+impl<F: Future> Future for Instrumented<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let start = Instant::now();
+        let result = self.future.as_mut().poll(cx);
+        let elapsed = start.elapsed();
+
+        self.recorder
+            .record_poll(self.task_id, elapsed, result.is_ready());
+        result
+    }
+}
+
+impl<F> Drop for Instrumented<F> {
+    fn drop(&mut self) {
+        self.recorder.record_drop(self.task_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_dummy_waker;
+
+    /// 前 `remaining` 次 poll 返回 Pending 的 Future
+    struct PendingTimes {
+        remaining: usize,
+    }
+
+    impl Future for PendingTimes {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<()> {
+            if self.remaining == 0 {
+                Poll::Ready(())
+            } else {
+                self.remaining -= 1;
+                Poll::Pending
+            }
+        }
+    }
+
+    #[test]
+    fn test_instrumented_counts_polls() {
+        let recorder = Arc::new(MetricsRecorder::new(1, 1));
+        let mut future = Instrumented::new(PendingTimes { remaining: 3 }, Arc::clone(&recorder));
+        let waker = create_dummy_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        while Pin::new(&mut future).poll(&mut cx).is_pending() {}
+
+        let metrics = recorder.snapshot();
+        assert_eq!(metrics.tasks.len(), 1);
+        assert_eq!(metrics.tasks[0].poll_count, 4);
+        assert!(metrics.tasks[0].completed);
+        assert!(metrics.tasks[0].max_poll <= metrics.tasks[0].busy_time);
+    }
+
+    #[test]
+    fn test_finished_tasks_are_rolled_up() {
+        let recorder = MetricsRecorder::new(1, 1);
+        let pending = recorder.register_task();
+        recorder.record_poll(pending, Duration::from_millis(1), false);
+
+        let finished = FINISHED_TASK_HISTORY + 10;
+        for _ in 0..finished {
+            let task_id = recorder.register_task();
+            recorder.record_poll(task_id, Duration::from_millis(1), false);
+            recorder.record_poll(task_id, Duration::from_millis(2), true);
+        }
+
+        let metrics = recorder.snapshot();
+        // 未完成的任务始终保留明细，已完成的只留最近一批
+        assert_eq!(metrics.tasks.len(), FINISHED_TASK_HISTORY + 1);
+        assert_eq!(metrics.tasks[0].task_id, pending);
+        assert!(!metrics.tasks[0].completed);
+        assert_eq!(metrics.tasks[1].task_id, 11);
+        assert_eq!(metrics.retired.count, 10);
+
+        // 汇总值不受明细被移出的影响
+        assert_eq!(metrics.task_count(), finished + 1);
+        assert_eq!(metrics.completed_tasks(), finished);
+        assert_eq!(metrics.total_polls(), 1 + 2 * finished as u64);
+        assert_eq!(metrics.total_busy_time(), Duration::from_millis(1 + 3 * finished as u64));
+        assert_eq!(metrics.max_poll(), Duration::from_millis(2));
+    }
+
+    #[test]
+    fn test_dropped_task_leaves_live_table() {
+        let recorder = Arc::new(MetricsRecorder::new(1, 1));
+        let mut pending = Instrumented::new(PendingTimes { remaining: 5 }, Arc::clone(&recorder));
+        let mut finished = Instrumented::new(PendingTimes { remaining: 0 }, Arc::clone(&recorder));
+        let waker = create_dummy_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        assert!(Pin::new(&mut pending).poll(&mut cx).is_pending());
+        assert!(Pin::new(&mut finished).poll(&mut cx).is_ready());
+        // 完成后再次 poll 不会重新登记
+        let _ = Pin::new(&mut finished).poll(&mut cx);
+        drop(pending);
+        drop(finished);
+
+        assert!(recorder.tasks.lock().unwrap().live.is_empty());
+        let metrics = recorder.snapshot();
+        assert_eq!(metrics.task_count(), 2);
+        assert_eq!(metrics.completed_tasks(), 1);
+        assert_eq!(metrics.total_polls(), 2);
+        assert!(!metrics.tasks[0].completed);
+    }
+
+    #[test]
+    fn test_slow_poll_detector() {
+        let detector = SlowPollDetector::new(Duration::from_millis(5));
+        assert!(!detector.check(0, Duration::from_millis(1)));
+        assert!(detector.check(0, Duration::from_millis(6)));
+        assert_eq!(detector.slow_poll_count(), 1);
+
+        detector.set_threshold(Duration::from_millis(10));
+        assert!(!detector.check(0, Duration::from_millis(6)));
+    }
+
+    #[test]
+    fn test_slow_poll_hook_receives_events() {
+        let detector = SlowPollDetector::new(Duration::from_millis(5));
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&events);
+        detector.set_hook(move |poll| sink.lock().unwrap().push(*poll));
+
+        detector.check(3, Duration::from_millis(1));
+        detector.check(7, Duration::from_millis(8));
+        assert_eq!(
+            *events.lock().unwrap(),
+            [SlowPoll {
+                task_id: 7,
+                elapsed: Duration::from_millis(8),
+                threshold: Duration::from_millis(5),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_blocking_future_is_flagged() {
+        let recorder = Arc::new(MetricsRecorder::new(1, 1));
+        recorder.slow_poll_detector().set_threshold(Duration::from_millis(1));
+        let mut future = Instrumented::new(
+            async { std::thread::sleep(Duration::from_millis(5)) },
+            Arc::clone(&recorder),
+        );
+        let waker = create_dummy_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        assert!(Pin::new(&mut future).poll(&mut cx).is_ready());
+        let metrics = recorder.snapshot();
+        assert_eq!(metrics.slow_polls, 1);
+        assert!(metrics.max_poll() >= Duration::from_millis(5));
+    }
+
+    #[test]
+    fn test_queue_depth_and_steals() {
+        let recorder = MetricsRecorder::new(2, 2);
+        recorder.record_queue_depth(0, 3);
+        recorder.record_queue_depth(0, 1);
+        recorder.record_queue_depth(1, 2);
+        recorder.record_steal(1);
+        recorder.record_steal(1);
+
+        let metrics = recorder.snapshot();
+        assert_eq!(metrics.queues[0].depth, 1);
+        assert_eq!(metrics.queues[0].max_depth, 3);
+        assert_eq!(metrics.queues[1].max_depth, 2);
+        assert_eq!(metrics.steal_counts, vec![0, 2]);
+        assert_eq!(metrics.total_steals(), 2);
+    }
+
+    #[test]
+    fn test_executors_expose_metrics() {
+        let executor = crate::SimpleExecutor::new();
+        for _ in 0..3 {
+            executor.spawn(PendingTimes { remaining: 2 });
+        }
+        executor.block_on(crate::DelayFuture::new(Duration::from_millis(30)));
+
+        let metrics = executor.metrics();
+        assert_eq!(metrics.tasks.len(), 3);
+        assert_eq!(metrics.completed_tasks(), 3);
+        assert_eq!(metrics.total_polls(), 9);
+        assert_eq!(metrics.queues[0].max_depth, 3);
+        assert_eq!(metrics.queues[0].depth, 0);
+    }
+}
@@ -0,0 +1,152 @@
+//! # 层级化的取消令牌
+//!
+//! `JoinHandle::abort` 只能取消单个任务，而结构化并发需要“取消一个父任务，
+//! 它派生出的所有子任务都跟着取消”。`CancellationToken` 组成一棵树：
+//! - `cancel()` 取消当前令牌以及所有子令牌（递归）
+//! - 取消子令牌不会影响父令牌和兄弟令牌
+//! - `cancelled()` 返回一个在令牌被取消时完成的 Future，可以放进 `select!`
+
+use std::future::Future;
+use std::sync::{Arc, Mutex, Weak};
+
+use tokio::sync::Notify;
+
+#[derive(Default)]
+struct Node {
+    state: Mutex<State>,
+    notify: Notify,
+}
+
+#[derive(Default)]
+struct State {
+    cancelled: bool,
+    // 子令牌用弱引用保存，已经丢弃的子令牌不会阻止释放
+    children: Vec<Weak<Node>>,
+}
+
+impl Node {
+    fn cancel(&self) {
+        let children = {
+            let mut state = self.state.lock().unwrap();
+            if state.cancelled {
+                return;
+            }
+            state.cancelled = true;
+            std::mem::take(&mut state.children)
+        };
+        self.notify.notify_waiters();
+
+        // 在锁外递归，避免父子节点的锁嵌套
+        for child in children.iter().filter_map(Weak::upgrade) {
+            child.cancel();
+        }
+    }
+}
+
+/// 可克隆的取消令牌，克隆出的令牌共享同一个取消状态
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    node: Arc<Node>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 创建子令牌：父令牌取消时子令牌也被取消，反之不然
+    pub fn child_token(&self) -> CancellationToken {
+        let child = CancellationToken::new();
+        let mut state = self.node.state.lock().unwrap();
+        if state.cancelled {
+            child.node.state.lock().unwrap().cancelled = true;
+        } else {
+            state.children.retain(|weak| weak.strong_count() > 0);
+            state.children.push(Arc::downgrade(&child.node));
+        }
+        child
+    }
+
+    pub fn cancel(&self) {
+        self.node.cancel();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.node.state.lock().unwrap().cancelled
+    }
+
+    /// 等待令牌被取消
+    pub async fn cancelled(&self) {
+        loop {
+            let notified = self.node.notify.notified();
+            tokio::pin!(notified);
+            // 先登记再检查状态，避免错过检查和等待之间发生的取消
+            notified.as_mut().enable();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// 运行 Future，直到它完成或令牌被取消；被取消时返回 `None`
+    pub async fn run_until_cancelled<F: Future>(&self, future: F) -> Option<F::Output> {
+        tokio::select! {
+            biased;
+            _ = self.cancelled() => None,
+            output = future => Some(output),
+        }
+    }
+}
+
+impl std::fmt::Debug for CancellationToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CancellationToken")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::time::sleep;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cancel_propagates_to_descendants_only() {
+        let root = CancellationToken::new();
+        let child = root.child_token();
+        let grandchild = child.child_token();
+        let sibling = root.child_token();
+
+        child.cancel();
+        assert!(child.is_cancelled());
+        assert!(grandchild.is_cancelled());
+        assert!(!root.is_cancelled());
+        assert!(!sibling.is_cancelled());
+
+        root.cancel();
+        assert!(sibling.is_cancelled());
+        // 在已取消的令牌上派生的子令牌一出生就是取消状态
+        assert!(root.child_token().is_cancelled());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cancelled_wakes_waiters() {
+        let token = CancellationToken::new();
+        let waiter = tokio::spawn({
+            let token = token.child_token();
+            async move {
+                token.run_until_cancelled(sleep(Duration::from_secs(60))).await
+            }
+        });
+
+        sleep(Duration::from_millis(10)).await;
+        token.cancel();
+        assert_eq!(waiter.await.unwrap(), None);
+
+        // 已经取消的令牌上 cancelled() 立即完成
+        token.cancelled().await;
+    }
+}
@@ -21,6 +21,16 @@ use tokio::{
 use anyhow::{anyhow, Result};
 use rand::Rng;
 
+// 结构化并发：取消令牌与任务组
+mod cancellation;
+mod task_group;
+use cancellation::CancellationToken;
+use task_group::{TaskGroup, TaskOutcome};
+
+// 弹性策略：重试、熔断、舱壁、对冲
+use resilience::{BreakerConfig, Bulkhead, CircuitBreaker, Hedge, Jitter, ResilienceError, ResiliencePolicy, Retry};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
 #[tokio::main]
 async fn main() -> Result<()> {
     println!("🚀 Rust 异步编程 - 多 Future 并发执行深度分析\n");
@@ -57,6 +67,14 @@ async fn main() -> Result<()> {
     println!("\n📋 8. 实际应用案例");
     demonstrate_real_world_examples().await?;
     
+    // 9. 结构化并发
+    println!("\n📋 9. 结构化并发：TaskGroup 与取消令牌");
+    demonstrate_structured_concurrency().await?;
+    
+    // 10. 弹性策略
+    println!("\n📋 10. 弹性策略：重试、熔断、舱壁与对冲");
+    demonstrate_resilience_policies().await?;
+    
     println!("\n✅ 所有演示完成！");
     Ok(())
 }
@@ -589,6 +607,209 @@ async fn demonstrate_real_world_examples() -> Result<()> {
     Ok(())
 }
 
+/// 9. 结构化并发：TaskGroup 与取消令牌
+async fn demonstrate_structured_concurrency() -> Result<()> {
+    println!("\n🔍 9.1 首个错误取消兄弟任务");
+    
+    let mut group = TaskGroup::new();
+    for i in 1..=4u64 {
+        group.spawn(move |_token| fallible_task(format!("组任务{}", i), 50 * i, i == 2));
+    }
+    
+    let start = Instant::now();
+    match group.join().await {
+        Ok(results) => println!("      ✅ 全部成功: {:?}", results),
+        Err(e) => println!("      ❌ 任务组失败: {} (耗时: {:?})", e, start.elapsed()),
+    }
+    println!("      📝 与 try_join! 一样快速失败，但兄弟任务是被真正取消的独立任务");
+    
+    println!("\n🔍 9.2 收集全部结局（不取消兄弟任务）");
+    
+    let mut group = TaskGroup::new().cancel_on_error(false);
+    for i in 1..=4u64 {
+        group.spawn(move |_token| fallible_task(format!("组任务{}", i), 50 * i, i == 2));
+    }
+    
+    for (i, outcome) in group.join_all().await.into_iter().enumerate() {
+        match outcome {
+            TaskOutcome::Completed(msg) => println!("      ✅ 任务{}: {}", i + 1, msg),
+            TaskOutcome::Failed(e) => println!("      ❌ 任务{}: {}", i + 1, e),
+            TaskOutcome::Panicked(msg) => println!("      💥 任务{}崩溃: {}", i + 1, msg),
+            TaskOutcome::Cancelled => println!("      🛑 任务{}被取消", i + 1),
+        }
+    }
+    
+    println!("\n🔍 9.3 截止时间");
+    
+    let mut group = TaskGroup::new().deadline(Duration::from_millis(150));
+    for i in 1..=3 {
+        group.spawn(move |_token| async move { Ok(async_task(format!("截止任务{}", i), i * 100).await) });
+    }
+    
+    let start = Instant::now();
+    match group.join().await {
+        Ok(results) => println!("      ✅ 全部完成: {:?}", results),
+        Err(e) => println!("      ⏰ {} (耗时: {:?})", e, start.elapsed()),
+    }
+    
+    println!("\n🔍 9.4 父令牌级联取消");
+    
+    let parent = CancellationToken::new();
+    let mut group = TaskGroup::with_parent(&parent);
+    for _ in 0..2 {
+        group.spawn(move |token: CancellationToken| async move {
+            // 协作式检查：令牌取消后循环退出（即使不检查，也会在 .await 处被丢弃）
+            let mut ticks = 0;
+            while !token.is_cancelled() {
+                sleep(Duration::from_millis(50)).await;
+                ticks += 1;
+            }
+            Ok(ticks)
+        });
+    }
+    let group_token = group.token();
+    let sibling = parent.child_token();
+    
+    tokio::spawn({
+        let parent = parent.clone();
+        async move {
+            sleep(Duration::from_millis(120)).await;
+            println!("      🛑 取消父令牌");
+            parent.cancel();
+        }
+    });
+    
+    let standalone = sibling.run_until_cancelled(async_task("独立任务".to_string(), 1_000)).await;
+    println!("      📝 同一父令牌下的独立任务: {:?}", standalone);
+    
+    match group.join().await {
+        Ok(ticks) => println!("      ✅ 子任务心跳: {:?}", ticks),
+        Err(e) => println!("      🛑 {}", e),
+    }
+    println!("      📊 任务组令牌已取消: {}", group_token.is_cancelled());
+    
+    Ok(())
+}
+
+/// 10. 弹性策略：重试、熔断、舱壁与对冲
+async fn demonstrate_resilience_policies() -> Result<()> {
+    println!("\n🔍 10.1 指数退避 + 抖动重试");
+    
+    let attempts = AtomicUsize::new(0);
+    let policy = ResiliencePolicy::new().retry(
+        Retry::new(5)
+            .backoff(Duration::from_millis(20), Duration::from_millis(500))
+            .jitter(Jitter::Full),
+    );
+    
+    let start = Instant::now();
+    let result = policy
+        .call(|| {
+            let attempt = attempts.fetch_add(1, Ordering::SeqCst) + 1;
+            // 随机失败：大约一半的调用会失败
+            let should_fail = rand::thread_rng().gen_bool(0.5);
+            fallible_task(format!("不稳定任务(第{}次)", attempt), 30, should_fail)
+        })
+        .await;
+    match result {
+        Ok(msg) => println!("      ✅ {} (共尝试 {} 次, 耗时: {:?})", msg, attempts.load(Ordering::SeqCst), start.elapsed()),
+        Err(e) => println!("      ❌ 重试耗尽: {} (耗时: {:?})", e, start.elapsed()),
+    }
+    
+    println!("\n🔍 10.2 熔断器与半开探测");
+    
+    let breaker = CircuitBreaker::new(BreakerConfig {
+        failure_threshold: 3,
+        open_duration: Duration::from_millis(200),
+        half_open_probes: 1,
+        success_threshold: 1,
+    });
+    let policy = ResiliencePolicy::new().circuit_breaker(breaker.clone());
+    
+    // 下游持续故障：3 次失败后熔断，之后的调用被直接拒绝
+    for i in 1..=5 {
+        let result = policy.call(|| fallible_task(format!("故障调用{}", i), 20, true)).await;
+        match result {
+            Err(ResilienceError::CircuitOpen) => println!("      ⛔ 调用{}: 被熔断器拒绝", i),
+            Err(e) => println!("      ❌ 调用{}: {} → 熔断器: {:?}", i, e, breaker.state()),
+            Ok(msg) => println!("      ✅ 调用{}: {}", i, msg),
+        }
+    }
+    
+    sleep(Duration::from_millis(200)).await;
+    println!("      ⏳ 冷却结束 → 熔断器: {:?}", breaker.state());
+    let probe = policy.call(|| fallible_task("探测调用".to_string(), 20, false)).await?;
+    println!("      ✅ {} → 熔断器: {:?}", probe, breaker.state());
+    
+    println!("\n🔍 10.3 舱壁隔离");
+    
+    let bulkhead = Bulkhead::new(2, 2);
+    let policy = ResiliencePolicy::new().bulkhead(bulkhead.clone());
+    let calls: Vec<_> = (1..=6)
+        .map(|i| policy.call(move || fallible_task(format!("舱壁调用{}", i), 100, false)))
+        .collect();
+    
+    let start = Instant::now();
+    for (i, result) in join_all(calls).await.into_iter().enumerate() {
+        match result {
+            Ok(msg) => println!("      ✅ {}", msg),
+            Err(e) => println!("      ⛔ 舱壁调用{}: {}", i + 1, e),
+        }
+    }
+    println!("      ⏱️ 耗时: {:?}，空闲名额: {}，排队: {}", start.elapsed(), bulkhead.available(), bulkhead.queued());
+    
+    println!("\n🔍 10.4 对冲请求");
+    
+    let policy = ResiliencePolicy::new()
+        .hedge(Hedge::new(Duration::from_millis(100), 2))
+        .timeout(Duration::from_millis(500));
+    
+    for round in 1..=3 {
+        let sent = AtomicUsize::new(0);
+        let start = Instant::now();
+        let result = policy
+            .call(|| {
+                let n = sent.fetch_add(1, Ordering::SeqCst) + 1;
+                // 随机延迟，偶尔出现 400ms 的长尾
+                let delay = if rand::thread_rng().gen_bool(0.3) { 400 } else { 60 };
+                async move { Ok::<_, anyhow::Error>(async_task(format!("请求{}({}ms)", n, delay), delay).await) }
+            })
+            .await?;
+        println!("      🏁 第{}轮: {} (发出 {} 个请求, 耗时: {:?})", round, result, sent.load(Ordering::SeqCst), start.elapsed());
+    }
+    
+    println!("\n🔍 10.5 组合策略与后台任务");
+    
+    let breaker = CircuitBreaker::new(BreakerConfig::default());
+    let policy = ResiliencePolicy::new()
+        .retry(Retry::new(3).backoff(Duration::from_millis(10), Duration::from_millis(100)))
+        .circuit_breaker(breaker.clone())
+        .bulkhead(Bulkhead::new(3, 10))
+        .timeout(Duration::from_millis(200));
+    
+    let handles: Vec<_> = (1..=6)
+        .map(|i| {
+            let policy = policy.clone();
+            tokio::spawn(async move {
+                policy
+                    .call(|| fallible_task(format!("组合任务{}", i), 50, rand::thread_rng().gen_bool(0.3)))
+                    .await
+            })
+        })
+        .collect();
+    
+    let mut success_count = 0;
+    for result in join_all(handles).await {
+        match result? {
+            Ok(_) => success_count += 1,
+            Err(e) => println!("      ❌ {}", e),
+        }
+    }
+    println!("      📊 组合策略: 成功 {}/6，熔断器: {:?}", success_count, breaker.state());
+    
+    Ok(())
+}
+
 // 辅助函数
 
 /// 模拟异步任务
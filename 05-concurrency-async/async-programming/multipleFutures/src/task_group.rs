@@ -0,0 +1,410 @@
+//! # TaskGroup：结构化并发
+//!
+//! `try_join!` 能快速失败，但只能组合固定数量的 Future，且它们都跑在当前任务里；
+//! `tokio::spawn` 能真正并行，但句柄一丢任务就“漏”了出去，失败时兄弟任务照样运行。
+//! `TaskGroup` 把两者结合起来：
+//! - 每个子任务都是独立的 tokio 任务，绑定到任务组的 `CancellationToken`
+//! - 任务组的令牌是父令牌的子令牌，父级取消会级联到所有子任务
+//! - 默认第一个错误（或 panic）取消所有兄弟任务，可以关闭
+//! - 可以设置截止时间，到期后取消所有未完成的子任务
+//! - 结果按 spawn 顺序返回，与完成顺序无关
+//! - 任务组被丢弃时，所有子任务随之中止，不会有任务活得比任务组更久
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::Duration;
+
+use anyhow::Result;
+use thiserror::Error;
+use tokio::task::{Id, JoinSet};
+use tokio::time::{sleep_until, Instant};
+
+use crate::cancellation::CancellationToken;
+
+/// 单个子任务的结局
+#[derive(Debug)]
+pub enum TaskOutcome<T> {
+    Completed(T),
+    Failed(anyhow::Error),
+    Panicked(String),
+    /// 被兄弟任务的错误、截止时间或父令牌取消
+    Cancelled,
+}
+
+/// 任务组整体失败的原因
+#[derive(Debug, Error)]
+pub enum GroupError {
+    #[error("子任务 {index} 失败: {source}")]
+    Failed { index: usize, source: anyhow::Error },
+    #[error("子任务 {index} panic: {message}")]
+    Panicked { index: usize, message: String },
+    #[error("任务组超过截止时间")]
+    DeadlineExceeded,
+    #[error("任务组被取消")]
+    Cancelled,
+}
+
+/// 导致任务组失败的第一个事件
+enum Failure {
+    Task(usize),
+    Deadline,
+}
+
+pub struct TaskGroup<T> {
+    token: CancellationToken,
+    tasks: JoinSet<TaskOutcome<T>>,
+    indices: HashMap<Id, usize>,
+    cancel_on_error: bool,
+    deadline: Option<Instant>,
+}
+
+impl<T: Send + 'static> TaskGroup<T> {
+    /// 创建独立的任务组（拥有自己的根令牌）
+    pub fn new() -> Self {
+        Self::with_token(CancellationToken::new())
+    }
+
+    /// 创建绑定到父令牌的任务组
+    pub fn with_parent(parent: &CancellationToken) -> Self {
+        Self::with_token(parent.child_token())
+    }
+
+    fn with_token(token: CancellationToken) -> Self {
+        Self {
+            token,
+            tasks: JoinSet::new(),
+            indices: HashMap::new(),
+            cancel_on_error: true,
+            deadline: None,
+        }
+    }
+
+    /// 子任务失败时是否取消兄弟任务（默认是）
+    pub fn cancel_on_error(mut self, enabled: bool) -> Self {
+        self.cancel_on_error = enabled;
+        self
+    }
+
+    /// 从现在起计算的截止时间
+    pub fn deadline(mut self, timeout: Duration) -> Self {
+        self.deadline = Some(Instant::now() + timeout);
+        self
+    }
+
+    /// 任务组的令牌，取消它等于取消整个任务组
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+
+    /// 生成子任务；子任务拿到任务组令牌，可以用它派生更深层的任务组
+    pub fn spawn<F, Fut>(&mut self, task: F)
+    where
+        F: FnOnce(CancellationToken) -> Fut,
+        Fut: Future<Output = Result<T>> + Send + 'static,
+    {
+        let token = self.token.clone();
+        let future = task(token.clone());
+        let handle = self.tasks.spawn(async move {
+            // 即使子任务不检查令牌，取消时也会在下一个 .await 处被丢弃
+            match token.run_until_cancelled(future).await {
+                Some(Ok(value)) => TaskOutcome::Completed(value),
+                Some(Err(error)) => TaskOutcome::Failed(error),
+                None => TaskOutcome::Cancelled,
+            }
+        });
+        self.indices.insert(handle.id(), self.indices.len());
+    }
+
+    /// 等待全部子任务，按 spawn 顺序返回结果；任一子任务失败则返回第一个失败
+    pub async fn join(self) -> Result<Vec<T>, GroupError> {
+        let (outcomes, failure) = self.drive().await;
+
+        match failure {
+            Some(Failure::Task(index)) => {
+                let outcome = outcomes.into_iter().nth(index).expect("失败的子任务一定有结果");
+                Err(outcome.into_result(index).err().expect("记录的子任务一定失败了"))
+            }
+            Some(Failure::Deadline) => Err(GroupError::DeadlineExceeded),
+            None => outcomes
+                .into_iter()
+                .enumerate()
+                .map(|(index, outcome)| outcome.into_result(index))
+                .collect(),
+        }
+    }
+
+    /// 等待全部子任务，按 spawn 顺序返回每个子任务的结局
+    pub async fn join_all(self) -> Vec<TaskOutcome<T>> {
+        self.drive().await.0
+    }
+
+    async fn drive(mut self) -> (Vec<TaskOutcome<T>>, Option<Failure>) {
+        let mut outcomes: Vec<Option<TaskOutcome<T>>> = (0..self.indices.len()).map(|_| None).collect();
+        let mut failure = None;
+
+        let deadline = self.deadline;
+        let deadline_sleep = async move {
+            match deadline {
+                Some(deadline) => sleep_until(deadline).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::pin!(deadline_sleep);
+        let mut deadline_passed = false;
+
+        loop {
+            let joined = tokio::select! {
+                joined = self.tasks.join_next_with_id() => joined,
+                _ = &mut deadline_sleep, if !deadline_passed => {
+                    deadline_passed = true;
+                    failure.get_or_insert(Failure::Deadline);
+                    self.token.cancel();
+                    continue;
+                }
+            };
+            let Some(joined) = joined else { break };
+
+            let (id, outcome) = match joined {
+                Ok((id, outcome)) => (id, outcome),
+                Err(e) if e.is_panic() => (e.id(), TaskOutcome::Panicked(panic_message(e.into_panic()))),
+                Err(e) => (e.id(), TaskOutcome::Cancelled),
+            };
+            let index = self.indices[&id];
+
+            if matches!(outcome, TaskOutcome::Failed(_) | TaskOutcome::Panicked(_)) {
+                failure.get_or_insert(Failure::Task(index));
+                if self.cancel_on_error {
+                    self.token.cancel();
+                }
+            }
+            outcomes[index] = Some(outcome);
+        }
+
+        let outcomes = outcomes
+            .into_iter()
+            .map(|outcome| outcome.unwrap_or(TaskOutcome::Cancelled))
+            .collect();
+        (outcomes, failure)
+    }
+}
+
+impl<T: Send + 'static> Default for TaskGroup<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> TaskOutcome<T> {
+    fn into_result(self, index: usize) -> Result<T, GroupError> {
+        match self {
+            TaskOutcome::Completed(value) => Ok(value),
+            TaskOutcome::Failed(source) => Err(GroupError::Failed { index, source }),
+            TaskOutcome::Panicked(message) => Err(GroupError::Panicked { index, message }),
+            TaskOutcome::Cancelled => Err(GroupError::Cancelled),
+        }
+    }
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "未知 panic".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{async_task, fallible_task};
+    use futures::future::{join_all, try_join_all};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::time::sleep;
+
+    fn ms(value: u64) -> Duration {
+        Duration::from_millis(value)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_results_in_spawn_order_like_join() {
+        // join_all 按传入顺序返回结果
+        let delays = [300, 100, 200];
+        let expected = join_all(delays.iter().map(|&d| async_task(format!("t{}", d), d))).await;
+
+        let mut group = TaskGroup::new();
+        for d in delays {
+            group.spawn(move |_| async move { Ok(async_task(format!("t{}", d), d).await) });
+        }
+        let start = Instant::now();
+        assert_eq!(group.join().await.unwrap(), expected);
+        // 并发执行，耗时等于最长的子任务
+        assert_eq!(start.elapsed(), ms(300));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_fail_fast_like_try_join_but_cancels_spawned_siblings() {
+        // try_join! 在 120ms 时失败，其余 Future 随之被丢弃
+        let start = Instant::now();
+        let raw = futures::try_join!(
+            fallible_task("a".to_string(), 100, false),
+            fallible_task("b".to_string(), 120, true),
+            fallible_task("c".to_string(), 200, false),
+        );
+        assert!(raw.is_err());
+        let try_join_elapsed = start.elapsed();
+
+        // 对 spawn 出来的任务用 try_join_all：同样快速失败，但丢弃 JoinHandle 不会取消任务
+        let finished = Arc::new(AtomicUsize::new(0));
+        let handles: Vec<_> = [(100, false), (120, true), (200, false)]
+            .into_iter()
+            .map(|(d, fail)| {
+                let finished = Arc::clone(&finished);
+                tokio::spawn(async move {
+                    let result = fallible_task(format!("任务{}", d), d, fail).await;
+                    finished.fetch_add(1, Ordering::SeqCst);
+                    result
+                })
+            })
+            .collect();
+        let start = Instant::now();
+        let raw = try_join_all(handles.into_iter().map(|handle| async move { handle.await? })).await;
+        assert!(raw.is_err());
+        assert_eq!(start.elapsed(), try_join_elapsed);
+        sleep(ms(500)).await;
+        assert_eq!(finished.load(Ordering::SeqCst), 3, "裸 spawn 的兄弟任务仍然跑完了");
+
+        // TaskGroup：同样在 120ms 快速失败，而且 200ms 的兄弟任务被真正取消
+        let finished = Arc::new(AtomicUsize::new(0));
+        let mut group = TaskGroup::new();
+        for (d, fail) in [(100, false), (120, true), (200, false)] {
+            let finished = Arc::clone(&finished);
+            group.spawn(move |_| async move {
+                let result = fallible_task(format!("任务{}", d), d, fail).await;
+                finished.fetch_add(1, Ordering::SeqCst);
+                result
+            });
+        }
+        let start = Instant::now();
+        match group.join().await {
+            Err(GroupError::Failed { index, source }) => {
+                assert_eq!(index, 1);
+                assert_eq!(source.to_string(), "任务120 执行失败");
+            }
+            other => panic!("意外的结果: {:?}", other),
+        }
+        assert_eq!(start.elapsed(), try_join_elapsed);
+        sleep(ms(500)).await;
+        assert_eq!(finished.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_continue_on_error_collects_every_outcome() {
+        let mut group = TaskGroup::new().cancel_on_error(false);
+        for i in 1..=4 {
+            group.spawn(move |_| fallible_task(format!("任务{}", i), 50 * i, i == 2));
+        }
+
+        let outcomes = group.join_all().await;
+        assert!(matches!(outcomes[0], TaskOutcome::Completed(_)));
+        assert!(matches!(outcomes[1], TaskOutcome::Failed(_)));
+        assert!(matches!(outcomes[2], TaskOutcome::Completed(_)));
+        assert!(matches!(outcomes[3], TaskOutcome::Completed(_)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_deadline_behaves_like_select_timeout() {
+        // select! 与 sleep 竞争：150ms 超时
+        let start = Instant::now();
+        let timed_out = tokio::select! {
+            _ = async_task("慢".to_string(), 300) => false,
+            _ = sleep(ms(150)) => true,
+        };
+        assert!(timed_out);
+        let select_elapsed = start.elapsed();
+
+        let mut group = TaskGroup::new().deadline(ms(150));
+        group.spawn(|_| async { Ok(async_task("快".to_string(), 100).await) });
+        group.spawn(|_| async { Ok(async_task("慢".to_string(), 300).await) });
+        let start = Instant::now();
+        let outcomes = group.join_all().await;
+        assert_eq!(start.elapsed(), select_elapsed);
+        assert!(matches!(outcomes[0], TaskOutcome::Completed(_)));
+        assert!(matches!(outcomes[1], TaskOutcome::Cancelled));
+
+        let mut group = TaskGroup::new().deadline(ms(150));
+        group.spawn(|_| async { Ok(async_task("慢".to_string(), 300).await) });
+        assert!(matches!(group.join().await, Err(GroupError::DeadlineExceeded)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_parent_cancellation_cascades_to_nested_groups() {
+        let parent = CancellationToken::new();
+        let mut outer = TaskGroup::with_parent(&parent);
+        outer.spawn(|token| async move {
+            // 子任务用自己拿到的令牌创建更深一层的任务组
+            let mut inner = TaskGroup::with_parent(&token);
+            inner.spawn(|_| async {
+                sleep(ms(1_000)).await;
+                Ok(())
+            });
+            inner.join().await.map_err(anyhow::Error::from)
+        });
+
+        tokio::spawn({
+            let parent = parent.clone();
+            async move {
+                sleep(ms(50)).await;
+                parent.cancel();
+            }
+        });
+
+        let start = Instant::now();
+        assert!(matches!(outer.join().await, Err(GroupError::Cancelled)));
+        assert_eq!(start.elapsed(), ms(50));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_panic_is_reported_and_cancels_siblings() {
+        let mut group: TaskGroup<()> = TaskGroup::new();
+        group.spawn(|_| async {
+            sleep(ms(10)).await;
+            panic!("子任务崩溃");
+        });
+        group.spawn(|_| async {
+            sleep(ms(1_000)).await;
+            Ok(())
+        });
+
+        let start = Instant::now();
+        match group.join().await {
+            Err(GroupError::Panicked { index, message }) => {
+                assert_eq!(index, 0);
+                assert_eq!(message, "子任务崩溃");
+            }
+            other => panic!("意外的结果: {:?}", other),
+        }
+        assert_eq!(start.elapsed(), ms(10));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_dropping_group_aborts_children() {
+        let finished = Arc::new(AtomicUsize::new(0));
+        let mut group = TaskGroup::new();
+        {
+            let finished = Arc::clone(&finished);
+            group.spawn(move |_| async move {
+                sleep(ms(100)).await;
+                finished.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            });
+        }
+        drop(group);
+
+        sleep(ms(200)).await;
+        assert_eq!(finished.load(Ordering::SeqCst), 0);
+    }
+}
@@ -0,0 +1,273 @@
+//! # 安全的自引用结构体
+//!
+//! `Solutions-to-some-difficult-problems` 的 `SelfReferential` 和 `Pin-Unpin` 的 `SafeSelfRef`
+//! 都是“把指向自己字段的裸指针存起来，用的时候再解引用”，每个类型都要重新写一遍 unsafe 代码，
+//! 而且 `Pin<Box<Self>>` + `PhantomPinned` 只防住了移动，防不住通过指针得到的引用活得比数据还久。
+//!
+//! [`PinnedSelfRef<Owner, D>`] 把这类结构体拆成两部分：
+//! - 所有者 `Owner`：放在堆上，地址在整个生命周期内固定，构造后只能共享访问
+//! - 依赖视图 `D::Of<'a>`：由构造闭包从 `&'a Owner` 借用出来，例如 `&'a str`、`Vec<&'a str>`
+//!
+//! 所有 unsafe 代码都集中在本 crate 中，使用者只写安全代码：
+//!
+//! ```
+//! use pinned_self_ref::{PinnedSelfRef, Ref};
+//!
+//! let text = PinnedSelfRef::<String, Ref<str>>::new("hello world".to_string(), |s| &s[6..]);
+//! // 结构体本身可以随意移动，所有者在堆上不动
+//! let moved = text;
+//! assert_eq!(moved.with(|owner, word| format!("{} / {}", owner, word)), "hello world / world");
+//! ```
+//!
+//! 借用视图只能在闭包中访问，闭包对任意生命周期 `'a` 都必须成立，
+//! 所以视图中的引用无法逃逸到闭包之外：
+//!
+//! ```compile_fail
+//! use pinned_self_ref::{PinnedSelfRef, Ref};
+//!
+//! let text = PinnedSelfRef::<String, Ref<str>>::new("hello".to_string(), |s| s.as_str());
+//! let escaped: &str = text.with(|_, view| *view);
+//! drop(text);
+//! println!("{}", escaped);
+//! ```
+//!
+//! 测试覆盖了移动、跨线程、可变视图、构造失败和构造时 panic，可以用
+//! `cargo +nightly miri test` 检查别名规则和内存泄漏。
+
+use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
+use std::panic::{RefUnwindSafe, UnwindSafe};
+use std::ptr::NonNull;
+
+/// 依赖视图的“类型族”：`Of<'a>` 是从 `&'a Owner` 借用出来的类型
+///
+/// 每种视图用一个零大小的标记类型描述，例如
+///
+/// ```
+/// use pinned_self_ref::Dependent;
+///
+/// struct Words;
+/// impl Dependent for Words {
+///     type Of<'a> = Vec<&'a str>;
+/// }
+/// ```
+pub trait Dependent: 'static {
+    type Of<'a>;
+}
+
+/// 最常见的视图：对所有者内部某个值的共享引用 `&'a T`
+pub struct Ref<T: ?Sized + 'static>(PhantomData<fn() -> &'static T>);
+
+impl<T: ?Sized + 'static> Dependent for Ref<T> {
+    type Of<'a> = &'a T;
+}
+
+/// 所有者 + 借用它的视图
+pub struct PinnedSelfRef<O, D: Dependent> {
+    // 字段顺序无关紧要，Drop 中显式地先释放视图再释放所有者
+    dependent: ManuallyDrop<D::Of<'static>>,
+    // 用裸指针而不是 Box 保存所有者：移动 Box 会让从它派生的引用失效（Stacked Borrows）
+    owner: NonNull<O>,
+    _owns: PhantomData<O>,
+}
+
+impl<O, D: Dependent> PinnedSelfRef<O, D> {
+    /// 把 `owner` 移到堆上，再用 `build` 从它借用出视图
+    pub fn new(owner: O, build: impl for<'a> FnOnce(&'a O) -> D::Of<'a>) -> Self {
+        match Self::try_new(owner, |owner| Ok::<_, std::convert::Infallible>(build(owner))) {
+            Ok(this) => this,
+            Err(never) => match never {},
+        }
+    }
+
+    /// 构造视图可能失败的版本，失败时所有者被释放
+    pub fn try_new<E>(owner: O, build: impl for<'a> FnOnce(&'a O) -> Result<D::Of<'a>, E>) -> Result<Self, E> {
+        // build 返回错误或 panic 时由 guard 释放所有者
+        let guard = OwnerGuard(NonNull::from(Box::leak(Box::new(owner))));
+        // SAFETY: 指针来自刚泄漏的 Box，在 guard 或 Self 释放它之前一直有效，且此后只做共享访问
+        let dependent = build(unsafe { guard.0.as_ref() })?;
+        let owner = guard.0;
+        std::mem::forget(guard);
+
+        Ok(Self {
+            // SAFETY: 视图借用的所有者活得和 Self 一样久；'static 只是占位，
+            // 对外只在高阶闭包中以调用者的借用生命周期暴露
+            dependent: ManuallyDrop::new(unsafe { extend_lifetime::<D>(dependent) }),
+            owner,
+            _owns: PhantomData,
+        })
+    }
+
+    pub fn owner(&self) -> &O {
+        // SAFETY: 所有者直到 Drop 才释放，期间没有可变访问
+        unsafe { self.owner.as_ref() }
+    }
+
+    /// 同时访问所有者和视图
+    ///
+    /// 闭包对任意 `'a` 成立，返回值不能带有 `'a`，因此视图中的引用不会逃逸
+    pub fn with<R>(&self, f: impl for<'a> FnOnce(&'a O, &'a D::Of<'a>) -> R) -> R {
+        let dependent = (&raw const *self.dependent).cast::<D::Of<'_>>();
+        // SAFETY: 见 try_new；缩短生命周期后只在 &self 借用期间使用
+        f(self.owner(), unsafe { &*dependent })
+    }
+
+    /// 修改视图，例如让它指向所有者的另一部分
+    ///
+    /// 闭包中能放进视图的引用只有从 `&'a O` 派生的，它们和所有者活得一样久
+    pub fn with_mut<R>(&mut self, f: impl for<'a> FnOnce(&'a O, &'a mut D::Of<'a>) -> R) -> R {
+        // SAFETY: 同上，且 &mut self 保证视图没有其他访问者
+        let owner = unsafe { self.owner.as_ref() };
+        let dependent = (&raw mut *self.dependent).cast::<D::Of<'_>>();
+        f(owner, unsafe { &mut *dependent })
+    }
+
+    /// 丢弃视图，取回所有者
+    pub fn into_owner(self) -> O {
+        let mut this = ManuallyDrop::new(self);
+        // SAFETY: this 不会再被 Drop；先释放视图，之后没有任何引用指向所有者
+        unsafe {
+            ManuallyDrop::drop(&mut this.dependent);
+            *Box::from_raw(this.owner.as_ptr())
+        }
+    }
+}
+
+impl<O, D: Dependent> Drop for PinnedSelfRef<O, D> {
+    fn drop(&mut self) {
+        // SAFETY: 视图可能在自己的 Drop 中读取所有者，所以必须先释放视图
+        unsafe {
+            ManuallyDrop::drop(&mut self.dependent);
+            drop(Box::from_raw(self.owner.as_ptr()));
+        }
+    }
+}
+
+// 视图中对所有者的引用已经体现在 D::Of 的 Send/Sync 中（`&O: Send` 要求 `O: Sync`）
+unsafe impl<O: Send, D: Dependent> Send for PinnedSelfRef<O, D> where for<'a> D::Of<'a>: Send {}
+unsafe impl<O: Sync, D: Dependent> Sync for PinnedSelfRef<O, D> where for<'a> D::Of<'a>: Sync {}
+
+// NonNull 默认不是 UnwindSafe，但这里只通过共享引用访问所有者
+impl<O: RefUnwindSafe, D: Dependent> UnwindSafe for PinnedSelfRef<O, D> where for<'a> D::Of<'a>: UnwindSafe {}
+impl<O: RefUnwindSafe, D: Dependent> RefUnwindSafe for PinnedSelfRef<O, D> where for<'a> D::Of<'a>: RefUnwindSafe {}
+
+struct OwnerGuard<O>(NonNull<O>);
+
+impl<O> Drop for OwnerGuard<O> {
+    fn drop(&mut self) {
+        // SAFETY: 只在构造失败时运行，此时没有任何视图借用所有者
+        drop(unsafe { Box::from_raw(self.0.as_ptr()) });
+    }
+}
+
+/// # Safety
+///
+/// 调用者保证 `'a` 借用的数据在返回值被释放之前一直有效
+unsafe fn extend_lifetime<'a, D: Dependent>(dependent: D::Of<'a>) -> D::Of<'static> {
+    // GAT 投影的大小在泛型上下文中不可比较，不能直接 transmute
+    let dependent = ManuallyDrop::new(dependent);
+    unsafe { std::ptr::read((&raw const *dependent).cast::<D::Of<'static>>()) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::panic::{AssertUnwindSafe, catch_unwind};
+    use std::rc::Rc;
+    use std::thread;
+
+    struct Words;
+    impl Dependent for Words {
+        type Of<'a> = Vec<&'a str>;
+    }
+
+    /// 在 Drop 中读取所有者的视图，用来检查释放顺序
+    struct Reporter<'a> {
+        owner: &'a String,
+        log: Rc<RefCell<Vec<String>>>,
+    }
+
+    impl Drop for Reporter<'_> {
+        fn drop(&mut self) {
+            self.log.borrow_mut().push(format!("释放视图，所有者仍是 {}", self.owner));
+        }
+    }
+
+    struct ReporterView;
+    impl Dependent for ReporterView {
+        type Of<'a> = Reporter<'a>;
+    }
+
+    #[test]
+    fn test_view_survives_moves() {
+        let text = PinnedSelfRef::<String, Ref<str>>::new("Hello, Pin!".to_string(), |s| &s[..5]);
+
+        // 移进 Vec、再移出来，视图依然指向堆上的所有者
+        let mut all = vec![text];
+        all.reserve(100);
+        let text = all.pop().unwrap();
+        assert_eq!(text.with(|_, view| view.to_string()), "Hello");
+        assert_eq!(text.owner(), "Hello, Pin!");
+
+        // 跨线程移动
+        let handle = thread::spawn(move || text.with(|owner, view| owner.len() + view.len()));
+        assert_eq!(handle.join().unwrap(), 16);
+    }
+
+    #[test]
+    fn test_shared_across_threads() {
+        let words = PinnedSelfRef::<String, Words>::new("a bb ccc".to_string(), |s| s.split(' ').collect());
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| assert_eq!(words.with(|_, view| view.len()), 3));
+            }
+        });
+    }
+
+    #[test]
+    fn test_with_mut_repoints_view() {
+        let mut words = PinnedSelfRef::<String, Words>::new("one two three".to_string(), |s| s.split(' ').collect());
+        words.with_mut(|owner, view| {
+            view.retain(|word| word.len() == 3);
+            view.push(&owner[..3]);
+        });
+        assert_eq!(words.with(|_, view| view.join(",")), "one,two,one");
+        assert_eq!(words.into_owner(), "one two three");
+    }
+
+    #[test]
+    fn test_dependent_dropped_before_owner() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+
+        let reporter = PinnedSelfRef::<String, ReporterView>::new("数据".to_string(), |owner| Reporter {
+            owner,
+            log: log.clone(),
+        });
+        drop(reporter);
+
+        let reporter = PinnedSelfRef::<String, ReporterView>::new("取回".to_string(), |owner| Reporter {
+            owner,
+            log: log.clone(),
+        });
+        assert_eq!(reporter.into_owner(), "取回");
+
+        assert_eq!(*log.borrow(), ["释放视图，所有者仍是 数据", "释放视图，所有者仍是 取回"]);
+    }
+
+    #[test]
+    fn test_failed_or_panicking_build_frees_owner() {
+        let owner = Rc::new(());
+
+        let result = PinnedSelfRef::<Rc<()>, Ref<()>>::try_new(owner.clone(), |_| Err("拒绝"));
+        assert_eq!(result.err(), Some("拒绝"));
+        assert_eq!(Rc::strong_count(&owner), 1);
+
+        let cloned = owner.clone();
+        let panicked = catch_unwind(AssertUnwindSafe(|| {
+            PinnedSelfRef::<Rc<()>, Ref<()>>::new(cloned, |_| panic!("构造失败"))
+        }));
+        assert!(panicked.is_err());
+        assert_eq!(Rc::strong_count(&owner), 1);
+    }
+}
@@ -0,0 +1,116 @@
+//! 舱壁隔离：限制同时执行的调用数，超出部分有限排队，队列满了直接拒绝
+//!
+//! 这样一个变慢的下游最多占用固定数量的任务，不会拖垮整个服务。
+
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use tokio::sync::Semaphore;
+
+use crate::ResilienceError;
+
+/// 可克隆的舱壁，克隆共享同一组名额
+#[derive(Clone)]
+pub struct Bulkhead {
+    permits: Arc<Semaphore>,
+    queued: Arc<AtomicUsize>,
+    max_queued: usize,
+}
+
+impl Bulkhead {
+    /// 最多 `max_concurrent` 个调用同时执行，另外最多 `max_queued` 个调用排队等待
+    pub fn new(max_concurrent: usize, max_queued: usize) -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(max_concurrent)),
+            queued: Arc::new(AtomicUsize::new(0)),
+            max_queued,
+        }
+    }
+
+    /// 当前空闲的执行名额
+    pub fn available(&self) -> usize {
+        self.permits.available_permits()
+    }
+
+    /// 当前排队等待的调用数
+    pub fn queued(&self) -> usize {
+        self.queued.load(Ordering::SeqCst)
+    }
+
+    pub(crate) async fn run<T, E, F, Fut>(&self, op: F) -> Result<T, ResilienceError<E>>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T, ResilienceError<E>>>,
+    {
+        let _permit = match self.permits.try_acquire() {
+            Ok(permit) => permit,
+            Err(_) => {
+                if self.queued.fetch_add(1, Ordering::SeqCst) >= self.max_queued {
+                    self.queued.fetch_sub(1, Ordering::SeqCst);
+                    return Err(ResilienceError::BulkheadFull);
+                }
+                // 排队的调用被取消时也要让出队列位置
+                let _slot = QueueSlot(&self.queued);
+                self.permits.acquire().await.expect("舱壁的信号量不会被关闭")
+            }
+        };
+
+        op().await
+    }
+}
+
+struct QueueSlot<'a>(&'a AtomicUsize);
+
+impl Drop for QueueSlot<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Scripted;
+    use futures::future::join_all;
+    use std::time::Duration;
+    use tokio::time::{Instant, timeout};
+
+    fn ms(value: u64) -> Duration {
+        Duration::from_millis(value)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_limits_concurrency_and_rejects_overflow() {
+        let bulkhead = Bulkhead::new(2, 2);
+        let op = Scripted::new(&[(100, true)]);
+
+        let start = Instant::now();
+        let results = join_all((0..5).map(|_| bulkhead.run(|| op.run()))).await;
+
+        // 2 个立即执行，2 个排队，第 5 个被拒绝
+        assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 4);
+        assert_eq!(results[4], Err(ResilienceError::BulkheadFull));
+        assert_eq!(start.elapsed(), ms(200));
+        assert_eq!(op.calls(), 4);
+        assert_eq!((bulkhead.available(), bulkhead.queued()), (2, 0));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cancelled_waiter_frees_queue_slot() {
+        let bulkhead = Bulkhead::new(1, 1);
+        let slow = Scripted::new(&[(1_000, true)]);
+
+        let running = bulkhead.run(|| slow.run());
+        tokio::pin!(running);
+        assert!(timeout(ms(1), running.as_mut()).await.is_err());
+
+        // 排队的调用超时放弃
+        assert!(timeout(ms(10), bulkhead.run(|| slow.run())).await.is_err());
+        assert_eq!(bulkhead.queued(), 0);
+
+        // 队列位置已经归还，新的调用可以排队并最终执行
+        let (first, second) = tokio::join!(running, bulkhead.run(|| slow.run()));
+        assert!(first.is_ok() && second.is_ok());
+    }
+}
@@ -0,0 +1,333 @@
+//! 熔断器：关闭 → 打开 → 半开 → 关闭
+//!
+//! - 关闭：正常放行，连续失败达到阈值后打开
+//! - 打开：直接拒绝调用，冷却时间过后进入半开
+//! - 半开：只放行有限个探测请求；探测成功足够次数后关闭，任何一次探测失败重新打开
+
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::ResilienceError;
+
+#[derive(Debug, Clone)]
+pub struct BreakerConfig {
+    /// 连续失败多少次后打开
+    pub failure_threshold: u32,
+    /// 打开状态持续多久后进入半开
+    pub open_duration: Duration,
+    /// 半开状态下同时允许的探测请求数
+    pub half_open_probes: u32,
+    /// 半开状态下需要多少次探测成功才关闭
+    pub success_threshold: u32,
+}
+
+impl Default for BreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            open_duration: Duration::from_secs(30),
+            half_open_probes: 1,
+            success_threshold: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+/// `window` 是半开窗口的编号，每次打开加一；探测只对发放它的那个窗口计数
+enum State {
+    Closed { failures: u32, window: u64 },
+    Open { until: Instant, window: u64 },
+    HalfOpen { window: u64, in_flight: u32, successes: u32 },
+}
+
+/// 一次调用对熔断器的影响
+#[derive(Clone, Copy)]
+enum Outcome {
+    Success,
+    Failure,
+    /// 被其他策略层拒绝或被取消，不能说明下游是否健康
+    Neutral,
+}
+
+/// 可克隆的熔断器，克隆共享同一个状态
+#[derive(Clone)]
+pub struct CircuitBreaker {
+    config: BreakerConfig,
+    state: Arc<Mutex<State>>,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: BreakerConfig) -> Self {
+        Self {
+            config,
+            state: Arc::new(Mutex::new(State::Closed { failures: 0, window: 0 })),
+        }
+    }
+
+    pub fn state(&self) -> CircuitState {
+        match *self.state.lock().unwrap() {
+            State::Closed { .. } => CircuitState::Closed,
+            State::Open { until, .. } if Instant::now() >= until => CircuitState::HalfOpen,
+            State::Open { .. } => CircuitState::Open,
+            State::HalfOpen { .. } => CircuitState::HalfOpen,
+        }
+    }
+
+    /// 申请放行；半开状态下的探测返回 `Some(Some(window))`，`window` 是它所属的窗口
+    fn try_acquire(&self) -> Option<Option<u64>> {
+        let mut state = self.state.lock().unwrap();
+        match *state {
+            State::Closed { .. } => Some(None),
+            State::Open { until, window } if Instant::now() >= until => {
+                *state = State::HalfOpen {
+                    window,
+                    in_flight: 1,
+                    successes: 0,
+                };
+                Some(Some(window))
+            }
+            State::Open { .. } => None,
+            State::HalfOpen {
+                window,
+                ref mut in_flight,
+                ..
+            } if *in_flight < self.config.half_open_probes => {
+                *in_flight += 1;
+                Some(Some(window))
+            }
+            State::HalfOpen { .. } => None,
+        }
+    }
+
+    fn record(&self, outcome: Outcome, probe: Option<u64>) {
+        let mut state = self.state.lock().unwrap();
+        let trip = |window: u64| State::Open {
+            until: Instant::now() + self.config.open_duration,
+            window: window + 1,
+        };
+
+        match *state {
+            State::Closed {
+                ref mut failures,
+                window,
+            } => match outcome {
+                Outcome::Success => *failures = 0,
+                Outcome::Failure => {
+                    *failures += 1;
+                    if *failures >= self.config.failure_threshold {
+                        *state = trip(window);
+                    }
+                }
+                Outcome::Neutral => {}
+            },
+            // 只有本窗口探测请求的结果才决定半开状态的去向；熔断前发出的慢请求、
+            // 以及上一个半开窗口遗留的探测结果都被忽略
+            State::HalfOpen {
+                window,
+                ref mut in_flight,
+                ref mut successes,
+            } if probe == Some(window) => {
+                *in_flight -= 1;
+                match outcome {
+                    Outcome::Success => {
+                        *successes += 1;
+                        if *successes >= self.config.success_threshold {
+                            *state = State::Closed { failures: 0, window };
+                        }
+                    }
+                    Outcome::Failure => *state = trip(window),
+                    Outcome::Neutral => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub(crate) async fn run<T, E, F, Fut>(&self, op: F) -> Result<T, ResilienceError<E>>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T, ResilienceError<E>>>,
+    {
+        let probe = self.try_acquire().ok_or(ResilienceError::CircuitOpen)?;
+        let mut call = CallGuard {
+            breaker: self,
+            probe,
+            finished: false,
+        };
+
+        let result = op().await;
+        call.finish(match &result {
+            Ok(_) => Outcome::Success,
+            Err(ResilienceError::CircuitOpen | ResilienceError::BulkheadFull) => Outcome::Neutral,
+            Err(_) => Outcome::Failure,
+        });
+        result
+    }
+}
+
+/// 调用被取消（Future 被丢弃）时归还半开探测名额
+struct CallGuard<'a> {
+    breaker: &'a CircuitBreaker,
+    /// 探测所属的半开窗口，非探测调用为 `None`
+    probe: Option<u64>,
+    finished: bool,
+}
+
+impl CallGuard<'_> {
+    fn finish(&mut self, outcome: Outcome) {
+        self.finished = true;
+        self.breaker.record(outcome, self.probe);
+    }
+}
+
+impl Drop for CallGuard<'_> {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.breaker.record(Outcome::Neutral, self.probe);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Scripted;
+    use tokio::time::{sleep, timeout};
+
+    fn ms(value: u64) -> Duration {
+        Duration::from_millis(value)
+    }
+
+    fn breaker() -> CircuitBreaker {
+        CircuitBreaker::new(BreakerConfig {
+            failure_threshold: 3,
+            open_duration: ms(1_000),
+            half_open_probes: 1,
+            success_threshold: 2,
+        })
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_opens_after_consecutive_failures() {
+        let breaker = breaker();
+        let op = Scripted::new(&[(0, false), (0, false), (0, true), (0, false)]);
+
+        // 成功会清零失败计数
+        for _ in 0..4 {
+            let _ = breaker.run(|| op.run()).await;
+        }
+        assert_eq!(breaker.state(), CircuitState::Closed);
+
+        let _ = breaker.run(|| op.run()).await;
+        let _ = breaker.run(|| op.run()).await;
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        // 打开状态下直接拒绝，操作不会被调用
+        let calls = op.calls();
+        assert_eq!(breaker.run(|| op.run()).await, Err(ResilienceError::CircuitOpen));
+        assert_eq!(op.calls(), calls);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_half_open_probe_failure_reopens() {
+        let breaker = breaker();
+        let failing = Scripted::new(&[(0, false)]);
+        for _ in 0..3 {
+            let _ = breaker.run(|| failing.run()).await;
+        }
+
+        sleep(ms(1_000)).await;
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        assert!(breaker.run(|| failing.run()).await.is_err());
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        // 重新计时冷却
+        sleep(ms(999)).await;
+        assert_eq!(breaker.state(), CircuitState::Open);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_half_open_limits_probes_and_closes_after_successes() {
+        let breaker = breaker();
+        let failing = Scripted::new(&[(0, false)]);
+        for _ in 0..3 {
+            let _ = breaker.run(|| failing.run()).await;
+        }
+        sleep(ms(1_000)).await;
+
+        // 一个慢探测进行中时，其他调用被拒绝
+        let slow = Scripted::new(&[(100, true)]);
+        let (probe, rejected) = tokio::join!(breaker.run(|| slow.run()), async {
+            sleep(ms(10)).await;
+            breaker.run(|| slow.run()).await
+        });
+        assert_eq!(probe, Ok(0));
+        assert_eq!(rejected, Err(ResilienceError::CircuitOpen));
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+
+        // 第二次探测成功后关闭
+        assert!(breaker.run(|| slow.run()).await.is_ok());
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cancelled_probe_releases_slot() {
+        let breaker = breaker();
+        let failing = Scripted::new(&[(0, false)]);
+        for _ in 0..3 {
+            let _ = breaker.run(|| failing.run()).await;
+        }
+        sleep(ms(1_000)).await;
+
+        let hanging = Scripted::new(&[(10_000, true)]);
+        assert!(timeout(ms(10), breaker.run(|| hanging.run())).await.is_err());
+
+        let quick = Scripted::new(&[(0, true)]);
+        assert_eq!(breaker.run(|| quick.run()).await, Ok(0));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_stale_probe_does_not_count_in_new_window() {
+        let breaker = CircuitBreaker::new(BreakerConfig {
+            failure_threshold: 1,
+            open_duration: ms(1_000),
+            half_open_probes: 2,
+            success_threshold: 2,
+        });
+        let failing = Scripted::new(&[(0, false)]);
+        let _ = breaker.run(|| failing.run()).await;
+        sleep(ms(1_000)).await;
+
+        // A 是第一个窗口的慢探测；B 失败重新打开；冷却后 C 开启新窗口，A 在 C 之前完成
+        let slow = Scripted::new(&[(1_500, true)]);
+        let fresh = Scripted::new(&[(1_000, true)]);
+        let (a, b, c) = tokio::join!(
+            breaker.run(|| slow.run()),
+            async {
+                sleep(ms(10)).await;
+                breaker.run(|| failing.run()).await
+            },
+            async {
+                sleep(ms(1_010)).await;
+                breaker.run(|| fresh.run()).await
+            }
+        );
+        assert_eq!(a, Ok(0));
+        assert!(matches!(b, Err(ResilienceError::Inner(_))));
+        assert_eq!(c, Ok(0));
+
+        // A 不计入新窗口：只有 C 这一次成功，还需要再成功一次才关闭
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        assert!(breaker.run(|| fresh.run()).await.is_ok());
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+}
@@ -0,0 +1,100 @@
+//! 对冲请求：主请求在 `delay` 内没有返回时再发出备份请求，取最先成功的结果
+//!
+//! 用少量额外负载换取更低的尾延迟。某个请求失败而其他请求都已结束时，
+//! 立即发出下一个备份请求，不再等待 `delay`。赢家返回后其余请求被丢弃（取消）。
+
+use std::future::Future;
+use std::time::Duration;
+
+use futures::StreamExt;
+use futures::stream::FuturesUnordered;
+use tokio::time::{Instant, sleep_until};
+
+use crate::ResilienceError;
+
+#[derive(Debug, Clone)]
+pub struct Hedge {
+    delay: Duration,
+    max_hedges: u32,
+}
+
+impl Hedge {
+    /// 最多额外发出 `max_hedges` 个备份请求，相邻两个请求至少间隔 `delay`
+    pub fn new(delay: Duration, max_hedges: u32) -> Self {
+        Self { delay, max_hedges }
+    }
+
+    pub(crate) async fn run<T, E, F, Fut>(&self, op: F) -> Result<T, ResilienceError<E>>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T, ResilienceError<E>>>,
+    {
+        let mut in_flight = FuturesUnordered::new();
+        in_flight.push(op());
+        let mut hedges = 0;
+        let mut next_hedge = Instant::now() + self.delay;
+
+        loop {
+            let can_hedge = hedges < self.max_hedges;
+            tokio::select! {
+                Some(result) = in_flight.next() => match result {
+                    Ok(value) => return Ok(value),
+                    Err(error) if in_flight.is_empty() && !can_hedge => return Err(error),
+                    Err(_) if in_flight.is_empty() => next_hedge = Instant::now(),
+                    Err(_) => {}
+                },
+                _ = sleep_until(next_hedge), if can_hedge => {
+                    in_flight.push(op());
+                    hedges += 1;
+                    next_hedge = Instant::now() + self.delay;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Scripted;
+
+    fn ms(value: u64) -> Duration {
+        Duration::from_millis(value)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_fast_primary_sends_no_hedge() {
+        let op = Scripted::new(&[(50, true)]);
+        assert_eq!(Hedge::new(ms(100), 2).run(|| op.run()).await, Ok(0));
+        assert_eq!(op.calls(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_slow_primary_loses_to_hedge() {
+        let op = Scripted::new(&[(1_000, true), (50, true)]);
+
+        let start = Instant::now();
+        assert_eq!(Hedge::new(ms(100), 2).run(|| op.run()).await, Ok(1));
+        assert_eq!(start.elapsed(), ms(150));
+        assert_eq!(op.calls(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_failure_triggers_immediate_hedge() {
+        let op = Scripted::new(&[(10, false), (30, true)]);
+
+        let start = Instant::now();
+        assert_eq!(Hedge::new(ms(100), 1).run(|| op.run()).await, Ok(1));
+        assert_eq!(start.elapsed(), ms(40));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_all_attempts_fail() {
+        let op = Scripted::new(&[(200, false), (200, false), (10, false)]);
+
+        let result = Hedge::new(ms(100), 2).run(|| op.run()).await;
+        // 最后一个结束的请求的错误被返回
+        assert_eq!(result, Err(ResilienceError::Inner("第 1 次调用失败".to_string())));
+        assert_eq!(op.calls(), 3);
+    }
+}
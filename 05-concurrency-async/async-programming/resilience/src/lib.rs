@@ -0,0 +1,279 @@
+//! # 异步操作的弹性策略
+//!
+//! `multipleFutures` 的 `fallible_task` 和 `Solutions-to-some-difficult-problems` 的
+//! `execute_query` 失败后没有任何恢复手段。本 crate 提供可组合的异步中间件：
+//! - `Retry`：指数退避 + 抖动的重试
+//! - `CircuitBreaker`：连续失败后熔断，冷却后半开探测
+//! - `Bulkhead`：舱壁隔离，限制并发数和排队数
+//! - `Hedge`：对冲请求，慢请求超过阈值时并行发出备份请求，取最先成功的结果
+//!
+//! 各层按固定顺序组合（外层在前）：
+//!
+//! ```text
+//! Retry → CircuitBreaker → Bulkhead → Hedge → timeout → 操作
+//! ```
+//!
+//! 熔断器和舱壁的状态可以在多次调用之间共享（内部是 `Arc`），
+//! 而每次调用都可以组合出不同的策略：
+//!
+//! ```
+//! use std::time::Duration;
+//! use resilience::{CircuitBreaker, BreakerConfig, ResiliencePolicy, Retry};
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() {
+//! let breaker = CircuitBreaker::new(BreakerConfig::default());
+//! let policy = ResiliencePolicy::new()
+//!     .retry(Retry::new(3).backoff(Duration::from_millis(10), Duration::from_secs(1)))
+//!     .circuit_breaker(breaker.clone())
+//!     .timeout(Duration::from_secs(1));
+//!
+//! let value = policy.call(|| async { Ok::<_, String>(42) }).await.unwrap();
+//! assert_eq!(value, 42);
+//! # }
+//! ```
+//!
+//! 所有等待都使用 `tokio::time`，测试中配合 `#[tokio::test(start_paused = true)]`
+//! 即可在虚拟时间里验证退避、冷却和对冲的时序。
+
+mod bulkhead;
+mod circuit_breaker;
+mod hedge;
+mod retry;
+
+pub use bulkhead::Bulkhead;
+pub use circuit_breaker::{BreakerConfig, CircuitBreaker, CircuitState};
+pub use hedge::Hedge;
+pub use retry::{Jitter, Retry};
+
+use std::future::Future;
+use std::time::Duration;
+
+use futures::TryFutureExt;
+use thiserror::Error;
+
+/// 经过弹性策略后的错误：操作本身的错误，或者某一层策略拒绝了调用
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ResilienceError<E> {
+    #[error("{0}")]
+    Inner(E),
+    #[error("操作超时（{0:?}）")]
+    Timeout(Duration),
+    #[error("熔断器已打开，拒绝调用")]
+    CircuitOpen,
+    #[error("舱壁已满，拒绝调用")]
+    BulkheadFull,
+}
+
+impl<E> ResilienceError<E> {
+    /// 取出操作本身的错误
+    pub fn into_inner(self) -> Option<E> {
+        match self {
+            ResilienceError::Inner(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// 组合后的弹性策略，未配置的层直接透传
+#[derive(Clone, Default)]
+pub struct ResiliencePolicy {
+    retry: Option<Retry>,
+    breaker: Option<CircuitBreaker>,
+    bulkhead: Option<Bulkhead>,
+    hedge: Option<Hedge>,
+    timeout: Option<Duration>,
+}
+
+impl ResiliencePolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn retry(mut self, retry: Retry) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    pub fn circuit_breaker(mut self, breaker: CircuitBreaker) -> Self {
+        self.breaker = Some(breaker);
+        self
+    }
+
+    pub fn bulkhead(mut self, bulkhead: Bulkhead) -> Self {
+        self.bulkhead = Some(bulkhead);
+        self
+    }
+
+    pub fn hedge(mut self, hedge: Hedge) -> Self {
+        self.hedge = Some(hedge);
+        self
+    }
+
+    /// 单次尝试的超时（对冲出的每个请求各自计时）
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// 在策略保护下执行操作；重试和对冲会多次调用 `op`，所以它必须是 `Fn`
+    pub async fn call<T, E, F, Fut>(&self, op: F) -> Result<T, ResilienceError<E>>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        // 每一层都是“接收一个操作、返回一个操作”，闭包只捕获共享引用，因此都是 Fn
+        let op = &op;
+        let timeout = self.timeout;
+        let attempt = move || async move {
+            match timeout {
+                Some(limit) => match tokio::time::timeout(limit, op()).await {
+                    Ok(result) => result.map_err(ResilienceError::Inner),
+                    Err(_) => Err(ResilienceError::Timeout(limit)),
+                },
+                None => op().map_err(ResilienceError::Inner).await,
+            }
+        };
+
+        let attempt = &attempt;
+        let hedge = self.hedge.as_ref();
+        let hedged = move || async move {
+            match hedge {
+                Some(hedge) => hedge.run(attempt).await,
+                None => attempt().await,
+            }
+        };
+
+        let hedged = &hedged;
+        let bulkhead = self.bulkhead.as_ref();
+        let isolated = move || async move {
+            match bulkhead {
+                Some(bulkhead) => bulkhead.run(hedged).await,
+                None => hedged().await,
+            }
+        };
+
+        let isolated = &isolated;
+        let breaker = self.breaker.as_ref();
+        let guarded = move || async move {
+            match breaker {
+                Some(breaker) => breaker.run(isolated).await,
+                None => isolated().await,
+            }
+        };
+
+        match &self.retry {
+            Some(retry) => retry.run(guarded).await,
+            None => guarded().await,
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod testing {
+    use super::ResilienceError;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    /// 按脚本返回结果的操作：第 i 次调用使用第 i 条脚本（超出后重复最后一条）
+    pub(crate) struct Scripted {
+        script: Vec<(u64, bool)>,
+        calls: Mutex<usize>,
+    }
+
+    impl Scripted {
+        /// 每条脚本是 (耗时毫秒, 是否成功)
+        pub(crate) fn new(script: &[(u64, bool)]) -> Self {
+            Self {
+                script: script.to_vec(),
+                calls: Mutex::new(0),
+            }
+        }
+
+        pub(crate) fn calls(&self) -> usize {
+            *self.calls.lock().unwrap()
+        }
+
+        pub(crate) async fn call(&self) -> Result<usize, String> {
+            let index = {
+                let mut calls = self.calls.lock().unwrap();
+                *calls += 1;
+                *calls - 1
+            };
+            let (delay, ok) = self.script[index.min(self.script.len() - 1)];
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+            if ok { Ok(index) } else { Err(format!("第 {} 次调用失败", index)) }
+        }
+
+        /// 供单层测试使用：把错误包装成 `ResilienceError`
+        pub(crate) async fn run(&self) -> Result<usize, ResilienceError<String>> {
+            self.call().await.map_err(ResilienceError::Inner)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Scripted;
+    use tokio::time::Instant;
+
+    fn ms(value: u64) -> Duration {
+        Duration::from_millis(value)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_empty_policy_passes_through() {
+        let op = Scripted::new(&[(10, false)]);
+        let result = ResiliencePolicy::new().call(|| op.call()).await;
+        assert_eq!(result, Err(ResilienceError::Inner("第 0 次调用失败".to_string())));
+        assert_eq!(op.calls(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_timeouts_are_retried() {
+        // 前两次挂起 1s，超过 100ms 超时，第三次成功
+        let op = Scripted::new(&[(1_000, true), (1_000, true), (10, true)]);
+        let policy = ResiliencePolicy::new()
+            .retry(Retry::new(3).backoff(ms(50), ms(1_000)).jitter(Jitter::None))
+            .timeout(ms(100));
+
+        let start = Instant::now();
+        assert_eq!(policy.call(|| op.call()).await, Ok(2));
+        // 100 + 50 + 100 + 100 + 10
+        assert_eq!(start.elapsed(), ms(360));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_open_circuit_stops_retries() {
+        let breaker = CircuitBreaker::new(BreakerConfig {
+            failure_threshold: 2,
+            open_duration: ms(10_000),
+            ..BreakerConfig::default()
+        });
+        let op = Scripted::new(&[(10, false)]);
+        let policy = ResiliencePolicy::new()
+            .retry(Retry::new(5).backoff(ms(10), ms(10)).jitter(Jitter::None))
+            .circuit_breaker(breaker.clone());
+
+        // 两次失败后熔断，重试层看到 CircuitOpen 立即放弃
+        assert_eq!(policy.call(|| op.call()).await, Err(ResilienceError::CircuitOpen));
+        assert_eq!(op.calls(), 2);
+        assert_eq!(breaker.state(), CircuitState::Open);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_hedge_inside_bulkhead_counts_as_one_call() {
+        let bulkhead = Bulkhead::new(1, 0);
+        let op = Scripted::new(&[(500, true), (50, true)]);
+        let policy = ResiliencePolicy::new()
+            .bulkhead(bulkhead.clone())
+            .hedge(Hedge::new(ms(100), 1));
+
+        let start = Instant::now();
+        // 对冲请求在舱壁内部发出，不额外占用舱壁名额
+        assert_eq!(policy.call(|| op.call()).await, Ok(1));
+        assert_eq!(start.elapsed(), ms(150));
+        assert_eq!(bulkhead.available(), 1);
+    }
+}
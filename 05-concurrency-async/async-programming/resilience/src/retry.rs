@@ -0,0 +1,176 @@
+//! 指数退避 + 抖动的重试
+
+use std::future::Future;
+use std::time::Duration;
+
+use rand::Rng;
+use tokio::time::sleep;
+
+use crate::ResilienceError;
+
+/// 退避时间的随机化方式，避免大量客户端在同一时刻一起重试
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Jitter {
+    /// 不加抖动，严格按指数退避
+    None,
+    /// 在 `[0, 退避时间]` 内均匀取值
+    Full,
+    /// 一半固定，另一半在 `[0, 退避时间 / 2]` 内随机
+    Equal,
+}
+
+#[derive(Debug, Clone)]
+pub struct Retry {
+    max_retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: f64,
+    jitter: Jitter,
+}
+
+impl Retry {
+    /// 最多重试 `max_retries` 次（总共最多调用 `max_retries + 1` 次）。
+    /// 默认：首次退避 100ms，上限 10s，倍数 2，全抖动
+    pub fn new(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            multiplier: 2.0,
+            jitter: Jitter::Full,
+        }
+    }
+
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// 每次重试退避时间的倍数，必须是不小于 1 的有限值
+    pub fn multiplier(mut self, multiplier: f64) -> Self {
+        assert!(
+            multiplier.is_finite() && multiplier >= 1.0,
+            "退避倍数必须是不小于 1 的有限值: {}",
+            multiplier
+        );
+        self.multiplier = multiplier;
+        self
+    }
+
+    pub fn jitter(mut self, jitter: Jitter) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// 第 `retry` 次重试（从 0 开始）之前的等待时间
+    pub fn delay(&self, retry: u32, rng: &mut impl Rng) -> Duration {
+        let exponential = self.initial_backoff.as_secs_f64() * self.multiplier.powi(retry as i32);
+        let capped = exponential.min(self.max_backoff.as_secs_f64());
+
+        let seconds = match self.jitter {
+            Jitter::None => capped,
+            Jitter::Full => rng.gen_range(0.0..=capped),
+            Jitter::Equal => capped / 2.0 + rng.gen_range(0.0..=capped / 2.0),
+        };
+        // 上限接近 Duration::MAX 时换算回 Duration 可能溢出
+        Duration::try_from_secs_f64(seconds).unwrap_or(self.max_backoff)
+    }
+
+    /// 熔断器打开时不重试：熔断的意义就是让调用方快速失败
+    pub(crate) async fn run<T, E, F, Fut>(&self, op: F) -> Result<T, ResilienceError<E>>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T, ResilienceError<E>>>,
+    {
+        let mut retry = 0;
+        loop {
+            match op().await {
+                Ok(value) => return Ok(value),
+                Err(ResilienceError::CircuitOpen) => return Err(ResilienceError::CircuitOpen),
+                Err(error) if retry >= self.max_retries => return Err(error),
+                Err(_) => {
+                    let delay = self.delay(retry, &mut rand::thread_rng());
+                    sleep(delay).await;
+                    retry += 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Scripted;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use tokio::time::Instant;
+
+    fn ms(value: u64) -> Duration {
+        Duration::from_millis(value)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retries_with_exponential_backoff() {
+        let op = Scripted::new(&[(0, false), (0, false), (0, true)]);
+        let retry = Retry::new(5).backoff(ms(100), ms(10_000)).jitter(Jitter::None);
+
+        let start = Instant::now();
+        assert_eq!(retry.run(|| op.run()).await, Ok(2));
+        // 100ms + 200ms
+        assert_eq!(start.elapsed(), ms(300));
+        assert_eq!(op.calls(), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_gives_up_with_last_error() {
+        let op = Scripted::new(&[(0, false)]);
+        let retry = Retry::new(2).backoff(ms(10), ms(10)).jitter(Jitter::None);
+
+        let result = retry.run(|| op.run()).await;
+        assert_eq!(result, Err(ResilienceError::Inner("第 2 次调用失败".to_string())));
+        assert_eq!(op.calls(), 3);
+    }
+
+    #[test]
+    fn test_delay_is_capped_and_jittered_within_bounds() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let plain = Retry::new(10).backoff(ms(100), ms(1_000)).jitter(Jitter::None);
+        assert_eq!(plain.delay(0, &mut rng), ms(100));
+        assert_eq!(plain.delay(3, &mut rng), ms(800));
+        assert_eq!(plain.delay(8, &mut rng), ms(1_000));
+
+        let full = plain.clone().jitter(Jitter::Full);
+        let equal = plain.clone().jitter(Jitter::Equal);
+        for retry in 0..10 {
+            let cap = plain.delay(retry, &mut rng);
+            assert!(full.delay(retry, &mut rng) <= cap);
+            let delay = equal.delay(retry, &mut rng);
+            assert!(delay >= cap / 2 && delay <= cap);
+        }
+
+        // 全抖动确实分散了退避时间
+        let samples: Vec<_> = (0..20).map(|_| full.delay(5, &mut rng)).collect();
+        assert!(samples.iter().any(|d| *d != samples[0]));
+    }
+
+    #[test]
+    fn test_delay_survives_unbounded_backoff() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let retry = Retry::new(10).backoff(ms(100), Duration::MAX).jitter(Jitter::None);
+        assert_eq!(retry.delay(2_000, &mut rng), Duration::MAX);
+    }
+
+    #[test]
+    #[should_panic(expected = "退避倍数")]
+    fn test_negative_multiplier_is_rejected() {
+        let _ = Retry::new(1).multiplier(-2.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "退避倍数")]
+    fn test_nan_multiplier_is_rejected() {
+        let _ = Retry::new(1).multiplier(f64::NAN);
+    }
+}
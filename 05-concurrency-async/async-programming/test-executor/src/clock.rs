@@ -0,0 +1,230 @@
+//! # 虚拟时钟
+//!
+//! `MockClock` 用一个只能手动推进的时间偏移替代 `Instant::now()`：
+//! - 时间只在调用 `advance` 时前进，与真实耗时无关
+//! - `sleep` 返回的 Future 把 Waker 注册到时钟上，到期时被唤醒
+//! - 时钟可以被克隆并在任务之间共享，所有克隆看到同一个时间
+
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+/// 定时器的键：(到期时间, 注册序号)，保证同一时刻的定时器按注册顺序排列
+type TimerKey = (Duration, u64);
+
+#[derive(Debug, Default)]
+struct ClockState {
+    elapsed: Duration,
+    next_timer_id: u64,
+    timers: BTreeMap<TimerKey, Waker>,
+}
+
+/// 可手动推进的虚拟时钟
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    base: Instant,
+    state: Arc<Mutex<ClockState>>,
+}
+
+impl MockClock {
+    pub fn new() -> Self {
+        MockClock {
+            base: Instant::now(),
+            state: Arc::new(Mutex::new(ClockState::default())),
+        }
+    }
+
+    /// 当前虚拟时间，可以直接与 `Instant` 比较和相减
+    pub fn now(&self) -> Instant {
+        self.base + self.elapsed()
+    }
+
+    /// 自时钟创建以来经过的虚拟时间
+    pub fn elapsed(&self) -> Duration {
+        self.state.lock().unwrap().elapsed
+    }
+
+    /// 把时钟向前推进 `duration`，并唤醒所有到期的定时器
+    pub fn advance(&self, duration: Duration) {
+        let target = self.elapsed() + duration;
+        self.advance_to(target);
+    }
+
+    /// 直接跳到最早的定时器到期时间；没有定时器时返回 `false`
+    pub fn advance_to_next_timer(&self) -> bool {
+        match self.next_deadline() {
+            Some(deadline) => {
+                self.advance_to(deadline);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 最早的定时器到期时间（相对时钟创建时刻）
+    pub fn next_deadline(&self) -> Option<Duration> {
+        let state = self.state.lock().unwrap();
+        state.timers.keys().next().map(|(deadline, _)| *deadline)
+    }
+
+    /// 尚未触发的定时器数量
+    pub fn pending_timers(&self) -> usize {
+        self.state.lock().unwrap().timers.len()
+    }
+
+    /// 在虚拟时间上睡眠 `duration`
+    pub fn sleep(&self, duration: Duration) -> Sleep {
+        Sleep {
+            clock: self.clone(),
+            deadline: self.elapsed() + duration,
+            timer: None,
+        }
+    }
+
+    /// 睡眠到虚拟时刻 `deadline`
+    pub fn sleep_until(&self, deadline: Instant) -> Sleep {
+        Sleep {
+            clock: self.clone(),
+            deadline: deadline.saturating_duration_since(self.base),
+            timer: None,
+        }
+    }
+
+    fn advance_to(&self, target: Duration) {
+        let expired = {
+            let mut state = self.state.lock().unwrap();
+            state.elapsed = state.elapsed.max(target);
+
+            // split_off 返回 >= 键的部分，剩下的就是已经到期的定时器
+            let pending = state.timers.split_off(&(target, u64::MAX));
+            std::mem::replace(&mut state.timers, pending)
+        };
+
+        // 释放锁之后再唤醒，避免被唤醒方立即访问时钟时死锁
+        for waker in expired.into_values() {
+            waker.wake();
+        }
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `MockClock::sleep` 返回的 Future
+#[derive(Debug)]
+pub struct Sleep {
+    clock: MockClock,
+    deadline: Duration,
+    timer: Option<TimerKey>,
+}
+
+impl Sleep {
+    /// 到期的虚拟时刻
+    pub fn deadline(&self) -> Instant {
+        self.clock.base + self.deadline
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let this = &mut *self;
+        let mut state = this.clock.state.lock().unwrap();
+
+        if state.elapsed >= this.deadline {
+            if let Some(key) = this.timer.take() {
+                state.timers.remove(&key);
+            }
+            return Poll::Ready(());
+        }
+
+        let key = match this.timer {
+            Some(key) => key,
+            None => {
+                state.next_timer_id += 1;
+                (this.deadline, state.next_timer_id)
+            }
+        };
+        state.timers.insert(key, cx.waker().clone());
+        this.timer = Some(key);
+        Poll::Pending
+    }
+}
+
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        if let Some(key) = self.timer.take() {
+            self.clock.state.lock().unwrap().timers.remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::Wake;
+
+    struct CountingWaker(AtomicUsize);
+
+    impl Wake for CountingWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_advance_wakes_expired_timers_only() {
+        let clock = MockClock::new();
+        let counter = Arc::new(CountingWaker(AtomicUsize::new(0)));
+        let waker = Waker::from(Arc::clone(&counter));
+        let mut cx = Context::from_waker(&waker);
+
+        let mut short = clock.sleep(Duration::from_millis(10));
+        let mut long = clock.sleep(Duration::from_millis(30));
+        assert!(Pin::new(&mut short).poll(&mut cx).is_pending());
+        assert!(Pin::new(&mut long).poll(&mut cx).is_pending());
+        assert_eq!(clock.next_deadline(), Some(Duration::from_millis(10)));
+
+        clock.advance(Duration::from_millis(20));
+        assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+        assert!(Pin::new(&mut short).poll(&mut cx).is_ready());
+        assert!(Pin::new(&mut long).poll(&mut cx).is_pending());
+        assert_eq!(clock.pending_timers(), 1);
+
+        assert!(clock.advance_to_next_timer());
+        assert_eq!(clock.elapsed(), Duration::from_millis(30));
+        assert!(Pin::new(&mut long).poll(&mut cx).is_ready());
+        assert!(!clock.advance_to_next_timer());
+    }
+
+    #[test]
+    fn test_dropped_sleep_unregisters_timer() {
+        let clock = MockClock::new();
+        let waker = Waker::noop();
+        let mut cx = Context::from_waker(waker);
+
+        let mut sleep = clock.sleep(Duration::from_secs(1));
+        assert!(Pin::new(&mut sleep).poll(&mut cx).is_pending());
+        assert_eq!(clock.pending_timers(), 1);
+
+        drop(sleep);
+        assert_eq!(clock.pending_timers(), 0);
+    }
+
+    #[test]
+    fn test_now_follows_virtual_time() {
+        let clock = MockClock::new();
+        let start = clock.now();
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(clock.now() - start, Duration::from_secs(5));
+        assert_eq!(clock.sleep_until(start + Duration::from_secs(7)).deadline() - start, Duration::from_secs(7));
+    }
+}
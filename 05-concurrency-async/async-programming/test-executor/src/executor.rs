@@ -0,0 +1,457 @@
+//! # 确定性单线程执行器
+//!
+//! `TestExecutor` 在当前线程上运行所有任务：
+//! - 每一步从就绪队列中用带种子的随机数挑选下一个任务，
+//!   同一个种子总是得到同一种交错顺序
+//! - 没有就绪任务时，虚拟时钟直接跳到最早的定时器，不做真实等待
+//! - 任务不要求 `Send`，可以自由使用 `Rc` / `RefCell`
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::{Pin, pin};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+use std::time::{Duration, Instant};
+
+use crate::clock::{MockClock, Sleep};
+
+type LocalTask = Pin<Box<dyn Future<Output = ()>>>;
+
+/// `block_on` 中主 Future 使用的任务 ID
+const MAIN_TASK: usize = usize::MAX;
+
+/// 单次运行允许的最大 poll 次数，超过即视为活锁
+const MAX_POLLS: u64 = 1_000_000;
+
+// ============================================================================
+// 随机数生成器
+// ============================================================================
+
+/// SplitMix64：足够简单，且同一种子的输出序列在任何平台上都一致
+#[derive(Debug)]
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+}
+
+// ============================================================================
+// Waker
+// ============================================================================
+
+/// 任务的 Waker：唤醒时把任务 ID 放回就绪队列（已在队列中则忽略）
+struct TaskWaker {
+    id: usize,
+    queued: AtomicBool,
+    ready: Arc<Mutex<Vec<usize>>>,
+}
+
+impl TaskWaker {
+    fn new(id: usize, ready: Arc<Mutex<Vec<usize>>>) -> Arc<Self> {
+        Arc::new(TaskWaker {
+            id,
+            queued: AtomicBool::new(false),
+            ready,
+        })
+    }
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        if !self.queued.swap(true, Ordering::AcqRel) {
+            self.ready.lock().unwrap().push(self.id);
+        }
+    }
+}
+
+// ============================================================================
+// 执行器
+// ============================================================================
+
+struct Inner {
+    seed: u64,
+    rng: RefCell<SplitMix64>,
+    clock: MockClock,
+    ready: Arc<Mutex<Vec<usize>>>,
+    tasks: RefCell<HashMap<usize, (LocalTask, Arc<TaskWaker>)>>,
+    next_task_id: Cell<usize>,
+    polls: Cell<u64>,
+}
+
+/// 确定性单线程测试执行器
+///
+/// 克隆得到的是同一个执行器的句柄，可以移动到任务内部继续 `spawn`。
+#[derive(Clone)]
+pub struct TestExecutor {
+    inner: Rc<Inner>,
+}
+
+impl TestExecutor {
+    pub fn new(seed: u64) -> Self {
+        TestExecutor {
+            inner: Rc::new(Inner {
+                seed,
+                rng: RefCell::new(SplitMix64(seed)),
+                clock: MockClock::new(),
+                ready: Arc::new(Mutex::new(Vec::new())),
+                tasks: RefCell::new(HashMap::new()),
+                next_task_id: Cell::new(0),
+                polls: Cell::new(0),
+            }),
+        }
+    }
+
+    /// 本执行器使用的种子，用于重放失败的交错顺序
+    pub fn seed(&self) -> u64 {
+        self.inner.seed
+    }
+
+    pub fn clock(&self) -> &MockClock {
+        &self.inner.clock
+    }
+
+    /// 当前虚拟时间
+    pub fn now(&self) -> Instant {
+        self.inner.clock.now()
+    }
+
+    /// 在虚拟时间上睡眠
+    pub fn sleep(&self, duration: Duration) -> Sleep {
+        self.inner.clock.sleep(duration)
+    }
+
+    /// 尚未完成的任务数（不含 `block_on` 的主 Future）
+    pub fn active_tasks(&self) -> usize {
+        self.inner.tasks.borrow().len()
+    }
+
+    /// 生成新任务，返回可 await 的 `JoinHandle`
+    pub fn spawn<F>(&self, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + 'static,
+    {
+        let state = Rc::new(RefCell::new(JoinState {
+            output: None,
+            waker: None,
+        }));
+        let task_state = Rc::clone(&state);
+        let task: LocalTask = Box::pin(async move {
+            let output = future.await;
+            let mut state = task_state.borrow_mut();
+            state.output = Some(output);
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        });
+
+        let id = self.inner.next_task_id.get();
+        self.inner.next_task_id.set(id + 1);
+        let waker = TaskWaker::new(id, Arc::clone(&self.inner.ready));
+        waker.wake_by_ref();
+        self.inner.tasks.borrow_mut().insert(id, (task, waker));
+
+        JoinHandle { state }
+    }
+
+    /// 运行所有就绪任务直到没有任务可以推进，不推进虚拟时间。
+    /// 返回本次执行的 poll 次数。
+    ///
+    /// 在 `block_on` 的主 Future 内部调用时，主 Future 的唤醒不能在这里处理，
+    /// 先记下来，结束时放回就绪队列交给 `block_on`。
+    pub fn run_until_stalled(&self) -> usize {
+        let mut polls = 0;
+        let mut main_woken = false;
+        while let Some(id) = self.pop_ready() {
+            if id == MAIN_TASK {
+                main_woken = true;
+            } else {
+                self.poll_task(id);
+                polls += 1;
+            }
+        }
+        if main_woken {
+            // queued 仍为 true，主 Future 只会在队列中出现一次
+            self.inner.ready.lock().unwrap().push(MAIN_TASK);
+        }
+        polls
+    }
+
+    /// 推进虚拟时间，然后运行被唤醒的任务直到停滞
+    pub fn advance(&self, duration: Duration) {
+        self.inner.clock.advance(duration);
+        self.run_until_stalled();
+    }
+
+    /// 运行 `future` 直到完成。
+    ///
+    /// 没有就绪任务时自动把时钟拨到下一个定时器；
+    /// 既没有就绪任务也没有定时器时说明发生了死锁，直接 panic。
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        let main_waker = TaskWaker::new(MAIN_TASK, Arc::clone(&self.inner.ready));
+        main_waker.wake_by_ref();
+        let waker = Waker::from(Arc::clone(&main_waker));
+        let mut cx = Context::from_waker(&waker);
+        let mut future = pin!(future);
+
+        loop {
+            match self.pop_ready() {
+                Some(MAIN_TASK) => {
+                    main_waker.queued.store(false, Ordering::Release);
+                    self.count_poll();
+                    if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                        return output;
+                    }
+                }
+                Some(id) => self.poll_task(id),
+                None => {
+                    if !self.inner.clock.advance_to_next_timer() {
+                        panic!(
+                            "TestExecutor 死锁：主 Future 仍在等待，但既没有就绪任务也没有定时器 (seed = {})",
+                            self.inner.seed
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// 用种子随机数从就绪队列中取出一个任务
+    fn pop_ready(&self) -> Option<usize> {
+        let mut ready = self.inner.ready.lock().unwrap();
+        if ready.is_empty() {
+            return None;
+        }
+        let index = self.inner.rng.borrow_mut().below(ready.len());
+        Some(ready.swap_remove(index))
+    }
+
+    fn poll_task(&self, id: usize) {
+        // 先把任务移出表，这样任务在 poll 期间可以继续 spawn
+        let Some((mut task, waker)) = self.inner.tasks.borrow_mut().remove(&id) else {
+            return;
+        };
+
+        waker.queued.store(false, Ordering::Release);
+        self.count_poll();
+        let task_waker = Waker::from(Arc::clone(&waker));
+        let mut cx = Context::from_waker(&task_waker);
+
+        if task.as_mut().poll(&mut cx).is_pending() {
+            self.inner.tasks.borrow_mut().insert(id, (task, waker));
+        }
+    }
+
+    fn count_poll(&self) {
+        let polls = self.inner.polls.get() + 1;
+        self.inner.polls.set(polls);
+        if polls > MAX_POLLS {
+            panic!(
+                "TestExecutor 超过 {} 次 poll，可能存在活锁（例如忙等待的 Future）(seed = {})",
+                MAX_POLLS, self.inner.seed
+            );
+        }
+    }
+}
+
+// ============================================================================
+// JoinHandle
+// ============================================================================
+
+struct JoinState<T> {
+    output: Option<T>,
+    waker: Option<Waker>,
+}
+
+/// `TestExecutor::spawn` 返回的句柄，await 得到任务的输出
+pub struct JoinHandle<T> {
+    state: Rc<RefCell<JoinState<T>>>,
+}
+
+impl<T> JoinHandle<T> {
+    /// 任务是否已经完成（且输出尚未被取走）
+    pub fn is_finished(&self) -> bool {
+        self.state.borrow().output.is_some()
+    }
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let mut state = self.state.borrow_mut();
+        match state.output.take() {
+            Some(output) => Poll::Ready(output),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+// ============================================================================
+// 让出执行权
+// ============================================================================
+
+/// 让出一次执行权，给调度器一个切换到其他任务的机会
+pub fn yield_now() -> YieldNow {
+    YieldNow { yielded: false }
+}
+
+/// `yield_now` 返回的 Future
+#[derive(Debug)]
+pub struct YieldNow {
+    yielded: bool,
+}
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.yielded {
+            return Poll::Ready(());
+        }
+        self.yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 记录多个任务交替执行的顺序
+    fn interleaving(seed: u64) -> Vec<usize> {
+        let executor = TestExecutor::new(seed);
+        let log = Rc::new(RefCell::new(Vec::new()));
+
+        for id in 0..4 {
+            let log = Rc::clone(&log);
+            executor.spawn(async move {
+                for _ in 0..3 {
+                    log.borrow_mut().push(id);
+                    yield_now().await;
+                }
+            });
+        }
+
+        executor.run_until_stalled();
+        Rc::try_unwrap(log).unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_same_seed_same_interleaving() {
+        assert_eq!(interleaving(42), interleaving(42));
+    }
+
+    #[test]
+    fn test_different_seeds_explore_orderings() {
+        let orderings: std::collections::HashSet<_> = (0..20).map(interleaving).collect();
+        assert!(orderings.len() > 1);
+    }
+
+    #[test]
+    fn test_block_on_jumps_over_timers() {
+        let executor = TestExecutor::new(0);
+        let start = executor.now();
+
+        let slow = executor.spawn({
+            let executor = executor.clone();
+            async move {
+                executor.sleep(Duration::from_secs(300)).await;
+                "slow"
+            }
+        });
+        let fast = executor.spawn({
+            let executor = executor.clone();
+            async move {
+                executor.sleep(Duration::from_secs(100)).await;
+                "fast"
+            }
+        });
+
+        let results = executor.block_on(async { (fast.await, slow.await) });
+        assert_eq!(results, ("fast", "slow"));
+        // 并发睡眠的总耗时等于最长的那个，而且不花真实时间
+        assert_eq!(executor.now() - start, Duration::from_secs(300));
+    }
+
+    #[test]
+    fn test_advance_drives_timers_manually() {
+        let executor = TestExecutor::new(7);
+        let handle = executor.spawn({
+            let executor = executor.clone();
+            async move {
+                executor.sleep(Duration::from_millis(50)).await;
+                1
+            }
+        });
+
+        executor.run_until_stalled();
+        assert!(!handle.is_finished());
+
+        executor.advance(Duration::from_millis(49));
+        assert!(!handle.is_finished());
+
+        executor.advance(Duration::from_millis(1));
+        assert!(handle.is_finished());
+        assert_eq!(executor.active_tasks(), 0);
+    }
+
+    #[test]
+    fn test_spawn_from_inside_task() {
+        let executor = TestExecutor::new(3);
+        let outer = executor.spawn({
+            let executor = executor.clone();
+            async move {
+                let inner = executor.spawn(async { 20 });
+                inner.await + 1
+            }
+        });
+        assert_eq!(executor.block_on(outer), 21);
+    }
+
+    #[test]
+    #[should_panic(expected = "死锁")]
+    fn test_deadlock_is_reported() {
+        let executor = TestExecutor::new(0);
+        executor.block_on(std::future::pending::<()>());
+    }
+
+    #[test]
+    fn test_main_wake_survives_advance_inside_block_on() {
+        let executor = TestExecutor::new(0);
+        let inner = executor.clone();
+        let value = executor.block_on(async move {
+            let mut handle = inner.spawn(async { 7 });
+            std::future::poll_fn(|cx| {
+                if let Poll::Ready(value) = Pin::new(&mut handle).poll(cx) {
+                    return Poll::Ready(value);
+                }
+                // 子任务在这里完成并唤醒主 Future，唤醒不能被 advance 吞掉
+                inner.advance(Duration::ZERO);
+                Poll::Pending
+            })
+            .await
+        });
+        assert_eq!(value, 7);
+    }
+}
@@ -0,0 +1,97 @@
+//! # 确定性测试执行器与虚拟时间
+//!
+//! 演示项目里的 `DelayFuture`、`TimerFuture` 依赖真实的 `Instant::now()`，
+//! 运行要花真实的时间，而且任务的交错顺序每次都可能不同。
+//! 本 crate 为测试提供一个确定性的替代品：
+//! - `MockClock`：只在 `advance` 时前进的虚拟时钟
+//! - `TestExecutor`：单线程执行器，按种子随机挑选就绪任务
+//! - `explore`：用一批种子反复运行同一个测试，失败时打印可重放的种子
+//!
+//! ```
+//! use std::time::Duration;
+//! use test_executor::TestExecutor;
+//!
+//! let executor = TestExecutor::new(0);
+//! let start = executor.now();
+//! executor.block_on(executor.sleep(Duration::from_secs(60)));
+//! assert_eq!(executor.now() - start, Duration::from_secs(60));
+//! ```
+
+mod clock;
+mod executor;
+
+pub use clock::{MockClock, Sleep};
+pub use executor::{JoinHandle, TestExecutor, YieldNow, yield_now};
+
+use std::panic::{self, AssertUnwindSafe};
+
+/// 设置该环境变量后，`explore` 只运行指定的种子
+pub const SEED_ENV: &str = "TEST_EXECUTOR_SEED";
+
+/// 从环境变量 `TEST_EXECUTOR_SEED` 读取要重放的种子
+pub fn seed_from_env() -> Option<u64> {
+    std::env::var(SEED_ENV).ok()?.trim().parse().ok()
+}
+
+/// 用种子 `0..iterations` 依次运行 `test`，探索不同的任务交错顺序。
+///
+/// 某个种子失败时会打印该种子，随后原样抛出 panic；
+/// 设置 `TEST_EXECUTOR_SEED=<seed>` 再次运行即可只重放这一种顺序。
+pub fn explore<F>(iterations: u64, test: F)
+where
+    F: Fn(TestExecutor),
+{
+    match seed_from_env() {
+        Some(seed) => run_seed(seed, &test),
+        None => (0..iterations).for_each(|seed| run_seed(seed, &test)),
+    }
+}
+
+fn run_seed<F>(seed: u64, test: &F)
+where
+    F: Fn(TestExecutor),
+{
+    let result = panic::catch_unwind(AssertUnwindSafe(|| test(TestExecutor::new(seed))));
+    if let Err(payload) = result {
+        eprintln!("❌ 种子 {} 测试失败，使用 {}={} 重放", seed, SEED_ENV, seed);
+        panic::resume_unwind(payload);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_explore_runs_every_seed() {
+        let runs = Cell::new(0);
+        explore(10, |executor| {
+            assert!(executor.seed() < 10);
+            runs.set(runs.get() + 1);
+        });
+        assert_eq!(runs.get(), seed_from_env().map_or(10, |_| 1));
+    }
+
+    #[test]
+    fn test_explore_finds_lost_update() {
+        // 读-让出-写 的经典竞态：只有部分交错顺序会丢失更新
+        let failure = panic::catch_unwind(|| {
+            explore(50, |executor| {
+                let counter = Rc::new(Cell::new(0));
+                for _ in 0..2 {
+                    let counter = Rc::clone(&counter);
+                    executor.spawn(async move {
+                        let value = counter.get();
+                        yield_now().await;
+                        counter.set(value + 1);
+                    });
+                }
+                executor.run_until_stalled();
+                assert_eq!(counter.get(), 2);
+            });
+        });
+        assert!(failure.is_err());
+    }
+}
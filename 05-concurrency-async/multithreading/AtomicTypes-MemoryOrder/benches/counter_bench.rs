@@ -0,0 +1,143 @@
+//! 并发计数器基准测试
+//!
+//! 每次迭代让若干线程共同完成固定次数的加一：
+//! - counter_shared：所有线程写同一个计数器，对比单个 `AtomicU64`、`Mutex<u64>` 与 `StripedCounter`
+//! - counter_per_thread：每个线程写自己的计数器，对比紧密排列（伪共享）与 `CachePadded` 填充
+//!
+//! 运行方式：`cargo bench --bench counter_bench`
+
+use std::hint::black_box;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+
+use atomic_types_memory_order::counter::StripedCounter;
+use atomic_types_memory_order::padded::CachePadded;
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+
+const OPS: u64 = 100_000;
+const THREADS: [usize; 4] = [1, 2, 4, 8];
+
+/// 基准测试所需的最小计数器接口
+trait Counter: Sync {
+    fn increment(&self);
+    fn total(&self) -> u64;
+}
+
+impl Counter for AtomicU64 {
+    fn increment(&self) {
+        self.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn total(&self) -> u64 {
+        self.load(Ordering::Relaxed)
+    }
+}
+
+impl Counter for Mutex<u64> {
+    fn increment(&self) {
+        *self.lock().unwrap() += 1;
+    }
+
+    fn total(&self) -> u64 {
+        *self.lock().unwrap()
+    }
+}
+
+impl Counter for StripedCounter {
+    fn increment(&self) {
+        StripedCounter::increment(self);
+    }
+
+    fn total(&self) -> u64 {
+        self.sum()
+    }
+}
+
+/// `threads` 个线程共同对 `counter` 加一 `OPS` 次
+fn hammer<C: Counter>(counter: &C, threads: usize) -> u64 {
+    let per_thread = OPS / threads as u64;
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| {
+                for _ in 0..per_thread {
+                    counter.increment();
+                }
+            });
+        }
+    });
+    counter.total()
+}
+
+/// 每个线程只写 `slots` 中属于自己的那个
+fn hammer_own_slot<S: std::ops::Deref<Target = AtomicU64> + Sync>(slots: &[S], threads: usize) -> u64 {
+    let per_thread = OPS / threads as u64;
+    thread::scope(|scope| {
+        for slot in &slots[..threads] {
+            scope.spawn(move || {
+                for _ in 0..per_thread {
+                    slot.fetch_add(1, Ordering::Relaxed);
+                }
+            });
+        }
+    });
+    slots.iter().map(|slot| slot.load(Ordering::Relaxed)).sum()
+}
+
+/// 紧密排列的计数器，相邻线程的计数器落在同一缓存行上
+struct Packed(AtomicU64);
+
+impl std::ops::Deref for Packed {
+    type Target = AtomicU64;
+
+    fn deref(&self) -> &AtomicU64 {
+        &self.0
+    }
+}
+
+fn bench_shared_counter(c: &mut Criterion) {
+    let mut group = c.benchmark_group("counter_shared");
+    group.throughput(Throughput::Elements(OPS));
+    group.sample_size(20);
+
+    for threads in THREADS {
+        group.bench_with_input(BenchmarkId::new("atomic_u64", threads), &threads, |b, &threads| {
+            let counter = AtomicU64::new(0);
+            b.iter(|| black_box(hammer(&counter, threads)))
+        });
+        group.bench_with_input(BenchmarkId::new("mutex_u64", threads), &threads, |b, &threads| {
+            let counter = Mutex::new(0u64);
+            b.iter(|| black_box(hammer(&counter, threads)))
+        });
+        group.bench_with_input(BenchmarkId::new("striped", threads), &threads, |b, &threads| {
+            let counter = StripedCounter::new();
+            b.iter(|| black_box(hammer(&counter, threads)))
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_per_thread_counter(c: &mut Criterion) {
+    let mut group = c.benchmark_group("counter_per_thread");
+    group.throughput(Throughput::Elements(OPS));
+    group.sample_size(20);
+
+    for threads in THREADS {
+        group.bench_with_input(BenchmarkId::new("packed", threads), &threads, |b, &threads| {
+            let slots: Vec<_> = (0..threads).map(|_| Packed(AtomicU64::new(0))).collect();
+            b.iter(|| black_box(hammer_own_slot(&slots, threads)))
+        });
+        group.bench_with_input(BenchmarkId::new("cache_padded", threads), &threads, |b, &threads| {
+            let slots: Vec<_> = (0..threads)
+                .map(|_| CachePadded::new(AtomicU64::new(0)))
+                .collect();
+            b.iter(|| black_box(hammer_own_slot(&slots, threads)))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_shared_counter, bench_per_thread_counter);
+criterion_main!(benches);
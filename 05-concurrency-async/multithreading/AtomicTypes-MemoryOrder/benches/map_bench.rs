@@ -0,0 +1,121 @@
+//! 并发哈希表基准测试
+//!
+//! 每次迭代让若干线程在固定的键空间上执行一批随机操作，
+//! 对比 `LockFreeHashMap` 与 `RwLock<HashMap>` 实现的 `ThreadSafeCache`：
+//! - read_heavy：95% 读、5% 写
+//! - mixed：50% 读、50% 写
+//!
+//! 运行方式：`cargo bench --bench map_bench`
+
+use std::hint::black_box;
+use std::thread;
+
+use atomic_types_memory_order::map::LockFreeHashMap;
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use thread_safety_send_sync::ThreadSafeCache;
+
+const KEY_SPACE: u64 = 4096;
+const OPS: u64 = 40_000;
+
+/// 基准测试所需的最小哈希表接口
+trait ConcurrentMap: Sync {
+    /// 在当前线程上执行 `ops` 次操作，`write_percent` 为写操作占比
+    fn run(&self, seed: u64, ops: u64, write_percent: u64) -> u64;
+}
+
+impl ConcurrentMap for LockFreeHashMap<u64, u64> {
+    fn run(&self, seed: u64, ops: u64, write_percent: u64) -> u64 {
+        let handle = self.register();
+        let mut rng = XorShift(seed);
+        let mut hits = 0;
+        for _ in 0..ops {
+            let key = rng.next() % KEY_SPACE;
+            // 每次操作单独 pin，避免长时间持有 guard 阻塞回收
+            let guard = handle.pin();
+            if rng.next() % 100 < write_percent {
+                self.insert(key, key, &guard);
+            } else if self.get(&key, &guard).is_some() {
+                hits += 1;
+            }
+        }
+        hits
+    }
+}
+
+impl ConcurrentMap for ThreadSafeCache<u64, u64> {
+    fn run(&self, seed: u64, ops: u64, write_percent: u64) -> u64 {
+        let mut rng = XorShift(seed);
+        let mut hits = 0;
+        for _ in 0..ops {
+            let key = rng.next() % KEY_SPACE;
+            if rng.next() % 100 < write_percent {
+                self.insert(key, key);
+            } else if self.get(&key).is_some() {
+                hits += 1;
+            }
+        }
+        hits
+    }
+}
+
+/// 足够便宜的伪随机数，避免随机数生成本身成为瓶颈
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+/// 预先填充一半的键，让读操作既有命中也有未命中
+fn prefill<M: ConcurrentMap>(map: &M) {
+    map.run(0x9E37_79B9_7F4A_7C15, KEY_SPACE / 2, 100);
+}
+
+/// `threads` 个线程共同完成 `OPS` 次操作
+fn workload<M: ConcurrentMap>(map: &M, threads: u64, write_percent: u64) -> u64 {
+    let per_thread = OPS / threads;
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|t| scope.spawn(move || map.run(t * 7919 + 1, per_thread, write_percent)))
+            .collect();
+        workers.into_iter().map(|h| h.join().unwrap()).sum()
+    })
+}
+
+fn bench_map(c: &mut Criterion) {
+    for (name, write_percent) in [("map_read_heavy", 5u64), ("map_mixed", 50)] {
+        let mut group = c.benchmark_group(name);
+        group.throughput(Throughput::Elements(OPS));
+        group.sample_size(20);
+
+        for threads in [1u64, 2, 4, 8] {
+            group.bench_with_input(
+                BenchmarkId::new("lock_free_hash_map", threads),
+                &threads,
+                |b, &threads| {
+                    let map = LockFreeHashMap::new();
+                    prefill(&map);
+                    b.iter(|| black_box(workload(&map, threads, write_percent)))
+                },
+            );
+            group.bench_with_input(
+                BenchmarkId::new("rwlock_hash_map", threads),
+                &threads,
+                |b, &threads| {
+                    let map = ThreadSafeCache::new();
+                    prefill(&map);
+                    b.iter(|| black_box(workload(&map, threads, write_percent)))
+                },
+            );
+        }
+
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_map);
+criterion_main!(benches);
@@ -0,0 +1,158 @@
+//! 有界队列吞吐量基准测试
+//!
+//! 每次迭代让若干生产者 / 消费者线程通过队列搬运固定数量的元素，
+//! 对比 Vyukov MPMC 队列、SPSC 队列与 `Mutex<VecDeque>`。
+//!
+//! 运行方式：`cargo bench --bench queue_bench`
+
+use std::collections::VecDeque;
+use std::hint::black_box;
+use std::sync::Mutex;
+use std::thread;
+
+use atomic_types_memory_order::queue::{self, MpmcQueue};
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+
+const ITEMS: u64 = 20_000;
+const CAPACITY: usize = 1024;
+
+/// 基准测试所需的最小队列接口
+trait BoundedQueue: Sync {
+    fn try_push(&self, value: u64) -> Result<(), u64>;
+    fn try_pop(&self) -> Option<u64>;
+}
+
+impl BoundedQueue for MpmcQueue<u64> {
+    fn try_push(&self, value: u64) -> Result<(), u64> {
+        self.push(value)
+    }
+
+    fn try_pop(&self) -> Option<u64> {
+        self.pop()
+    }
+}
+
+/// 加锁的对照组，满时同样拒绝入队
+struct MutexQueue {
+    inner: Mutex<VecDeque<u64>>,
+    capacity: usize,
+}
+
+impl MutexQueue {
+    fn new(capacity: usize) -> Self {
+        Self {
+            inner: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity,
+        }
+    }
+}
+
+impl BoundedQueue for MutexQueue {
+    fn try_push(&self, value: u64) -> Result<(), u64> {
+        let mut queue = self.inner.lock().unwrap();
+        if queue.len() == self.capacity {
+            return Err(value);
+        }
+        queue.push_back(value);
+        Ok(())
+    }
+
+    fn try_pop(&self) -> Option<u64> {
+        self.inner.lock().unwrap().pop_front()
+    }
+}
+
+/// `pairs` 个生产者和 `pairs` 个消费者共同搬运 `ITEMS` 个元素
+fn transfer<Q: BoundedQueue>(queue: &Q, pairs: u64) -> u64 {
+    let per_thread = ITEMS / pairs;
+    thread::scope(|scope| {
+        for _ in 0..pairs {
+            scope.spawn(|| {
+                for i in 0..per_thread {
+                    let mut value = i;
+                    while let Err(back) = queue.try_push(value) {
+                        value = back;
+                        thread::yield_now();
+                    }
+                }
+            });
+        }
+        let consumers: Vec<_> = (0..pairs)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut sum = 0;
+                    let mut received = 0;
+                    while received < per_thread {
+                        match queue.try_pop() {
+                            Some(value) => {
+                                sum += value;
+                                received += 1;
+                            }
+                            None => thread::yield_now(),
+                        }
+                    }
+                    sum
+                })
+            })
+            .collect();
+        consumers.into_iter().map(|h| h.join().unwrap()).sum()
+    })
+}
+
+fn transfer_spsc() -> u64 {
+    let (mut producer, mut consumer) = queue::spsc(CAPACITY);
+    thread::scope(|scope| {
+        scope.spawn(move || {
+            for i in 0..ITEMS {
+                let mut value = i;
+                while let Err(back) = producer.push(value) {
+                    value = back;
+                    thread::yield_now();
+                }
+            }
+        });
+        let mut sum = 0;
+        let mut received = 0;
+        while received < ITEMS {
+            match consumer.pop() {
+                Some(value) => {
+                    sum += value;
+                    received += 1;
+                }
+                None => thread::yield_now(),
+            }
+        }
+        sum
+    })
+}
+
+fn bench_queue_throughput(c: &mut Criterion) {
+    let mut group = c.benchmark_group("queue_throughput");
+    group.throughput(Throughput::Elements(ITEMS));
+    group.sample_size(20);
+
+    group.bench_function(BenchmarkId::new("spsc", "1P1C"), |b| {
+        b.iter(|| black_box(transfer_spsc()))
+    });
+
+    for pairs in [1u64, 2, 4] {
+        let label = format!("{}P{}C", pairs, pairs);
+        group.bench_with_input(BenchmarkId::new("mpmc", &label), &pairs, |b, &pairs| {
+            let queue = MpmcQueue::new(CAPACITY);
+            b.iter(|| black_box(transfer(&queue, pairs)))
+        });
+        group.bench_with_input(
+            BenchmarkId::new("mutex_vecdeque", &label),
+            &pairs,
+            |b, &pairs| {
+                let queue = MutexQueue::new(CAPACITY);
+                b.iter(|| black_box(transfer(&queue, pairs)))
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_queue_throughput);
+criterion_main!(benches);
@@ -0,0 +1,34 @@
+//! 内存模型 litmus 测试
+//!
+//! 对 SB / MP / LB / IRIW 四种形状、十种内存顺序组合各执行若干次，
+//! 打印每种结果出现的次数，并标出内存模型禁止的结果。
+//!
+//! 运行方式：`cargo run --release --example litmus [-- <每种组合的迭代次数>]`
+
+use atomic_types_memory_order::litmus::{self, DEFAULT_ITERATIONS};
+
+fn main() {
+    let iterations = std::env::args()
+        .nth(1)
+        .and_then(|arg| arg.parse().ok())
+        .unwrap_or(DEFAULT_ITERATIONS);
+
+    println!("🧪 内存模型 litmus 测试，每种组合执行 {} 次", iterations);
+    println!("{}", "=".repeat(80));
+
+    let reports = litmus::run_all(iterations);
+    for report in &reports {
+        print!("{}", report);
+    }
+
+    println!("{}", "=".repeat(80));
+    let violations: Vec<_> = reports.iter().filter(|r| r.has_violation()).collect();
+    if violations.is_empty() {
+        println!("✅ 未观察到任何被禁止的结果");
+    } else {
+        for report in &violations {
+            println!("❌ {} {}: 弱结果出现 {} 次", report.shape, report.orderings, report.weak_count());
+        }
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,148 @@
+//! 分片计数器
+//!
+//! 所有线程对同一个 `AtomicU64` 做 `fetch_add` 时，那一条缓存行会在核心之间不停搬运，
+//! 线程越多越慢。[`StripedCounter`] 把计数分散到多个各自占满缓存行的分片上，
+//! 每个线程固定写自己的分片，读取时再把所有分片加起来：写入几乎没有争用，读取变成 O(分片数)。
+//! 适合"写多读少"的统计类计数，例如请求数、字节数。
+
+use std::cell::Cell;
+use std::sync::atomic::AtomicUsize as StdAtomicUsize;
+
+use crate::padded::CachePadded;
+use crate::sync::{AtomicU64, Ordering};
+
+/// 给每个线程分配的序号，按首次使用的顺序轮流分配，决定它写哪个分片
+static NEXT_THREAD_INDEX: StdAtomicUsize = StdAtomicUsize::new(0);
+
+thread_local! {
+    static THREAD_INDEX: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+fn thread_index() -> usize {
+    THREAD_INDEX.with(|index| match index.get() {
+        Some(index) => index,
+        None => {
+            let assigned = NEXT_THREAD_INDEX.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            index.set(Some(assigned));
+            assigned
+        }
+    })
+}
+
+/// 按线程分片的计数器
+pub struct StripedCounter {
+    stripes: Box<[CachePadded<AtomicU64>]>,
+    /// 分片数减一，分片数总是 2 的幂
+    mask: usize,
+}
+
+impl StripedCounter {
+    /// 分片数取可用并行度的两倍（向上取整为 2 的幂），减少不同线程落到同一分片的概率
+    pub fn new() -> Self {
+        let parallelism = std::thread::available_parallelism().map_or(1, |n| n.get());
+        Self::with_stripes(parallelism * 2)
+    }
+
+    /// 指定分片数，向上取整为 2 的幂，至少为 1
+    pub fn with_stripes(stripes: usize) -> Self {
+        let stripes = stripes.max(1).next_power_of_two();
+        Self {
+            stripes: (0..stripes).map(|_| CachePadded::new(AtomicU64::new(0))).collect(),
+            mask: stripes - 1,
+        }
+    }
+
+    /// 分片数
+    pub fn stripes(&self) -> usize {
+        self.stripes.len()
+    }
+
+    pub fn add(&self, n: u64) {
+        // 计数本身不用来同步其他数据，Relaxed 足够
+        self.stripes[thread_index() & self.mask].fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn increment(&self) {
+        self.add(1);
+    }
+
+    /// 所有分片之和
+    ///
+    /// 并发写入时读到的是各分片在不同时刻的值之和：不会丢失已完成的写入，
+    /// 但不是某一瞬间的精确快照。所有写线程结束（join）之后读取则是精确值。
+    pub fn sum(&self) -> u64 {
+        self.stripes
+            .iter()
+            .map(|stripe| stripe.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    /// 清零并返回清零前的总和；与并发写入同时进行时，写入只会计入这次或下次
+    pub fn reset(&self) -> u64 {
+        self.stripes
+            .iter()
+            .map(|stripe| stripe.swap(0, Ordering::Relaxed))
+            .sum()
+    }
+}
+
+impl Default for StripedCounter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn stripe_count_is_power_of_two() {
+        assert_eq!(StripedCounter::with_stripes(0).stripes(), 1);
+        assert_eq!(StripedCounter::with_stripes(3).stripes(), 4);
+        assert_eq!(StripedCounter::with_stripes(8).stripes(), 8);
+        assert!(StripedCounter::new().stripes() >= 2);
+    }
+
+    #[test]
+    fn concurrent_increments_are_not_lost() {
+        let counter = StripedCounter::with_stripes(4);
+        thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    for _ in 0..10_000 {
+                        counter.increment();
+                    }
+                    counter.add(5);
+                });
+            }
+        });
+        assert_eq!(counter.sum(), 8 * 10_005);
+    }
+
+    #[test]
+    fn threads_spread_over_stripes() {
+        let counter = StripedCounter::with_stripes(64);
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| counter.increment());
+            }
+        });
+        // 每个新线程拿到不同的序号，64 个分片下不会全部落到同一分片
+        let used = counter
+            .stripes
+            .iter()
+            .filter(|stripe| stripe.load(Ordering::Relaxed) > 0)
+            .count();
+        assert!(used > 1);
+    }
+
+    #[test]
+    fn reset_returns_previous_sum() {
+        let counter = StripedCounter::with_stripes(2);
+        counter.add(7);
+        assert_eq!(counter.reset(), 7);
+        assert_eq!(counter.sum(), 0);
+    }
+}
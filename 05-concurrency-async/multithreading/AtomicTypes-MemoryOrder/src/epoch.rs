@@ -0,0 +1,454 @@
+//! 基于纪元的内存回收（Epoch-based Reclamation, EBR）
+//!
+//! 无锁结构里，一个线程把节点从结构中摘下后不能立刻释放：别的线程可能刚读到
+//! 这个指针，正准备解引用。EBR 的做法是：
+//!
+//! - 全局维护一个单调递增的纪元（epoch）；
+//! - 线程访问共享指针前先"钉住"（pin），记录下当时看到的纪元，访问结束后解除；
+//! - 摘下的节点不立即释放，而是连同当时的纪元 `e` 放入待回收袋；
+//! - 只有当所有被钉住的线程都已进入当前纪元时，全局纪元才能前进一步。
+//!   因此全局纪元到达 `e + 2` 时，所有可能看到旧指针的线程都已解除钉住，可以安全释放。
+//!
+//! 钉住 / 解除钉住只涉及自己槽位上的原子写，不会阻塞；注册表是只增不减的无锁链表，
+//! 注销的槽位会被后来者复用。
+
+use std::cell::{Cell, RefCell};
+use std::mem;
+use std::ptr;
+
+use crate::sync::{Arc, AtomicBool, AtomicPtr, AtomicUsize, Mutex, Ordering, fence};
+
+/// 参与者状态的最低位：1 表示正被钉住，其余位存放钉住时的纪元
+const PINNED: usize = 1;
+
+/// 本地待回收袋攒到这么多对象时，自动尝试推进纪元并回收
+const COLLECT_THRESHOLD: usize = 64;
+
+// ============================================================================
+// 延迟销毁
+// ============================================================================
+
+/// 一个延迟到安全时刻再执行的销毁动作
+struct Deferred {
+    /// 退休时所在的纪元
+    epoch: usize,
+    ptr: *mut u8,
+    destroy: unsafe fn(*mut u8),
+}
+
+// 退休的对象已经不可被新读者访问，销毁动作可以交给任意线程执行
+unsafe impl Send for Deferred {}
+
+impl Deferred {
+    fn is_expired(&self, global_epoch: usize) -> bool {
+        global_epoch.wrapping_sub(self.epoch) >= 2
+    }
+
+    fn run(self) {
+        unsafe { (self.destroy)(self.ptr) }
+    }
+}
+
+unsafe fn drop_box<T>(ptr: *mut u8) {
+    drop(unsafe { Box::from_raw(ptr.cast::<T>()) });
+}
+
+/// 把已过期的销毁动作从 `bag` 中取出，剩下的留在原处
+fn take_expired(bag: &mut Vec<Deferred>, global_epoch: usize) -> Vec<Deferred> {
+    let (expired, pending) = mem::take(bag)
+        .into_iter()
+        .partition(|deferred| deferred.is_expired(global_epoch));
+    *bag = pending;
+    expired
+}
+
+// ============================================================================
+// 全局状态
+// ============================================================================
+
+/// 注册表中的一个槽位，由一个 `LocalHandle` 独占使用
+struct Participant {
+    /// 0 表示未钉住；否则为 `(epoch << 1) | PINNED`
+    state: AtomicUsize,
+    in_use: AtomicBool,
+    /// 发布到链表之前写入，之后只读
+    next: *mut Participant,
+}
+
+struct Global {
+    epoch: AtomicUsize,
+    participants: AtomicPtr<Participant>,
+    /// 线程注销时还没来得及回收的对象
+    orphans: Mutex<Vec<Deferred>>,
+}
+
+// 参与者链表只通过原子操作修改，节点在 `Global` 销毁前不会释放
+unsafe impl Send for Global {}
+unsafe impl Sync for Global {}
+
+impl Global {
+    /// 只要所有被钉住的参与者都处于当前纪元，就把全局纪元推进一步；返回推进后的纪元
+    fn try_advance(&self) -> usize {
+        let global_epoch = self.epoch.load(Ordering::Relaxed);
+        // 与 pin 中的 SeqCst 栅栏配对：要么我们看到对方已钉住，
+        // 要么对方钉住之后一定能看到我们之前对共享结构的修改
+        fence(Ordering::SeqCst);
+
+        let mut current = self.participants.load(Ordering::Acquire);
+        while let Some(participant) = unsafe { current.as_ref() } {
+            let state = participant.state.load(Ordering::Relaxed);
+            if state & PINNED != 0 && state >> 1 != global_epoch {
+                return global_epoch;
+            }
+            current = participant.next;
+        }
+        fence(Ordering::Acquire);
+
+        let next_epoch = global_epoch.wrapping_add(1);
+        // 失败说明别的线程已经推进过了，同样算作推进成功。失败时也要 Acquire：
+        // 调用者会据此释放对象，必须与推进者观察到的那些"解除钉住"建立 happens-before
+        match self.epoch.compare_exchange(
+            global_epoch,
+            next_epoch,
+            Ordering::Release,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => next_epoch,
+            Err(actual) => actual,
+        }
+    }
+
+    fn collect_orphans(&self, global_epoch: usize) {
+        let expired = {
+            let mut orphans = self.orphans.lock().unwrap();
+            if orphans.is_empty() {
+                return;
+            }
+            take_expired(&mut orphans, global_epoch)
+        };
+        for deferred in expired {
+            deferred.run();
+        }
+    }
+
+    fn acquire_participant(&self) -> *const Participant {
+        // 优先复用已注销的槽位
+        let mut current = self.participants.load(Ordering::Acquire);
+        while let Some(participant) = unsafe { current.as_ref() } {
+            if participant
+                .in_use
+                .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+                .is_ok()
+            {
+                return current;
+            }
+            current = participant.next;
+        }
+
+        let participant = Box::into_raw(Box::new(Participant {
+            state: AtomicUsize::new(0),
+            in_use: AtomicBool::new(true),
+            next: ptr::null_mut(),
+        }));
+        let mut head = self.participants.load(Ordering::Relaxed);
+        loop {
+            unsafe { (*participant).next = head };
+            match self.participants.compare_exchange_weak(
+                head,
+                participant,
+                Ordering::Release,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return participant,
+                Err(actual) => head = actual,
+            }
+        }
+    }
+}
+
+impl Drop for Global {
+    fn drop(&mut self) {
+        // 所有句柄都已销毁，不再有任何读者
+        let mut current = self.participants.load(Ordering::Relaxed);
+        while !current.is_null() {
+            let participant = unsafe { Box::from_raw(current) };
+            current = participant.next;
+        }
+        let orphans = mem::take(&mut *self.orphans.lock().unwrap());
+        for deferred in orphans {
+            deferred.run();
+        }
+    }
+}
+
+// ============================================================================
+// 对外接口
+// ============================================================================
+
+/// 回收器：一组共享同一纪元的线程
+///
+/// 可以廉价地克隆并发送到其他线程；每个线程通过 [`Collector::register`]
+/// 取得自己的 [`LocalHandle`] 后才能钉住。
+#[derive(Clone)]
+pub struct Collector {
+    global: Arc<Global>,
+}
+
+impl Collector {
+    pub fn new() -> Self {
+        Self {
+            global: Arc::new(Global {
+                epoch: AtomicUsize::new(0),
+                participants: AtomicPtr::new(ptr::null_mut()),
+                orphans: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// 为当前线程注册一个参与者
+    pub fn register(&self) -> LocalHandle {
+        LocalHandle {
+            global: Arc::clone(&self.global),
+            participant: self.global.acquire_participant(),
+            guard_count: Cell::new(0),
+            bag: RefCell::new(Vec::new()),
+            next_collect: Cell::new(COLLECT_THRESHOLD),
+        }
+    }
+
+    /// 当前全局纪元
+    pub fn epoch(&self) -> usize {
+        self.global.epoch.load(Ordering::Relaxed)
+    }
+
+    fn same_as(&self, handle: &LocalHandle) -> bool {
+        Arc::ptr_eq(&self.global, &handle.global)
+    }
+}
+
+impl Default for Collector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 线程私有的参与者句柄，不能跨线程移动
+pub struct LocalHandle {
+    global: Arc<Global>,
+    participant: *const Participant,
+    /// 嵌套钉住的层数，只有最外层真正修改参与者状态
+    guard_count: Cell<usize>,
+    bag: RefCell<Vec<Deferred>>,
+    /// 本地垃圾达到这个数量时才尝试回收；回收后按剩余数量重新计算，
+    /// 避免在纪元推进不了时每次 defer 都扫描一遍整个垃圾袋
+    next_collect: Cell<usize>,
+}
+
+impl LocalHandle {
+    /// 钉住当前线程；在返回的 [`Guard`] 存活期间读到的共享指针都不会被释放
+    pub fn pin(&self) -> Guard<'_> {
+        let count = self.guard_count.get();
+        self.guard_count.set(count + 1);
+        if count == 0 {
+            let global_epoch = self.global.epoch.load(Ordering::Relaxed);
+            self.participant()
+                .state
+                .store((global_epoch << 1) | PINNED, Ordering::Relaxed);
+            // 保证"已钉住"先于之后对共享结构的任何读取被其他线程看到
+            fence(Ordering::SeqCst);
+        }
+        Guard { handle: self }
+    }
+
+    /// 尝试推进纪元，并回收所有已经安全的对象
+    pub fn flush(&self) {
+        let global_epoch = self.global.try_advance();
+        let expired = {
+            let mut bag = self.bag.borrow_mut();
+            let expired = take_expired(&mut bag, global_epoch);
+            self.next_collect.set(bag.len() + COLLECT_THRESHOLD);
+            expired
+        };
+        // 先释放 RefCell 借用再执行析构，析构函数里可能再次 defer
+        for deferred in expired {
+            deferred.run();
+        }
+        self.global.collect_orphans(global_epoch);
+    }
+
+    /// 本地仍在等待回收的对象数
+    pub fn pending(&self) -> usize {
+        self.bag.borrow().len()
+    }
+
+    fn participant(&self) -> &Participant {
+        unsafe { &*self.participant }
+    }
+
+    fn unpin(&self) {
+        let count = self.guard_count.get() - 1;
+        self.guard_count.set(count);
+        if count == 0 {
+            // Release：钉住期间的所有读取都先于回收者看到"已解除"
+            self.participant().state.store(0, Ordering::Release);
+        }
+    }
+}
+
+impl Drop for LocalHandle {
+    fn drop(&mut self) {
+        self.flush();
+        let remaining = mem::take(self.bag.get_mut());
+        if !remaining.is_empty() {
+            self.global.orphans.lock().unwrap().extend(remaining);
+        }
+        self.participant().state.store(0, Ordering::Release);
+        self.participant().in_use.store(false, Ordering::Release);
+    }
+}
+
+/// 钉住期间持有的守卫，离开作用域时自动解除钉住
+pub struct Guard<'a> {
+    handle: &'a LocalHandle,
+}
+
+impl Guard<'_> {
+    /// 延迟释放一个由 `Box` 分配的对象，直到没有线程可能再访问它
+    ///
+    /// # Safety
+    ///
+    /// `ptr` 必须来自 `Box::into_raw`，已经从共享结构中摘除（新的读者不可能再拿到它），
+    /// 且只被退休一次。析构可能在任意注册过的线程上执行。
+    pub unsafe fn defer_destroy<T>(&self, ptr: *mut T) {
+        let handle = self.handle;
+        // 不能用自己钉住时的纪元：它可能已经过时，比其他读者钉住的纪元还小，
+        // 对象就会被提前释放。栅栏之后读到的全局纪元不小于任何可能持有该指针的读者
+        fence(Ordering::SeqCst);
+        let deferred = Deferred {
+            epoch: handle.global.epoch.load(Ordering::Relaxed),
+            ptr: ptr.cast::<u8>(),
+            destroy: drop_box::<T>,
+        };
+        let len = {
+            let mut bag = handle.bag.borrow_mut();
+            bag.push(deferred);
+            bag.len()
+        };
+        if len >= handle.next_collect.get() {
+            handle.flush();
+        }
+    }
+
+    /// 守卫是否来自给定的回收器
+    pub(crate) fn belongs_to(&self, collector: &Collector) -> bool {
+        collector.same_as(self.handle)
+    }
+}
+
+impl Drop for Guard<'_> {
+    fn drop(&mut self) {
+        self.handle.unpin();
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize as StdAtomicUsize;
+
+    struct DropCounter(std::sync::Arc<StdAtomicUsize>);
+
+    impl Drop for DropCounter {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        }
+    }
+
+    fn retire(guard: &Guard<'_>, drops: &std::sync::Arc<StdAtomicUsize>) {
+        let ptr = Box::into_raw(Box::new(DropCounter(std::sync::Arc::clone(drops))));
+        unsafe { guard.defer_destroy(ptr) };
+    }
+
+    #[test]
+    fn pinned_reader_blocks_reclamation() {
+        let collector = Collector::new();
+        let reader = collector.register();
+        let writer = collector.register();
+        let drops = std::sync::Arc::new(StdAtomicUsize::new(0));
+
+        let read_guard = reader.pin();
+        retire(&writer.pin(), &drops);
+
+        // 读者一直钉在旧纪元，纪元最多前进一步，对象不能被释放
+        for _ in 0..5 {
+            writer.flush();
+        }
+        assert_eq!(drops.load(std::sync::atomic::Ordering::SeqCst), 0);
+        assert_eq!(writer.pending(), 1);
+
+        drop(read_guard);
+        for _ in 0..3 {
+            writer.flush();
+        }
+        assert_eq!(drops.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert_eq!(writer.pending(), 0);
+    }
+
+    #[test]
+    fn nested_guards_stay_pinned_until_outermost_drops() {
+        let collector = Collector::new();
+        let handle = collector.register();
+        let other = collector.register();
+
+        let outer = handle.pin();
+        let inner = handle.pin();
+        drop(inner);
+        let epoch = collector.epoch();
+        for _ in 0..3 {
+            other.flush();
+        }
+        // 外层守卫仍在，纪元最多比钉住时前进一步
+        assert!(collector.epoch().wrapping_sub(epoch) <= 1);
+
+        drop(outer);
+        for _ in 0..3 {
+            other.flush();
+        }
+        assert!(collector.epoch().wrapping_sub(epoch) >= 3);
+    }
+
+    #[test]
+    fn orphaned_garbage_is_freed_with_collector() {
+        let drops = std::sync::Arc::new(StdAtomicUsize::new(0));
+        let collector = Collector::new();
+        let blocker = collector.register();
+        let blocker_guard = blocker.pin();
+
+        let worker = {
+            let collector = collector.clone();
+            let drops = std::sync::Arc::clone(&drops);
+            std::thread::spawn(move || {
+                let handle = collector.register();
+                retire(&handle.pin(), &drops);
+                // 句柄销毁时对象还不安全，只能转交给全局
+            })
+        };
+        worker.join().unwrap();
+        assert_eq!(drops.load(std::sync::atomic::Ordering::SeqCst), 0);
+
+        drop(blocker_guard);
+        drop(blocker);
+        drop(collector);
+        assert_eq!(drops.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn unregistered_slots_are_reused() {
+        let collector = Collector::new();
+        let first = collector.register();
+        let slot = first.participant;
+        drop(first);
+        let second = collector.register();
+        assert_eq!(second.participant, slot);
+    }
+}
@@ -0,0 +1,13 @@
+//! 原子类型教程中可复用的无锁组件
+//!
+//! `main.rs` 的演示和 `benches/` 下的基准测试共用这里的实现；
+//! 以 `RUSTFLAGS="--cfg loom"` 编译时，各模块的 `loom_tests` 会穷举线程交错。
+
+pub mod counter;
+pub mod epoch;
+pub mod litmus;
+pub mod map;
+pub mod padded;
+pub mod queue;
+pub mod stack;
+mod sync;
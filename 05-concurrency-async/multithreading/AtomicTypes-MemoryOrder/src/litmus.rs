@@ -0,0 +1,555 @@
+//! 内存模型 litmus 测试
+//!
+//! 把经典的几种"形状"在每一种 `Ordering` 组合下反复执行，统计各种读取结果出现的次数：
+//!
+//! | 形状 | 线程代码 | 弱结果 |
+//! |------|----------|--------|
+//! | SB（store buffering） | `x=1; r1=y` ∥ `y=1; r2=x` | `r1=0 r2=0` |
+//! | MP（message passing） | `data=1; flag=1` ∥ `r1=flag; r2=data` | `r1=1 r2=0` |
+//! | LB（load buffering） | `r1=x; y=1` ∥ `r2=y; x=1` | `r1=1 r2=1` |
+//! | IRIW | `x=1` ∥ `y=1` ∥ `r1=x; r2=y` ∥ `r3=y; r4=x` | `r1=1 r2=0 r3=1 r4=0` |
+//!
+//! 弱结果在顺序一致的交错里不可能出现；它是否被 Rust（C++20）内存模型允许取决于使用的内存顺序，
+//! 见 [`Shape::forbids_weak`]。被禁止的结果一旦在真实硬件上出现，就说明实现有 bug。
+//!
+//! 执行方式参考 litmus7 的"数组模式"：每一批为每次迭代准备独立的一组变量，
+//! 所有线程在屏障处对齐后顺序扫过整批，让同一迭代的访问在时间上尽量重叠。
+//! 写入的值是批次号而不是 1，读到本批次的值才算"看到了写入"，因此变量不需要在批次之间清零。
+//!
+//! 以 `--cfg loom` 编译时只保留形状定义供 `loom_tests` 穷举，[`run`] 等真实线程上的统计不参与编译。
+
+#[cfg(not(loom))]
+use std::collections::BTreeMap;
+use std::fmt;
+#[cfg(not(loom))]
+use std::sync::Barrier;
+#[cfg(not(loom))]
+use std::thread;
+
+use crate::sync::{AtomicU32, Ordering, fence};
+
+/// 每批迭代数，批与批之间用屏障同步
+#[cfg(not(loom))]
+const BATCH: usize = 4096;
+
+/// 示例程序默认的迭代次数
+#[cfg(not(loom))]
+pub const DEFAULT_ITERATIONS: u64 = 1_000_000;
+
+// ============================================================================
+// 形状与内存顺序
+// ============================================================================
+
+/// 经典 litmus 测试形状
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Shape {
+    StoreBuffering,
+    MessagePassing,
+    LoadBuffering,
+    Iriw,
+}
+
+impl Shape {
+    pub const ALL: [Shape; 4] = [
+        Shape::StoreBuffering,
+        Shape::MessagePassing,
+        Shape::LoadBuffering,
+        Shape::Iriw,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Shape::StoreBuffering => "SB",
+            Shape::MessagePassing => "MP",
+            Shape::LoadBuffering => "LB",
+            Shape::Iriw => "IRIW",
+        }
+    }
+
+    /// 参与的线程数
+    pub fn threads(&self) -> usize {
+        self.reads_per_thread().len()
+    }
+
+    /// 每个线程依次读取的次数，结果按线程顺序拼成 r1, r2, ...
+    fn reads_per_thread(&self) -> &'static [u8] {
+        match self {
+            Shape::StoreBuffering | Shape::LoadBuffering => &[1, 1],
+            Shape::MessagePassing => &[0, 2],
+            Shape::Iriw => &[0, 0, 2, 2],
+        }
+    }
+
+    /// 顺序一致性下不可能出现的那个结果
+    pub fn weak_outcome(&self) -> Outcome {
+        match self {
+            Shape::StoreBuffering => Outcome::new(&[0, 0]),
+            Shape::MessagePassing => Outcome::new(&[1, 0]),
+            Shape::LoadBuffering => Outcome::new(&[1, 1]),
+            Shape::Iriw => Outcome::new(&[1, 0, 1, 0]),
+        }
+    }
+
+    /// 内存模型是否禁止在 `orderings` 下出现弱结果
+    ///
+    /// - SB / IRIW 需要全局一致的顺序：读写都是 SeqCst，或者中间插入 SeqCst 栅栏
+    /// - MP / LB 只需要 happens-before：Release 写配 Acquire 读即可
+    pub fn forbids_weak(&self, orderings: Orderings) -> bool {
+        if orderings.fence {
+            return true;
+        }
+        match self {
+            Shape::StoreBuffering | Shape::Iriw => {
+                orderings.store == Ordering::SeqCst && orderings.load == Ordering::SeqCst
+            }
+            Shape::MessagePassing | Shape::LoadBuffering => {
+                orderings.store != Ordering::Relaxed && orderings.load != Ordering::Relaxed
+            }
+        }
+    }
+
+    /// 执行第 `tid` 个线程的代码，返回该线程的读取结果（第 i 次读取在第 i 位）
+    ///
+    /// 读到 `stamp` 记为 1，读到更早的值记为 0。MP 中的数据变量始终用 Relaxed 访问，
+    /// `orderings` 只作用于 flag。
+    pub(crate) fn run_thread(
+        &self,
+        tid: usize,
+        x: &AtomicU32,
+        y: &AtomicU32,
+        stamp: u32,
+        orderings: Orderings,
+    ) -> u8 {
+        let Orderings { store, load, .. } = orderings;
+        let seen = |value: u32| (value == stamp) as u8;
+        match (self, tid) {
+            (Shape::StoreBuffering, 0) => {
+                x.store(stamp, store);
+                orderings.fence();
+                seen(y.load(load))
+            }
+            (Shape::StoreBuffering, _) => {
+                y.store(stamp, store);
+                orderings.fence();
+                seen(x.load(load))
+            }
+            (Shape::MessagePassing, 0) => {
+                x.store(stamp, Ordering::Relaxed);
+                orderings.fence();
+                y.store(stamp, store);
+                0
+            }
+            (Shape::MessagePassing, _) => {
+                let flag = seen(y.load(load));
+                orderings.fence();
+                let data = seen(x.load(Ordering::Relaxed));
+                flag | (data << 1)
+            }
+            (Shape::LoadBuffering, 0) => {
+                let r = seen(x.load(load));
+                orderings.fence();
+                y.store(stamp, store);
+                r
+            }
+            (Shape::LoadBuffering, _) => {
+                let r = seen(y.load(load));
+                orderings.fence();
+                x.store(stamp, store);
+                r
+            }
+            (Shape::Iriw, 0) => {
+                x.store(stamp, store);
+                0
+            }
+            (Shape::Iriw, 1) => {
+                y.store(stamp, store);
+                0
+            }
+            (Shape::Iriw, 2) => {
+                let first = seen(x.load(load));
+                orderings.fence();
+                first | (seen(y.load(load)) << 1)
+            }
+            (Shape::Iriw, _) => {
+                let first = seen(y.load(load));
+                orderings.fence();
+                first | (seen(x.load(load)) << 1)
+            }
+        }
+    }
+
+    /// 把各线程的读取结果按 r1, r2, ... 的顺序拼起来
+    pub(crate) fn outcome(&self, per_thread: &[u8]) -> Outcome {
+        let mut bits = 0;
+        let mut len = 0;
+        for (&reads, &thread_bits) in self.reads_per_thread().iter().zip(per_thread) {
+            bits |= thread_bits << len;
+            len += reads;
+        }
+        Outcome { bits, len }
+    }
+}
+
+impl fmt::Display for Shape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// 一次测试使用的内存顺序
+///
+/// `store` 作用于所有写（MP 的数据变量除外），`load` 作用于所有读；
+/// `fence` 为 true 时在每个线程的两次访问之间插入 `fence(SeqCst)`。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Orderings {
+    pub store: Ordering,
+    pub load: Ordering,
+    pub fence: bool,
+}
+
+impl Orderings {
+    /// `store` 只能是 Relaxed / Release / SeqCst，`load` 只能是 Relaxed / Acquire / SeqCst
+    pub const fn new(store: Ordering, load: Ordering) -> Self {
+        Self {
+            store,
+            load,
+            fence: false,
+        }
+    }
+
+    /// 全部 Relaxed，靠 SeqCst 栅栏排序
+    pub const fn relaxed_with_fence() -> Self {
+        Self {
+            store: Ordering::Relaxed,
+            load: Ordering::Relaxed,
+            fence: true,
+        }
+    }
+
+    /// 所有合法的读写顺序组合，外加一个栅栏版本
+    pub fn all() -> Vec<Orderings> {
+        let stores = [Ordering::Relaxed, Ordering::Release, Ordering::SeqCst];
+        let loads = [Ordering::Relaxed, Ordering::Acquire, Ordering::SeqCst];
+        let mut all: Vec<_> = stores
+            .iter()
+            .flat_map(|&store| loads.iter().map(move |&load| Orderings::new(store, load)))
+            .collect();
+        all.push(Orderings::relaxed_with_fence());
+        all
+    }
+
+    fn fence(&self) {
+        if self.fence {
+            fence(Ordering::SeqCst);
+        }
+    }
+}
+
+impl fmt::Display for Orderings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}/{:?}", self.store, self.load)?;
+        if self.fence {
+            f.write_str(" + fence(SeqCst)")?;
+        }
+        Ok(())
+    }
+}
+
+/// 一次迭代中所有读取的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Outcome {
+    /// 第 i 位为 r(i+1) 的值
+    bits: u8,
+    len: u8,
+}
+
+impl Outcome {
+    /// 由 r1, r2, ... 的值（0 或 1）构造
+    pub fn new(reads: &[u8]) -> Self {
+        assert!(reads.len() <= 8, "最多支持 8 次读取");
+        let bits = reads
+            .iter()
+            .enumerate()
+            .fold(0, |bits, (i, &r)| bits | ((r & 1) << i));
+        Self {
+            bits,
+            len: reads.len() as u8,
+        }
+    }
+
+    /// 第 `index` 次读取（从 0 开始）的值
+    pub fn read(&self, index: usize) -> u8 {
+        assert!(index < self.len as usize);
+        (self.bits >> index) & 1
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for i in 0..self.len as usize {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "r{}={}", i + 1, self.read(i))?;
+        }
+        Ok(())
+    }
+}
+
+// ============================================================================
+// 运行与统计
+// ============================================================================
+
+/// 一种形状在一种内存顺序下的统计结果
+#[cfg(not(loom))]
+#[derive(Debug, Clone)]
+pub struct LitmusReport {
+    pub shape: Shape,
+    pub orderings: Orderings,
+    pub iterations: u64,
+    counts: BTreeMap<Outcome, u64>,
+}
+
+#[cfg(not(loom))]
+impl LitmusReport {
+    /// 某个结果出现的次数
+    pub fn count(&self, outcome: Outcome) -> u64 {
+        self.counts.get(&outcome).copied().unwrap_or(0)
+    }
+
+    /// 所有出现过的结果及次数，按结果排序
+    pub fn outcomes(&self) -> impl Iterator<Item = (Outcome, u64)> + '_ {
+        self.counts.iter().map(|(&outcome, &count)| (outcome, count))
+    }
+
+    /// 弱结果出现的次数
+    pub fn weak_count(&self) -> u64 {
+        self.count(self.shape.weak_outcome())
+    }
+
+    /// 是否观察到了内存模型禁止的结果
+    pub fn has_violation(&self) -> bool {
+        self.shape.forbids_weak(self.orderings) && self.weak_count() > 0
+    }
+}
+
+#[cfg(not(loom))]
+impl fmt::Display for LitmusReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verdict = match (self.shape.forbids_weak(self.orderings), self.weak_count()) {
+            (true, 0) => "禁止，未出现",
+            (true, _) => "禁止，却出现了！",
+            (false, 0) => "允许，未观察到",
+            (false, _) => "允许，已观察到",
+        };
+        writeln!(
+            f,
+            "{:<4} {:<34} 弱结果 {} 出现 {} 次 [{}]",
+            self.shape,
+            self.orderings.to_string(),
+            self.shape.weak_outcome(),
+            self.weak_count(),
+            verdict
+        )?;
+        for (outcome, count) in self.outcomes() {
+            writeln!(f, "       {}: {}", outcome, count)?;
+        }
+        Ok(())
+    }
+}
+
+/// 在 `orderings` 下把 `shape` 执行 `iterations` 次
+#[cfg(not(loom))]
+pub fn run(shape: Shape, orderings: Orderings, iterations: u64) -> LitmusReport {
+    let threads = shape.threads();
+    let x: Vec<AtomicU32> = (0..BATCH).map(|_| AtomicU32::new(0)).collect();
+    let y: Vec<AtomicU32> = (0..BATCH).map(|_| AtomicU32::new(0)).collect();
+    let barrier = Barrier::new(threads);
+    let batches = iterations.div_ceil(BATCH as u64);
+
+    // 每个线程记录自己每次迭代的读取结果，全部结束后再拼接统计
+    let per_thread: Vec<Vec<u8>> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|tid| {
+                let (x, y, barrier) = (&x, &y, &barrier);
+                scope.spawn(move || {
+                    let mut reads = Vec::with_capacity(iterations as usize);
+                    for batch in 0..batches {
+                        let start = batch * BATCH as u64;
+                        let len = (iterations - start).min(BATCH as u64) as usize;
+                        let stamp = batch as u32 + 1;
+                        barrier.wait();
+                        for slot in 0..len {
+                            reads.push(shape.run_thread(tid, &x[slot], &y[slot], stamp, orderings));
+                        }
+                    }
+                    reads
+                })
+            })
+            .collect();
+        workers.into_iter().map(|w| w.join().unwrap()).collect()
+    });
+
+    let mut counts = BTreeMap::new();
+    let mut bits = vec![0; threads];
+    for i in 0..iterations as usize {
+        for (tid, reads) in per_thread.iter().enumerate() {
+            bits[tid] = reads[i];
+        }
+        *counts.entry(shape.outcome(&bits)).or_insert(0) += 1;
+    }
+
+    LitmusReport {
+        shape,
+        orderings,
+        iterations,
+        counts,
+    }
+}
+
+/// 对每种形状、每种内存顺序组合各执行 `iterations` 次
+#[cfg(not(loom))]
+pub fn run_all(iterations: u64) -> Vec<LitmusReport> {
+    Shape::ALL
+        .iter()
+        .flat_map(|&shape| {
+            Orderings::all()
+                .into_iter()
+                .map(move |orderings| run(shape, orderings, iterations))
+        })
+        .collect()
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outcome_bits_follow_read_order() {
+        let outcome = Outcome::new(&[1, 0, 1, 0]);
+        assert_eq!(outcome.read(0), 1);
+        assert_eq!(outcome.read(1), 0);
+        assert_eq!(outcome.to_string(), "r1=1 r2=0 r3=1 r4=0");
+        // IRIW：写线程没有读取，两个读线程各贡献两位
+        assert_eq!(Shape::Iriw.outcome(&[0, 0, 0b01, 0b01]), outcome);
+    }
+
+    #[test]
+    fn forbidden_table_matches_memory_model() {
+        use Ordering::*;
+        let relaxed = Orderings::new(Relaxed, Relaxed);
+        let acq_rel = Orderings::new(Release, Acquire);
+        let seq_cst = Orderings::new(SeqCst, SeqCst);
+        let fenced = Orderings::relaxed_with_fence();
+
+        for shape in Shape::ALL {
+            assert!(!shape.forbids_weak(relaxed), "{} 在 Relaxed 下应允许弱结果", shape);
+            assert!(shape.forbids_weak(seq_cst), "{} 在 SeqCst 下应禁止弱结果", shape);
+            assert!(shape.forbids_weak(fenced), "{} 加栅栏后应禁止弱结果", shape);
+        }
+        // Acquire/Release 只建立 happens-before，不提供全局顺序
+        assert!(!Shape::StoreBuffering.forbids_weak(acq_rel));
+        assert!(!Shape::Iriw.forbids_weak(acq_rel));
+        assert!(Shape::MessagePassing.forbids_weak(acq_rel));
+        assert!(Shape::LoadBuffering.forbids_weak(acq_rel));
+        // 只有一边是 SeqCst 不够
+        assert!(!Shape::StoreBuffering.forbids_weak(Orderings::new(SeqCst, Acquire)));
+    }
+
+    #[test]
+    fn every_iteration_is_tallied() {
+        // 迭代次数故意不是批大小的整数倍
+        let iterations = BATCH as u64 * 2 + 123;
+        for shape in Shape::ALL {
+            let report = run(shape, Orderings::new(Ordering::Relaxed, Ordering::Relaxed), iterations);
+            let total: u64 = report.outcomes().map(|(_, count)| count).sum();
+            assert_eq!(total, iterations);
+        }
+    }
+
+    #[test]
+    fn forbidden_outcomes_never_observed() {
+        for report in run_all(20_000) {
+            assert!(!report.has_violation(), "出现了被禁止的结果:\n{}", report);
+        }
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_tests {
+    //! 运行方式：`RUSTFLAGS="--cfg loom" cargo test --release loom_`
+    //!
+    //! loom 穷举每种形状在给定内存顺序下所有可能的结果。两个已知限制：
+    //! - loom 把 SeqCst 读写当作 AcqRel 处理，会在 SB / IRIW 上给出假阳性，
+    //!   所以这里用 SeqCst 栅栏版本验证"禁止"
+    //! - loom 的读只能看到已经执行过的写，无法产生 LB 的弱结果
+    use super::*;
+    use crate::sync::Arc;
+    use loom::thread;
+    use std::collections::BTreeSet;
+    use std::sync::Mutex as StdMutex;
+
+    fn model(f: impl Fn() + Sync + Send + 'static) {
+        let mut builder = loom::model::Builder::new();
+        // 每个线程最多三次访问，3 次抢占已足够覆盖所有结果；IRIW 有 4 个线程，再大就很慢
+        builder.preemption_bound.get_or_insert(3);
+        builder.check(f);
+    }
+
+    /// 收集所有交错下出现过的结果
+    fn explore(shape: Shape, orderings: Orderings) -> BTreeSet<Outcome> {
+        let seen = std::sync::Arc::new(StdMutex::new(BTreeSet::new()));
+        let sink = seen.clone();
+        model(move || {
+            let x = Arc::new(AtomicU32::new(0));
+            let y = Arc::new(AtomicU32::new(0));
+            let handles: Vec<_> = (1..shape.threads())
+                .map(|tid| {
+                    let (x, y) = (x.clone(), y.clone());
+                    thread::spawn(move || shape.run_thread(tid, &x, &y, 1, orderings))
+                })
+                .collect();
+            let mut bits = vec![shape.run_thread(0, &x, &y, 1, orderings)];
+            bits.extend(handles.into_iter().map(|h| h.join().unwrap()));
+            sink.lock().unwrap().insert(shape.outcome(&bits));
+        });
+        let outcomes = seen.lock().unwrap().clone();
+        outcomes
+    }
+
+    fn reachable(shape: Shape, orderings: Orderings) -> bool {
+        explore(shape, orderings).contains(&shape.weak_outcome())
+    }
+
+    #[test]
+    fn loom_store_buffering() {
+        let shape = Shape::StoreBuffering;
+        assert!(reachable(shape, Orderings::new(Ordering::Relaxed, Ordering::Relaxed)));
+        assert!(reachable(shape, Orderings::new(Ordering::Release, Ordering::Acquire)));
+        assert!(!reachable(shape, Orderings::relaxed_with_fence()));
+    }
+
+    #[test]
+    fn loom_message_passing() {
+        let shape = Shape::MessagePassing;
+        assert!(reachable(shape, Orderings::new(Ordering::Relaxed, Ordering::Relaxed)));
+        assert!(!reachable(shape, Orderings::new(Ordering::Release, Ordering::Acquire)));
+        assert!(!reachable(shape, Orderings::relaxed_with_fence()));
+    }
+
+    #[test]
+    fn loom_load_buffering() {
+        let shape = Shape::LoadBuffering;
+        assert!(!reachable(shape, Orderings::new(Ordering::Release, Ordering::Acquire)));
+        assert!(!reachable(shape, Orderings::relaxed_with_fence()));
+        // 顺序一致的三种结果都应被探索到
+        assert_eq!(explore(shape, Orderings::new(Ordering::Relaxed, Ordering::Relaxed)).len(), 3);
+    }
+
+    #[test]
+    fn loom_iriw() {
+        let shape = Shape::Iriw;
+        assert!(reachable(shape, Orderings::new(Ordering::Release, Ordering::Acquire)));
+        assert!(!reachable(shape, Orderings::relaxed_with_fence()));
+    }
+}
@@ -13,6 +13,14 @@ use std::time::{Duration, Instant};
 use std::ptr;
 use std::cell::UnsafeCell;
 
+use atomic_types_memory_order::counter::StripedCounter;
+use atomic_types_memory_order::epoch::Collector;
+use atomic_types_memory_order::litmus::{self, Orderings, Shape};
+use atomic_types_memory_order::map::{Entry, LockFreeHashMap};
+use atomic_types_memory_order::padded::CachePadded;
+use atomic_types_memory_order::queue::{self, MpmcQueue};
+use atomic_types_memory_order::stack::{LockFreeStack, TaggedStack};
+
 fn main() {
     println!("{}", "=".repeat(80));
     println!("🚀 Rust 原子类型和内存顺序全面教程");
@@ -159,6 +167,10 @@ fn memory_ordering_concepts_demo() {
     // 内存重排序演示
     println!("\n🔹 内存重排序演示:");
     memory_reordering_demo();
+
+    // litmus 测试：反复执行，统计各种结果
+    println!("\n🔹 litmus 测试统计:");
+    litmus_test_demo();
 }
 
 /// Relaxed 顺序示例
@@ -292,6 +304,36 @@ fn memory_reordering_demo() {
     println!("在弱内存模型的处理器上，可能出现重排序现象");
 }
 
+/// litmus 测试演示
+///
+/// 单次运行几乎看不到重排序，这里把 SB / MP 各执行数万次并统计弱结果出现的次数；
+/// 完整的四种形状 × 十种内存顺序见 `cargo run --release --example litmus`
+fn litmus_test_demo() {
+    const ITERATIONS: u64 = 50_000;
+    let orderings = [
+        Orderings::new(Ordering::Relaxed, Ordering::Relaxed),
+        Orderings::new(Ordering::Release, Ordering::Acquire),
+        Orderings::new(Ordering::SeqCst, Ordering::SeqCst),
+        Orderings::relaxed_with_fence(),
+    ];
+
+    for shape in [Shape::StoreBuffering, Shape::MessagePassing] {
+        println!("{} 的弱结果为 {}，每种顺序执行 {} 次:", shape, shape.weak_outcome(), ITERATIONS);
+        for &ordering in &orderings {
+            let report = litmus::run(shape, ordering, ITERATIONS);
+            let verdict = if shape.forbids_weak(ordering) { "禁止" } else { "允许" };
+            println!(
+                "  {:<34} 弱结果 {:>6} 次 ({})",
+                ordering.to_string(),
+                report.weak_count(),
+                verdict
+            );
+            assert!(!report.has_violation(), "观察到被禁止的结果");
+        }
+    }
+    println!("x86 只会重排 \"写后读\"，所以通常只有 SB 能观察到弱结果；ARM 等弱内存架构上 MP 也会出现");
+}
+
 /// 3. 原子操作详解演示
 fn atomic_operations_demo() {
     println!("\n📚 3. 原子操作详解");
@@ -601,80 +643,16 @@ fn lock_free_data_structures_demo() {
     println!("\n🔹 无锁计数器实现:");
     lock_free_counter_demo();
     
+    // 分片计数器
+    println!("\n🔹 分片计数器:");
+    striped_counter_demo();
+    
     // 无锁哈希表
     println!("\n🔹 简单无锁哈希表:");
     simple_lock_free_hash_demo();
 }
 
-/// 无锁栈实现
-struct LockFreeStack<T> {
-    head: AtomicPtr<Node<T>>,
-}
-
-struct Node<T> {
-    data: T,
-    next: *mut Node<T>,
-}
-
-impl<T> LockFreeStack<T> {
-    fn new() -> Self {
-        Self {
-            head: AtomicPtr::new(ptr::null_mut()),
-        }
-    }
-    
-    fn push(&self, data: T) {
-        let new_node = Box::into_raw(Box::new(Node {
-            data,
-            next: ptr::null_mut(),
-        }));
-        
-        loop {
-            let head = self.head.load(Ordering::Relaxed);
-            unsafe {
-                (*new_node).next = head;
-            }
-            
-            match self.head.compare_exchange_weak(
-                head,
-                new_node,
-                Ordering::Release,
-                Ordering::Relaxed,
-            ) {
-                Ok(_) => break,
-                Err(_) => continue,
-            }
-        }
-    }
-    
-    fn pop(&self) -> Option<T> {
-        loop {
-            let head = self.head.load(Ordering::Acquire);
-            if head.is_null() {
-                return None;
-            }
-            
-            let next = unsafe { (*head).next };
-            
-            match self.head.compare_exchange_weak(
-                head,
-                next,
-                Ordering::Release,
-                Ordering::Relaxed,
-            ) {
-                Ok(_) => {
-                    let data = unsafe { Box::from_raw(head).data };
-                    return Some(data);
-                }
-                Err(_) => continue,
-            }
-        }
-    }
-}
-
-unsafe impl<T: Send> Send for LockFreeStack<T> {}
-unsafe impl<T: Send> Sync for LockFreeStack<T> {}
-
+/// 基于纪元回收的无锁栈：push 无需钉住，pop 必须在 guard 保护下进行
 fn lock_free_stack_demo() {
     let stack = Arc::new(LockFreeStack::new());
     let mut handles = vec![];
@@ -691,22 +669,31 @@ fn lock_free_stack_demo() {
         handles.push(handle);
     }
     
-    // 消费者线程
+    // 消费者线程：每个线程注册自己的回收句柄
     for i in 0..3 {
         let stack_clone = Arc::clone(&stack);
         let handle = thread::spawn(move || {
+            let local = stack_clone.register();
             let mut count = 0;
             while count < 15 {
-                if let Some(value) = stack_clone.pop() {
+                // guard 存活期间读到的节点不会被其他线程释放
+                let guard = local.pin();
+                if let Some(value) = stack_clone.pop(&guard) {
                     count += 1;
                     if count <= 5 {
                         println!("消费者 {} 弹出: {}", i, value);
                     }
                 } else {
+                    drop(guard);
                     thread::yield_now();
                 }
             }
-            println!("消费者 {} 完成，共弹出 {} 个元素", i, count);
+            println!(
+                "消费者 {} 完成，共弹出 {} 个元素，{} 个节点等待回收",
+                i,
+                count,
+                local.pending()
+            );
         });
         handles.push(handle);
     }
@@ -716,73 +703,18 @@ fn lock_free_stack_demo() {
     }
 }
 
-/// 简单无锁队列实现（单生产者单消费者）
-struct SimpleLockFreeQueue<T> {
-    buffer: Vec<UnsafeCell<Option<T>>>,
-    head: AtomicUsize,
-    tail: AtomicUsize,
-    capacity: usize,
-}
-
-impl<T> SimpleLockFreeQueue<T> {
-    fn new(capacity: usize) -> Self {
-        let mut buffer = Vec::with_capacity(capacity);
-        for _ in 0..capacity {
-            buffer.push(UnsafeCell::new(None));
-        }
-        
-        Self {
-            buffer,
-            head: AtomicUsize::new(0),
-            tail: AtomicUsize::new(0),
-            capacity,
-        }
-    }
-    
-    fn enqueue(&self, item: T) -> Result<(), T> {
-        let tail = self.tail.load(Ordering::Relaxed);
-        let next_tail = (tail + 1) % self.capacity;
-        
-        if next_tail == self.head.load(Ordering::Acquire) {
-            return Err(item); // 队列满
-        }
-        
-        unsafe {
-            *self.buffer[tail].get() = Some(item);
-        }
-        
-        self.tail.store(next_tail, Ordering::Release);
-        Ok(())
-    }
-    
-    fn dequeue(&self) -> Option<T> {
-        let head = self.head.load(Ordering::Relaxed);
-        
-        if head == self.tail.load(Ordering::Acquire) {
-            return None; // 队列空
-        }
-        
-        let item = unsafe { (*self.buffer[head].get()).take() };
-        let next_head = (head + 1) % self.capacity;
-        self.head.store(next_head, Ordering::Release);
-        
-        item
-    }
-}
-
-unsafe impl<T: Send> Send for SimpleLockFreeQueue<T> {}
-unsafe impl<T: Send> Sync for SimpleLockFreeQueue<T> {}
-
+/// 无锁队列演示：SPSC 的生产端 / 消费端分离，以及 Vyukov MPMC 队列
 fn lock_free_queue_demo() {
-    let queue = Arc::new(SimpleLockFreeQueue::new(100));
-    
-    let queue_producer = Arc::clone(&queue);
-    let queue_consumer = Arc::clone(&queue);
+    // SPSC：Producer / Consumer 不可克隆、操作需要 &mut self，
+    // 第二个生产者在编译期就被拒绝
+    let (mut producer, mut consumer) = queue::spsc(64);
+    println!("SPSC 队列容量: {}", producer.capacity());
     
-    // 生产者
-    let producer = thread::spawn(move || {
+    let producer_thread = thread::spawn(move || {
         for i in 0..50 {
-            while queue_producer.enqueue(i).is_err() {
+            let mut item = i;
+            while let Err(back) = producer.push(item) {
+                item = back;
                 thread::yield_now();
             }
             if i < 10 {
@@ -792,11 +724,10 @@ fn lock_free_queue_demo() {
         println!("生产者完成，共入队 50 个元素");
     });
     
-    // 消费者
-    let consumer = thread::spawn(move || {
+    let consumer_thread = thread::spawn(move || {
         let mut count = 0;
         while count < 50 {
-            if let Some(item) = queue_consumer.dequeue() {
+            if let Some(item) = consumer.pop() {
                 count += 1;
                 if count <= 10 {
                     println!("出队: {}", item);
@@ -805,11 +736,63 @@ fn lock_free_queue_demo() {
                 thread::yield_now();
             }
         }
-        println!("消费者完成，共出队 {} 个元素", count);
+        println!("消费者完成，共出队 {} 个元素，剩余 {}", count, consumer.len());
     });
     
-    producer.join().unwrap();
-    consumer.join().unwrap();
+    producer_thread.join().unwrap();
+    consumer_thread.join().unwrap();
+    
+    // MPMC：多个生产者和消费者共享同一个队列
+    let queue = Arc::new(MpmcQueue::new(16));
+    let producers = 3;
+    let per_producer = 1000u64;
+    let total = producers * per_producer;
+    let consumed = Arc::new(AtomicU64::new(0));
+    println!("\nMPMC 队列容量: {}，{} 个生产者 / 2 个消费者", queue.capacity(), producers);
+    
+    let mut handles = Vec::new();
+    for p in 0..producers {
+        let queue = Arc::clone(&queue);
+        handles.push(thread::spawn(move || {
+            let mut rejected = 0;
+            for i in 0..per_producer {
+                let mut item = p * per_producer + i;
+                while let Err(back) = queue.push(item) {
+                    item = back;
+                    rejected += 1;
+                    thread::yield_now();
+                }
+            }
+            println!("生产者 {} 完成，队列满被拒绝 {} 次", p, rejected);
+            0
+        }));
+    }
+    for c in 0..2 {
+        let queue = Arc::clone(&queue);
+        let consumed = Arc::clone(&consumed);
+        handles.push(thread::spawn(move || {
+            let mut sum = 0;
+            while consumed.load(Ordering::Relaxed) < total {
+                if let Some(item) = queue.pop() {
+                    sum += item;
+                    consumed.fetch_add(1, Ordering::Relaxed);
+                } else {
+                    thread::yield_now();
+                }
+            }
+            println!("消费者 {} 完成", c);
+            sum
+        }));
+    }
+    
+    let sum: u64 = handles.into_iter().map(|h| h.join().unwrap()).sum();
+    println!(
+        "共出队 {} 个元素，校验和 {} (期望 {})，队列是否为空: {}",
+        consumed.load(Ordering::Relaxed),
+        sum,
+        (0..total).sum::<u64>(),
+        queue.is_empty()
+    );
 }
 
 /// 无锁计数器演示
@@ -888,119 +871,110 @@ fn lock_free_counter_demo() {
     println!("期望值: {} (5*1000 - 3*500 = 3500)", 5 * 1000 - 3 * 500);
 }
 
-/// 简单无锁哈希表演示
-fn simple_lock_free_hash_demo() {
-    const BUCKET_COUNT: usize = 16;
-    
-    struct SimpleLockFreeHashMap {
-        buckets: [AtomicPtr<HashNode>; BUCKET_COUNT],
-    }
-    
-    struct HashNode {
-        key: u32,
-        value: u32,
-        next: *mut HashNode,
-    }
-    
-    impl SimpleLockFreeHashMap {
-        fn new() -> Self {
-            const NULL_PTR: AtomicPtr<HashNode> = AtomicPtr::new(ptr::null_mut());
-            Self {
-                buckets: [NULL_PTR; BUCKET_COUNT],
-            }
-        }
-        
-        fn hash(&self, key: u32) -> usize {
-            (key as usize) % BUCKET_COUNT
-        }
-        
-        fn insert(&self, key: u32, value: u32) {
-            let bucket_idx = self.hash(key);
-            let new_node = Box::into_raw(Box::new(HashNode {
-                key,
-                value,
-                next: ptr::null_mut(),
-            }));
-            
-            loop {
-                let head = self.buckets[bucket_idx].load(Ordering::Acquire);
-                unsafe {
-                    (*new_node).next = head;
-                }
-                
-                match self.buckets[bucket_idx].compare_exchange_weak(
-                    head,
-                    new_node,
-                    Ordering::Release,
-                    Ordering::Relaxed,
-                ) {
-                    Ok(_) => break,
-                    Err(_) => continue,
+/// 分片计数器演示
+///
+/// 对比所有线程争用同一个 `AtomicU64` 与按线程分片的 `StripedCounter`
+fn striped_counter_demo() {
+    const THREADS: usize = 4;
+    const PER_THREAD: u64 = 200_000;
+
+    let single = AtomicU64::new(0);
+    let start = Instant::now();
+    thread::scope(|scope| {
+        for _ in 0..THREADS {
+            scope.spawn(|| {
+                for _ in 0..PER_THREAD {
+                    single.fetch_add(1, Ordering::Relaxed);
                 }
-            }
+            });
         }
-        
-        fn get(&self, key: u32) -> Option<u32> {
-            let bucket_idx = self.hash(key);
-            let mut current = self.buckets[bucket_idx].load(Ordering::Acquire);
-            
-            while !current.is_null() {
-                unsafe {
-                    if (*current).key == key {
-                        return Some((*current).value);
-                    }
-                    current = (*current).next;
+    });
+    let single_time = start.elapsed();
+
+    let striped = StripedCounter::new();
+    let start = Instant::now();
+    thread::scope(|scope| {
+        for _ in 0..THREADS {
+            scope.spawn(|| {
+                for _ in 0..PER_THREAD {
+                    striped.increment();
                 }
-            }
-            
-            None
+            });
         }
-    }
-    
-    let hashmap = Arc::new(SimpleLockFreeHashMap::new());
+    });
+    let striped_time = start.elapsed();
+
+    println!("单个 AtomicU64: {} 耗时 {:?}", single.load(Ordering::Relaxed), single_time);
+    println!(
+        "StripedCounter ({} 个分片): {} 耗时 {:?}",
+        striped.stripes(),
+        striped.sum(),
+        striped_time
+    );
+    println!("分片计数器写入几乎无争用，代价是 sum() 需要遍历所有分片");
+}
+
+/// 无锁哈希表演示
+///
+/// 使用 `map::LockFreeHashMap`：基于 split-ordered list，支持并发插入、原地更新、
+/// 删除和自动扩容，被删除的节点通过 epoch 回收
+fn simple_lock_free_hash_demo() {
+    let hashmap = Arc::new(LockFreeHashMap::<u32, u32>::new());
+    println!("初始桶数: {}", hashmap.bucket_count());
     let mut handles = vec![];
-    
+
     // 插入线程
     for i in 0..4 {
         let hashmap_clone = Arc::clone(&hashmap);
         let handle = thread::spawn(move || {
+            let handle = hashmap_clone.register();
             for j in 0..25 {
                 let key = i * 25 + j;
                 let value = key * 10;
-                hashmap_clone.insert(key, value);
+                let guard = handle.pin();
+                hashmap_clone.insert(key, value, &guard);
             }
             println!("插入线程 {} 完成", i);
         });
         handles.push(handle);
     }
-    
+
     for handle in handles {
         handle.join().unwrap();
     }
-    
+
+    println!("插入 100 个元素后: len = {}, 桶数 = {}", hashmap.len(), hashmap.bucket_count());
+
+    let local = hashmap.register();
+    let guard = local.pin();
+
     // 验证插入的数据
     println!("验证插入的数据:");
-    for i in 0..10 {
-        if let Some(value) = hashmap.get(i) {
+    for i in 0..5 {
+        if let Some(value) = hashmap.get(&i, &guard) {
             println!("key: {}, value: {}", i, value);
         }
     }
-    
-    // 统计每个桶的元素数量
-    println!("\n桶分布统计:");
-    for i in 0..BUCKET_COUNT {
-        let mut count = 0;
-        let mut current = hashmap.buckets[i].load(Ordering::Acquire);
-        while !current.is_null() {
-            count += 1;
-            unsafe {
-                current = (*current).next;
-            }
-        }
-        if count > 0 {
-            println!("桶 {}: {} 个元素", i, count);
-        }
+
+    // 原地更新：返回旧值
+    let old = hashmap.insert(1, 111, &guard).copied();
+    println!("更新 key 1: 旧值 {:?} -> 新值 {:?}", old, hashmap.get(&1, &guard));
+
+    // 删除：旧值在 guard 存活期间仍然可读
+    let removed = hashmap.remove(&2, &guard).copied();
+    println!("删除 key 2: {:?}, 之后 contains_key = {}", removed, hashmap.contains_key(&2, &guard));
+
+    // Entry API："不存在才插入"
+    let existing = *hashmap.entry(3, &guard).or_insert(0);
+    let inserted = *hashmap.entry(1000, &guard).or_insert_with(|| 42);
+    println!("entry(3).or_insert(0) = {} (已存在，保持原值)", existing);
+    println!("entry(1000).or_insert_with(|| 42) = {} (新插入)", inserted);
+    if let Entry::Occupied(entry) = hashmap.entry(1000, &guard) {
+        let removed = entry.remove().copied();
+        println!("通过 OccupiedEntry 删除 key {}: {:?}", 1000, removed);
     }
+
+    println!("最终: len = {}, 桶数 = {}", hashmap.len(), hashmap.bucket_count());
 }
 
 /// 6. 性能分析演示
@@ -1213,12 +1187,40 @@ fn cache_line_analysis() {
         println!("计数器 {}: {}", i, counter.load(Ordering::Relaxed));
     }
     
+    // 对比：每个线程只写自己的计数器，紧密排列与缓存行填充
+    println!("\n测试伪共享:");
+    let packed: [AtomicUsize; 4] = Default::default();
+    let padded: [CachePadded<AtomicUsize>; 4] = Default::default();
+    let packed_time = own_slot_increments(&packed.iter().collect::<Vec<_>>());
+    let padded_time = own_slot_increments(&padded.iter().map(|slot| &**slot).collect::<Vec<_>>());
+    println!("紧密排列 (每个 {} 字节): {:?}", std::mem::size_of::<AtomicUsize>(), packed_time);
+    println!(
+        "CachePadded (每个 {} 字节): {:?}",
+        std::mem::size_of::<CachePadded<AtomicUsize>>(),
+        padded_time
+    );
+    
     println!("\n缓存行优化建议:");
     println!("• 使用 #[repr(align(64))] 对齐到缓存行边界");
     println!("• 避免多个线程频繁访问同一缓存行的不同原子变量");
     println!("• 考虑使用填充（padding）分离热点数据");
 }
 
+/// 每个线程对 `slots` 中属于自己的计数器加一，返回总耗时
+fn own_slot_increments(slots: &[&AtomicUsize]) -> Duration {
+    let start = Instant::now();
+    thread::scope(|scope| {
+        for &slot in slots {
+            scope.spawn(move || {
+                for _ in 0..100_000 {
+                    slot.fetch_add(1, Ordering::Relaxed);
+                }
+            });
+        }
+    });
+    start.elapsed()
+}
+
 /// 7. 高级模式演示
 fn advanced_patterns_demo() {
     println!("\n📚 7. 高级模式");
@@ -1360,6 +1362,51 @@ fn aba_problem_demo() {
      
      let (final_value, final_version) = example.get();
      println!("最终状态: 值 = {}, 版本 = {}", final_value, final_version);
+     
+     // 上面的版本号与值分属两个原子变量，并不能一起原子地比较。
+     // TaggedStack 把版本号打包进栈顶指针的高位，一次 CAS 同时校验两者
+     println!("\n解决方案: 带版本号的指针 (TaggedStack)");
+     tagged_stack_demo();
+}
+
+/// 多个线程反复弹出再压回，同一批节点被不断复用，是最容易触发 ABA 的场景
+fn tagged_stack_demo() {
+    let stack = Arc::new(TaggedStack::new());
+    for i in 0..8u64 {
+        stack.push(i);
+    }
+    let expected_sum: u64 = (0..8).sum();
+    
+    let start = Instant::now();
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let stack = Arc::clone(&stack);
+            thread::spawn(move || {
+                let mut cycles = 0;
+                for _ in 0..50_000 {
+                    if let Some(value) = stack.pop() {
+                        stack.push(value);
+                        cycles += 1;
+                    }
+                }
+                cycles
+            })
+        })
+        .collect();
+    let cycles: u64 = handles.into_iter().map(|h| h.join().unwrap()).sum();
+    
+    let mut values = Vec::new();
+    while let Some(value) = stack.pop() {
+        values.push(value);
+    }
+    let sum: u64 = values.iter().sum();
+    println!("{} 次弹出/压回循环，耗时 {:?}", cycles, start.elapsed());
+    println!("剩余元素 {} 个，校验和 {} (期望 {})", values.len(), sum, expected_sum);
+    if values.len() == 8 && sum == expected_sum {
+        println!("✅ 没有元素丢失或重复，tag 让过时的 CAS 全部失败");
+    } else {
+        println!("❌ 栈被 ABA 破坏");
+    }
 }
 
 /// 内存回收策略演示
@@ -1409,6 +1456,56 @@ fn memory_reclamation_demo() {
     
     // 注意：这里 data 仍然存在，所以不会被回收
     println!("原始数据仍存在: {}", data.data);
+    
+    // 纪元回收：退休的对象要等所有钉住的线程离开旧纪元后才释放
+    println!("\n纪元回收 (EBR) 演示:");
+    epoch_reclamation_demo();
+}
+
+/// 用一个长期钉住的读者观察纪元推进和延迟释放
+fn epoch_reclamation_demo() {
+    struct Tracked(u32);
+    
+    impl Drop for Tracked {
+        fn drop(&mut self) {
+            println!("  对象 {} 被安全释放", self.0);
+        }
+    }
+    
+    let collector = Collector::new();
+    let reader = collector.register();
+    let writer = collector.register();
+    
+    let read_guard = reader.pin();
+    println!("读者在纪元 {} 钉住", collector.epoch());
+    
+    {
+        let guard = writer.pin();
+        for id in 0..3 {
+            let retired = Box::into_raw(Box::new(Tracked(id)));
+            // 对象已"摘除"，但读者可能仍持有它的指针
+            unsafe { guard.defer_destroy(retired) };
+        }
+    }
+    for _ in 0..3 {
+        writer.flush();
+    }
+    println!(
+        "读者仍钉住: 纪元 = {}, 待回收 = {}",
+        collector.epoch(),
+        writer.pending()
+    );
+    
+    drop(read_guard);
+    println!("读者解除钉住");
+    for _ in 0..2 {
+        writer.flush();
+    }
+    println!(
+        "回收之后: 纪元 = {}, 待回收 = {}",
+        collector.epoch(),
+        writer.pending()
+    );
 }
 
 /// 无锁算法设计模式演示
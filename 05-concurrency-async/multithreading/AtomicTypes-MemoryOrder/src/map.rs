@@ -0,0 +1,880 @@
+//! 可扩容的无锁哈希表（split-ordered list）
+//!
+//! 所有元素串在同一条按"分裂序"排序的无锁链表上：哈希值按位反转后作为排序键，
+//! 桶只是指向链表中哨兵节点的快捷入口。桶数翻倍时不需要搬移任何元素——
+//! 新桶 `b` 的哨兵在第一次被访问时插入到父桶 `b - 2^k` 的链段中间，
+//! 原链段就自然一分为二。这样扩容被摊到后续的每次访问上，不存在"停下来整体 rehash"。
+//!
+//! - 删除：先把节点的值指针 CAS 为空（线性化点），再在 `next` 上打删除标记，
+//!   最后由任意一次遍历把它从链表中摘除；
+//! - 原地更新：对已有节点的值指针做 CAS，旧值交给纪元回收；
+//! - 内存回收：摘下的节点和被替换的旧值都通过 [`crate::epoch`] 延迟释放，
+//!   因此 `get` 返回的引用在 guard 存活期间始终有效。
+
+use std::borrow::Borrow;
+use std::hash::{BuildHasher, Hash};
+use std::ptr;
+
+use crate::epoch::{Collector, Guard, LocalHandle};
+use crate::sync::{AtomicPtr, AtomicUsize, Ordering};
+
+#[cfg(not(loom))]
+type MapHasher = std::collections::hash_map::RandomState;
+/// loom 要求每次执行完全确定，随机种子会让不同执行走上不同的路径
+#[cfg(loom)]
+type MapHasher = std::hash::BuildHasherDefault<std::collections::hash_map::DefaultHasher>;
+
+/// 平均每个桶超过这么多元素时桶数翻倍
+const LOAD_FACTOR: usize = 2;
+
+/// 第 0 段只有桶 0，第 k 段（k >= 1）存放桶 `[2^(k-1), 2^k)`
+const SEGMENTS: usize = usize::BITS as usize;
+
+/// 普通节点排序键的最低位为 1，哨兵为 0，保证同一位置哨兵总排在普通节点之前
+const REGULAR_BIT: usize = 1 << (usize::BITS - 1);
+
+fn regular_key(hash: usize) -> usize {
+    (hash | REGULAR_BIT).reverse_bits()
+}
+
+fn dummy_key(bucket: usize) -> usize {
+    bucket.reverse_bits()
+}
+
+/// 桶号 -> (段号, 段内偏移)
+fn segment_of(bucket: usize) -> (usize, usize) {
+    if bucket == 0 {
+        return (0, 0);
+    }
+    let segment = (usize::BITS - bucket.leading_zeros()) as usize;
+    (segment, bucket - (1 << (segment - 1)))
+}
+
+fn segment_len(segment: usize) -> usize {
+    if segment == 0 { 1 } else { 1 << (segment - 1) }
+}
+
+/// 父桶：去掉最高位的 1
+fn parent_bucket(bucket: usize) -> usize {
+    bucket & !(1 << (usize::BITS - 1 - bucket.leading_zeros()))
+}
+
+// 链表指针的最低位用作删除标记
+fn is_marked<T>(ptr: *mut T) -> bool {
+    ptr.addr() & 1 == 1
+}
+
+fn with_mark<T>(ptr: *mut T) -> *mut T {
+    ptr.map_addr(|addr| addr | 1)
+}
+
+fn without_mark<T>(ptr: *mut T) -> *mut T {
+    ptr.map_addr(|addr| addr & !1)
+}
+
+// ============================================================================
+// 链表节点
+// ============================================================================
+
+struct Node<K, V> {
+    so_key: usize,
+    /// 哨兵节点没有键
+    key: Option<K>,
+    /// 为空表示已删除（或是哨兵）
+    value: AtomicPtr<V>,
+    next: AtomicPtr<Node<K, V>>,
+}
+
+impl<K, V> Node<K, V> {
+    fn dummy(so_key: usize) -> Self {
+        Self {
+            so_key,
+            key: None,
+            value: AtomicPtr::new(ptr::null_mut()),
+            next: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+
+    fn matches<Q>(&self, so_key: usize, key: Option<&Q>) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        if self.so_key != so_key {
+            return false;
+        }
+        match (&self.key, key) {
+            (Some(own), Some(key)) => own.borrow() == key,
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    /// 在 `next` 上打删除标记，之后任何以它为前驱的 CAS 都会失败
+    fn mark_deleted(&self) {
+        let mut next = self.next.load(Ordering::Acquire);
+        while !is_marked(next) {
+            match self.next.compare_exchange_weak(
+                next,
+                with_mark(next),
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => return,
+                Err(actual) => next = actual,
+            }
+        }
+    }
+}
+
+impl<K, V> Drop for Node<K, V> {
+    fn drop(&mut self) {
+        let value = self.value.load(Ordering::Relaxed);
+        if !value.is_null() {
+            drop(unsafe { Box::from_raw(value) });
+        }
+    }
+}
+
+/// 一次插入的结果
+enum Inserted<'g, V> {
+    /// 新建了节点
+    New(&'g V),
+    /// 覆盖了已有的值
+    Replaced { old: &'g V },
+    /// 键已存在且不允许覆盖
+    Existing(&'g V),
+}
+
+// ============================================================================
+// 哈希表
+// ============================================================================
+
+/// 可扩容、支持删除和原地更新的无锁哈希表
+///
+/// 与 `HashMap` 的主要区别是所有操作都需要一个 guard（来自 [`LockFreeHashMap::register`]
+/// 返回的句柄），返回的引用在 guard 存活期间有效。
+pub struct LockFreeHashMap<K, V> {
+    segments: [AtomicPtr<AtomicPtr<Node<K, V>>>; SEGMENTS],
+    bucket_count: AtomicUsize,
+    len: AtomicUsize,
+    hasher: MapHasher,
+    collector: Collector,
+}
+
+unsafe impl<K: Send + Sync, V: Send + Sync> Send for LockFreeHashMap<K, V> {}
+unsafe impl<K: Send + Sync, V: Send + Sync> Sync for LockFreeHashMap<K, V> {}
+
+impl<K, V> LockFreeHashMap<K, V>
+where
+    K: Hash + Eq + Send + 'static,
+    V: Send + 'static,
+{
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    /// 预先设置足够容纳 `capacity` 个元素而不扩容的桶数
+    pub fn with_capacity(capacity: usize) -> Self {
+        let map = Self {
+            segments: std::array::from_fn(|_| AtomicPtr::new(ptr::null_mut())),
+            bucket_count: AtomicUsize::new(capacity.div_ceil(LOAD_FACTOR).next_power_of_two()),
+            len: AtomicUsize::new(0),
+            hasher: MapHasher::default(),
+            collector: Collector::new(),
+        };
+        // 桶 0 的哨兵就是整条链表的表头
+        let head = Box::into_raw(Box::new(Node::dummy(dummy_key(0))));
+        map.bucket_slot(0).store(head, Ordering::Release);
+        map
+    }
+
+    /// 为当前线程注册回收句柄
+    pub fn register(&self) -> LocalHandle {
+        self.collector.register()
+    }
+
+    /// 元素个数；并发修改时只是一个快照
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 当前桶数（逻辑上的，尚未访问的桶还没有哨兵）
+    pub fn bucket_count(&self) -> usize {
+        self.bucket_count.load(Ordering::Relaxed)
+    }
+
+    pub fn get<'g, Q>(&'g self, key: &Q, guard: &'g Guard<'_>) -> Option<&'g V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.check_guard(guard);
+        let hash = self.hash(key);
+        let so_key = regular_key(hash);
+        let head = self.bucket_head(self.bucket_of(hash), guard);
+        let (_, current) = self.find(head, so_key, Some(key), guard);
+
+        let node = unsafe { current.as_ref() }?;
+        if !node.matches(so_key, Some(key)) {
+            return None;
+        }
+        // Acquire：与写入该值的 CAS 配对，保证读到完整初始化的值
+        unsafe { node.value.load(Ordering::Acquire).as_ref() }
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q, guard: &Guard<'_>) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get(key, guard).is_some()
+    }
+
+    /// 插入或原地更新，返回旧值
+    pub fn insert<'g>(&'g self, key: K, value: V, guard: &'g Guard<'_>) -> Option<&'g V> {
+        match self.insert_impl(key, value, true, guard) {
+            Inserted::New(_) => None,
+            Inserted::Replaced { old } => Some(old),
+            Inserted::Existing(_) => unreachable!("覆盖模式不会返回 Existing"),
+        }
+    }
+
+    /// 删除并返回旧值；旧值在 guard 存活期间仍可访问
+    pub fn remove<'g, Q>(&'g self, key: &Q, guard: &'g Guard<'_>) -> Option<&'g V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.check_guard(guard);
+        let hash = self.hash(key);
+        let so_key = regular_key(hash);
+        let head = self.bucket_head(self.bucket_of(hash), guard);
+        let (_, current) = self.find(head, so_key, Some(key), guard);
+
+        let node = unsafe { current.as_ref() }?;
+        if !node.matches(so_key, Some(key)) {
+            return None;
+        }
+
+        let mut value = node.value.load(Ordering::Acquire);
+        loop {
+            if value.is_null() {
+                // 已被并发删除
+                return None;
+            }
+            match node.value.compare_exchange_weak(
+                value,
+                ptr::null_mut(),
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => break,
+                Err(actual) => value = actual,
+            }
+        }
+
+        node.mark_deleted();
+        self.len.fetch_sub(1, Ordering::Relaxed);
+        unsafe { guard.defer_destroy(value) };
+        // 再遍历一次，顺手把节点从链表中摘除
+        self.find(head, so_key, Some(key), guard);
+        Some(unsafe { &*value })
+    }
+
+    /// 获取键对应的条目，用于"不存在才插入"等组合操作
+    pub fn entry<'g>(&'g self, key: K, guard: &'g Guard<'_>) -> Entry<'g, K, V> {
+        match self.get(&key, guard) {
+            Some(value) => Entry::Occupied(OccupiedEntry {
+                map: self,
+                key,
+                value,
+                guard,
+            }),
+            None => Entry::Vacant(VacantEntry {
+                map: self,
+                key,
+                guard,
+            }),
+        }
+    }
+
+    fn check_guard(&self, guard: &Guard<'_>) {
+        assert!(
+            guard.belongs_to(&self.collector),
+            "guard 必须来自 LockFreeHashMap::register 返回的句柄"
+        );
+    }
+
+    fn hash<Q: Hash + ?Sized>(&self, key: &Q) -> usize {
+        // 最高位留给 REGULAR_BIT
+        (self.hasher.hash_one(key) as usize) & !REGULAR_BIT
+    }
+
+    fn bucket_of(&self, hash: usize) -> usize {
+        hash & (self.bucket_count.load(Ordering::Relaxed) - 1)
+    }
+
+    fn insert_impl<'g>(
+        &'g self,
+        key: K,
+        value: V,
+        overwrite: bool,
+        guard: &'g Guard<'_>,
+    ) -> Inserted<'g, V> {
+        self.check_guard(guard);
+        let hash = self.hash(&key);
+        let so_key = regular_key(hash);
+        let head = self.bucket_head(self.bucket_of(hash), guard);
+
+        // 先把节点建好，重试时复用；如果最终只是更新已有节点，再把它丢弃
+        let new_value = Box::into_raw(Box::new(value));
+        let new_node = Box::into_raw(Box::new(Node {
+            so_key,
+            key: Some(key),
+            value: AtomicPtr::new(new_value),
+            next: AtomicPtr::new(ptr::null_mut()),
+        }));
+        let key = unsafe { (*new_node).key.as_ref().unwrap() };
+
+        loop {
+            let (prev, current) = self.find(head, so_key, Some(key), guard);
+
+            if let Some(node) = unsafe { current.as_ref() }
+                && node.matches(so_key, Some(key))
+            {
+                let old = node.value.load(Ordering::Acquire);
+                if old.is_null() {
+                    // 节点正在被删除：帮它打上标记，下一轮遍历会把它摘掉
+                    node.mark_deleted();
+                    continue;
+                }
+                if !overwrite {
+                    drop(unsafe { Box::from_raw(new_node) });
+                    return Inserted::Existing(unsafe { &*old });
+                }
+                if node
+                    .value
+                    .compare_exchange(old, new_value, Ordering::AcqRel, Ordering::Acquire)
+                    .is_ok()
+                {
+                    unsafe {
+                        // 新值已归已有节点所有，丢弃临时节点前先解除它对值的所有权
+                        (*new_node).value.store(ptr::null_mut(), Ordering::Relaxed);
+                        drop(Box::from_raw(new_node));
+                        guard.defer_destroy(old);
+                        return Inserted::Replaced { old: &*old };
+                    }
+                }
+                continue;
+            }
+
+            unsafe { (*new_node).next.store(current, Ordering::Relaxed) };
+            // Release：发布节点的键、值和 next
+            if prev
+                .compare_exchange(current, new_node, Ordering::Release, Ordering::Relaxed)
+                .is_ok()
+            {
+                let len = self.len.fetch_add(1, Ordering::Relaxed) + 1;
+                self.maybe_grow(len);
+                return Inserted::New(unsafe { &*new_value });
+            }
+        }
+    }
+
+    /// 负载过高时把桶数翻倍；新桶的哨兵在第一次访问时才插入
+    fn maybe_grow(&self, len: usize) {
+        let buckets = self.bucket_count.load(Ordering::Relaxed);
+        if len > buckets * LOAD_FACTOR && buckets < REGULAR_BIT {
+            // 失败说明别人已经扩过了
+            let _ = self.bucket_count.compare_exchange(
+                buckets,
+                buckets * 2,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            );
+        }
+    }
+
+    /// 从 `start` 开始查找第一个排序位置不早于 `(so_key, key)` 的节点，返回 `(前驱的 next, 该节点)`
+    ///
+    /// 途中遇到带删除标记的节点会尝试摘除并交给纪元回收。
+    fn find<'g, Q>(
+        &'g self,
+        start: &'g Node<K, V>,
+        so_key: usize,
+        key: Option<&Q>,
+        guard: &'g Guard<'_>,
+    ) -> (&'g AtomicPtr<Node<K, V>>, *mut Node<K, V>)
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        'retry: loop {
+            // 哨兵从不删除，它的 next 不会带标记
+            let mut prev = &start.next;
+            let mut current = prev.load(Ordering::Acquire);
+
+            while let Some(node) = unsafe { current.as_ref() } {
+                let next = node.next.load(Ordering::Acquire);
+                if is_marked(next) {
+                    let next = without_mark(next);
+                    if prev
+                        .compare_exchange(current, next, Ordering::AcqRel, Ordering::Acquire)
+                        .is_err()
+                    {
+                        // 前驱变了或者它自己也被删除了，从头再来
+                        continue 'retry;
+                    }
+                    unsafe { guard.defer_destroy(current) };
+                    current = next;
+                    continue;
+                }
+
+                if node.so_key > so_key || node.matches(so_key, key) {
+                    return (prev, current);
+                }
+                prev = &node.next;
+                current = next;
+            }
+            return (prev, current);
+        }
+    }
+
+    fn bucket_slot(&self, bucket: usize) -> &AtomicPtr<Node<K, V>> {
+        let (segment, offset) = segment_of(bucket);
+        let slots = &self.segments[segment];
+        let mut base = slots.load(Ordering::Acquire);
+        if base.is_null() {
+            let len = segment_len(segment);
+            let fresh: Box<[AtomicPtr<Node<K, V>>]> =
+                (0..len).map(|_| AtomicPtr::new(ptr::null_mut())).collect();
+            let fresh = Box::into_raw(fresh).cast::<AtomicPtr<Node<K, V>>>();
+            match slots.compare_exchange(ptr::null_mut(), fresh, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => base = fresh,
+                Err(existing) => {
+                    drop(unsafe { Box::from_raw(ptr::slice_from_raw_parts_mut(fresh, len)) });
+                    base = existing;
+                }
+            }
+        }
+        unsafe { &*base.add(offset) }
+    }
+
+    fn bucket_head<'g>(&'g self, bucket: usize, guard: &'g Guard<'_>) -> &'g Node<K, V> {
+        let head = self.bucket_slot(bucket).load(Ordering::Acquire);
+        match unsafe { head.as_ref() } {
+            Some(head) => head,
+            None => self.initialize_bucket(bucket, guard),
+        }
+    }
+
+    /// 在父桶的链段中插入新桶的哨兵，相当于把父桶一分为二
+    fn initialize_bucket<'g>(&'g self, bucket: usize, guard: &'g Guard<'_>) -> &'g Node<K, V> {
+        let parent = self.bucket_head(parent_bucket(bucket), guard);
+        let so_key = dummy_key(bucket);
+        let dummy = Box::into_raw(Box::new(Node::dummy(so_key)));
+
+        let node = loop {
+            let (prev, current) = self.find::<K>(parent, so_key, None, guard);
+            if let Some(existing) = unsafe { current.as_ref() }
+                && existing.matches::<K>(so_key, None)
+            {
+                // 别的线程已经插入了同一个哨兵
+                drop(unsafe { Box::from_raw(dummy) });
+                break current;
+            }
+            unsafe { (*dummy).next.store(current, Ordering::Relaxed) };
+            if prev
+                .compare_exchange(current, dummy, Ordering::Release, Ordering::Relaxed)
+                .is_ok()
+            {
+                break dummy;
+            }
+        };
+
+        // 失败说明别人已经登记了同一个哨兵
+        let _ = self.bucket_slot(bucket).compare_exchange(
+            ptr::null_mut(),
+            node,
+            Ordering::Release,
+            Ordering::Relaxed,
+        );
+        unsafe { &*node }
+    }
+}
+
+impl<K, V> Default for LockFreeHashMap<K, V>
+where
+    K: Hash + Eq + Send + 'static,
+    V: Send + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> Drop for LockFreeHashMap<K, V> {
+    fn drop(&mut self) {
+        // 独占访问：链表上剩下的节点（包括已标记但未摘除的）都由这里释放，
+        // 已摘除的节点在回收器里，不会重复释放
+        let first_segment = self.segments[0].load(Ordering::Relaxed);
+        let mut current = match unsafe { first_segment.as_ref() } {
+            Some(head) => head.load(Ordering::Relaxed),
+            None => ptr::null_mut(),
+        };
+        while !current.is_null() {
+            let node = unsafe { Box::from_raw(current) };
+            current = without_mark(node.next.load(Ordering::Relaxed));
+        }
+
+        for (segment, slots) in self.segments.iter().enumerate() {
+            let base = slots.load(Ordering::Relaxed);
+            if !base.is_null() {
+                let len = segment_len(segment);
+                drop(unsafe { Box::from_raw(ptr::slice_from_raw_parts_mut(base, len)) });
+            }
+        }
+    }
+}
+
+// ============================================================================
+// Entry API
+// ============================================================================
+
+/// 与 `HashMap::entry` 对应的条目；创建后其他线程仍可能修改同一个键，
+/// 条目只是创建那一刻的快照
+pub enum Entry<'g, K, V> {
+    Occupied(OccupiedEntry<'g, K, V>),
+    Vacant(VacantEntry<'g, K, V>),
+}
+
+pub struct OccupiedEntry<'g, K, V> {
+    map: &'g LockFreeHashMap<K, V>,
+    key: K,
+    value: &'g V,
+    guard: &'g Guard<'g>,
+}
+
+pub struct VacantEntry<'g, K, V> {
+    map: &'g LockFreeHashMap<K, V>,
+    key: K,
+    guard: &'g Guard<'g>,
+}
+
+impl<'g, K, V> Entry<'g, K, V>
+where
+    K: Hash + Eq + Send + 'static,
+    V: Send + 'static,
+{
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    pub fn or_insert(self, default: V) -> &'g V {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_insert_with(self, default: impl FnOnce() -> V) -> &'g V {
+        match self {
+            Entry::Occupied(entry) => entry.get(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+}
+
+impl<'g, K, V> OccupiedEntry<'g, K, V>
+where
+    K: Hash + Eq + Send + 'static,
+    V: Send + 'static,
+{
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// 创建条目时读到的值
+    pub fn get(&self) -> &'g V {
+        self.value
+    }
+
+    /// 覆盖当前值并返回旧值；若期间键已被删除，则重新插入并返回 `None`
+    pub fn insert(self, value: V) -> Option<&'g V> {
+        self.map.insert(self.key, value, self.guard)
+    }
+
+    pub fn remove(self) -> Option<&'g V> {
+        self.map.remove(&self.key, self.guard)
+    }
+}
+
+impl<'g, K, V> VacantEntry<'g, K, V>
+where
+    K: Hash + Eq + Send + 'static,
+    V: Send + 'static,
+{
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// 键仍不存在时插入 `value`；若其他线程抢先插入了，则保留对方的值。
+    /// 返回最终留在表中的值
+    pub fn insert(self, value: V) -> &'g V {
+        match self.map.insert_impl(self.key, value, false, self.guard) {
+            Inserted::New(value) | Inserted::Existing(value) => value,
+            Inserted::Replaced { .. } => unreachable!("不覆盖模式不会替换已有值"),
+        }
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn segment_layout_covers_every_bucket_once() {
+        assert_eq!(segment_of(0), (0, 0));
+        assert_eq!(segment_of(1), (1, 0));
+        assert_eq!(segment_of(2), (2, 0));
+        assert_eq!(segment_of(3), (2, 1));
+        assert_eq!(segment_of(6), (3, 2));
+        assert_eq!(parent_bucket(6), 2);
+        assert_eq!(parent_bucket(1), 0);
+        // 哨兵总是排在本桶所有普通节点之前
+        assert!(dummy_key(1) < regular_key(1));
+        assert!(dummy_key(1) < regular_key(3));
+    }
+
+    #[test]
+    fn insert_get_update_remove() {
+        let map = LockFreeHashMap::new();
+        let handle = map.register();
+        let guard = handle.pin();
+
+        assert_eq!(map.insert("a".to_string(), 1, &guard), None);
+        assert_eq!(map.insert("b".to_string(), 2, &guard), None);
+        assert_eq!(map.get("a", &guard), Some(&1));
+
+        // 原地更新而不是插入重复节点
+        assert_eq!(map.insert("a".to_string(), 10, &guard), Some(&1));
+        assert_eq!(map.get("a", &guard), Some(&10));
+        assert_eq!(map.len(), 2);
+
+        assert_eq!(map.remove("a", &guard), Some(&10));
+        assert_eq!(map.remove("a", &guard), None);
+        assert!(!map.contains_key("a", &guard));
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn entry_api_mirrors_hash_map() {
+        let map = LockFreeHashMap::new();
+        let handle = map.register();
+        let guard = handle.pin();
+
+        assert_eq!(*map.entry(1, &guard).or_insert(100), 100);
+        assert_eq!(*map.entry(1, &guard).or_insert(200), 100);
+        assert_eq!(*map.entry(2, &guard).or_insert_with(|| 300), 300);
+
+        match map.entry(1, &guard) {
+            Entry::Occupied(entry) => {
+                assert_eq!(*entry.key(), 1);
+                assert_eq!(entry.insert(111), Some(&100));
+            }
+            Entry::Vacant(_) => panic!("键 1 应该存在"),
+        }
+        match map.entry(2, &guard) {
+            Entry::Occupied(entry) => assert_eq!(entry.remove(), Some(&300)),
+            Entry::Vacant(_) => panic!("键 2 应该存在"),
+        }
+        assert!(matches!(map.entry(2, &guard), Entry::Vacant(_)));
+        assert_eq!(map.get(&1, &guard), Some(&111));
+    }
+
+    #[test]
+    fn grows_incrementally_and_keeps_every_key() {
+        let map = LockFreeHashMap::new();
+        let handle = map.register();
+        let guard = handle.pin();
+        assert_eq!(map.bucket_count(), 1);
+
+        for i in 0..1000 {
+            map.insert(i, i * 2, &guard);
+        }
+        assert!(map.bucket_count() >= 1000 / LOAD_FACTOR);
+        for i in 0..1000 {
+            assert_eq!(map.get(&i, &guard), Some(&(i * 2)));
+        }
+        assert_eq!(map.len(), 1000);
+    }
+
+    #[test]
+    fn concurrent_writers_and_removers_agree_on_final_state() {
+        let map = Arc::new(LockFreeHashMap::new());
+        let threads = 4u64;
+        let per_thread = 2_000u64;
+        let shared_base = threads * per_thread;
+
+        let workers: Vec<_> = (0..threads)
+            .map(|t| {
+                let map = Arc::clone(&map);
+                thread::spawn(move || {
+                    let handle = map.register();
+                    for i in 0..per_thread {
+                        let key = t * per_thread + i;
+                        let guard = handle.pin();
+                        map.insert(key, key, &guard);
+                        // 所有线程争抢同一组共享键
+                        map.insert(shared_base + i % 64, key, &guard);
+                        if key % 2 == 1 {
+                            assert_eq!(map.remove(&key, &guard), Some(&key));
+                        }
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        let handle = map.register();
+        let guard = handle.pin();
+        for key in 0..shared_base {
+            let expected = (key % 2 == 0).then_some(&key);
+            assert_eq!(map.get(&key, &guard), expected);
+        }
+        for key in shared_base..shared_base + 64 {
+            assert!(map.get(&key, &guard).is_some());
+        }
+        assert_eq!(map.len() as u64, shared_base / 2 + 64);
+    }
+
+    #[test]
+    fn values_are_dropped_exactly_once() {
+        let marker = Arc::new(());
+        {
+            let map = LockFreeHashMap::new();
+            let handle = map.register();
+            {
+                let guard = handle.pin();
+                for i in 0..10 {
+                    map.insert(i, Arc::clone(&marker), &guard);
+                }
+                map.insert(0, Arc::clone(&marker), &guard);
+                map.remove(&1, &guard);
+            }
+            for _ in 0..3 {
+                handle.flush();
+            }
+            // 共克隆 11 次，被替换和被删除的两个旧值已经回收，剩 9 个仍在表中
+            assert_eq!(Arc::strong_count(&marker), 10);
+        }
+        assert_eq!(Arc::strong_count(&marker), 1);
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_tests {
+    //! 运行方式：`RUSTFLAGS="--cfg loom" cargo test --release loom_`
+    use super::*;
+    use loom::sync::Arc;
+    use loom::thread;
+
+    fn model(f: impl Fn() + Sync + Send + 'static) {
+        let mut builder = loom::model::Builder::new();
+        builder.preemption_bound.get_or_insert(3);
+        builder.check(f);
+    }
+
+    #[test]
+    fn loom_concurrent_insert_same_key_updates_in_place() {
+        model(|| {
+            let map = Arc::new(LockFreeHashMap::new());
+            let other = {
+                let map = Arc::clone(&map);
+                thread::spawn(move || {
+                    let handle = map.register();
+                    map.insert(7, 1, &handle.pin()).copied()
+                })
+            };
+            let handle = map.register();
+            let mine = map.insert(7, 2, &handle.pin()).copied();
+            let theirs = other.join().unwrap();
+
+            // 恰好一方看到对方的值，另一方是首次插入
+            let guard = handle.pin();
+            let last = *map.get(&7, &guard).unwrap();
+            match (mine, theirs) {
+                (None, Some(2)) => assert_eq!(last, 1),
+                (Some(1), None) => assert_eq!(last, 2),
+                outcome => panic!("不可能的结果: {:?}", outcome),
+            }
+            assert_eq!(map.len(), 1);
+        });
+    }
+
+    #[test]
+    fn loom_remove_races_with_get() {
+        model(|| {
+            let map = Arc::new(LockFreeHashMap::new());
+            {
+                let handle = map.register();
+                map.insert(1, 10, &handle.pin());
+            }
+            let reader = {
+                let map = Arc::clone(&map);
+                thread::spawn(move || {
+                    let handle = map.register();
+                    let guard = handle.pin();
+                    // 读到的引用在 guard 存活期间必须有效
+                    map.get(&1, &guard).copied()
+                })
+            };
+            let handle = map.register();
+            assert_eq!(map.remove(&1, &handle.pin()).copied(), Some(10));
+            handle.flush();
+            handle.flush();
+
+            let seen = reader.join().unwrap();
+            assert!(seen.is_none() || seen == Some(10));
+        });
+    }
+
+    #[test]
+    fn loom_bucket_split_races_with_insert() {
+        model(|| {
+            // 容量 4 -> 2 个桶；第 5 个元素触发扩容，新桶在并发访问中被初始化
+            let map = Arc::new(LockFreeHashMap::with_capacity(4));
+            {
+                let handle = map.register();
+                let guard = handle.pin();
+                for i in 0..4 {
+                    map.insert(i, i, &guard);
+                }
+            }
+            let other = {
+                let map = Arc::clone(&map);
+                thread::spawn(move || {
+                    let handle = map.register();
+                    map.insert(4, 4, &handle.pin());
+                })
+            };
+            let handle = map.register();
+            map.insert(5, 5, &handle.pin());
+            other.join().unwrap();
+
+            let guard = handle.pin();
+            for i in 0..6 {
+                assert_eq!(map.get(&i, &guard), Some(&i));
+            }
+        });
+    }
+}
@@ -0,0 +1,85 @@
+//! 缓存行填充
+//!
+//! 两个线程频繁写入的变量如果落在同一缓存行上，即使逻辑上互不相关，
+//! 缓存一致性协议也会让这一行在核心之间来回失效，这就是伪共享（false sharing）。
+//! [`CachePadded`] 把值单独放进一个（或一对）缓存行，从布局上杜绝这种干扰。
+
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+/// 对齐并填充到缓存行大小的值
+///
+/// x86_64 的相邻行预取器会成对拉取 64 字节的缓存行，aarch64 上也有 128 字节缓存行的实现，
+/// 因此这两个架构按 128 字节对齐，其余按 64 字节对齐。
+#[cfg_attr(any(target_arch = "x86_64", target_arch = "aarch64"), repr(align(128)))]
+#[cfg_attr(not(any(target_arch = "x86_64", target_arch = "aarch64")), repr(align(64)))]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct CachePadded<T> {
+    value: T,
+}
+
+impl<T> CachePadded<T> {
+    pub const fn new(value: T) -> Self {
+        Self { value }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T> Deref for CachePadded<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> DerefMut for CachePadded<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T> From<T> for CachePadded<T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for CachePadded<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CachePadded").field("value", &self.value).finish()
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use super::*;
+    use std::mem;
+    use std::sync::atomic::AtomicU64;
+
+    #[test]
+    fn occupies_whole_cache_lines() {
+        assert!(mem::align_of::<CachePadded<u8>>() >= 64);
+        assert_eq!(mem::size_of::<CachePadded<u8>>(), mem::align_of::<CachePadded<u8>>());
+        // 超过一行的值占用整数个对齐单位
+        let align = mem::align_of::<CachePadded<[u8; 200]>>();
+        assert_eq!(mem::size_of::<CachePadded<[u8; 200]>>() % align, 0);
+
+        // 数组中相邻元素不会共享缓存行
+        let counters = [CachePadded::new(AtomicU64::new(0)), CachePadded::new(AtomicU64::new(0))];
+        let first = &*counters[0] as *const AtomicU64 as usize;
+        let second = &*counters[1] as *const AtomicU64 as usize;
+        assert!(second - first >= 64);
+    }
+
+    #[test]
+    fn derefs_to_inner_value() {
+        let mut padded = CachePadded::from(vec![1, 2]);
+        padded.push(3);
+        assert_eq!(padded.len(), 3);
+        assert_eq!(padded.into_inner(), vec![1, 2, 3]);
+    }
+}
@@ -0,0 +1,483 @@
+//! 有界无锁队列
+//!
+//! - [`MpmcQueue`]：Dmitry Vyukov 的有界多生产者多消费者环形队列。每个槽位带一个序号，
+//!   生产者和消费者各自用 CAS 抢占位置，再通过槽位序号交接数据，彼此之间不争用同一个计数器；
+//! - [`spsc`]：单生产者单消费者环形队列。生产端 [`Producer`] 与消费端 [`Consumer`]
+//!   都不可克隆，且操作需要 `&mut self`，"只有一个生产者和一个消费者"由类型系统保证，
+//!   因此只需两个单调递增的下标，不需要任何 CAS。
+//!
+//! 两种队列的容量都向上取整为 2 的幂，用位与代替取模定位槽位。
+
+use std::mem::MaybeUninit;
+
+use crate::padded::CachePadded;
+use crate::sync::{Arc, AtomicUsize, Ordering, UnsafeCell};
+
+// ============================================================================
+// MPMC：Vyukov 有界队列
+// ============================================================================
+
+struct Slot<T> {
+    /// 等于 `pos` 时可写入第 `pos` 个元素，等于 `pos + 1` 时可读出它
+    sequence: AtomicUsize,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+/// 有界多生产者多消费者无锁队列
+pub struct MpmcQueue<T> {
+    buffer: Box<[Slot<T>]>,
+    mask: usize,
+    enqueue_pos: CachePadded<AtomicUsize>,
+    dequeue_pos: CachePadded<AtomicUsize>,
+}
+
+unsafe impl<T: Send> Send for MpmcQueue<T> {}
+unsafe impl<T: Send> Sync for MpmcQueue<T> {}
+
+impl<T> MpmcQueue<T> {
+    /// 创建队列，容量向上取整为 2 的幂且至少为 2
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "容量必须大于 0");
+        // 只有一个槽位时，"可写第 pos+1 个"与"可读第 pos 个"的序号相同，算法无法区分
+        let capacity = capacity.next_power_of_two().max(2);
+        let buffer = (0..capacity)
+            .map(|i| Slot {
+                sequence: AtomicUsize::new(i),
+                value: UnsafeCell::new(MaybeUninit::uninit()),
+            })
+            .collect();
+
+        Self {
+            buffer,
+            mask: capacity - 1,
+            enqueue_pos: CachePadded::new(AtomicUsize::new(0)),
+            dequeue_pos: CachePadded::new(AtomicUsize::new(0)),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// 入队；队列已满时原样返回元素
+    pub fn push(&self, value: T) -> Result<(), T> {
+        let mut pos = self.enqueue_pos.load(Ordering::Relaxed);
+        loop {
+            let slot = &self.buffer[pos & self.mask];
+            // Acquire：与消费者释放槽位时的 Release 配对，确保旧值已被取走
+            let sequence = slot.sequence.load(Ordering::Acquire);
+            let diff = sequence.wrapping_sub(pos) as isize;
+
+            if diff == 0 {
+                match self.enqueue_pos.compare_exchange_weak(
+                    pos,
+                    pos.wrapping_add(1),
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        slot.value.with_mut(|cell| unsafe { (*cell).write(value) });
+                        slot.sequence.store(pos.wrapping_add(1), Ordering::Release);
+                        return Ok(());
+                    }
+                    Err(actual) => pos = actual,
+                }
+            } else if diff < 0 {
+                // 槽位还停留在上一圈：消费者尚未取走，队列已满
+                return Err(value);
+            } else {
+                // 别的生产者已经占用了这个位置，重新读取最新下标
+                pos = self.enqueue_pos.load(Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// 出队；队列为空时返回 `None`
+    pub fn pop(&self) -> Option<T> {
+        let mut pos = self.dequeue_pos.load(Ordering::Relaxed);
+        loop {
+            let slot = &self.buffer[pos & self.mask];
+            let sequence = slot.sequence.load(Ordering::Acquire);
+            let diff = sequence.wrapping_sub(pos.wrapping_add(1)) as isize;
+
+            if diff == 0 {
+                match self.dequeue_pos.compare_exchange_weak(
+                    pos,
+                    pos.wrapping_add(1),
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        let value = slot.value.with(|cell| unsafe { (*cell).assume_init_read() });
+                        // 把槽位交给下一圈的生产者
+                        slot.sequence
+                            .store(pos.wrapping_add(self.mask + 1), Ordering::Release);
+                        return Some(value);
+                    }
+                    Err(actual) => pos = actual,
+                }
+            } else if diff < 0 {
+                // 生产者还没写入这个位置：队列为空
+                return None;
+            } else {
+                pos = self.dequeue_pos.load(Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// 近似长度：并发修改时只是一个快照
+    pub fn len(&self) -> usize {
+        let dequeue = self.dequeue_pos.load(Ordering::Relaxed);
+        let enqueue = self.enqueue_pos.load(Ordering::Relaxed);
+        enqueue.wrapping_sub(dequeue).min(self.capacity())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T> Drop for MpmcQueue<T> {
+    fn drop(&mut self) {
+        while self.pop().is_some() {}
+    }
+}
+
+// ============================================================================
+// SPSC：生产端 / 消费端分离的环形队列
+// ============================================================================
+
+struct Ring<T> {
+    buffer: Box<[UnsafeCell<MaybeUninit<T>>]>,
+    mask: usize,
+    /// 消费者下一个要读的位置，只由消费者写
+    head: CachePadded<AtomicUsize>,
+    /// 生产者下一个要写的位置，只由生产者写
+    tail: CachePadded<AtomicUsize>,
+}
+
+unsafe impl<T: Send> Send for Ring<T> {}
+unsafe impl<T: Send> Sync for Ring<T> {}
+
+impl<T> Drop for Ring<T> {
+    fn drop(&mut self) {
+        let mut head = self.head.load(Ordering::Relaxed);
+        let tail = self.tail.load(Ordering::Relaxed);
+        while head != tail {
+            self.buffer[head & self.mask].with_mut(|cell| unsafe { (*cell).assume_init_drop() });
+            head = head.wrapping_add(1);
+        }
+    }
+}
+
+/// 创建单生产者单消费者队列，容量向上取整为 2 的幂
+pub fn spsc<T>(capacity: usize) -> (Producer<T>, Consumer<T>) {
+    assert!(capacity > 0, "容量必须大于 0");
+    let capacity = capacity.next_power_of_two();
+    let ring = Arc::new(Ring {
+        buffer: (0..capacity)
+            .map(|_| UnsafeCell::new(MaybeUninit::uninit()))
+            .collect(),
+        mask: capacity - 1,
+        head: CachePadded::new(AtomicUsize::new(0)),
+        tail: CachePadded::new(AtomicUsize::new(0)),
+    });
+
+    let producer = Producer {
+        ring: Arc::clone(&ring),
+        tail: 0,
+        cached_head: 0,
+    };
+    let consumer = Consumer {
+        ring,
+        head: 0,
+        cached_tail: 0,
+    };
+    (producer, consumer)
+}
+
+/// SPSC 队列的生产端
+pub struct Producer<T> {
+    ring: Arc<Ring<T>>,
+    tail: usize,
+    /// 上次读到的消费者位置；只在看起来已满时才重新读取，减少跨核缓存流量
+    cached_head: usize,
+}
+
+impl<T> Producer<T> {
+    pub fn push(&mut self, value: T) -> Result<(), T> {
+        let capacity = self.ring.mask + 1;
+        if self.tail.wrapping_sub(self.cached_head) == capacity {
+            self.cached_head = self.ring.head.load(Ordering::Acquire);
+            if self.tail.wrapping_sub(self.cached_head) == capacity {
+                return Err(value);
+            }
+        }
+
+        self.ring.buffer[self.tail & self.ring.mask]
+            .with_mut(|cell| unsafe { (*cell).write(value) });
+        self.tail = self.tail.wrapping_add(1);
+        self.ring.tail.store(self.tail, Ordering::Release);
+        Ok(())
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.ring.mask + 1
+    }
+}
+
+/// SPSC 队列的消费端
+pub struct Consumer<T> {
+    ring: Arc<Ring<T>>,
+    head: usize,
+    /// 上次读到的生产者位置；只在看起来为空时才重新读取
+    cached_tail: usize,
+}
+
+impl<T> Consumer<T> {
+    pub fn pop(&mut self) -> Option<T> {
+        if self.head == self.cached_tail {
+            self.cached_tail = self.ring.tail.load(Ordering::Acquire);
+            if self.head == self.cached_tail {
+                return None;
+            }
+        }
+
+        let value = self.ring.buffer[self.head & self.ring.mask]
+            .with(|cell| unsafe { (*cell).assume_init_read() });
+        self.head = self.head.wrapping_add(1);
+        self.ring.head.store(self.head, Ordering::Release);
+        Some(value)
+    }
+
+    /// 当前可读的元素个数
+    pub fn len(&self) -> usize {
+        self.ring.tail.load(Ordering::Acquire).wrapping_sub(self.head)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn mpmc_is_fifo_and_reports_full() {
+        let queue = MpmcQueue::new(3);
+        assert_eq!(queue.capacity(), 4);
+        for i in 0..4 {
+            queue.push(i).unwrap();
+        }
+        assert_eq!(queue.push(99), Err(99));
+        assert_eq!(queue.len(), 4);
+
+        let drained: Vec<_> = std::iter::from_fn(|| queue.pop()).collect();
+        assert_eq!(drained, vec![0, 1, 2, 3]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn mpmc_capacity_is_at_least_two() {
+        let queue = MpmcQueue::new(1);
+        assert_eq!(queue.capacity(), 2);
+        queue.push(1).unwrap();
+        queue.push(2).unwrap();
+        assert_eq!(queue.push(3), Err(3));
+        assert_eq!(queue.pop(), Some(1));
+    }
+
+    #[test]
+    fn mpmc_many_producers_and_consumers_deliver_everything_once() {
+        let queue = std::sync::Arc::new(MpmcQueue::new(64));
+        let producers = 4;
+        let per_producer = 10_000u64;
+        let total = producers * per_producer;
+        let received = std::sync::Arc::new(std::sync::atomic::AtomicU64::new(0));
+
+        let consumers: Vec<_> = (0..4)
+            .map(|_| {
+                let queue = std::sync::Arc::clone(&queue);
+                let received = std::sync::Arc::clone(&received);
+                thread::spawn(move || {
+                    let mut sum = 0;
+                    while received.load(std::sync::atomic::Ordering::Relaxed) < total {
+                        match queue.pop() {
+                            Some(value) => {
+                                sum += value;
+                                received.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                            }
+                            None => thread::yield_now(),
+                        }
+                    }
+                    sum
+                })
+            })
+            .collect();
+
+        let producer_handles: Vec<_> = (0..producers)
+            .map(|p| {
+                let queue = std::sync::Arc::clone(&queue);
+                thread::spawn(move || {
+                    for i in 0..per_producer {
+                        let mut value = p * per_producer + i;
+                        while let Err(back) = queue.push(value) {
+                            value = back;
+                            thread::yield_now();
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        for handle in producer_handles {
+            handle.join().unwrap();
+        }
+        let sum: u64 = consumers.into_iter().map(|h| h.join().unwrap()).sum();
+        assert_eq!(sum, (0..total).sum::<u64>());
+    }
+
+    #[test]
+    fn spsc_preserves_order_across_threads() {
+        let (mut producer, mut consumer) = spsc(8);
+        assert_eq!(producer.capacity(), 8);
+
+        let handle = thread::spawn(move || {
+            for i in 0..10_000 {
+                let mut value = i;
+                while let Err(back) = producer.push(value) {
+                    value = back;
+                    thread::yield_now();
+                }
+            }
+        });
+
+        let mut expected = 0;
+        while expected < 10_000 {
+            match consumer.pop() {
+                Some(value) => {
+                    assert_eq!(value, expected);
+                    expected += 1;
+                }
+                None => thread::yield_now(),
+            }
+        }
+        handle.join().unwrap();
+        assert!(consumer.is_empty());
+    }
+
+    #[test]
+    fn queues_drop_remaining_elements() {
+        let marker = std::sync::Arc::new(());
+        {
+            let queue = MpmcQueue::new(4);
+            let (mut producer, consumer) = spsc(4);
+            for _ in 0..3 {
+                queue.push(std::sync::Arc::clone(&marker)).unwrap();
+                producer.push(std::sync::Arc::clone(&marker)).unwrap();
+            }
+            assert_eq!(consumer.len(), 3);
+            assert_eq!(std::sync::Arc::strong_count(&marker), 7);
+        }
+        assert_eq!(std::sync::Arc::strong_count(&marker), 1);
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_tests {
+    //! 运行方式：`RUSTFLAGS="--cfg loom" cargo test --release loom_`
+    use super::*;
+    use loom::thread;
+
+    fn model(f: impl Fn() + Sync + Send + 'static) {
+        let mut builder = loom::model::Builder::new();
+        builder.preemption_bound.get_or_insert(5);
+        builder.check(f);
+    }
+
+    #[test]
+    fn loom_mpmc_two_producers_one_consumer() {
+        model(|| {
+            let queue = Arc::new(MpmcQueue::new(2));
+
+            let producers: Vec<_> = (1..=2)
+                .map(|value| {
+                    let queue = Arc::clone(&queue);
+                    thread::spawn(move || queue.push(value).unwrap())
+                })
+                .collect();
+
+            let mut received = Vec::new();
+            while received.len() < 2 {
+                match queue.pop() {
+                    Some(value) => received.push(value),
+                    None => thread::yield_now(),
+                }
+            }
+            for producer in producers {
+                producer.join().unwrap();
+            }
+            received.sort_unstable();
+            assert_eq!(received, vec![1, 2]);
+        });
+    }
+
+    #[test]
+    fn loom_mpmc_slot_reuse_across_laps() {
+        model(|| {
+            // 容量为 2，第三个元素必须复用第一个槽位
+            let queue = Arc::new(MpmcQueue::new(2));
+            queue.push(1).unwrap();
+            queue.push(2).unwrap();
+
+            let consumer = {
+                let queue = Arc::clone(&queue);
+                thread::spawn(move || queue.pop())
+            };
+
+            // 与消费者竞争：要么队列仍满被拒绝，要么写进刚释放的槽位
+            let pushed = queue.push(3).is_ok();
+            let first = consumer.join().unwrap();
+            assert_eq!(first, Some(1));
+
+            let rest: Vec<_> = std::iter::from_fn(|| queue.pop()).collect();
+            if pushed {
+                assert_eq!(rest, vec![2, 3]);
+            } else {
+                assert_eq!(rest, vec![2]);
+            }
+        });
+    }
+
+    #[test]
+    fn loom_spsc_wraps_around_in_order() {
+        model(|| {
+            let (mut producer, mut consumer) = spsc(2);
+
+            let handle = thread::spawn(move || {
+                for i in 0..3 {
+                    let mut value = i;
+                    while let Err(back) = producer.push(value) {
+                        value = back;
+                        thread::yield_now();
+                    }
+                }
+            });
+
+            let mut expected = 0;
+            while expected < 3 {
+                match consumer.pop() {
+                    Some(value) => {
+                        assert_eq!(value, expected);
+                        expected += 1;
+                    }
+                    None => thread::yield_now(),
+                }
+            }
+            handle.join().unwrap();
+        });
+    }
+}
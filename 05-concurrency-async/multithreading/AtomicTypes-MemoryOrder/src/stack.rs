@@ -0,0 +1,484 @@
+//! 无锁栈的两种安全实现
+//!
+//! 朴素的 Treiber 栈在 `pop` 中读取 `(*head).next` 时，`head` 可能已被另一个线程弹出并释放，
+//! 而同一地址被重新分配后又会引出 ABA 问题。这里给出两种修复：
+//!
+//! - [`LockFreeStack`]：借助 [`crate::epoch`] 延迟释放节点。钉住期间看到的节点不会被释放，
+//!   地址也就不会被复用，释放后使用和 ABA 一并消除；
+//! - [`TaggedStack`]：栈顶指针附带一个每次修改都递增的版本号（tag），节点只回收到内部的
+//!   空闲链表、从不归还分配器，因此读取旧节点总是合法的，过时的 CAS 会因 tag 不同而失败。
+
+use std::marker::PhantomData;
+use std::mem::{ManuallyDrop, MaybeUninit};
+use std::ptr;
+
+use crate::epoch::{Collector, Guard, LocalHandle};
+use crate::sync::{AtomicPtr, AtomicU64, Ordering, UnsafeCell};
+
+// ============================================================================
+// 基于纪元回收的 Treiber 栈
+// ============================================================================
+
+struct Node<T> {
+    data: ManuallyDrop<T>,
+    next: UnsafeCell<*mut Node<T>>,
+}
+
+impl<T> Drop for Node<T> {
+    fn drop(&mut self) {
+        // 释放前登记一次写访问：在 loom 下，任何与释放没有 happens-before 关系的
+        // `next` 读取（即释放后使用）都会被报告出来
+        self.next.with_mut(|_| ());
+    }
+}
+
+/// 使用纪元回收的无锁栈
+pub struct LockFreeStack<T> {
+    head: AtomicPtr<Node<T>>,
+    collector: Collector,
+}
+
+unsafe impl<T: Send> Send for LockFreeStack<T> {}
+unsafe impl<T: Send> Sync for LockFreeStack<T> {}
+
+impl<T> LockFreeStack<T> {
+    pub fn new() -> Self {
+        Self {
+            head: AtomicPtr::new(ptr::null_mut()),
+            collector: Collector::new(),
+        }
+    }
+
+    /// 为当前线程注册回收句柄，`pop` 前需要用它钉住
+    pub fn register(&self) -> LocalHandle {
+        self.collector.register()
+    }
+
+    pub fn push(&self, data: T) {
+        let node = Box::into_raw(Box::new(Node {
+            data: ManuallyDrop::new(data),
+            next: UnsafeCell::new(ptr::null_mut()),
+        }));
+
+        // push 从不解引用共享节点，无需钉住
+        let mut head = self.head.load(Ordering::Relaxed);
+        loop {
+            unsafe { (*node).next.with_mut(|next| *next = head) };
+            match self.head.compare_exchange_weak(
+                head,
+                node,
+                Ordering::Release,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return,
+                Err(actual) => head = actual,
+            }
+        }
+    }
+
+    /// 弹出栈顶元素，`guard` 必须来自本栈注册的句柄
+    pub fn pop(&self, guard: &Guard<'_>) -> Option<T> {
+        assert!(
+            guard.belongs_to(&self.collector),
+            "guard 必须来自 LockFreeStack::register 返回的句柄"
+        );
+
+        loop {
+            let head = self.head.load(Ordering::Acquire);
+            if head.is_null() {
+                return None;
+            }
+
+            // 即使 head 已被别的线程弹出，它也要等我们解除钉住后才会被释放
+            let next = unsafe { (*head).next.with(|next| *next) };
+
+            if self
+                .head
+                .compare_exchange_weak(head, next, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+            {
+                // 只有 CAS 胜出者会取走数据，节点本身交给回收器
+                let data = unsafe { ptr::read(&*(*head).data) };
+                unsafe { guard.defer_destroy(head) };
+                return Some(data);
+            }
+        }
+    }
+}
+
+impl<T> Default for LockFreeStack<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for LockFreeStack<T> {
+    fn drop(&mut self) {
+        // 独占访问，不会再有并发读者
+        let mut current = self.head.load(Ordering::Relaxed);
+        while !current.is_null() {
+            let mut node = unsafe { Box::from_raw(current) };
+            current = node.next.with(|next| unsafe { *next });
+            unsafe { ManuallyDrop::drop(&mut node.data) };
+        }
+    }
+}
+
+// ============================================================================
+// 带版本号指针的栈
+// ============================================================================
+
+/// 高 16 位存放 tag，低 48 位存放节点地址（主流 64 位平台的用户态地址不超过 48 位）
+const TAG_SHIFT: u32 = 48;
+const ADDR_MASK: u64 = (1 << TAG_SHIFT) - 1;
+
+struct TaggedNode<T> {
+    data: UnsafeCell<MaybeUninit<T>>,
+    /// 节点可能正被复用，读到过时值时 tag 校验会让 CAS 失败，但读取本身必须是原子的
+    next: AtomicPtr<TaggedNode<T>>,
+}
+
+fn pack<T>(node: *mut TaggedNode<T>, tag: u64) -> u64 {
+    let addr = node.expose_provenance() as u64;
+    assert_eq!(addr & !ADDR_MASK, 0, "节点地址超出 48 位，无法打包 tag");
+    addr | (tag << TAG_SHIFT)
+}
+
+fn unpack<T>(word: u64) -> (*mut TaggedNode<T>, u64) {
+    let node = ptr::with_exposed_provenance_mut((word & ADDR_MASK) as usize);
+    (node, word >> TAG_SHIFT)
+}
+
+/// 一条由 `(指针, tag)` 作为栈顶的 Treiber 链表
+struct TaggedList<T> {
+    head: AtomicU64,
+    _marker: PhantomData<*mut TaggedNode<T>>,
+}
+
+impl<T> TaggedList<T> {
+    fn new() -> Self {
+        Self {
+            head: AtomicU64::new(0),
+            _marker: PhantomData,
+        }
+    }
+
+    fn push(&self, node: *mut TaggedNode<T>) {
+        let mut current = self.head.load(Ordering::Relaxed);
+        loop {
+            let (head, tag) = unpack::<T>(current);
+            unsafe { (*node).next.store(head, Ordering::Relaxed) };
+            let new = pack(node, tag.wrapping_add(1));
+            match self.head.compare_exchange_weak(
+                current,
+                new,
+                Ordering::Release,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return,
+                Err(actual) => current = actual,
+            }
+        }
+    }
+
+    fn pop(&self) -> Option<*mut TaggedNode<T>> {
+        let mut current = self.head.load(Ordering::Acquire);
+        loop {
+            let (head, tag) = unpack::<T>(current);
+            if head.is_null() {
+                return None;
+            }
+            // 节点从不释放，这里的读取总是合法的；如果 head 期间被弹出又压回（ABA），
+            // tag 已经变了，下面的 CAS 必然失败
+            let next = unsafe { (*head).next.load(Ordering::Relaxed) };
+            let new = pack(next, tag.wrapping_add(1));
+            match self.head.compare_exchange_weak(
+                current,
+                new,
+                Ordering::Acquire,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => return Some(head),
+                Err(actual) => current = actual,
+            }
+        }
+    }
+
+    /// 独占地取出整条链表
+    fn take_all(&mut self) -> *mut TaggedNode<T> {
+        let (head, _) = unpack::<T>(self.head.swap(0, Ordering::Relaxed));
+        head
+    }
+}
+
+/// 用版本号解决 ABA 的无锁栈
+///
+/// 弹出的节点进入空闲链表供后续 `push` 复用，栈占用的内存等于历史最大长度，
+/// 在栈销毁时统一释放。tag 只有 16 位，一个线程在读取栈顶与 CAS 之间恰好错过
+/// 65536 次修改才可能误判，这是版本号方案固有的权衡。
+pub struct TaggedStack<T> {
+    items: TaggedList<T>,
+    free: TaggedList<T>,
+}
+
+unsafe impl<T: Send> Send for TaggedStack<T> {}
+unsafe impl<T: Send> Sync for TaggedStack<T> {}
+
+impl<T> TaggedStack<T> {
+    pub fn new() -> Self {
+        Self {
+            items: TaggedList::new(),
+            free: TaggedList::new(),
+        }
+    }
+
+    pub fn push(&self, data: T) {
+        let node = self.free.pop().unwrap_or_else(|| {
+            Box::into_raw(Box::new(TaggedNode {
+                data: UnsafeCell::new(MaybeUninit::uninit()),
+                next: AtomicPtr::new(ptr::null_mut()),
+            }))
+        });
+        unsafe { (*node).data.with_mut(|slot| (*slot).write(data)) };
+        self.items.push(node);
+    }
+
+    pub fn pop(&self) -> Option<T> {
+        let node = self.items.pop()?;
+        let data = unsafe { (*node).data.with(|slot| (*slot).assume_init_read()) };
+        self.free.push(node);
+        Some(data)
+    }
+}
+
+impl<T> Default for TaggedStack<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for TaggedStack<T> {
+    fn drop(&mut self) {
+        for (list, initialized) in [(self.items.take_all(), true), (self.free.take_all(), false)] {
+            let mut current = list;
+            while !current.is_null() {
+                let node = unsafe { Box::from_raw(current) };
+                current = node.next.load(Ordering::Relaxed);
+                if initialized {
+                    node.data.with_mut(|slot| unsafe { (*slot).assume_init_drop() });
+                }
+            }
+        }
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn lock_free_stack_is_lifo() {
+        let stack = LockFreeStack::new();
+        let handle = stack.register();
+        for i in 0..5 {
+            stack.push(i);
+        }
+        let guard = handle.pin();
+        let popped: Vec<_> = std::iter::from_fn(|| stack.pop(&guard)).collect();
+        assert_eq!(popped, vec![4, 3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn lock_free_stack_concurrent_push_pop_keeps_every_value() {
+        let stack = Arc::new(LockFreeStack::new());
+        let threads = 4;
+        let per_thread = 2_000;
+
+        let workers: Vec<_> = (0..threads)
+            .map(|t| {
+                let stack = Arc::clone(&stack);
+                thread::spawn(move || {
+                    let handle = stack.register();
+                    let mut popped = Vec::new();
+                    for i in 0..per_thread {
+                        stack.push(t * per_thread + i);
+                        // 每次压入后立刻尝试弹出，最大化节点被并发释放的机会
+                        if let Some(value) = stack.pop(&handle.pin()) {
+                            popped.push(value);
+                        }
+                    }
+                    popped
+                })
+            })
+            .collect();
+
+        let mut seen = HashSet::new();
+        for worker in workers {
+            for value in worker.join().unwrap() {
+                assert!(seen.insert(value), "值 {} 被弹出了两次", value);
+            }
+        }
+        let handle = stack.register();
+        while let Some(value) = stack.pop(&handle.pin()) {
+            assert!(seen.insert(value));
+        }
+        assert_eq!(seen.len(), threads * per_thread);
+    }
+
+    #[test]
+    #[should_panic(expected = "guard 必须来自")]
+    fn lock_free_stack_rejects_foreign_guard() {
+        let stack: LockFreeStack<i32> = LockFreeStack::new();
+        let other = Collector::new();
+        let handle = other.register();
+        stack.pop(&handle.pin());
+    }
+
+    #[test]
+    fn stacks_drop_remaining_elements() {
+        let marker = Arc::new(());
+        {
+            let stack = LockFreeStack::new();
+            let tagged = TaggedStack::new();
+            for _ in 0..3 {
+                stack.push(Arc::clone(&marker));
+                tagged.push(Arc::clone(&marker));
+            }
+            // 弹出一个，让空闲链表里也有节点
+            drop(tagged.pop());
+            assert_eq!(Arc::strong_count(&marker), 6);
+        }
+        assert_eq!(Arc::strong_count(&marker), 1);
+    }
+
+    #[test]
+    fn tagged_stack_recycles_nodes_without_losing_values() {
+        let stack = Arc::new(TaggedStack::new());
+        for i in 0..8 {
+            stack.push(i);
+        }
+
+        // 所有线程反复弹出再压回，同一批节点被不断复用，最容易触发 ABA
+        let workers: Vec<_> = (0..4)
+            .map(|_| {
+                let stack = Arc::clone(&stack);
+                thread::spawn(move || {
+                    for _ in 0..10_000 {
+                        if let Some(value) = stack.pop() {
+                            stack.push(value);
+                        }
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        let mut values: Vec<_> = std::iter::from_fn(|| stack.pop()).collect();
+        values.sort_unstable();
+        assert_eq!(values, (0..8).collect::<Vec<_>>());
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_tests {
+    //! 运行方式：`RUSTFLAGS="--cfg loom" cargo test --release loom_`
+    use super::*;
+    use loom::sync::Arc;
+    use loom::thread;
+
+    /// 不限制抢占次数时状态空间会爆炸；默认最多 5 次抢占，可用 `LOOM_MAX_PREEMPTIONS` 调整
+    fn model(f: impl Fn() + Sync + Send + 'static) {
+        let mut builder = loom::model::Builder::new();
+        builder.preemption_bound.get_or_insert(5);
+        builder.check(f);
+    }
+
+    #[test]
+    fn loom_concurrent_pops_never_touch_freed_nodes() {
+        model(|| {
+            let stack = Arc::new(LockFreeStack::new());
+            stack.push(1);
+            stack.push(2);
+
+            let other = {
+                let stack = Arc::clone(&stack);
+                thread::spawn(move || {
+                    let handle = stack.register();
+                    let value = stack.pop(&handle.pin());
+                    // 主动推进纪元，让另一个线程弹出的节点尽早进入可回收状态
+                    handle.flush();
+                    handle.flush();
+                    value
+                })
+            };
+
+            let handle = stack.register();
+            let mine = stack.pop(&handle.pin());
+            handle.flush();
+            handle.flush();
+
+            let theirs = other.join().unwrap();
+            let mut values = vec![mine.unwrap(), theirs.unwrap()];
+            values.sort_unstable();
+            assert_eq!(values, vec![1, 2]);
+        });
+    }
+
+    #[test]
+    fn loom_push_races_with_pop() {
+        model(|| {
+            let stack = Arc::new(LockFreeStack::new());
+            stack.push(1);
+
+            let pusher = {
+                let stack = Arc::clone(&stack);
+                thread::spawn(move || stack.push(2))
+            };
+
+            let handle = stack.register();
+            let first = stack.pop(&handle.pin());
+            handle.flush();
+            pusher.join().unwrap();
+            let second = stack.pop(&handle.pin());
+
+            let mut values: Vec<_> = first.into_iter().chain(second).collect();
+            values.sort_unstable();
+            assert_eq!(values, vec![1, 2]);
+        });
+    }
+
+    #[test]
+    fn loom_tagged_stack_survives_aba() {
+        model(|| {
+            let stack = Arc::new(TaggedStack::new());
+            stack.push(1);
+            stack.push(2);
+            stack.push(3);
+
+            // 另一个线程弹出 A、B 后把 A 压回：经典的 ABA 交错
+            let other = {
+                let stack = Arc::clone(&stack);
+                thread::spawn(move || {
+                    let a = stack.pop().unwrap();
+                    let b = stack.pop().unwrap();
+                    stack.push(a);
+                    b
+                })
+            };
+
+            let mine = stack.pop().unwrap();
+            let b = other.join().unwrap();
+            let rest = stack.pop().unwrap();
+            assert!(stack.pop().is_none());
+
+            let mut values = vec![mine, b, rest];
+            values.sort_unstable();
+            assert_eq!(values, vec![1, 2, 3]);
+        });
+    }
+}
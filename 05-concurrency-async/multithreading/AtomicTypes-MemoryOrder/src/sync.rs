@@ -0,0 +1,39 @@
+//! 同步原语适配层
+//!
+//! 正常编译时直接使用标准库；以 `RUSTFLAGS="--cfg loom"` 编译时切换为 loom 的模型实现，
+//! 这样同一份无锁代码既能跑演示，也能交给 loom 穷举所有线程交错。
+
+#[cfg(loom)]
+pub(crate) use loom::cell::UnsafeCell;
+#[cfg(loom)]
+pub(crate) use loom::sync::atomic::{AtomicBool, AtomicPtr, AtomicU32, AtomicU64, AtomicUsize, Ordering, fence};
+#[cfg(loom)]
+pub(crate) use loom::sync::{Arc, Mutex};
+
+#[cfg(not(loom))]
+pub(crate) use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicU32, AtomicU64, AtomicUsize, Ordering, fence};
+#[cfg(not(loom))]
+pub(crate) use std::sync::{Arc, Mutex};
+
+/// 与 `loom::cell::UnsafeCell` 接口一致的包装
+///
+/// loom 通过 `with` / `with_mut` 记录每一次读写，从而发现没有 happens-before
+/// 关系的并发访问（包括释放后使用）；正常编译时它们只是零开销的指针访问。
+#[cfg(not(loom))]
+#[derive(Debug)]
+pub(crate) struct UnsafeCell<T>(std::cell::UnsafeCell<T>);
+
+#[cfg(not(loom))]
+impl<T> UnsafeCell<T> {
+    pub(crate) const fn new(data: T) -> Self {
+        Self(std::cell::UnsafeCell::new(data))
+    }
+
+    pub(crate) fn with<R>(&self, f: impl FnOnce(*const T) -> R) -> R {
+        f(self.0.get())
+    }
+
+    pub(crate) fn with_mut<R>(&self, f: impl FnOnce(*mut T) -> R) -> R {
+        f(self.0.get())
+    }
+}
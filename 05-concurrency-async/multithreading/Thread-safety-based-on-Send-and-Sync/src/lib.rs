@@ -0,0 +1,58 @@
+//! 可在其他示例中复用的线程安全组件
+//!
+//! `ThreadSafeCache` 是 `RwLock<HashMap>` 的直接封装，
+//! 也是 AtomicTypes-MemoryOrder 中无锁哈希表基准测试的对照组。
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Arc, RwLock};
+
+/// 线程安全的缓存系统
+pub struct ThreadSafeCache<K, V>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+{
+    cache: Arc<RwLock<HashMap<K, V>>>,
+}
+
+impl<K, V> ThreadSafeCache<K, V>
+where
+    K: Hash + Eq + Clone + Send + Sync,
+    V: Clone + Send + Sync,
+{
+    pub fn new() -> Self {
+        ThreadSafeCache {
+            cache: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        let cache = self.cache.read().unwrap();
+        cache.get(key).cloned()
+    }
+
+    pub fn insert(&self, key: K, value: V) {
+        let mut cache = self.cache.write().unwrap();
+        cache.insert(key, value);
+    }
+
+    pub fn len(&self) -> usize {
+        let cache = self.cache.read().unwrap();
+        cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<K, V> Default for ThreadSafeCache<K, V>
+where
+    K: Hash + Eq + Clone + Send + Sync,
+    V: Clone + Send + Sync,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -11,6 +11,8 @@ use std::rc::Rc;
 use std::marker::PhantomData;
 use std::ptr::NonNull;
 
+use thread_safety_send_sync::ThreadSafeCache;
+
 fn main() {
     println!("=== Rust线程安全基于Send和Sync Trait的深度教程 ===");
     
@@ -1144,42 +1146,6 @@ fn demonstrate_thread_pool_concept() {
     println!("✓ 线程池概念演示完成");
 }
 
-/// 线程安全的缓存系统
-struct ThreadSafeCache<K, V> 
-where
-    K: std::hash::Hash + Eq + Clone,
-    V: Clone,
-{
-    cache: Arc<RwLock<std::collections::HashMap<K, V>>>,
-}
-
-impl<K, V> ThreadSafeCache<K, V>
-where
-    K: std::hash::Hash + Eq + Clone + Send + Sync,
-    V: Clone + Send + Sync,
-{
-    fn new() -> Self {
-        ThreadSafeCache {
-            cache: Arc::new(RwLock::new(std::collections::HashMap::new())),
-        }
-    }
-    
-    fn get(&self, key: &K) -> Option<V> {
-        let cache = self.cache.read().unwrap();
-        cache.get(key).cloned()
-    }
-    
-    fn insert(&self, key: K, value: V) {
-        let mut cache = self.cache.write().unwrap();
-        cache.insert(key, value);
-    }
-    
-    fn len(&self) -> usize {
-        let cache = self.cache.read().unwrap();
-        cache.len()
-    }
-}
-
 // ThreadSafeCache自动实现Send + Sync
 
 /// 演示缓存系统
@@ -19,6 +19,10 @@ use std::{
     panic,
 };
 
+mod thread_pool;
+
+use thread_pool::{RejectionPolicy, ThreadPool};
+
 fn main() {
     println!("{}", "=".repeat(60));
     println!("🦀 Rust 线程编程全面教程");
@@ -480,80 +484,93 @@ fn thread_local_storage() {
 
 /// 6. 线程池实现
 fn thread_pool_implementation() {
-    println!("   🏊 简单线程池实现：");
+    println!("   🏊 线程池 (thread_pool::ThreadPool)：");
     
-    use std::sync::mpsc;
+    // 命名线程 + 有界队列，队列满时由提交者自己执行
+    let pool = ThreadPool::builder()
+        .threads(3)
+        .name("worker")
+        .queue_capacity(4, RejectionPolicy::CallerRuns)
+        .build();
     
-    struct SimpleThreadPool {
-        workers: Vec<Worker>,
-        sender: mpsc::Sender<Job>,
-    }
-    
-    struct Worker {
-        id: usize,
-        thread: thread::JoinHandle<()>,
-    }
+    let handles: Vec<_> = (0..6)
+        .map(|i| {
+            pool.execute(move || {
+                let name = thread::current().name().unwrap_or("调用者").to_string();
+                println!("     任务 {} 在 {} 上开始执行", i, name);
+                thread::sleep(Duration::from_millis(100));
+                i * 10
+            })
+            .unwrap()
+        })
+        .collect();
     
-    type Job = Box<dyn FnOnce() + Send + 'static>;
+    // 不再需要 sleep 等待，句柄直接取回结果
+    let results: Vec<i32> = handles.into_iter().map(|handle| handle.join().unwrap()).collect();
+    println!("     所有任务结果: {:?}", results);
+    
+    println!("\n   💥 panic 隔离：");
+    let failed = pool.execute(|| -> i32 { panic!("任务内部错误") }).unwrap();
+    println!("     失败任务: {:?}", failed.join());
+    let after = pool.execute(|| "线程池仍然可用").unwrap();
+    println!("     后续任务: {}", after.join().unwrap());
+    pool.join();
+    println!("     panic 的任务数: {} (每次都换上了新的工作线程)", pool.panicked_tasks());
+    
+    println!("\n   🚦 拒绝策略：");
+    let strict = ThreadPool::builder()
+        .threads(1)
+        .queue_capacity(1, RejectionPolicy::Reject)
+        .build();
+    let submitted: Vec<_> = (0..4)
+        .map(|_| strict.execute(|| thread::sleep(Duration::from_millis(50))))
+        .collect();
+    let rejected = submitted.iter().filter(|result| result.is_err()).count();
+    println!("     队列容量 1 时连续提交 4 个任务，被拒绝 {} 个", rejected);
+    strict.join();
+    
+    let latest_only = ThreadPool::builder()
+        .threads(1)
+        .queue_capacity(1, RejectionPolicy::DiscardOldest)
+        .build();
+    let versions: Vec<_> = (1..=3)
+        .map(|version| {
+            latest_only
+                .execute(move || {
+                    thread::sleep(Duration::from_millis(30));
+                    version
+                })
+                .unwrap()
+        })
+        .collect();
+    let outcomes: Vec<_> = versions.into_iter().map(|handle| handle.join()).collect();
+    println!("     只保留最新任务: {:?}", outcomes);
     
-    impl SimpleThreadPool {
-        fn new(size: usize) -> SimpleThreadPool {
-            assert!(size > 0);
-            
-            let (sender, receiver) = mpsc::channel();
-            let receiver = Arc::new(Mutex::new(receiver));
-            
-            let mut workers = Vec::with_capacity(size);
-            
-            for id in 0..size {
-                workers.push(Worker::new(id, Arc::clone(&receiver)));
-            }
-            
-            SimpleThreadPool { workers, sender }
-        }
-        
-        fn execute<F>(&self, f: F)
-        where
-            F: FnOnce() + Send + 'static,
-        {
-            let job = Box::new(f);
-            self.sender.send(job).unwrap();
-        }
+    let blocking = ThreadPool::builder()
+        .threads(2)
+        .queue_capacity(1, RejectionPolicy::Block)
+        .build();
+    let start = Instant::now();
+    for _ in 0..4 {
+        blocking.execute(|| thread::sleep(Duration::from_millis(50))).unwrap();
     }
+    println!("     Block 策略: 提交 4 个任务等待了 {:?}", start.elapsed());
     
-    impl Worker {
-        fn new(id: usize, receiver: Arc<Mutex<mpsc::Receiver<Job>>>) -> Worker {
-            let thread = thread::spawn(move || loop {
-                let job = receiver.lock().unwrap().recv();
-                
-                match job {
-                    Ok(job) => {
-                        println!("     Worker {} 执行任务", id);
-                        job();
-                    }
-                    Err(_) => {
-                        println!("     Worker {} 关闭", id);
-                        break;
-                    }
-                }
-            });
-            
-            Worker { id, thread }
+    println!("\n   🔭 作用域任务 (借用栈上数据)：");
+    let mut scores = vec![55, 72, 90, 38, 66, 81];
+    let scoped_pool = ThreadPool::new(2);
+    scoped_pool.scope(|scope| {
+        for score in scores.iter_mut() {
+            scope.execute(move || *score = (*score + 10).min(100));
         }
-    }
-    
-    // 使用线程池
-    let pool = SimpleThreadPool::new(3);
-    
-    for i in 0..6 {
-        pool.execute(move || {
-            println!("     任务 {} 开始执行", i);
-            thread::sleep(Duration::from_millis(100));
-            println!("     任务 {} 执行完成", i);
-        });
-    }
+    });
+    println!("     加分后的成绩: {:?}", scores);
     
-    thread::sleep(Duration::from_millis(1000)); // 等待任务完成
+    // Drop 时执行完排队的任务并 join 所有工作线程
+    drop(blocking);
+    drop(scoped_pool);
+    drop(pool);
+    println!("     线程池已关闭，所有工作线程均已回收");
     
     println!("\n   ⚡ 线程池优势：");
     println!("   • 减少线程创建/销毁开销");
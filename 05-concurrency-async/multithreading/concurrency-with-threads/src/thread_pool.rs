@@ -0,0 +1,533 @@
+//! 可复用的线程池
+//!
+//! 第 6 节最初的 `SimpleThreadPool` 只演示了“共享任务队列 + 固定数量的工作线程”这个核心思路，
+//! 离真正能用还差不少：没有 `Drop`（工作线程从不 join）、拿不到任务结果、任务 panic 会让
+//! 工作线程永久消失、演示只能靠 `sleep(1000ms)` 等待。本模块补齐这些能力：
+//!
+//! - `execute` 返回 [`TaskHandle`]，`join` 取回结果或 panic 信息
+//! - 任务 panic 只影响它自己：工作线程把 panic 交给句柄后退出，并启动一个同名的新线程代替自己
+//! - [`ThreadPool::join`] 等待队列清空；`Drop` 先执行完已排队的任务再回收所有线程
+//! - 有界队列满时按 [`RejectionPolicy`] 处理新任务
+//! - [`ThreadPool::scope`] 允许任务借用调用者栈上的数据，返回前等待这些任务全部结束
+
+use std::any::Any;
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, mpsc};
+use std::thread::{self, JoinHandle};
+
+/// 返回值表示任务是否 panic
+type Job = Box<dyn FnOnce() -> bool + Send + 'static>;
+
+struct QueuedJob {
+    job: Job,
+    /// 作用域任务借用了调用者的数据，不能被 `DiscardOldest` 丢弃
+    scoped: bool,
+}
+
+/// 有界队列满时如何处理新任务
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectionPolicy {
+    /// 阻塞提交者，直到队列有空位
+    Block,
+    /// 立即返回 [`Rejected`]
+    Reject,
+    /// 在提交者的线程中直接执行，自然地降低提交速度
+    CallerRuns,
+    /// 丢弃队列中最老的任务，它的句柄得到 [`TaskError::Discarded`]
+    DiscardOldest,
+}
+
+/// 任务因队列已满被拒绝
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rejected;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskError {
+    /// 任务 panic，附带 panic 信息
+    Panicked(String),
+    /// 任务被 `DiscardOldest` 丢弃，没有执行
+    Discarded,
+}
+
+/// 任务结果的句柄
+pub struct TaskHandle<R> {
+    rx: mpsc::Receiver<thread::Result<R>>,
+}
+
+impl<R> TaskHandle<R> {
+    /// 阻塞等待任务结束
+    pub fn join(self) -> Result<R, TaskError> {
+        match self.rx.recv() {
+            Ok(Ok(value)) => Ok(value),
+            Ok(Err(payload)) => Err(TaskError::Panicked(panic_message(payload.as_ref()))),
+            // 任务连同结果发送端一起被丢弃
+            Err(_) => Err(TaskError::Discarded),
+        }
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "未知 panic".to_string()
+    }
+}
+
+struct Queue {
+    jobs: VecDeque<QueuedJob>,
+    /// 正在执行任务的线程数
+    active: usize,
+    shutdown: bool,
+    panicked: usize,
+}
+
+struct Shared {
+    queue: Mutex<Queue>,
+    job_available: Condvar,
+    space_available: Condvar,
+    idle: Condvar,
+    capacity: Option<usize>,
+    policy: RejectionPolicy,
+    name: String,
+    /// 按编号存放每个工作线程当前的句柄；替换线程先 join 被它替换的线程
+    threads: Mutex<Vec<Option<JoinHandle<()>>>>,
+}
+
+pub struct Builder {
+    threads: usize,
+    name: String,
+    queue_capacity: Option<usize>,
+    policy: RejectionPolicy,
+}
+
+impl Builder {
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// 线程名前缀，线程名为 `{name}-{编号}`
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    /// 限制排队（尚未执行）的任务数量，默认不限制
+    pub fn queue_capacity(mut self, capacity: usize, policy: RejectionPolicy) -> Self {
+        assert!(capacity > 0, "队列容量至少为 1");
+        self.queue_capacity = Some(capacity);
+        self.policy = policy;
+        self
+    }
+
+    pub fn build(self) -> ThreadPool {
+        assert!(self.threads > 0, "线程池至少需要一个线程");
+        let shared = Arc::new(Shared {
+            queue: Mutex::new(Queue {
+                jobs: VecDeque::new(),
+                active: 0,
+                shutdown: false,
+                panicked: 0,
+            }),
+            job_available: Condvar::new(),
+            space_available: Condvar::new(),
+            idle: Condvar::new(),
+            capacity: self.queue_capacity,
+            policy: self.policy,
+            name: self.name,
+            threads: Mutex::new((0..self.threads).map(|_| None).collect()),
+        });
+        for id in 0..self.threads {
+            spawn_worker(&shared, id);
+        }
+        ThreadPool { shared }
+    }
+}
+
+pub struct ThreadPool {
+    shared: Arc<Shared>,
+}
+
+impl ThreadPool {
+    pub fn new(threads: usize) -> Self {
+        Self::builder().threads(threads).build()
+    }
+
+    pub fn builder() -> Builder {
+        Builder {
+            threads: thread::available_parallelism().map_or(4, |n| n.get()),
+            name: "pool".to_string(),
+            queue_capacity: None,
+            policy: RejectionPolicy::Block,
+        }
+    }
+
+    /// 提交任务；只有队列已满且策略为 `Reject` 时返回错误
+    pub fn execute<F, R>(&self, f: F) -> Result<TaskHandle<R>, Rejected>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel();
+        let job: Job = Box::new(move || {
+            let result = panic::catch_unwind(AssertUnwindSafe(f));
+            let panicked = result.is_err();
+            // 调用者可能已经丢弃了句柄
+            let _ = tx.send(result);
+            panicked
+        });
+
+        let mut queue = self.shared.queue.lock().unwrap();
+        if let Some(capacity) = self.shared.capacity {
+            while queue.jobs.len() >= capacity {
+                match self.shared.policy {
+                    RejectionPolicy::Block => queue = self.shared.space_available.wait(queue).unwrap(),
+                    RejectionPolicy::Reject => return Err(Rejected),
+                    RejectionPolicy::CallerRuns => {
+                        drop(queue);
+                        job();
+                        return Ok(TaskHandle { rx });
+                    }
+                    RejectionPolicy::DiscardOldest => match queue.jobs.iter().position(|queued| !queued.scoped) {
+                        // 被丢弃的任务在锁外释放，它的句柄随即得到 Discarded
+                        Some(index) => {
+                            let discarded = queue.jobs.remove(index);
+                            drop(queue);
+                            drop(discarded);
+                            queue = self.shared.queue.lock().unwrap();
+                        }
+                        None => queue = self.shared.space_available.wait(queue).unwrap(),
+                    },
+                }
+            }
+        }
+        queue.jobs.push_back(QueuedJob { job, scoped: false });
+        self.shared.job_available.notify_one();
+        Ok(TaskHandle { rx })
+    }
+
+    /// 阻塞到队列为空且没有正在执行的任务
+    pub fn join(&self) {
+        let mut queue = self.shared.queue.lock().unwrap();
+        while !queue.jobs.is_empty() || queue.active > 0 {
+            queue = self.shared.idle.wait(queue).unwrap();
+        }
+    }
+
+    /// 至今 panic 的任务数（每次都替换了一个工作线程）
+    pub fn panicked_tasks(&self) -> usize {
+        self.shared.queue.lock().unwrap().panicked
+    }
+
+    /// 在作用域内提交可以借用栈上数据的任务，返回前等待它们全部结束
+    ///
+    /// 作用域任务不受队列容量限制。不要在池自己的工作线程里调用，否则可能等不到空闲线程。
+    /// 和 `std::thread::scope` 一样，任一任务 panic 时，作用域在所有任务结束后 panic。
+    pub fn scope<'env, F, T>(&self, f: F) -> T
+    where
+        F: for<'scope> FnOnce(&'scope Scope<'scope, 'env>) -> T,
+    {
+        let scope = Scope {
+            pool: self,
+            state: Arc::new(ScopeState {
+                pending: Mutex::new(0),
+                finished: Condvar::new(),
+                panicked: AtomicBool::new(false),
+            }),
+            scope: PhantomData,
+            env: PhantomData,
+        };
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| f(&scope)));
+        // 无论 f 是否 panic，都要等借用数据的任务结束
+        let mut pending = scope.state.pending.lock().unwrap();
+        while *pending > 0 {
+            pending = scope.state.finished.wait(pending).unwrap();
+        }
+        drop(pending);
+
+        match result {
+            Err(payload) => panic::resume_unwind(payload),
+            Ok(_) if scope.state.panicked.load(Ordering::Relaxed) => panic!("作用域中的任务 panic"),
+            Ok(value) => value,
+        }
+    }
+}
+
+impl Drop for ThreadPool {
+    /// 优雅关闭：已排队的任务照常执行，然后回收所有线程
+    fn drop(&mut self) {
+        self.shared.queue.lock().unwrap().shutdown = true;
+        self.shared.job_available.notify_all();
+
+        // join 期间该编号的线程可能刚换上替换线程，所以每个编号都循环到句柄为空
+        let slots = self.shared.threads.lock().unwrap().len();
+        for id in 0..slots {
+            loop {
+                let handle = self.shared.threads.lock().unwrap()[id].take();
+                match handle {
+                    Some(handle) => {
+                        let _ = handle.join();
+                    }
+                    None => break,
+                }
+            }
+        }
+    }
+}
+
+/// 启动编号为 `id` 的工作线程；替换时由新线程 join 它的前任，句柄数始终等于线程数
+fn spawn_worker(shared: &Arc<Shared>, id: usize) {
+    let worker_shared = Arc::clone(shared);
+    let mut threads = shared.threads.lock().unwrap();
+    let predecessor = threads[id].take();
+    let handle = thread::Builder::new()
+        .name(format!("{}-{}", shared.name, id))
+        .spawn(move || {
+            if let Some(predecessor) = predecessor {
+                let _ = predecessor.join();
+            }
+            worker_loop(worker_shared, id)
+        })
+        .expect("创建工作线程失败");
+    threads[id] = Some(handle);
+}
+
+fn worker_loop(shared: Arc<Shared>, id: usize) {
+    loop {
+        let mut queue = shared.queue.lock().unwrap();
+        let queued = loop {
+            if let Some(queued) = queue.jobs.pop_front() {
+                break queued;
+            }
+            if queue.shutdown {
+                return;
+            }
+            queue = shared.job_available.wait(queue).unwrap();
+        };
+        queue.active += 1;
+        drop(queue);
+        shared.space_available.notify_one();
+
+        let panicked = (queued.job)();
+
+        let mut queue = shared.queue.lock().unwrap();
+        queue.active -= 1;
+        if queue.jobs.is_empty() && queue.active == 0 {
+            shared.idle.notify_all();
+        }
+        if panicked {
+            // panic 可能破坏了线程局部状态，换一个干净的线程继续服务
+            queue.panicked += 1;
+            drop(queue);
+            spawn_worker(&shared, id);
+            return;
+        }
+    }
+}
+
+struct ScopeState {
+    pending: Mutex<usize>,
+    finished: Condvar,
+    panicked: AtomicBool,
+}
+
+/// 任务结束（或者没执行就被释放）时计数减一
+struct PendingGuard(Arc<ScopeState>);
+
+impl Drop for PendingGuard {
+    fn drop(&mut self) {
+        let mut pending = self.0.pending.lock().unwrap();
+        *pending -= 1;
+        if *pending == 0 {
+            self.0.finished.notify_all();
+        }
+    }
+}
+
+/// [`ThreadPool::scope`] 中提交任务的句柄，生命周期参数的含义与 `std::thread::Scope` 相同
+pub struct Scope<'scope, 'env: 'scope> {
+    pool: &'scope ThreadPool,
+    state: Arc<ScopeState>,
+    scope: PhantomData<&'scope mut &'scope ()>,
+    env: PhantomData<&'env mut &'env ()>,
+}
+
+impl<'scope> Scope<'scope, '_> {
+    pub fn execute<F>(&'scope self, f: F)
+    where
+        F: FnOnce() + Send + 'scope,
+    {
+        *self.state.pending.lock().unwrap() += 1;
+        let guard = PendingGuard(Arc::clone(&self.state));
+        let job: Box<dyn FnOnce() -> bool + Send + 'scope> = Box::new(move || {
+            let panicked = panic::catch_unwind(AssertUnwindSafe(f)).is_err();
+            if panicked {
+                guard.0.panicked.store(true, Ordering::Relaxed);
+            }
+            drop(guard);
+            panicked
+        });
+        // SAFETY: scope() 在返回（或继续 panic）之前等待 pending 归零，
+        // 而 pending 只在任务执行完或被释放后才减一，所以任务不会比它借用的数据活得久
+        let job: Job = unsafe { std::mem::transmute::<Box<dyn FnOnce() -> bool + Send + 'scope>, Job>(job) };
+
+        let mut queue = self.pool.shared.queue.lock().unwrap();
+        queue.jobs.push_back(QueuedJob { job, scoped: true });
+        self.pool.shared.job_available.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use std::time::Duration;
+
+    #[test]
+    fn test_execute_returns_results() {
+        let pool = ThreadPool::new(4);
+        let handles: Vec<_> = (0..20u64).map(|i| pool.execute(move || i * i).unwrap()).collect();
+        let results: Vec<u64> = handles.into_iter().map(|handle| handle.join().unwrap()).collect();
+        assert_eq!(results, (0..20).map(|i| i * i).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_panic_isolated_and_worker_respawned() {
+        let pool = ThreadPool::builder().threads(1).name("solo").build();
+
+        let failed = pool.execute(|| -> u32 { panic!("任务出错") }).unwrap();
+        assert_eq!(failed.join(), Err(TaskError::Panicked("任务出错".to_string())));
+
+        // 唯一的工作线程被同名的新线程替换，后续任务照常执行
+        let name = pool.execute(|| thread::current().name().map(String::from)).unwrap();
+        assert_eq!(name.join().unwrap().as_deref(), Some("solo-0"));
+        assert_eq!(pool.panicked_tasks(), 1);
+    }
+
+    #[test]
+    fn test_repeated_panics_keep_one_handle_per_worker() {
+        let pool = ThreadPool::builder().threads(2).build();
+        for _ in 0..20 {
+            let failed = pool.execute(|| panic!("任务出错")).unwrap();
+            assert!(failed.join().is_err());
+        }
+        pool.join();
+
+        assert_eq!(pool.panicked_tasks(), 20);
+        let threads = pool.shared.threads.lock().unwrap();
+        assert_eq!(threads.len(), 2);
+        assert!(threads.iter().all(Option::is_some));
+        drop(threads);
+        assert_eq!(pool.execute(|| 1).unwrap().join(), Ok(1));
+    }
+
+    #[test]
+    fn test_join_and_graceful_drop() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let pool = ThreadPool::new(2);
+        for _ in 0..6 {
+            let counter = Arc::clone(&counter);
+            pool.execute(move || {
+                thread::sleep(Duration::from_millis(10));
+                counter.fetch_add(1, Ordering::SeqCst);
+            })
+            .unwrap();
+        }
+        pool.join();
+        assert_eq!(counter.load(Ordering::SeqCst), 6);
+
+        // Drop 前排队的任务也会执行完
+        for _ in 0..6 {
+            let counter = Arc::clone(&counter);
+            pool.execute(move || {
+                thread::sleep(Duration::from_millis(10));
+                counter.fetch_add(1, Ordering::SeqCst);
+            })
+            .unwrap();
+        }
+        drop(pool);
+        assert_eq!(counter.load(Ordering::SeqCst), 12);
+    }
+
+    /// 单线程、队列容量 1 的池，先用一个任务占住线程、一个任务占满队列
+    fn saturated(policy: RejectionPolicy) -> (ThreadPool, mpsc::Sender<()>, TaskHandle<&'static str>) {
+        let pool = ThreadPool::builder().threads(1).queue_capacity(1, policy).build();
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        pool.execute(move || release_rx.recv().unwrap()).unwrap();
+        // 等线程取走第一个任务，队列重新为空
+        loop {
+            let queue = pool.shared.queue.lock().unwrap();
+            if queue.jobs.is_empty() && queue.active == 1 {
+                break;
+            }
+            drop(queue);
+            thread::yield_now();
+        }
+        let queued = pool.execute(|| "排队的任务").unwrap();
+        (pool, release_tx, queued)
+    }
+
+    #[test]
+    fn test_rejection_policies() {
+        let (pool, release, queued) = saturated(RejectionPolicy::Reject);
+        assert!(pool.execute(|| "新任务").is_err());
+        release.send(()).unwrap();
+        assert_eq!(queued.join(), Ok("排队的任务"));
+
+        let (pool, release, queued) = saturated(RejectionPolicy::CallerRuns);
+        let caller = thread::current().id();
+        let ran_on = pool.execute(move || thread::current().id() == caller).unwrap();
+        assert_eq!(ran_on.join(), Ok(true));
+        release.send(()).unwrap();
+        assert_eq!(queued.join(), Ok("排队的任务"));
+
+        let (pool, release, queued) = saturated(RejectionPolicy::DiscardOldest);
+        let newest = pool.execute(|| "新任务").unwrap();
+        assert_eq!(queued.join(), Err(TaskError::Discarded));
+        release.send(()).unwrap();
+        assert_eq!(newest.join(), Ok("新任务"));
+    }
+
+    #[test]
+    fn test_scope_borrows_stack_data() {
+        let pool = ThreadPool::new(3);
+        let mut data: Vec<u64> = (1..=100).collect();
+        let total = AtomicUsize::new(0);
+
+        pool.scope(|scope| {
+            for chunk in data.chunks_mut(10) {
+                let total = &total;
+                scope.execute(move || {
+                    for value in chunk.iter_mut() {
+                        *value *= 2;
+                    }
+                    total.fetch_add(chunk.iter().sum::<u64>() as usize, Ordering::SeqCst);
+                });
+            }
+        });
+
+        assert_eq!(total.load(Ordering::SeqCst), 10100);
+        assert_eq!(data[99], 200);
+
+        // 任务 panic 时作用域在所有任务结束后 panic
+        let finished = AtomicUsize::new(0);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            pool.scope(|scope| {
+                scope.execute(|| panic!("作用域任务出错"));
+                scope.execute(|| {
+                    thread::sleep(Duration::from_millis(20));
+                    finished.fetch_add(1, Ordering::SeqCst);
+                });
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(finished.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,548 @@
+//! # 类型化的 Actor
+//!
+//! `event_system_demo`、`task_queue_system` 这类代码每次都要手写一遍“线程 + 消息枚举 + 通道”，
+//! 还要自己处理退出信号和线程回收。本模块把这个模式固定下来：
+//! - [`Actor`]：一个线程独占的状态 + 只接收 `Actor::Msg` 类型消息的邮箱
+//! - [`Addr<A>`]：可克隆的地址，`send` 只投递，`ask` 附带一个 [`Reply`] 并带超时等待回复
+//! - 监督：`handle` panic 时丢弃出错的消息和旧状态，按 [`RestartPolicy`] 退避后用工厂函数重建，
+//!   地址和邮箱中尚未处理的消息保持不变；重建时工厂函数 panic 同样计入重启次数
+//! - 重启和放弃通过 [`SupervisionEvent`] 报告给 [`System::with_supervision_hook`] 注册的回调，
+//!   库本身不打印任何内容
+//! - [`System::shutdown`]：按启动的相反顺序让每个 actor 处理完已收到的消息后停止，并等待线程退出
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+use std::sync::mpsc::{RecvTimeoutError, SendError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::channel::{self, Receiver, Sender};
+
+pub trait Actor: Sized + Send + 'static {
+    type Msg: Send + 'static;
+
+    /// 启动（以及每次重启）后、处理第一条消息前调用
+    fn started(&mut self, _ctx: &mut Context<Self>) {}
+
+    fn handle(&mut self, msg: Self::Msg, ctx: &mut Context<Self>);
+
+    /// 正常停止时调用；panic 的实例不会调用
+    fn stopped(&mut self) {}
+}
+
+enum Envelope<M> {
+    Msg(M),
+    Stop,
+}
+
+/// actor 的地址，所有克隆共享同一个邮箱
+pub struct Addr<A: Actor> {
+    tx: Sender<Envelope<A::Msg>>,
+}
+
+impl<A: Actor> Clone for Addr<A> {
+    fn clone(&self) -> Self {
+        Self { tx: self.tx.clone() }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AskError {
+    /// 超时前没有收到回复
+    Timeout,
+    /// actor 已停止，或者处理消息时 panic、丢弃了 `Reply`
+    NoReply,
+}
+
+impl<A: Actor> Addr<A> {
+    /// 投递消息，actor 已停止时返回原消息
+    pub fn send(&self, msg: A::Msg) -> Result<(), SendError<A::Msg>> {
+        self.tx.send(Envelope::Msg(msg)).map_err(|SendError(envelope)| match envelope {
+            Envelope::Msg(msg) => SendError(msg),
+            Envelope::Stop => unreachable!("只投递了普通消息"),
+        })
+    }
+
+    /// 请求/响应：`make` 把回复通道装进消息，最多等待 `timeout`
+    pub fn ask<R>(&self, make: impl FnOnce(Reply<R>) -> A::Msg, timeout: Duration) -> Result<R, AskError> {
+        let (tx, rx) = channel::bounded(1);
+        self.send(make(Reply(tx))).map_err(|_| AskError::NoReply)?;
+        rx.recv_timeout(timeout).map_err(|error| match error {
+            RecvTimeoutError::Timeout => AskError::Timeout,
+            RecvTimeoutError::Disconnected => AskError::NoReply,
+        })
+    }
+}
+
+/// `ask` 的回复通道，只能回复一次
+pub struct Reply<R>(Sender<R>);
+
+impl<R> Reply<R> {
+    /// 提问者已超时离开时回复被丢弃
+    pub fn send(self, value: R) {
+        let _ = self.0.send(value);
+    }
+}
+
+/// 传给 `started` / `handle` 的上下文
+pub struct Context<A: Actor> {
+    name: String,
+    addr: Addr<A>,
+    restarts: u32,
+    stopping: bool,
+}
+
+impl<A: Actor> Context<A> {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// 自己的地址，可以交给其他 actor 用来回信
+    pub fn addr(&self) -> Addr<A> {
+        self.addr.clone()
+    }
+
+    /// 当前实例之前已经重启过几次
+    pub fn restarts(&self) -> u32 {
+        self.restarts
+    }
+
+    /// 处理完当前消息后停止，邮箱中剩余的消息被丢弃
+    pub fn stop(&mut self) {
+        self.stopping = true;
+    }
+}
+
+/// panic 后的重启策略：指数退避，超过次数上限就放弃
+#[derive(Debug, Clone, Copy)]
+pub struct RestartPolicy {
+    pub max_restarts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl RestartPolicy {
+    /// panic 一次就停止
+    pub fn never() -> Self {
+        Self {
+            max_restarts: 0,
+            ..Self::default()
+        }
+    }
+
+    /// 第 `attempt` 次重启前的等待时间（从 1 开始）
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff.saturating_mul(factor).min(self.max_backoff)
+    }
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            max_restarts: 3,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+/// 监督循环中发生的事件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SupervisionEvent {
+    /// 实例 panic，等待 `backoff` 后进行第 `attempt` 次重启
+    Restarting {
+        actor: String,
+        attempt: u32,
+        backoff: Duration,
+    },
+    /// 已重启 `restarts` 次仍然 panic，actor 停止
+    GaveUp { actor: String, restarts: u32 },
+}
+
+/// 在 actor 线程上调用，不能阻塞
+type SupervisionHook = Arc<dyn Fn(&SupervisionEvent) + Send + Sync>;
+
+/// 正在运行的 actor：停止它需要的发送端 + 线程句柄
+struct Running {
+    stop: Box<dyn FnOnce() + Send>,
+    thread: JoinHandle<()>,
+}
+
+/// 持有所有 actor 的线程，释放时同样会优雅停止
+#[derive(Default)]
+pub struct System {
+    running: Vec<Running>,
+    hook: Option<SupervisionHook>,
+}
+
+impl System {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 接收之后启动的 actor 的监督事件，回调在 actor 线程上执行
+    pub fn with_supervision_hook(mut self, hook: impl Fn(&SupervisionEvent) + Send + Sync + 'static) -> Self {
+        self.hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// 在独立线程中启动 actor，`factory` 用于首次创建和每次重启
+    pub fn spawn<A: Actor>(
+        &mut self,
+        name: &str,
+        policy: RestartPolicy,
+        factory: impl Fn() -> A + Send + 'static,
+    ) -> Addr<A> {
+        let (tx, rx) = channel::unbounded();
+        let addr = Addr { tx };
+        let name = name.to_string();
+
+        let context_addr = addr.clone();
+        let hook = self.hook.clone();
+        let thread = thread::Builder::new()
+            .name(name.clone())
+            .spawn(move || supervise(name, policy, factory, rx, context_addr, hook))
+            .expect("创建 actor 线程失败");
+
+        let stop_tx = addr.tx.clone();
+        self.running.push(Running {
+            stop: Box::new(move || {
+                // actor 已因 panic 放弃时发送失败，忽略即可
+                let _ = stop_tx.send(Envelope::Stop);
+            }),
+            thread,
+        });
+        addr
+    }
+
+    /// 按启动的相反顺序停止：先停上游，下游还能处理它们最后发来的消息
+    pub fn shutdown(mut self) {
+        self.stop_all();
+    }
+
+    fn stop_all(&mut self) {
+        while let Some(running) = self.running.pop() {
+            (running.stop)();
+            // actor 的 panic 已由监督循环处理，这里不会再传播
+            let _ = running.thread.join();
+        }
+    }
+}
+
+impl Drop for System {
+    fn drop(&mut self) {
+        self.stop_all();
+    }
+}
+
+/// actor 线程的主循环：处理消息，panic 时按策略重建实例
+fn supervise<A: Actor>(
+    name: String,
+    policy: RestartPolicy,
+    factory: impl Fn() -> A,
+    rx: Receiver<Envelope<A::Msg>>,
+    addr: Addr<A>,
+    hook: Option<SupervisionHook>,
+) {
+    let report = |event: SupervisionEvent| {
+        if let Some(hook) = &hook {
+            hook(&event);
+        }
+    };
+    let mut restarts = 0;
+    loop {
+        let mut ctx = Context {
+            name: name.clone(),
+            addr: addr.clone(),
+            restarts,
+            stopping: false,
+        };
+
+        // 工厂函数 panic 和处理消息时 panic 一样，计入重启次数
+        let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut actor = factory();
+            actor.started(&mut ctx);
+            while !ctx.stopping {
+                match rx.recv() {
+                    Ok(Envelope::Msg(msg)) => actor.handle(msg, &mut ctx),
+                    Ok(Envelope::Stop) | Err(_) => break,
+                }
+            }
+            actor
+        }));
+
+        match outcome {
+            Ok(mut actor) => {
+                actor.stopped();
+                return;
+            }
+            Err(_) if restarts >= policy.max_restarts => {
+                report(SupervisionEvent::GaveUp {
+                    actor: name.clone(),
+                    restarts,
+                });
+                return;
+            }
+            Err(_) => {
+                restarts += 1;
+                let backoff = policy.backoff(restarts);
+                report(SupervisionEvent::Restarting {
+                    actor: name.clone(),
+                    attempt: restarts,
+                    backoff,
+                });
+                thread::sleep(backoff);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Instant;
+
+    enum CounterMsg {
+        Add(u32),
+        Get(Reply<u32>),
+        Crash,
+        Slow(Duration, Reply<u32>),
+    }
+
+    struct Counter {
+        total: u32,
+        starts: Arc<AtomicU32>,
+    }
+
+    impl Actor for Counter {
+        type Msg = CounterMsg;
+
+        fn started(&mut self, _ctx: &mut Context<Self>) {
+            self.starts.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn handle(&mut self, msg: CounterMsg, _ctx: &mut Context<Self>) {
+            match msg {
+                CounterMsg::Add(n) => self.total += n,
+                CounterMsg::Get(reply) => reply.send(self.total),
+                CounterMsg::Crash => panic!("计数器崩溃"),
+                CounterMsg::Slow(delay, reply) => {
+                    thread::sleep(delay);
+                    reply.send(self.total);
+                }
+            }
+        }
+    }
+
+    fn counter(system: &mut System, policy: RestartPolicy) -> (Addr<Counter>, Arc<AtomicU32>) {
+        let starts = Arc::new(AtomicU32::new(0));
+        let shared = starts.clone();
+        let addr = system.spawn("counter", policy, move || Counter {
+            total: 0,
+            starts: shared.clone(),
+        });
+        (addr, starts)
+    }
+
+    const WAIT: Duration = Duration::from_secs(2);
+
+    #[test]
+    fn test_send_and_ask() {
+        let mut system = System::new();
+        let (addr, _) = counter(&mut system, RestartPolicy::default());
+
+        for n in 1..=10 {
+            addr.send(CounterMsg::Add(n)).unwrap();
+        }
+        // 同一个邮箱按顺序处理，ask 看到的是前面所有 Add 之后的结果
+        assert_eq!(addr.ask(CounterMsg::Get, WAIT), Ok(55));
+        system.shutdown();
+    }
+
+    #[test]
+    fn test_ask_timeout() {
+        let mut system = System::new();
+        let (addr, _) = counter(&mut system, RestartPolicy::default());
+
+        let result = addr.ask(|reply| CounterMsg::Slow(Duration::from_millis(200), reply), Duration::from_millis(20));
+        assert_eq!(result, Err(AskError::Timeout));
+        // 迟到的回复被丢弃，actor 继续工作
+        assert_eq!(addr.ask(CounterMsg::Get, WAIT), Ok(0));
+    }
+
+    #[test]
+    fn test_restart_after_panic_with_backoff() {
+        let mut system = System::new();
+        let policy = RestartPolicy {
+            max_restarts: 5,
+            initial_backoff: Duration::from_millis(20),
+            max_backoff: Duration::from_millis(40),
+        };
+        let (addr, starts) = counter(&mut system, policy);
+
+        addr.send(CounterMsg::Add(5)).unwrap();
+        let started = Instant::now();
+        addr.send(CounterMsg::Crash).unwrap();
+        addr.send(CounterMsg::Crash).unwrap();
+        addr.send(CounterMsg::Add(1)).unwrap();
+
+        // 重建后状态从头开始，崩溃后排队的消息仍被处理
+        assert_eq!(addr.ask(CounterMsg::Get, WAIT), Ok(1));
+        assert_eq!(starts.load(Ordering::SeqCst), 3);
+        assert!(started.elapsed() >= Duration::from_millis(60), "退避 20ms + 40ms");
+        assert_eq!(policy.backoff(10), Duration::from_millis(40));
+    }
+
+    #[test]
+    fn test_gives_up_after_max_restarts() {
+        let mut system = System::new();
+        let (addr, starts) = counter(&mut system, RestartPolicy::never());
+
+        let result = addr.ask(
+            |reply: Reply<u32>| {
+                drop(reply);
+                CounterMsg::Crash
+            },
+            WAIT,
+        );
+        assert_eq!(result, Err(AskError::NoReply));
+        system.shutdown();
+
+        assert_eq!(starts.load(Ordering::SeqCst), 1);
+        assert!(addr.send(CounterMsg::Add(1)).is_err());
+    }
+
+    #[test]
+    fn test_supervision_events_reach_hook() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        let mut system = System::new().with_supervision_hook(move |event| sink.lock().unwrap().push(event.clone()));
+        let policy = RestartPolicy {
+            max_restarts: 1,
+            initial_backoff: Duration::from_millis(5),
+            max_backoff: Duration::from_millis(5),
+        };
+        let (addr, _) = counter(&mut system, policy);
+
+        addr.send(CounterMsg::Crash).unwrap();
+        addr.send(CounterMsg::Crash).unwrap();
+        system.shutdown();
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                SupervisionEvent::Restarting {
+                    actor: "counter".to_string(),
+                    attempt: 1,
+                    backoff: Duration::from_millis(5),
+                },
+                SupervisionEvent::GaveUp {
+                    actor: "counter".to_string(),
+                    restarts: 1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_factory_panic_counts_as_restart() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        let mut system = System::new().with_supervision_hook(move |event| sink.lock().unwrap().push(event.clone()));
+        let policy = RestartPolicy {
+            max_restarts: 2,
+            initial_backoff: Duration::from_millis(5),
+            max_backoff: Duration::from_millis(5),
+        };
+        // 只有第一次创建成功，之后每次重建都 panic
+        let built = Arc::new(AtomicU32::new(0));
+        let starts = Arc::new(AtomicU32::new(0));
+        let shared = starts.clone();
+        let addr = system.spawn("counter", policy, move || {
+            assert_eq!(built.fetch_add(1, Ordering::SeqCst), 0, "重建失败");
+            Counter {
+                total: 0,
+                starts: shared.clone(),
+            }
+        });
+
+        addr.send(CounterMsg::Crash).unwrap();
+        system.shutdown();
+
+        let restarting = |attempt| SupervisionEvent::Restarting {
+            actor: "counter".to_string(),
+            attempt,
+            backoff: Duration::from_millis(5),
+        };
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                restarting(1),
+                restarting(2),
+                SupervisionEvent::GaveUp {
+                    actor: "counter".to_string(),
+                    restarts: 2,
+                },
+            ]
+        );
+        assert_eq!(starts.load(Ordering::SeqCst), 1);
+        assert!(addr.send(CounterMsg::Add(1)).is_err());
+    }
+
+    struct Recorder {
+        name: &'static str,
+        log: Arc<Mutex<Vec<String>>>,
+        next: Option<Addr<Recorder>>,
+    }
+
+    impl Actor for Recorder {
+        type Msg = u32;
+
+        fn handle(&mut self, msg: u32, _ctx: &mut Context<Self>) {
+            thread::sleep(Duration::from_millis(2));
+            self.log.lock().unwrap().push(format!("{}:{}", self.name, msg));
+            if let Some(next) = &self.next {
+                next.send(msg).unwrap();
+            }
+        }
+
+        fn stopped(&mut self) {
+            self.log.lock().unwrap().push(format!("{} 停止", self.name));
+        }
+    }
+
+    #[test]
+    fn test_shutdown_drains_in_reverse_order() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut system = System::new();
+
+        let sink_log = log.clone();
+        let sink = system.spawn("sink", RestartPolicy::default(), move || Recorder {
+            name: "sink",
+            log: sink_log.clone(),
+            next: None,
+        });
+        let source_log = log.clone();
+        let source = system.spawn("source", RestartPolicy::default(), move || Recorder {
+            name: "source",
+            log: source_log.clone(),
+            next: Some(sink.clone()),
+        });
+
+        for n in 0..3 {
+            source.send(n).unwrap();
+        }
+        system.shutdown();
+
+        // 上游先处理完并停止，转发给下游的消息也都被处理
+        let log = log.lock().unwrap();
+        let position = |entry: &str| log.iter().position(|line| line == entry).unwrap();
+        assert!(position("source:2") < position("source 停止"));
+        assert!(position("sink:2") < position("sink 停止"));
+        assert_eq!(log.last().map(String::as_str), Some("sink 停止"));
+        assert_eq!(log.len(), 8);
+    }
+}
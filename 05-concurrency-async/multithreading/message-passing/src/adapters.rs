@@ -0,0 +1,634 @@
+//! # 可组合的通道适配器
+//!
+//! `uppercase_adapter` 这种“接收者进、接收者出”的适配器写起来很方便，但每个适配器都占一个线程，
+//! 一条稍长的流水线就是十几个大部分时间都在阻塞的线程。
+//!
+//! [`AdapterPool`] 用几个线程承载任意多个适配器：每个池线程用 [`Select`] 同时等待它负责的
+//! 所有适配器的输入，哪个输入有消息就推进哪个适配器，`batch` / `rate_limit` 的定时需求通过
+//! `timeout` 分支实现。适配器的输出都是无界通道，推进时不会阻塞池线程。
+//!
+//! 关闭沿着流水线传播：
+//! - 向下游：输入全部断开（并输出完缓冲的数据）后，适配器释放输出的发送者
+//! - 向上游：输出的接收者全部释放后，适配器在下一次收到消息时发现（即使这条消息
+//!   被过滤掉或只是进了批次缓冲），释放自己的输入
+//!
+//! 传给 `map` / `filter` 的闭包直接在池线程上运行：
+//! - 闭包不能阻塞，一个慢闭包会拖住同一线程上的所有适配器
+//! - 闭包 panic 只会结束它所在的适配器（其输出随之断开），同一线程上的其他适配器不受影响
+
+use std::cell::RefCell;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::channel::{self, Receiver, Select, Sender};
+
+/// 池线程一次等待的结果
+enum Wake {
+    Added(Box<dyn Stage>),
+    ControlClosed,
+    Input(usize),
+    Deadline,
+}
+
+/// 一个在池线程上推进的适配器，所有方法都不能阻塞
+trait Stage: Send {
+    /// 把需要等待的输入加入选择，`index` 是自己在池线程中的位置
+    fn arms<'a>(&'a self, select: Select<'a, Wake>, index: usize) -> Select<'a, Wake>;
+
+    /// 某个输入收到了消息或已断开；返回 `false` 表示适配器结束
+    fn on_input(&mut self) -> bool;
+
+    fn deadline(&self) -> Option<Instant> {
+        None
+    }
+
+    fn on_deadline(&mut self, _now: Instant) -> bool {
+        true
+    }
+}
+
+/// 适配器的一个输入：选择完成时消息先放进 `slot`，随后由 `on_input` 取走
+struct Input<T> {
+    rx: Receiver<T>,
+    /// `Some(None)` 表示输入已断开
+    slot: RefCell<Option<Option<T>>>,
+    closed: bool,
+}
+
+impl<T> Input<T> {
+    fn new(rx: Receiver<T>) -> Self {
+        Self {
+            rx,
+            slot: RefCell::new(None),
+            closed: false,
+        }
+    }
+
+    fn arm<'a>(&'a self, select: Select<'a, Wake>, index: usize) -> Select<'a, Wake> {
+        if self.closed {
+            return select;
+        }
+        select.recv(&self.rx, move |msg| {
+            *self.slot.borrow_mut() = Some(msg.ok());
+            Wake::Input(index)
+        })
+    }
+
+    /// 取出收到的消息：`None` 没有新消息，`Some(None)` 刚刚断开
+    fn take(&mut self) -> Option<Option<T>> {
+        let taken = self.slot.get_mut().take();
+        if let Some(None) = taken {
+            self.closed = true;
+        }
+        taken
+    }
+}
+
+// ==================== 适配器 ====================
+
+/// map 和 filter：每条消息变换为零或一条输出
+struct Transform<T, U, F> {
+    input: Input<T>,
+    output: Sender<U>,
+    f: F,
+}
+
+impl<T: Send, U: Send, F: FnMut(T) -> Option<U> + Send> Stage for Transform<T, U, F> {
+    fn arms<'a>(&'a self, select: Select<'a, Wake>, index: usize) -> Select<'a, Wake> {
+        self.input.arm(select, index)
+    }
+
+    fn on_input(&mut self) -> bool {
+        match self.input.take() {
+            Some(Some(msg)) => match (self.f)(msg) {
+                Some(out) => self.output.send(out).is_ok(),
+                // 没有输出也要检查下游是否还在，否则全被过滤的流水线永远不会向上游关闭
+                None => !self.output.is_disconnected(),
+            },
+            Some(None) => false,
+            None => true,
+        }
+    }
+}
+
+struct Batch<T> {
+    input: Input<T>,
+    output: Sender<Vec<T>>,
+    size: usize,
+    timeout: Duration,
+    buffer: Vec<T>,
+    /// 当前批次第一条消息到达的时间
+    started: Option<Instant>,
+}
+
+impl<T> Batch<T> {
+    fn flush(&mut self) -> bool {
+        self.started = None;
+        if self.buffer.is_empty() {
+            return true;
+        }
+        let batch = std::mem::replace(&mut self.buffer, Vec::with_capacity(self.size));
+        self.output.send(batch).is_ok()
+    }
+}
+
+impl<T: Send> Stage for Batch<T> {
+    fn arms<'a>(&'a self, select: Select<'a, Wake>, index: usize) -> Select<'a, Wake> {
+        self.input.arm(select, index)
+    }
+
+    fn on_input(&mut self) -> bool {
+        match self.input.take() {
+            Some(Some(msg)) => {
+                self.started.get_or_insert_with(Instant::now);
+                self.buffer.push(msg);
+                if self.buffer.len() < self.size {
+                    !self.output.is_disconnected()
+                } else {
+                    self.flush()
+                }
+            }
+            // 输入断开时输出最后一个不满的批次
+            Some(None) => {
+                self.flush();
+                false
+            }
+            None => true,
+        }
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        self.started.map(|started| started + self.timeout)
+    }
+
+    fn on_deadline(&mut self, _now: Instant) -> bool {
+        self.flush()
+    }
+}
+
+struct Tee<T> {
+    input: Input<T>,
+    /// 已释放接收者的输出置为 `None`
+    outputs: Vec<Option<Sender<T>>>,
+}
+
+impl<T: Clone + Send> Stage for Tee<T> {
+    fn arms<'a>(&'a self, select: Select<'a, Wake>, index: usize) -> Select<'a, Wake> {
+        self.input.arm(select, index)
+    }
+
+    fn on_input(&mut self) -> bool {
+        match self.input.take() {
+            Some(Some(msg)) => {
+                for output in &mut self.outputs {
+                    if output.as_ref().is_some_and(|tx| tx.send(msg.clone()).is_err()) {
+                        *output = None;
+                    }
+                }
+                self.outputs.iter().any(Option::is_some)
+            }
+            Some(None) => false,
+            None => true,
+        }
+    }
+}
+
+struct Merge<T> {
+    inputs: Vec<Input<T>>,
+    output: Sender<T>,
+}
+
+impl<T: Send> Stage for Merge<T> {
+    fn arms<'a>(&'a self, select: Select<'a, Wake>, index: usize) -> Select<'a, Wake> {
+        self.inputs.iter().fold(select, |select, input| input.arm(select, index))
+    }
+
+    fn on_input(&mut self) -> bool {
+        for input in &mut self.inputs {
+            if let Some(Some(msg)) = input.take()
+                && self.output.send(msg).is_err()
+            {
+                return false;
+            }
+        }
+        !self.inputs.iter().all(|input| input.closed)
+    }
+}
+
+struct Zip<A, B> {
+    left: Input<A>,
+    right: Input<B>,
+    output: Sender<(A, B)>,
+    /// 每边最多暂存一条，凑齐一对才继续接收
+    pending: (Option<A>, Option<B>),
+}
+
+impl<A: Send, B: Send> Stage for Zip<A, B> {
+    fn arms<'a>(&'a self, mut select: Select<'a, Wake>, index: usize) -> Select<'a, Wake> {
+        if self.pending.0.is_none() {
+            select = self.left.arm(select, index);
+        }
+        if self.pending.1.is_none() {
+            select = self.right.arm(select, index);
+        }
+        select
+    }
+
+    fn on_input(&mut self) -> bool {
+        // 任意一边断开后再也凑不齐新的一对
+        match self.left.take() {
+            Some(Some(msg)) => self.pending.0 = Some(msg),
+            Some(None) => return false,
+            None => {}
+        }
+        match self.right.take() {
+            Some(Some(msg)) => self.pending.1 = Some(msg),
+            Some(None) => return false,
+            None => {}
+        }
+        match &mut self.pending {
+            (left @ Some(_), right @ Some(_)) => {
+                let pair = (left.take().unwrap(), right.take().unwrap());
+                self.output.send(pair).is_ok()
+            }
+            _ => !self.output.is_disconnected(),
+        }
+    }
+}
+
+struct RateLimit<T> {
+    input: Input<T>,
+    output: Sender<T>,
+    interval: Duration,
+    next_allowed: Instant,
+    /// 还没到发送时间的消息；有暂存时不再接收新消息，背压传给上游
+    pending: Option<T>,
+}
+
+impl<T> RateLimit<T> {
+    fn emit(&mut self, msg: T, now: Instant) -> bool {
+        self.next_allowed = now + self.interval;
+        self.output.send(msg).is_ok()
+    }
+}
+
+impl<T: Send> Stage for RateLimit<T> {
+    fn arms<'a>(&'a self, select: Select<'a, Wake>, index: usize) -> Select<'a, Wake> {
+        if self.pending.is_some() {
+            return select;
+        }
+        self.input.arm(select, index)
+    }
+
+    fn on_input(&mut self) -> bool {
+        match self.input.take() {
+            Some(Some(msg)) => {
+                let now = Instant::now();
+                if now >= self.next_allowed {
+                    return self.emit(msg, now);
+                }
+                self.pending = Some(msg);
+                true
+            }
+            Some(None) => false,
+            None => true,
+        }
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        self.pending.as_ref().map(|_| self.next_allowed)
+    }
+
+    fn on_deadline(&mut self, now: Instant) -> bool {
+        match self.pending.take() {
+            Some(msg) => self.emit(msg, now),
+            None => true,
+        }
+    }
+}
+
+// ==================== 线程池 ====================
+
+/// 承载适配器的小线程池
+///
+/// 池本身释放后，已创建的适配器继续运行，池线程在自己负责的适配器全部结束后退出。
+pub struct AdapterPool {
+    controls: Vec<Sender<Box<dyn Stage>>>,
+    next: AtomicUsize,
+}
+
+impl AdapterPool {
+    pub fn new(threads: usize) -> Self {
+        assert!(threads > 0, "至少需要一个线程");
+        let controls = (0..threads)
+            .map(|i| {
+                let (tx, rx) = channel::unbounded();
+                thread::Builder::new()
+                    .name(format!("adapter-{}", i))
+                    .spawn(move || run(rx))
+                    .expect("创建适配器线程失败");
+                tx
+            })
+            .collect();
+        Self {
+            controls,
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// 轮流分配给池线程
+    fn add(&self, stage: Box<dyn Stage>) {
+        let index = self.next.fetch_add(1, Ordering::Relaxed) % self.controls.len();
+        // 池线程只在控制通道断开后退出，而发送者就在这里
+        self.controls[index].send(stage).expect("适配器线程已退出");
+    }
+
+    /// `f` 在池线程上运行，不能阻塞
+    pub fn map<T, U>(&self, input: Receiver<T>, mut f: impl FnMut(T) -> U + Send + 'static) -> Receiver<U>
+    where
+        T: Send + 'static,
+        U: Send + 'static,
+    {
+        self.transform(input, move |msg| Some(f(msg)))
+    }
+
+    /// `predicate` 在池线程上运行，不能阻塞
+    pub fn filter<T>(&self, input: Receiver<T>, mut predicate: impl FnMut(&T) -> bool + Send + 'static) -> Receiver<T>
+    where
+        T: Send + 'static,
+    {
+        self.transform(input, move |msg| predicate(&msg).then_some(msg))
+    }
+
+    fn transform<T, U, F>(&self, input: Receiver<T>, f: F) -> Receiver<U>
+    where
+        T: Send + 'static,
+        U: Send + 'static,
+        F: FnMut(T) -> Option<U> + Send + 'static,
+    {
+        let (output, rx) = channel::unbounded();
+        self.add(Box::new(Transform {
+            input: Input::new(input),
+            output,
+            f,
+        }));
+        rx
+    }
+
+    /// 攒满 `size` 条或者批次中第一条消息等待超过 `timeout` 时输出一批
+    pub fn batch<T: Send + 'static>(&self, input: Receiver<T>, size: usize, timeout: Duration) -> Receiver<Vec<T>> {
+        assert!(size > 0, "批次大小至少为 1");
+        let (output, rx) = channel::unbounded();
+        self.add(Box::new(Batch {
+            input: Input::new(input),
+            output,
+            size,
+            timeout,
+            buffer: Vec::with_capacity(size),
+            started: None,
+        }));
+        rx
+    }
+
+    /// 把每条消息复制给 `n` 个接收者，某个接收者释放后其余的不受影响
+    pub fn tee<T: Clone + Send + 'static>(&self, input: Receiver<T>, n: usize) -> Vec<Receiver<T>> {
+        let (outputs, receivers) = (0..n).map(|_| channel::unbounded()).map(|(tx, rx)| (Some(tx), rx)).unzip();
+        self.add(Box::new(Tee {
+            input: Input::new(input),
+            outputs,
+        }));
+        receivers
+    }
+
+    /// 合并多个输入，保持每个输入内部的顺序，全部断开后结束
+    pub fn merge<T: Send + 'static>(&self, inputs: Vec<Receiver<T>>) -> Receiver<T> {
+        let (output, rx) = channel::unbounded();
+        self.add(Box::new(Merge {
+            inputs: inputs.into_iter().map(Input::new).collect(),
+            output,
+        }));
+        rx
+    }
+
+    /// 按顺序配对两个输入，任意一边断开后结束
+    pub fn zip<A, B>(&self, left: Receiver<A>, right: Receiver<B>) -> Receiver<(A, B)>
+    where
+        A: Send + 'static,
+        B: Send + 'static,
+    {
+        let (output, rx) = channel::unbounded();
+        self.add(Box::new(Zip {
+            left: Input::new(left),
+            right: Input::new(right),
+            output,
+            pending: (None, None),
+        }));
+        rx
+    }
+
+    /// 相邻两条输出至少间隔 `interval`
+    pub fn rate_limit<T: Send + 'static>(&self, input: Receiver<T>, interval: Duration) -> Receiver<T> {
+        let (output, rx) = channel::unbounded();
+        self.add(Box::new(RateLimit {
+            input: Input::new(input),
+            output,
+            interval,
+            next_allowed: Instant::now(),
+            pending: None,
+        }));
+        rx
+    }
+}
+
+/// 运行适配器的一步；用户闭包 panic 时视为该适配器结束，池线程继续服务其他适配器
+fn guarded(step: impl FnOnce() -> bool) -> bool {
+    panic::catch_unwind(AssertUnwindSafe(step)).unwrap_or(false)
+}
+
+/// 池线程：同时等待控制通道、所有适配器的输入和最近的定时
+fn run(control: Receiver<Box<dyn Stage>>) {
+    let mut stages: Vec<Box<dyn Stage>> = Vec::new();
+    let mut accepting = true;
+
+    while accepting || !stages.is_empty() {
+        let mut select = Select::new();
+        if accepting {
+            select = select.recv(&control, |stage| match stage {
+                Ok(stage) => Wake::Added(stage),
+                Err(_) => Wake::ControlClosed,
+            });
+        }
+        for (index, stage) in stages.iter().enumerate() {
+            select = stage.arms(select, index);
+        }
+        if let Some(deadline) = stages.iter().filter_map(|stage| stage.deadline()).min() {
+            select = select.timeout(deadline.saturating_duration_since(Instant::now()), || Wake::Deadline);
+        }
+
+        match select.wait() {
+            Wake::Added(stage) => stages.push(stage),
+            Wake::ControlClosed => accepting = false,
+            Wake::Input(index) => {
+                if !guarded(|| stages[index].on_input()) {
+                    // 释放适配器，关闭向下游和上游传播
+                    stages.swap_remove(index);
+                }
+            }
+            Wake::Deadline => {
+                let now = Instant::now();
+                stages.retain_mut(|stage| match stage.deadline() {
+                    Some(deadline) if deadline <= now => guarded(|| stage.on_deadline(now)),
+                    _ => true,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source<T: Send + 'static>(items: Vec<T>) -> Receiver<T> {
+        let (tx, rx) = channel::unbounded();
+        for item in items {
+            tx.send(item).unwrap();
+        }
+        rx
+    }
+
+    #[test]
+    fn test_chain_preserves_order() {
+        let pool = AdapterPool::new(2);
+        let evens = pool.filter(source((0..1000).collect()), |n| n % 2 == 0);
+        let squares = pool.map(evens, |n: u64| n * n);
+        let batches = pool.batch(squares, 100, Duration::from_secs(10));
+
+        let batches: Vec<Vec<u64>> = batches.iter().collect();
+        assert_eq!(batches.len(), 5);
+        let flat: Vec<u64> = batches.concat();
+        assert_eq!(flat, (0..1000).filter(|n| n % 2 == 0).map(|n| n * n).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_batch_flushes_on_timeout() {
+        let pool = AdapterPool::new(1);
+        let (tx, rx) = channel::unbounded();
+        let batches = pool.batch(rx, 10, Duration::from_millis(30));
+
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        let started = Instant::now();
+        assert_eq!(batches.recv_timeout(Duration::from_secs(1)), Ok(vec![1, 2]));
+        assert!(started.elapsed() >= Duration::from_millis(20));
+
+        // 断开时输出剩余的消息，然后关闭输出
+        tx.send(3).unwrap();
+        drop(tx);
+        assert_eq!(batches.iter().collect::<Vec<_>>(), [vec![3]]);
+    }
+
+    #[test]
+    fn test_tee_merge_and_zip() {
+        let pool = AdapterPool::new(2);
+        let mut copies = pool.tee(source(vec![1, 2, 3]), 3);
+        let third = copies.pop().unwrap();
+        let second = copies.pop().unwrap();
+        let first = copies.pop().unwrap();
+
+        let letters = source(vec!['a', 'b']);
+        let pairs: Vec<_> = pool.zip(first, letters).iter().collect();
+        assert_eq!(pairs, [(1, 'a'), (2, 'b')]);
+
+        let mut merged: Vec<_> = pool.merge(vec![second, third, source(vec![10])]).iter().collect();
+        merged.sort();
+        assert_eq!(merged, [1, 1, 2, 2, 3, 3, 10]);
+    }
+
+    #[test]
+    fn test_rate_limit_spacing() {
+        let pool = AdapterPool::new(1);
+        let limited = pool.rate_limit(source((0..5).collect()), Duration::from_millis(20));
+
+        let started = Instant::now();
+        let received: Vec<i32> = limited.iter().collect();
+        assert_eq!(received, [0, 1, 2, 3, 4]);
+        // 第一条立即发出，之后每条间隔 20ms
+        assert!(started.elapsed() >= Duration::from_millis(80));
+    }
+
+    #[test]
+    fn test_shutdown_propagates_both_ways() {
+        let pool = AdapterPool::new(2);
+
+        // 向下游：源头断开后，整条流水线依次关闭
+        let (tx, rx) = channel::unbounded::<i32>();
+        let output = pool.map(pool.filter(pool.map(rx, |n| n + 1), |_| true), |n| n * 2);
+        tx.send(1).unwrap();
+        drop(tx);
+        assert_eq!(output.iter().collect::<Vec<_>>(), [4]);
+
+        // 向上游：最终接收者释放后，下一条消息让各级依次发现并释放输入
+        let (tx, rx) = channel::unbounded::<i32>();
+        let output = pool.map(pool.map(rx, |n| n + 1), |n| n * 2);
+        tx.send(1).unwrap();
+        assert_eq!(output.recv(), Ok(4));
+        drop(output);
+
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while tx.send(2).is_ok() {
+            assert!(Instant::now() < deadline, "上游没有收到关闭");
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn test_panicking_closure_only_ends_its_own_adapter() {
+        let pool = AdapterPool::new(1);
+        let (tx, rx) = channel::unbounded::<i32>();
+        let healthy = pool.map(rx, |n| n + 1);
+        let broken = pool.map(source(vec![1, 2, 3]), |n: i32| {
+            assert!(n != 2, "故意 panic");
+            n
+        });
+
+        // panic 的适配器被移除，输出在 panic 之前的消息之后断开
+        assert_eq!(broken.iter().collect::<Vec<_>>(), [1]);
+
+        // 同一线程上的其他适配器照常工作，池也还能继续添加适配器
+        tx.send(1).unwrap();
+        assert_eq!(healthy.recv_timeout(Duration::from_secs(1)), Ok(2));
+        let later = pool.map(source(vec![10]), |n: i32| n * 2);
+        assert_eq!(later.iter().collect::<Vec<_>>(), [20]);
+    }
+
+    #[test]
+    fn test_shutdown_propagates_without_output() {
+        let pool = AdapterPool::new(1);
+
+        // 全部被过滤掉的 filter 和攒不满的 batch 都从不发送，也要发现下游已释放
+        let (filter_tx, rx) = channel::unbounded::<i32>();
+        drop(pool.filter(rx, |_| false));
+        let (batch_tx, rx) = channel::unbounded::<i32>();
+        drop(pool.batch(rx, 100, Duration::from_secs(60)));
+
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while filter_tx.send(1).is_ok() || batch_tx.send(1).is_ok() {
+            assert!(Instant::now() < deadline, "上游没有收到关闭");
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn test_many_adapters_share_few_threads() {
+        let pool = AdapterPool::new(2);
+        let mut rx = source((0..100).collect::<Vec<u32>>());
+        for _ in 0..50 {
+            rx = pool.map(rx, |n| n + 1);
+        }
+        let result: Vec<u32> = rx.iter().collect();
+        assert_eq!(result, (50..150).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,686 @@
+//! # 可以同时等待多个通道的 channel 与 Select
+//!
+//! `std::sync::mpsc` 的接收者只能一个一个地等待，想同时等两个通道时只能用 `try_recv`
+//! 轮询再睡一会儿：消息最多晚到一个睡眠周期，空闲时线程也在不停地醒来。
+//!
+//! 本模块的通道接口和错误类型与 `mpsc` 一致（`unbounded` 对应 `channel`，`bounded` 对应
+//! `sync_channel`），区别是每个通道都维护着等待者队列，[`Select`] 可以把当前线程同时挂到
+//! 多个通道上：
+//! - 任意一个分支就绪（有消息、有空位、或对端已断开）就立即唤醒，没有轮询
+//! - `timeout` 分支限定最长等待时间，`default` 分支在没有分支就绪时立即返回
+//! - 每次选择都从随机位置开始检查分支，一直就绪的通道不会饿死其他分支
+//!
+//! 接收者也可以克隆（MPMC），多个工作线程直接各持一个 `Receiver` 取任务，不需要
+//! `Arc<Mutex<Receiver>>` 把每次接收都串行化。断开语义与 `mpsc` 相同：所有发送者释放后，
+//! 接收者取完剩余消息再收到 `RecvError`；所有接收者释放后，发送失败并带回原消息。
+//!
+//! ```text
+//! let event = Select::new()
+//!     .recv(&orders, |order| Event::Order(order))
+//!     .send(&audit, record, |result| Event::Audited(result))
+//!     .timeout(Duration::from_secs(1), || Event::Idle)
+//!     .wait();
+//! ```
+
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{RecvError, RecvTimeoutError, SendError, TryRecvError, TrySendError};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, Thread};
+use std::time::{Duration, Instant};
+
+// ==================== 等待者 ====================
+
+const WAITING: usize = 0;
+const ABORTED: usize = usize::MAX;
+
+/// 一次阻塞的选择：同时挂在若干通道上，第一个通知它的通道“认领”它
+struct Waiter {
+    thread: Thread,
+    /// `WAITING`、`ABORTED`，或者认领它的分支下标 + 1
+    state: AtomicUsize,
+}
+
+impl Waiter {
+    fn new() -> Arc<Self> {
+        Arc::new(Self {
+            thread: thread::current(),
+            state: AtomicUsize::new(WAITING),
+        })
+    }
+
+    /// 由通道调用，只有第一个认领者能成功
+    fn claim(&self, arm: usize) -> bool {
+        let claimed = self
+            .state
+            .compare_exchange(WAITING, arm + 1, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok();
+        if claimed {
+            self.thread.unpark();
+        }
+        claimed
+    }
+
+    /// 睡到被认领或超过截止时间（park 可能虚假唤醒，所以要循环检查）
+    fn park(&self, deadline: Option<Instant>) {
+        while self.state.load(Ordering::Acquire) == WAITING {
+            match deadline {
+                None => thread::park(),
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return;
+                    }
+                    thread::park_timeout(deadline - now);
+                }
+            }
+        }
+    }
+
+    /// 停止接受认领，返回此前认领它的分支
+    fn abort(&self) -> Option<usize> {
+        match self.state.compare_exchange(WAITING, ABORTED, Ordering::AcqRel, Ordering::Acquire) {
+            Ok(_) => None,
+            Err(state) => Some(state - 1),
+        }
+    }
+}
+
+/// 某个通道上等待收或发的选择，先来先唤醒
+#[derive(Default)]
+struct WaitList(VecDeque<(Arc<Waiter>, usize)>);
+
+impl WaitList {
+    fn register(&mut self, waiter: &Arc<Waiter>, arm: usize) {
+        self.0.push_back((waiter.clone(), arm));
+    }
+
+    fn unregister(&mut self, waiter: &Arc<Waiter>) {
+        self.0.retain(|(registered, _)| !Arc::ptr_eq(registered, waiter));
+    }
+
+    /// 唤醒最早开始等待、且还没被其他通道认领的一个等待者
+    fn notify_one(&mut self) {
+        while let Some((waiter, arm)) = self.0.pop_front() {
+            if waiter.claim(arm) {
+                return;
+            }
+        }
+    }
+
+    fn notify_all(&mut self) {
+        for (waiter, arm) in self.0.drain(..) {
+            waiter.claim(arm);
+        }
+    }
+}
+
+// ==================== 通道 ====================
+
+struct State<T> {
+    queue: VecDeque<T>,
+    /// `None` 表示无界
+    capacity: Option<usize>,
+    senders: usize,
+    receivers: usize,
+    recv_waiters: WaitList,
+    send_waiters: WaitList,
+}
+
+impl<T> State<T> {
+    fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        match self.queue.pop_front() {
+            Some(value) => {
+                self.send_waiters.notify_one();
+                Ok(value)
+            }
+            None if self.senders == 0 => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    fn try_send(&mut self, value: T) -> Result<(), TrySendError<T>> {
+        if self.receivers == 0 {
+            return Err(TrySendError::Disconnected(value));
+        }
+        if self.capacity.is_some_and(|capacity| self.queue.len() >= capacity) {
+            return Err(TrySendError::Full(value));
+        }
+        self.queue.push_back(value);
+        self.recv_waiters.notify_one();
+        Ok(())
+    }
+}
+
+type Chan<T> = Arc<Mutex<State<T>>>;
+
+fn new_channel<T>(capacity: Option<usize>) -> (Sender<T>, Receiver<T>) {
+    let chan = Arc::new(Mutex::new(State {
+        queue: VecDeque::new(),
+        capacity,
+        senders: 1,
+        receivers: 1,
+        recv_waiters: WaitList::default(),
+        send_waiters: WaitList::default(),
+    }));
+    (Sender { chan: chan.clone() }, Receiver { chan })
+}
+
+/// 无界通道，对应 `mpsc::channel`
+pub fn unbounded<T>() -> (Sender<T>, Receiver<T>) {
+    new_channel(None)
+}
+
+/// 有界通道，对应 `mpsc::sync_channel`；不支持容量为 0 的会合通道
+pub fn bounded<T>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(capacity > 0, "bounded 通道的容量至少为 1");
+    new_channel(Some(capacity))
+}
+
+pub struct Sender<T> {
+    chan: Chan<T>,
+}
+
+impl<T> Sender<T> {
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        self.chan.lock().unwrap()
+    }
+
+    /// 有界通道满时阻塞，接收者已释放时返回原消息
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        match self.try_send(value) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Disconnected(value)) => Err(SendError(value)),
+            Err(TrySendError::Full(value)) => Select::new().send(self, value, |result| result).wait(),
+        }
+    }
+
+    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        self.lock().try_send(value)
+    }
+
+    /// 所有接收者是否都已释放，此后的发送必然失败
+    pub fn is_disconnected(&self) -> bool {
+        self.lock().receivers == 0
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.lock().senders += 1;
+        Self { chan: self.chan.clone() }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut state = self.lock();
+        state.senders -= 1;
+        if state.senders == 0 {
+            // 所有接收方的等待都会以 Disconnected 结束
+            state.recv_waiters.notify_all();
+        }
+    }
+}
+
+pub struct Receiver<T> {
+    chan: Chan<T>,
+}
+
+impl<T> Receiver<T> {
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        self.chan.lock().unwrap()
+    }
+
+    /// 阻塞到有消息；通道为空且所有发送者都已释放时返回错误
+    pub fn recv(&self) -> Result<T, RecvError> {
+        match self.try_recv() {
+            Ok(value) => Ok(value),
+            Err(TryRecvError::Disconnected) => Err(RecvError),
+            Err(TryRecvError::Empty) => Select::new().recv(self, |result| result).wait(),
+        }
+    }
+
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        self.lock().try_recv()
+    }
+
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        Select::new()
+            .recv(self, |result| result.map_err(|_| RecvTimeoutError::Disconnected))
+            .timeout(timeout, || Err(RecvTimeoutError::Timeout))
+            .wait()
+    }
+
+    /// 阻塞迭代，所有发送者释放且消息取完后结束
+    pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+        std::iter::from_fn(move || self.recv().ok())
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Self {
+        self.lock().receivers += 1;
+        Self { chan: self.chan.clone() }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let mut state = self.lock();
+        state.receivers -= 1;
+        if state.receivers == 0 {
+            state.send_waiters.notify_all();
+            // 和 mpsc 一样，没人接收的消息立即释放（在锁外，消息的 Drop 可能还要访问其他通道）
+            let discarded = std::mem::take(&mut state.queue);
+            drop(state);
+            drop(discarded);
+        }
+    }
+}
+
+// ==================== Select ====================
+
+/// 一个待选择的操作，返回值由分支的闭包转换为统一的 `R`
+trait Arm<R> {
+    /// 持有通道锁时尝试完成操作；不能完成就把等待者登记到通道上，这样不会漏掉通知
+    fn try_or_register(&mut self, waiter: &Arc<Waiter>, index: usize) -> Option<R>;
+
+    fn unregister(&self, waiter: &Arc<Waiter>);
+
+    /// 被这个通道唤醒、最后却完成了别的分支时，把唤醒转交给通道上的下一个等待者
+    fn renotify(&self);
+}
+
+struct RecvArm<'a, T, F> {
+    receiver: &'a Receiver<T>,
+    callback: Option<F>,
+}
+
+impl<T, R, F: FnOnce(Result<T, RecvError>) -> R> Arm<R> for RecvArm<'_, T, F> {
+    fn try_or_register(&mut self, waiter: &Arc<Waiter>, index: usize) -> Option<R> {
+        let mut state = self.receiver.lock();
+        let result = match state.try_recv() {
+            Ok(value) => Ok(value),
+            Err(TryRecvError::Disconnected) => Err(RecvError),
+            Err(TryRecvError::Empty) => {
+                state.recv_waiters.register(waiter, index);
+                return None;
+            }
+        };
+        drop(state);
+        self.callback.take().map(|callback| callback(result))
+    }
+
+    fn unregister(&self, waiter: &Arc<Waiter>) {
+        self.receiver.lock().recv_waiters.unregister(waiter);
+    }
+
+    fn renotify(&self) {
+        self.receiver.lock().recv_waiters.notify_one();
+    }
+}
+
+struct SendArm<'a, T, F> {
+    sender: &'a Sender<T>,
+    value: Option<T>,
+    callback: Option<F>,
+}
+
+impl<T, R, F: FnOnce(Result<(), SendError<T>>) -> R> Arm<R> for SendArm<'_, T, F> {
+    fn try_or_register(&mut self, waiter: &Arc<Waiter>, index: usize) -> Option<R> {
+        let mut state = self.sender.lock();
+        let value = self.value.take()?;
+        let result = match state.try_send(value) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Disconnected(value)) => Err(SendError(value)),
+            Err(TrySendError::Full(value)) => {
+                self.value = Some(value);
+                state.send_waiters.register(waiter, index);
+                return None;
+            }
+        };
+        drop(state);
+        self.callback.take().map(|callback| callback(result))
+    }
+
+    fn unregister(&self, waiter: &Arc<Waiter>) {
+        self.sender.lock().send_waiters.unregister(waiter);
+    }
+
+    fn renotify(&self) {
+        self.sender.lock().send_waiters.notify_one();
+    }
+}
+
+/// 同时等待多个收发操作，只执行其中最先就绪的一个
+///
+/// 断开的通道也算就绪：接收分支收到 `Err(RecvError)`，发送分支收到带回原消息的 `Err(SendError)`。
+pub struct Select<'a, R> {
+    arms: Vec<Box<dyn Arm<R> + 'a>>,
+    default: Option<Box<dyn FnOnce() -> R + 'a>>,
+    timeout: Option<(Duration, Box<dyn FnOnce() -> R + 'a>)>,
+}
+
+impl<'a, R> Select<'a, R> {
+    pub fn new() -> Self {
+        Self {
+            arms: Vec::new(),
+            default: None,
+            timeout: None,
+        }
+    }
+
+    pub fn recv<T>(mut self, receiver: &'a Receiver<T>, callback: impl FnOnce(Result<T, RecvError>) -> R + 'a) -> Self {
+        self.arms.push(Box::new(RecvArm {
+            receiver,
+            callback: Some(callback),
+        }));
+        self
+    }
+
+    pub fn send<T>(
+        mut self,
+        sender: &'a Sender<T>,
+        value: T,
+        callback: impl FnOnce(Result<(), SendError<T>>) -> R + 'a,
+    ) -> Self {
+        self.arms.push(Box::new(SendArm {
+            sender,
+            value: Some(value),
+            callback: Some(callback),
+        }));
+        self
+    }
+
+    /// 没有分支就绪时不阻塞，直接执行 `callback`
+    pub fn default(mut self, callback: impl FnOnce() -> R + 'a) -> Self {
+        self.default = Some(Box::new(callback));
+        self
+    }
+
+    /// 从调用 `wait` 起超过 `timeout` 仍没有分支就绪时执行 `callback`
+    pub fn timeout(mut self, timeout: Duration, callback: impl FnOnce() -> R + 'a) -> Self {
+        self.timeout = Some((timeout, Box::new(callback)));
+        self
+    }
+
+    /// 阻塞到某个分支完成（或 default / timeout），返回对应闭包的结果
+    pub fn wait(mut self) -> R {
+        let deadline = self.timeout.as_ref().map(|(timeout, _)| Instant::now() + *timeout);
+        let count = self.arms.len();
+        // 上一轮唤醒我们的分支
+        let mut woken_by = None;
+
+        loop {
+            let waiter = Waiter::new();
+            let start = random_index(count);
+            let mut registered = Vec::with_capacity(count);
+            let mut completed = None;
+
+            for offset in 0..count {
+                let index = (start + offset) % count;
+                match self.arms[index].try_or_register(&waiter, index) {
+                    Some(result) => {
+                        completed = Some((index, result));
+                        break;
+                    }
+                    None => registered.push(index),
+                }
+            }
+
+            if completed.is_none() && self.default.is_none() {
+                waiter.park(deadline);
+            }
+            let claimed = waiter.abort();
+            for &index in &registered {
+                self.arms[index].unregister(&waiter);
+            }
+
+            if let Some((index, result)) = completed {
+                // 唤醒没有被用上，交给同一通道上的其他等待者，否则它们会一直睡下去
+                for woken in [woken_by, claimed].into_iter().flatten() {
+                    if woken != index {
+                        self.arms[woken].renotify();
+                    }
+                }
+                return result;
+            }
+            if claimed.is_some() {
+                woken_by = claimed;
+                continue;
+            }
+            if let Some(default) = self.default.take() {
+                return default();
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline)
+                && let Some((_, timeout)) = self.timeout.take()
+            {
+                return timeout();
+            }
+        }
+    }
+}
+
+/// 每个线程一个 xorshift 生成器，决定本轮从哪个分支开始检查
+fn random_index(count: usize) -> usize {
+    thread_local! {
+        static SEED: Cell<u64> = Cell::new(RandomState::new().hash_one(thread::current().id()) | 1);
+    }
+    if count == 0 {
+        return 0;
+    }
+    SEED.with(|seed| {
+        let mut x = seed.get();
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        seed.set(x);
+        (x % count as u64) as usize
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    enum Picked {
+        First(i32),
+        Second(i32),
+        Closed,
+        Idle,
+    }
+
+    #[test]
+    fn test_select_wakes_on_any_channel() {
+        let (_tx1, rx1) = unbounded::<i32>();
+        let (tx2, rx2) = unbounded();
+
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            tx2.send(7).unwrap();
+        });
+
+        let started = Instant::now();
+        let selected = Select::new()
+            .recv(&rx1, |msg| Picked::First(msg.unwrap()))
+            .recv(&rx2, |msg| Picked::Second(msg.unwrap()))
+            .wait();
+        assert_eq!(selected, Picked::Second(7));
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_default_and_timeout_arms() {
+        let (_tx, rx) = unbounded::<i32>();
+
+        let selected = Select::new().recv(&rx, |_| Picked::Closed).default(|| Picked::Idle).wait();
+        assert_eq!(selected, Picked::Idle);
+
+        let started = Instant::now();
+        let selected = Select::new()
+            .recv(&rx, |_| Picked::Closed)
+            .timeout(Duration::from_millis(30), || Picked::Idle)
+            .wait();
+        assert_eq!(selected, Picked::Idle);
+        assert!(started.elapsed() >= Duration::from_millis(30));
+        assert_eq!(rx.recv_timeout(Duration::from_millis(5)), Err(RecvTimeoutError::Timeout));
+    }
+
+    #[test]
+    fn test_disconnect_is_ready() {
+        let (tx, rx) = unbounded::<i32>();
+        let handle = thread::spawn(move || Select::new().recv(&rx, |msg| msg.is_err()).wait());
+        thread::sleep(Duration::from_millis(10));
+        drop(tx);
+        assert!(handle.join().unwrap());
+
+        // 接收者释放后发送分支带回原消息
+        let (tx, rx) = bounded(1);
+        drop(rx);
+        let returned = Select::new().send(&tx, 5, |result| result.unwrap_err().0).wait();
+        assert_eq!(returned, 5);
+    }
+
+    #[test]
+    fn test_send_arm_waits_for_capacity() {
+        let (tx, rx) = bounded(1);
+        tx.send(1).unwrap();
+        assert!(matches!(tx.try_send(2), Err(TrySendError::Full(2))));
+
+        let consumer = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            let first = rx.recv().unwrap();
+            (first, rx.recv().unwrap())
+        });
+
+        let (_other_tx, other_rx) = unbounded::<i32>();
+        let selected = Select::new()
+            .recv(&other_rx, |_| "recv")
+            .send(&tx, 2, |result| {
+                result.unwrap();
+                "send"
+            })
+            .wait();
+        assert_eq!(selected, "send");
+        assert_eq!(consumer.join().unwrap(), (1, 2));
+    }
+
+    #[test]
+    fn test_fairness_between_ready_arms() {
+        let (tx1, rx1) = unbounded();
+        let (tx2, rx2) = unbounded();
+        for i in 0..2000 {
+            tx1.send(i).unwrap();
+            tx2.send(i).unwrap();
+        }
+
+        let mut first = 0;
+        for _ in 0..2000 {
+            let picked_first = Select::new().recv(&rx1, |_| true).recv(&rx2, |_| false).wait();
+            first += picked_first as usize;
+        }
+        // 两个通道一直都有消息，随机起点让它们各被选中约一半
+        assert!((800..=1200).contains(&first), "通道1被选中 {} 次", first);
+    }
+
+    #[test]
+    fn test_cloned_receivers_share_work() {
+        let (tx, rx) = bounded(8);
+        let workers: Vec<_> = (0..4)
+            .map(|_| {
+                let rx = rx.clone();
+                thread::spawn(move || rx.iter().collect::<Vec<u32>>())
+            })
+            .collect();
+        drop(rx);
+
+        for i in 0..10_000 {
+            tx.send(i).unwrap();
+        }
+        drop(tx);
+
+        // 每条消息恰好被一个工作线程收到，所有工作线程都因断开而退出
+        let mut received: Vec<u32> = workers.into_iter().flat_map(|worker| worker.join().unwrap()).collect();
+        received.sort();
+        assert_eq!(received, (0..10_000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_disconnect_waits_for_last_clone() {
+        let (tx, rx) = unbounded();
+        let rx2 = rx.clone();
+        drop(rx);
+        assert!(tx.send(1).is_ok());
+        assert_eq!(rx2.recv(), Ok(1));
+        drop(rx2);
+        assert_eq!(tx.send(2), Err(SendError(2)));
+
+        // 发送者全部释放时，阻塞中的多个接收者都会醒来
+        let (tx, rx) = unbounded::<i32>();
+        let blocked: Vec<_> = (0..3)
+            .map(|_| {
+                let rx = rx.clone();
+                thread::spawn(move || rx.recv())
+            })
+            .collect();
+        thread::sleep(Duration::from_millis(20));
+        let tx2 = tx.clone();
+        drop(tx);
+        tx2.send(9).unwrap();
+        drop(tx2);
+        let results: Vec<_> = blocked.into_iter().map(|handle| handle.join().unwrap()).collect();
+        assert_eq!(results.iter().filter(|result| **result == Ok(9)).count(), 1);
+        assert_eq!(results.iter().filter(|result| **result == Err(RecvError)).count(), 2);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+    }
+
+    #[test]
+    fn test_select_latency_comparable_to_recv() {
+        const ROUNDS: usize = 500;
+
+        // 回声线程：收到什么就原样发回，单独 recv 和 select 用同一套线程
+        fn echo_rounds(wait_reply: impl Fn(&Receiver<Instant>) -> Instant) -> Duration {
+            let (ping_tx, ping_rx) = unbounded::<Instant>();
+            let (pong_tx, pong_rx) = unbounded();
+            let echo = thread::spawn(move || {
+                for sent in ping_rx.iter() {
+                    pong_tx.send(sent).unwrap();
+                }
+            });
+
+            let mut samples = Vec::with_capacity(ROUNDS);
+            for _ in 0..ROUNDS {
+                ping_tx.send(Instant::now()).unwrap();
+                samples.push(wait_reply(&pong_rx).elapsed());
+            }
+            drop(ping_tx);
+            echo.join().unwrap();
+            samples.sort();
+            samples[ROUNDS / 2]
+        }
+
+        let recv_median = echo_rounds(|pong| pong.recv().unwrap());
+
+        let (_idle_txs, idle_rxs): (Vec<_>, Vec<_>) = (0..3).map(|_| unbounded::<Instant>()).unzip();
+        let select_median = echo_rounds(|pong| {
+            idle_rxs
+                .iter()
+                .fold(Select::new(), |select, rx| select.recv(rx, |_| unreachable!("空闲通道不会就绪")))
+                .recv(pong, |sent| sent.unwrap())
+                .wait()
+        });
+
+        // 轮询版本每轮要睡 50ms；这里和单个 recv 处于同一量级
+        assert!(
+            select_median <= recv_median * 5 + Duration::from_micros(200),
+            "recv {:?}, select {:?}",
+            recv_median,
+            select_median
+        );
+    }
+}
@@ -3,12 +3,20 @@
 //! 本教程基于 https://course.rs/advance/concurrency-with-threads/message-passing.html
 //! 深入分析 Rust 中的消息传递机制，涵盖所有相关知识点
 
-use std::sync::mpsc::{self, Receiver, Sender, SyncSender};
+use std::sync::mpsc::{self, Sender, SyncSender};
 use std::thread;
 use std::time::{Duration, Instant};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
+mod actor;
+mod adapters;
+mod channel;
+
+use actor::{Actor, Addr, Context, Reply, RestartPolicy, SupervisionEvent, System};
+use adapters::AdapterPool;
+use channel::Select;
+
 fn main() {
     println!("=== Rust 线程同步：消息传递 (Message Passing) 全面教程 ===");
     println!();
@@ -152,25 +160,19 @@ fn multiple_producers_demo() {
 
 /// 工作分发模式：主线程分发任务，多个工作线程处理
 fn work_distribution_pattern() {
-    let (job_tx, job_rx) = mpsc::channel();
+    // 多消费者通道：每个工作线程持有一个接收者的克隆，不需要 Arc<Mutex<Receiver>>
+    let (job_tx, job_rx) = channel::unbounded();
     let (result_tx, result_rx) = mpsc::channel();
     
-    let job_rx = Arc::new(Mutex::new(job_rx));
-    
     // 创建工作线程池
     let num_workers = 3;
     for worker_id in 0..num_workers {
-        let job_rx = Arc::clone(&job_rx);
+        let job_rx = job_rx.clone();
         let result_tx = result_tx.clone();
         
         thread::spawn(move || {
             loop {
-                let job = {
-                    let rx = job_rx.lock().unwrap();
-                    rx.recv()
-                };
-                
-                match job {
+                match job_rx.recv() {
                     Ok(n) => {
                         println!("    工作线程 {} 处理任务: {}", worker_id, n);
                         let result = n * n; // 简单的计算任务
@@ -661,6 +663,67 @@ fn message_throughput_test() {
         println!("      异步通道: {:?} ({:.0} msg/s)", async_duration, async_throughput);
         println!("      同步通道: {:?} ({:.0} msg/s)", sync_duration, sync_throughput);
     }
+    
+    // 多消费者：Mutex 包装的 mpsc 接收者 vs 可克隆接收者的 MPMC 通道
+    let count = 100000;
+    let workers = 4;
+    println!("    {} 条消息, {} 个消费者 (每条消息模拟少量处理):", count, workers);
+    
+    let start = Instant::now();
+    let (tx, rx) = mpsc::channel();
+    let rx = Arc::new(Mutex::new(rx));
+    let handles: Vec<_> = (0..workers)
+        .map(|_| {
+            let rx = Arc::clone(&rx);
+            thread::spawn(move || {
+                let mut sum = 0u64;
+                // 取消息时持有锁，其他消费者只能排队
+                while let Ok(n) = rx.lock().unwrap().recv() {
+                    sum += simulate_work(n);
+                }
+                sum
+            })
+        })
+        .collect();
+    for i in 0..count {
+        tx.send(i).unwrap();
+    }
+    drop(tx);
+    let mutex_sum: u64 = handles.into_iter().map(|handle| handle.join().unwrap()).sum();
+    let mutex_duration = start.elapsed();
+    
+    let mut mpmc_results = vec![];
+    for capacity in [None, Some(100)] {
+        let start = Instant::now();
+        let (tx, rx) = match capacity {
+            None => channel::unbounded(),
+            Some(capacity) => channel::bounded(capacity),
+        };
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                let rx = rx.clone();
+                thread::spawn(move || rx.iter().map(simulate_work).sum::<u64>())
+            })
+            .collect();
+        drop(rx);
+        for i in 0..count {
+            tx.send(i).unwrap();
+        }
+        drop(tx);
+        let sum: u64 = handles.into_iter().map(|handle| handle.join().unwrap()).sum();
+        assert_eq!(sum, mutex_sum, "每条消息都应该恰好被处理一次");
+        mpmc_results.push(start.elapsed());
+    }
+    
+    let throughput = |duration: Duration| count as f64 / duration.as_secs_f64();
+    println!("      Arc<Mutex<mpsc::Receiver>>: {:?} ({:.0} msg/s)", mutex_duration, throughput(mutex_duration));
+    println!("      MPMC 无界通道:              {:?} ({:.0} msg/s)", mpmc_results[0], throughput(mpmc_results[0]));
+    println!("      MPMC 有界通道 (100):        {:?} ({:.0} msg/s)", mpmc_results[1], throughput(mpmc_results[1]));
+}
+
+/// 消费者对每条消息做的少量计算
+fn simulate_work(n: u64) -> u64 {
+    (0..200).fold(n, |acc, i| acc.wrapping_mul(31).wrapping_add(i)) % 1000
 }
 
 /// 内存使用分析
@@ -844,14 +907,14 @@ fn channel_composition_demo() {
 
 /// Select 模拟演示
 fn select_simulation_demo() {
-    let (tx1, rx1) = mpsc::channel();
-    let (tx2, rx2) = mpsc::channel();
+    let (fast_tx, fast_rx) = channel::unbounded();
+    let (slow_tx, slow_rx) = channel::unbounded();
     
     // 发送者1：快速发送
     thread::spawn(move || {
         for i in 0..3 {
             thread::sleep(Duration::from_millis(100));
-            tx1.send(format!("快速-{}", i)).unwrap();
+            fast_tx.send(format!("快速-{}", i)).unwrap();
         }
     });
     
@@ -859,67 +922,79 @@ fn select_simulation_demo() {
     thread::spawn(move || {
         for i in 0..3 {
             thread::sleep(Duration::from_millis(300));
-            tx2.send(format!("慢速-{}", i)).unwrap();
+            slow_tx.send(format!("慢速-{}", i)).unwrap();
         }
     });
     
-    // 模拟 select 行为
-    println!("    模拟 select 操作 (非阻塞轮询):");
-    let mut rx1_closed = false;
-    let mut rx2_closed = false;
-    
-    while !rx1_closed || !rx2_closed {
-        // 尝试从 rx1 接收
-        match rx1.try_recv() {
-            Ok(msg) => println!("    从通道1收到: {}", msg),
-            Err(mpsc::TryRecvError::Empty) => {}, // 继续
-            Err(mpsc::TryRecvError::Disconnected) => {
-                if !rx1_closed {
-                    println!("    通道1已关闭");
-                    rx1_closed = true;
-                }
-            }
+    // 阻塞在两个通道上，哪个先有消息就处理哪个
+    println!("    阻塞 select 操作 (消息到达即唤醒，无需轮询):");
+    let start = Instant::now();
+    let mut fast_open = true;
+    let mut slow_open = true;
+    
+    while fast_open || slow_open {
+        // 已关闭的通道不再参与选择，否则它会一直处于就绪状态
+        let mut select = Select::new();
+        if fast_open {
+            select = select.recv(&fast_rx, |msg| (1, msg));
+        }
+        if slow_open {
+            select = select.recv(&slow_rx, |msg| (2, msg));
         }
         
-        // 尝试从 rx2 接收
-        match rx2.try_recv() {
-            Ok(msg) => println!("    从通道2收到: {}", msg),
-            Err(mpsc::TryRecvError::Empty) => {}, // 继续
-            Err(mpsc::TryRecvError::Disconnected) => {
-                if !rx2_closed {
-                    println!("    通道2已关闭");
-                    rx2_closed = true;
-                }
+        match select.timeout(Duration::from_secs(1), || (0, Err(mpsc::RecvError))).wait() {
+            (0, _) => println!("    1 秒内没有任何消息"),
+            (index, Ok(msg)) => println!("    [{:>3}ms] 从通道{}收到: {}", start.elapsed().as_millis(), index, msg),
+            (1, Err(_)) => {
+                println!("    通道1已关闭");
+                fast_open = false;
+            }
+            (_, Err(_)) => {
+                println!("    通道2已关闭");
+                slow_open = false;
             }
         }
-        
-        thread::sleep(Duration::from_millis(50)); // 避免忙等待
     }
+    
+    // default 与发送分支
+    let (tx, rx) = channel::bounded::<u32>(1);
+    let status = Select::new()
+        .recv(&rx, |_| "收到消息")
+        .default(|| "没有就绪的分支，立即返回")
+        .wait();
+    println!("    default 分支: {}", status);
+    
+    tx.send(1).unwrap();
+    let sent = Select::new()
+        .send(&tx, 2, |result| result.is_ok())
+        .timeout(Duration::from_millis(50), || false)
+        .wait();
+    println!("    通道已满，send 分支等待 50ms: 发送成功 = {}", sent);
+    
+    let consumer = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(20));
+        let first = rx.recv_timeout(Duration::from_millis(100)).unwrap();
+        let rest: Vec<u32> = rx.iter().collect();
+        (first, rest)
+    });
+    let sent = Select::new()
+        .send(&tx, 2, |result| result.is_ok())
+        .timeout(Duration::from_millis(500), || false)
+        .wait();
+    println!("    接收者取走一条消息后腾出空位: 发送成功 = {}", sent);
+    drop(tx);
+    let (first, rest) = consumer.join().unwrap();
+    println!("    接收者依次收到: {} {:?}", first, rest);
 }
 
-/// 通道适配器演示
+/// 通道适配器演示：所有适配器共享一个两线程的池，而不是每个适配器一个线程
 fn channel_adapter_demo() {
     println!("    实现通道适配器模式:");
+    let pool = AdapterPool::new(2);
     
-    // 创建一个将字符串转换为大写的适配器
-    fn uppercase_adapter(input: Receiver<String>) -> Receiver<String> {
-        let (tx, rx) = mpsc::channel();
-        
-        thread::spawn(move || {
-            for msg in input {
-                let uppercase_msg = msg.to_uppercase();
-                if tx.send(uppercase_msg).is_err() {
-                    break; // 接收者已关闭
-                }
-            }
-        });
-        
-        rx
-    }
-    
-    // 使用适配器
-    let (input_tx, input_rx) = mpsc::channel();
-    let output_rx = uppercase_adapter(input_rx);
+    // map：将字符串转换为大写
+    let (input_tx, input_rx) = channel::unbounded();
+    let output_rx = pool.map(input_rx, |msg: String| msg.to_uppercase());
     
     // 发送数据
     thread::spawn(move || {
@@ -930,9 +1005,51 @@ fn channel_adapter_demo() {
     });
     
     // 接收转换后的数据
-    for msg in output_rx {
+    for msg in output_rx.iter() {
         println!("    适配器输出: {}", msg);
     }
+    
+    // filter + batch：偶数每 3 个一批，或者等待 50ms 后输出不满的一批
+    let (num_tx, num_rx) = channel::unbounded();
+    let batches = pool.batch(pool.filter(num_rx, |n: &u32| n.is_multiple_of(2)), 3, Duration::from_millis(50));
+    for n in 1..=8 {
+        num_tx.send(n).unwrap();
+    }
+    println!("    batch 输出: {:?}", batches.recv().unwrap());
+    println!("    batch 超时输出: {:?}", batches.recv().unwrap());
+    drop(num_tx);
+    
+    // tee + zip + merge：一路复制成两份，一份与序号配对，一份与另一个来源合并
+    let (event_tx, event_rx) = channel::unbounded();
+    let mut copies = pool.tee(event_rx, 2);
+    let for_merge = copies.pop().unwrap();
+    let for_zip = copies.pop().unwrap();
+    let (seq_tx, seq_rx) = channel::unbounded();
+    let numbered = pool.zip(seq_rx, for_zip);
+    let (alert_tx, alert_rx) = channel::unbounded();
+    let merged = pool.merge(vec![for_merge, alert_rx]);
+    
+    for (i, event) in ["登录", "下单", "支付"].into_iter().enumerate() {
+        seq_tx.send(i + 1).unwrap();
+        event_tx.send(event.to_string()).unwrap();
+    }
+    alert_tx.send("告警: 库存不足".to_string()).unwrap();
+    drop((event_tx, seq_tx, alert_tx));
+    
+    println!("    zip 输出: {:?}", numbered.iter().collect::<Vec<_>>());
+    println!("    merge 输出: {:?}", merged.iter().collect::<Vec<_>>());
+    
+    // rate_limit：相邻两条至少间隔 30ms
+    let (burst_tx, burst_rx) = channel::unbounded();
+    let limited = pool.rate_limit(burst_rx, Duration::from_millis(30));
+    for i in 0..4 {
+        burst_tx.send(i).unwrap();
+    }
+    drop(burst_tx);
+    let start = Instant::now();
+    for i in limited.iter() {
+        println!("    [{:>3}ms] rate_limit 输出: {}", start.elapsed().as_millis(), i);
+    }
 }
 
 /// 8. 实际应用案例
@@ -948,9 +1065,12 @@ fn real_world_examples() {
     
     println!("  8.4 分布式计算模拟");
     distributed_computing_simulation();
+    
+    println!("  8.5 Actor 监督与请求响应");
+    actor_supervision_demo();
 }
 
-/// 任务队列系统
+/// 任务队列系统：每个工作线程是一个 actor，结果发给收集器 actor
 fn task_queue_system() {
     #[derive(Debug, Clone)]
     enum Task {
@@ -966,171 +1086,163 @@ fn task_queue_system() {
         NetworkResult(String, bool),
     }
     
-    let (task_tx, task_rx) = mpsc::channel::<Task>();
-    let (result_tx, result_rx) = mpsc::channel();
-    
-    let task_rx = Arc::new(Mutex::new(task_rx));
+    // 结果收集器
+    #[derive(Default)]
+    struct Collector {
+        results: Vec<TaskResult>,
+    }
     
-    // 创建工作线程池
-    let mut workers = vec![];
-    for worker_id in 0..3 {
-        let task_rx = Arc::clone(&task_rx);
-        let result_tx = result_tx.clone();
+    impl Actor for Collector {
+        type Msg = TaskResult;
         
-        let worker = thread::spawn(move || {
-            loop {
-                let task = {
-                    let rx = task_rx.lock().unwrap();
-                    rx.recv()
-                };
-                
-                match task {
-                    Ok(task) => {
-                        println!("    工作线程 {} 处理任务: {:?}", worker_id, task);
-                        
-                        let result = match task {
-                            Task::Compute(n) => {
-                                thread::sleep(Duration::from_millis(100));
-                                TaskResult::ComputeResult(n, (n as u64).pow(2))
-                            }
-                            Task::IO(file) => {
-                                thread::sleep(Duration::from_millis(200));
-                                TaskResult::IOResult(file, true)
-                            }
-                            Task::Network(url) => {
-                                thread::sleep(Duration::from_millis(150));
-                                TaskResult::NetworkResult(url, true)
-                            }
-                        };
-                        
-                        result_tx.send(result).unwrap();
-                    }
-                    Err(_) => {
-                        println!("    工作线程 {} 退出", worker_id);
-                        break;
-                    }
-                }
-            }
-        });
-        
-        workers.push(worker);
-    }
-    
-    // 任务调度器
-    let task_tx_clone = task_tx.clone();
-    let scheduler = thread::spawn(move || {
-        let tasks = vec![
-            Task::Compute(5),
-            Task::IO("file1.txt".to_string()),
-            Task::Network("http://example.com".to_string()),
-            Task::Compute(10),
-            Task::IO("file2.txt".to_string()),
-        ];
+        fn handle(&mut self, result: TaskResult, _ctx: &mut Context<Self>) {
+            println!("    收到结果: {:?}", result);
+            self.results.push(result);
+        }
         
-        for task in tasks {
-            task_tx_clone.send(task).unwrap();
+        fn stopped(&mut self) {
+            println!("    收集器关闭，共 {} 个结果", self.results.len());
         }
-    });
+    }
     
-    // 结果收集器
-    let collector = thread::spawn(move || {
-        let mut results = vec![];
-        for _ in 0..5 {
-            let result = result_rx.recv().unwrap();
-            println!("    收到结果: {:?}", result);
-            results.push(result);
-        }
-        results
-    });
+    struct Worker {
+        id: usize,
+        collector: Addr<Collector>,
+    }
     
-    scheduler.join().unwrap();
-    let _results = collector.join().unwrap();
+    impl Actor for Worker {
+        type Msg = Task;
+        
+        fn handle(&mut self, task: Task, _ctx: &mut Context<Self>) {
+            println!("    工作线程 {} 处理任务: {:?}", self.id, task);
+            
+            let result = match task {
+                Task::Compute(n) => {
+                    thread::sleep(Duration::from_millis(100));
+                    TaskResult::ComputeResult(n, (n as u64).pow(2))
+                }
+                Task::IO(file) => {
+                    thread::sleep(Duration::from_millis(200));
+                    TaskResult::IOResult(file, true)
+                }
+                Task::Network(url) => {
+                    thread::sleep(Duration::from_millis(150));
+                    TaskResult::NetworkResult(url, true)
+                }
+            };
+            
+            // 收集器比工作线程晚停止，这里不会失败
+            let _ = self.collector.send(result);
+        }
+        
+        fn stopped(&mut self) {
+            println!("    工作线程 {} 退出", self.id);
+        }
+    }
     
-    // 关闭工作线程
-    drop(task_tx);
-    for worker in workers {
-        worker.join().unwrap();
+    // 先启动收集器：关闭时它最后停止，能收到工作线程的全部结果
+    let mut system = System::new();
+    let collector = system.spawn("collector", RestartPolicy::default(), Collector::default);
+    let workers: Vec<_> = (0..3)
+        .map(|id| {
+            let collector = collector.clone();
+            system.spawn(&format!("worker-{}", id), RestartPolicy::default(), move || Worker {
+                id,
+                collector: collector.clone(),
+            })
+        })
+        .collect();
+    
+    // 任务调度器：轮流投递到各工作线程的邮箱
+    let tasks = vec![
+        Task::Compute(5),
+        Task::IO("file1.txt".to_string()),
+        Task::Network("http://example.com".to_string()),
+        Task::Compute(10),
+        Task::IO("file2.txt".to_string()),
+    ];
+    for (task, worker) in tasks.into_iter().zip(workers.iter().cycle()) {
+        worker.send(task).unwrap();
     }
+    
+    // 工作线程处理完邮箱中的任务后退出，收集器随后停止
+    system.shutdown();
 }
 
-/// 事件系统演示
+/// 事件系统演示：每个监听器是一个 actor，系统关闭时各自处理完剩余事件
 fn event_system_demo() {
     #[derive(Debug, Clone)]
     enum Event {
         UserLogin(String),
         UserLogout(String),
         DataUpdate(String, i32),
-        SystemShutdown,
     }
     
-    let (event_tx, event_rx) = mpsc::channel::<Event>();
-    
     // 日志监听器
-    let (log_tx, log_rx) = mpsc::channel::<Event>();
-    thread::spawn(move || {
-        for event in log_rx {
+    struct Logger;
+    
+    impl Actor for Logger {
+        type Msg = Event;
+        
+        fn handle(&mut self, event: Event, _ctx: &mut Context<Self>) {
             match event {
                 Event::UserLogin(user) => println!("    [日志] 用户登录: {}", user),
                 Event::UserLogout(user) => println!("    [日志] 用户登出: {}", user),
                 Event::DataUpdate(key, value) => println!("    [日志] 数据更新: {} = {}", key, value),
-                Event::SystemShutdown => {
-                    println!("    [日志] 系统关闭");
-                    break;
-                }
             }
         }
-    });
+        
+        fn stopped(&mut self) {
+            println!("    [日志] 系统关闭");
+        }
+    }
     
     // 统计监听器
-    let (stats_tx, stats_rx) = mpsc::channel::<Event>();
-    thread::spawn(move || {
-        let mut login_count = 0;
-        let mut logout_count = 0;
-        let mut update_count = 0;
+    #[derive(Default)]
+    struct Stats {
+        login_count: u32,
+        logout_count: u32,
+        update_count: u32,
+    }
+    
+    impl Actor for Stats {
+        type Msg = Event;
         
-        for event in stats_rx {
+        fn handle(&mut self, event: Event, _ctx: &mut Context<Self>) {
             match event {
-                Event::UserLogin(_) => login_count += 1,
-                Event::UserLogout(_) => logout_count += 1,
-                Event::DataUpdate(_, _) => update_count += 1,
-                Event::SystemShutdown => {
-                    println!("    [统计] 登录: {}, 登出: {}, 更新: {}", 
-                            login_count, logout_count, update_count);
-                    break;
-                }
+                Event::UserLogin(_) => self.login_count += 1,
+                Event::UserLogout(_) => self.logout_count += 1,
+                Event::DataUpdate(_, _) => self.update_count += 1,
             }
         }
-    });
-    
-    // 事件分发器
-    thread::spawn(move || {
-        for event in event_rx {
-            // 广播事件到所有监听器
-            let _ = log_tx.send(event.clone());
-            let _ = stats_tx.send(event.clone());
-            
-            if matches!(event, Event::SystemShutdown) {
-                break;
-            }
+        
+        fn stopped(&mut self) {
+            println!("    [统计] 登录: {}, 登出: {}, 更新: {}", 
+                    self.login_count, self.logout_count, self.update_count);
         }
-    });
+    }
+    
+    let mut system = System::new();
+    let logger = system.spawn("logger", RestartPolicy::default(), || Logger);
+    let stats = system.spawn("stats", RestartPolicy::default(), Stats::default);
     
-    // 模拟事件发生
+    // 模拟事件发生，广播到所有监听器
     let events = vec![
         Event::UserLogin("Alice".to_string()),
         Event::DataUpdate("config".to_string(), 42),
         Event::UserLogin("Bob".to_string()),
         Event::UserLogout("Alice".to_string()),
         Event::DataUpdate("counter".to_string(), 100),
-        Event::SystemShutdown,
     ];
     
     for event in events {
-        event_tx.send(event).unwrap();
+        logger.send(event.clone()).unwrap();
+        stats.send(event).unwrap();
         thread::sleep(Duration::from_millis(100));
     }
     
-    thread::sleep(Duration::from_millis(500)); // 等待处理完成
+    // 不再需要 SystemShutdown 事件和固定的等待时间
+    system.shutdown();
 }
 
 /// 数据流处理
@@ -1207,7 +1319,7 @@ fn data_stream_processing() {
     processor.join().unwrap();
 }
 
-/// 分布式计算模拟
+/// 分布式计算模拟：工作节点是 actor，收集器 actor 在关闭时汇总结果
 fn distributed_computing_simulation() {
     #[derive(Debug)]
     struct ComputeTask {
@@ -1222,97 +1334,169 @@ fn distributed_computing_simulation() {
         worker_id: usize,
     }
     
-    let (task_tx, task_rx) = mpsc::channel::<ComputeTask>();
-    let (result_tx, result_rx) = mpsc::channel::<ComputeResult>();
+    // 结果收集器
+    #[derive(Default)]
+    struct Collector {
+        results: Vec<ComputeResult>,
+    }
     
-    let task_rx = Arc::new(Mutex::new(task_rx));
+    impl Actor for Collector {
+        type Msg = ComputeResult;
+        
+        fn handle(&mut self, result: ComputeResult, _ctx: &mut Context<Self>) {
+            println!("    收集结果: {:?}", result);
+            self.results.push(result);
+        }
+        
+        fn stopped(&mut self) {
+            // 按任务ID排序
+            self.results.sort_by_key(|r| r.task_id);
+            println!("    所有任务完成，最终结果:");
+            for result in &self.results {
+                println!("      任务 {}: {} (节点 {})", 
+                        result.task_id, result.result, result.worker_id);
+            }
+        }
+    }
     
-    // 创建分布式工作节点
-    let num_workers = 4;
-    let mut workers = vec![];
+    // 分布式工作节点
+    struct Node {
+        id: usize,
+        collector: Addr<Collector>,
+    }
     
-    for worker_id in 0..num_workers {
-        let task_rx = Arc::clone(&task_rx);
-        let result_tx = result_tx.clone();
+    impl Actor for Node {
+        type Msg = ComputeTask;
+        
+        fn started(&mut self, _ctx: &mut Context<Self>) {
+            println!("    工作节点 {} 启动", self.id);
+        }
         
-        let worker = thread::spawn(move || {
-            println!("    工作节点 {} 启动", worker_id);
+        fn handle(&mut self, task: ComputeTask, _ctx: &mut Context<Self>) {
+            println!("    节点 {} 处理任务 {}", self.id, task.id);
             
-            loop {
-                let task = {
-                    let rx = task_rx.lock().unwrap();
-                    rx.recv()
-                };
-                
-                match task {
-                    Ok(task) => {
-                        println!("    节点 {} 处理任务 {}", worker_id, task.id);
-                        
-                        // 模拟计算密集型任务
-                        let result: u64 = task.data.iter().map(|&x| x as u64 * x as u64).sum();
-                        thread::sleep(Duration::from_millis(200)); // 模拟计算时间
-                        
-                        let compute_result = ComputeResult {
-                            task_id: task.id,
-                            result,
-                            worker_id,
-                        };
-                        
-                        result_tx.send(compute_result).unwrap();
-                    }
-                    Err(_) => {
-                        println!("    工作节点 {} 关闭", worker_id);
-                        break;
-                    }
-                }
-            }
-        });
+            // 模拟计算密集型任务
+            let result: u64 = task.data.iter().map(|&x| x as u64 * x as u64).sum();
+            thread::sleep(Duration::from_millis(200)); // 模拟计算时间
+            
+            let _ = self.collector.send(ComputeResult {
+                task_id: task.id,
+                result,
+                worker_id: self.id,
+            });
+        }
         
-        workers.push(worker);
+        fn stopped(&mut self) {
+            println!("    工作节点 {} 关闭", self.id);
+        }
     }
     
-    // 任务分发器
-    let task_tx_clone = task_tx.clone();
-    let distributor = thread::spawn(move || {
-        for i in 0..8 {
-            let task = ComputeTask {
-                id: i,
-                data: (1..=10).map(|x| x + i * 10).collect(),
-            };
-            
-            println!("    分发任务: {:?}", task);
-            task_tx_clone.send(task).unwrap();
-        }
-    });
+    let mut system = System::new();
+    let collector = system.spawn("collector", RestartPolicy::default(), Collector::default);
+    let num_workers = 4;
+    let nodes: Vec<_> = (0..num_workers)
+        .map(|id| {
+            let collector = collector.clone();
+            system.spawn(&format!("node-{}", id), RestartPolicy::default(), move || Node {
+                id,
+                collector: collector.clone(),
+            })
+        })
+        .collect();
+    
+    // 任务分发器：按任务ID轮流分配到各节点
+    for i in 0..8 {
+        let task = ComputeTask {
+            id: i,
+            data: (1..=10).map(|x| x + i * 10).collect(),
+        };
+        
+        println!("    分发任务: {:?}", task);
+        nodes[i as usize % num_workers].send(task).unwrap();
+    }
     
-    // 结果收集器
-    let collector = thread::spawn(move || {
-        let mut results = vec![];
-        for _ in 0..8 {
-            let result = result_rx.recv().unwrap();
-            println!("    收集结果: {:?}", result);
-            results.push(result);
-        }
+    // 节点先处理完各自的任务并关闭，收集器最后汇总
+    system.shutdown();
+}
+
+/// Actor 监督与请求响应：panic 后按退避策略重启，ask 带超时
+fn actor_supervision_demo() {
+    enum WorkerMsg {
+        Square { n: u64, delay: Duration, reply: Reply<u64> },
+        Crash,
+        Countdown(u32),
+    }
+    
+    struct Worker;
+    
+    impl Actor for Worker {
+        type Msg = WorkerMsg;
         
-        // 按任务ID排序
-        results.sort_by_key(|r| r.task_id);
-        println!("    所有任务完成，最终结果:");
-        for result in &results {
-            println!("      任务 {}: {} (节点 {})", 
-                    result.task_id, result.result, result.worker_id);
+        fn started(&mut self, ctx: &mut Context<Self>) {
+            if ctx.restarts() > 0 {
+                println!("    [{}] 第 {} 次重启后恢复服务，状态已重置", ctx.name(), ctx.restarts());
+            }
         }
         
-        results
-    });
+        fn handle(&mut self, msg: WorkerMsg, ctx: &mut Context<Self>) {
+            match msg {
+                WorkerMsg::Square { n, delay, reply } => {
+                    thread::sleep(delay);
+                    reply.send(n * n);
+                }
+                WorkerMsg::Crash => panic!("模拟故障"),
+                WorkerMsg::Countdown(0) => {
+                    println!("    [{}] 倒计时结束，主动停止", ctx.name());
+                    ctx.stop();
+                }
+                WorkerMsg::Countdown(n) => {
+                    println!("    [{}] 倒计时 {}", ctx.name(), n);
+                    // 给自己发下一条消息
+                    ctx.addr().send(WorkerMsg::Countdown(n - 1)).unwrap();
+                }
+            }
+        }
+    }
     
-    distributor.join().unwrap();
-    let _final_results = collector.join().unwrap();
+    let square = |n: u64, delay: Duration| move |reply| WorkerMsg::Square { n, delay, reply };
     
-    // 关闭所有工作节点
-    drop(task_tx);
-    for worker in workers {
-        worker.join().unwrap();
-    }
+    let mut system = System::new().with_supervision_hook(|event| match event {
+        SupervisionEvent::Restarting { actor, attempt, backoff } => {
+            println!("    [监督] {} panic，{:?} 后第 {} 次重启", actor, backoff, attempt)
+        }
+        SupervisionEvent::GaveUp { actor, restarts } => {
+            println!("    [监督] {} 已重启 {} 次，放弃", actor, restarts)
+        }
+    });
+    let policy = RestartPolicy {
+        max_restarts: 2,
+        initial_backoff: Duration::from_millis(50),
+        max_backoff: Duration::from_millis(200),
+    };
+    let worker = system.spawn("worker", policy, || Worker);
+    
+    let answer = worker.ask(square(7, Duration::ZERO), Duration::from_secs(1));
+    println!("    ask 7² = {:?}", answer);
+    
+    let answer = worker.ask(square(8, Duration::from_millis(200)), Duration::from_millis(50));
+    println!("    处理太慢，ask 8² = {:?}", answer);
+    
+    // panic 的消息被丢弃，后面排队的消息在重启后继续处理
+    worker.send(WorkerMsg::Crash).unwrap();
+    let answer = worker.ask(square(9, Duration::ZERO), Duration::from_secs(1));
+    println!("    重启后 ask 9² = {:?}", answer);
+    
+    worker.send(WorkerMsg::Crash).unwrap();
+    worker.send(WorkerMsg::Crash).unwrap();
+    let answer = worker.ask(square(10, Duration::ZERO), Duration::from_secs(1));
+    println!("    超过重启上限后 ask 10² = {:?}", answer);
+    
+    let countdown = system.spawn("countdown", RestartPolicy::never(), || Worker);
+    countdown.send(WorkerMsg::Countdown(3)).unwrap();
+    thread::sleep(Duration::from_millis(50)); // 等倒计时走完
+    println!("    countdown 停止后还能发送: {}", countdown.send(WorkerMsg::Countdown(1)).is_ok());
+    
+    system.shutdown();
 }
 
 #[cfg(test)]